@@ -0,0 +1,102 @@
+-- ============================================
+-- Migration 014: 庫存成本層 (FIFO / 移動加權平均)
+--
+-- 包含：
+-- 1. stock_cost_layers 成本層表（倉庫 / 產品 / 批號）
+-- 2. stock_cost_consumptions 成本層耗用明細
+-- 3. stock_ledger 新增 total_cost 欄位
+-- 4. 由既有庫存流水建立期初成本層
+-- ============================================
+
+-- ============================================
+-- 1. 成本層表
+-- ============================================
+
+CREATE TABLE stock_cost_layers (
+    id UUID PRIMARY KEY,
+    warehouse_id UUID NOT NULL REFERENCES warehouses(id),
+    product_id UUID NOT NULL REFERENCES products(id),
+    batch_no VARCHAR(50),
+    expiry_date DATE,
+    source_ledger_id UUID REFERENCES stock_ledger(id),
+    layer_date TIMESTAMPTZ NOT NULL,
+    original_qty NUMERIC(18, 4) NOT NULL,
+    remaining_qty NUMERIC(18, 4) NOT NULL,
+    unit_cost NUMERIC(18, 4) NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT chk_cost_layer_remaining CHECK (remaining_qty >= 0 AND remaining_qty <= original_qty)
+);
+
+CREATE INDEX idx_stock_cost_layers_bucket ON stock_cost_layers(warehouse_id, product_id, batch_no);
+CREATE INDEX idx_stock_cost_layers_open ON stock_cost_layers(warehouse_id, product_id, layer_date) WHERE remaining_qty > 0;
+CREATE INDEX idx_stock_cost_layers_source_ledger ON stock_cost_layers(source_ledger_id);
+
+-- ============================================
+-- 2. 成本層耗用明細（出庫流水對應耗用的成本層）
+-- ============================================
+
+CREATE TABLE stock_cost_consumptions (
+    id UUID PRIMARY KEY,
+    layer_id UUID NOT NULL REFERENCES stock_cost_layers(id),
+    ledger_id UUID NOT NULL REFERENCES stock_ledger(id),
+    qty NUMERIC(18, 4) NOT NULL,
+    unit_cost NUMERIC(18, 4) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_stock_cost_consumptions_layer_id ON stock_cost_consumptions(layer_id);
+CREATE INDEX idx_stock_cost_consumptions_ledger_id ON stock_cost_consumptions(ledger_id);
+
+-- ============================================
+-- 3. 庫存流水成本金額
+-- ============================================
+
+ALTER TABLE stock_ledger ADD COLUMN IF NOT EXISTS total_cost NUMERIC(18, 4);
+
+UPDATE stock_ledger SET total_cost = qty_base * unit_cost WHERE unit_cost IS NOT NULL AND total_cost IS NULL;
+
+-- ============================================
+-- 4. 期初成本層
+-- 以入庫流水的加權平均單價作為既有庫存的期初成本
+-- ============================================
+
+INSERT INTO stock_cost_layers (
+    id, warehouse_id, product_id, batch_no, expiry_date, layer_date,
+    original_qty, remaining_qty, unit_cost, created_at
+)
+SELECT
+    gen_random_uuid(),
+    t.warehouse_id,
+    t.product_id,
+    t.batch_no,
+    t.expiry_date,
+    t.first_trx_date,
+    t.qty_on_hand,
+    t.qty_on_hand,
+    ROUND(COALESCE(t.in_value / NULLIF(t.in_qty, 0), 0), 4),
+    NOW()
+FROM (
+    SELECT
+        warehouse_id,
+        product_id,
+        batch_no,
+        MAX(expiry_date) as expiry_date,
+        MIN(trx_date) as first_trx_date,
+        SUM(CASE
+            WHEN direction IN ('in', 'transfer_in', 'adjust_in') THEN qty_base
+            ELSE -qty_base
+        END) as qty_on_hand,
+        SUM(qty_base * unit_cost) FILTER (
+            WHERE direction IN ('in', 'transfer_in', 'adjust_in') AND unit_cost IS NOT NULL
+        ) as in_value,
+        SUM(qty_base) FILTER (
+            WHERE direction IN ('in', 'transfer_in', 'adjust_in') AND unit_cost IS NOT NULL
+        ) as in_qty
+    FROM stock_ledger
+    GROUP BY warehouse_id, product_id, batch_no
+) t
+WHERE t.qty_on_hand > 0;
+
+-- ============================================
+-- 完成
+-- ============================================
@@ -0,0 +1,27 @@
+-- ============================================
+-- Migration 015: 已核准單據沖銷
+--
+-- 包含：
+-- 1. doc_status 新增 reversed 狀態
+-- 2. documents 新增 is_reversal 欄位（沖銷單以 source_doc_id 指向原單據）
+-- ============================================
+
+-- ============================================
+-- 1. 單據狀態
+-- ============================================
+
+ALTER TYPE doc_status ADD VALUE IF NOT EXISTS 'reversed';
+
+-- ============================================
+-- 2. 沖銷單標記
+-- ============================================
+
+ALTER TABLE documents ADD COLUMN IF NOT EXISTS is_reversal BOOLEAN NOT NULL DEFAULT false;
+
+-- 每張單據只能被沖銷一次
+CREATE UNIQUE INDEX IF NOT EXISTS idx_documents_reversal_source
+    ON documents(source_doc_id) WHERE is_reversal = true;
+
+-- ============================================
+-- 完成
+-- ============================================
@@ -0,0 +1,64 @@
+-- ============================================
+-- Migration 016: 倉庫儲位管理
+--
+-- 包含：
+-- 1. storage_locations 儲位表（區 zone / 架 rack / 格 bin）
+-- 2. document_lines 新增來源 / 目的儲位
+-- 3. stock_ledger 新增儲位
+-- 4. doc_type 新增 MV（庫內上架 / 移動單）
+-- ============================================
+
+-- ============================================
+-- 1. 儲位表
+-- ============================================
+
+CREATE TABLE storage_locations (
+    id UUID PRIMARY KEY,
+    warehouse_id UUID NOT NULL REFERENCES warehouses(id),
+    parent_id UUID REFERENCES storage_locations(id),
+    code VARCHAR(50) NOT NULL,
+    name VARCHAR(200) NOT NULL,
+    -- zone: 區, rack: 架, bin: 格
+    location_type VARCHAR(20) NOT NULL,
+    -- 容量（以基本單位數量計，NULL 表示不限）
+    capacity NUMERIC(18, 4),
+    -- 保存條件：RT 常溫 / RF 冷藏 / FZ 冷凍 / DK 避光 / DY 乾燥
+    storage_condition VARCHAR(10),
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (warehouse_id, code),
+    CONSTRAINT chk_storage_location_type CHECK (location_type IN ('zone', 'rack', 'bin')),
+    CONSTRAINT chk_storage_location_capacity CHECK (capacity IS NULL OR capacity >= 0),
+    CONSTRAINT chk_storage_location_condition CHECK (
+        storage_condition IS NULL OR storage_condition IN ('RT', 'RF', 'FZ', 'DK', 'DY')
+    )
+);
+
+CREATE INDEX idx_storage_locations_warehouse_id ON storage_locations(warehouse_id);
+CREATE INDEX idx_storage_locations_parent_id ON storage_locations(parent_id);
+
+-- ============================================
+-- 2. 單據明細儲位
+-- ============================================
+
+ALTER TABLE document_lines ADD COLUMN IF NOT EXISTS from_location_id UUID REFERENCES storage_locations(id);
+ALTER TABLE document_lines ADD COLUMN IF NOT EXISTS to_location_id UUID REFERENCES storage_locations(id);
+
+-- ============================================
+-- 3. 庫存流水儲位（NULL 表示未指定儲位）
+-- ============================================
+
+ALTER TABLE stock_ledger ADD COLUMN IF NOT EXISTS location_id UUID REFERENCES storage_locations(id);
+
+CREATE INDEX IF NOT EXISTS idx_stock_ledger_location ON stock_ledger(warehouse_id, location_id, product_id);
+
+-- ============================================
+-- 4. 庫內上架 / 移動單
+-- ============================================
+
+ALTER TYPE doc_type ADD VALUE IF NOT EXISTS 'MV';
+
+-- ============================================
+-- 完成
+-- ============================================
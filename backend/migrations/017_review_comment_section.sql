@@ -0,0 +1,15 @@
+-- ============================================
+-- Migration 017: 審查意見對應計畫書章節
+--
+-- 包含：
+-- 1. review_comments 新增 section 欄位（對應 working_content 第一層章節，例如 design）
+--    供版本比較標示有未解決意見的變更章節
+-- ============================================
+
+ALTER TABLE review_comments ADD COLUMN IF NOT EXISTS section VARCHAR(100);
+
+CREATE INDEX IF NOT EXISTS idx_review_comments_section ON review_comments(section) WHERE is_resolved = false;
+
+-- ============================================
+-- 完成
+-- ============================================
@@ -0,0 +1,73 @@
+-- ============================================
+-- Migration 018: 疼痛 / 福祉評分與人道終點警示
+--
+-- 包含：
+-- 1. welfare_score_sheets 評分表（依計畫或物種設定，含加權評分項目與人道終點）
+-- 2. pig_welfare_scores 豬隻福祉評分紀錄（軟刪除、版本歷史同其他豬隻紀錄）
+-- 3. version_record_type 新增 welfare_score
+-- 4. notification_type 新增 humane_endpoint
+-- ============================================
+
+-- ============================================
+-- 1. 評分表
+-- ============================================
+
+CREATE TABLE welfare_score_sheets (
+    id UUID PRIMARY KEY,
+    name VARCHAR(200) NOT NULL,
+    -- 計畫專用評分表（優先於物種預設）
+    protocol_id UUID REFERENCES protocols(id),
+    -- 物種預設評分表，例如 pig
+    species VARCHAR(50),
+    -- 評分項目：[{code, name, weight, max_score, description}]
+    criteria JSONB NOT NULL,
+    -- 加權總分達此值即達人道終點
+    endpoint_total NUMERIC(8, 2),
+    -- 與前次評分相比上升達此值即達人道終點
+    endpoint_trend_increase NUMERIC(8, 2),
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_welfare_score_sheets_protocol_id ON welfare_score_sheets(protocol_id);
+CREATE INDEX idx_welfare_score_sheets_species ON welfare_score_sheets(species);
+
+-- ============================================
+-- 2. 豬隻福祉評分紀錄
+-- ============================================
+
+CREATE TABLE pig_welfare_scores (
+    id SERIAL PRIMARY KEY,
+    pig_id INTEGER NOT NULL REFERENCES pigs(id) ON DELETE CASCADE,
+    score_sheet_id UUID NOT NULL REFERENCES welfare_score_sheets(id),
+    assessed_at TIMESTAMPTZ NOT NULL,
+    -- 各項評分：{criterion_code: score}
+    scores JSONB NOT NULL,
+    total_score NUMERIC(8, 2) NOT NULL,
+    endpoint_reached BOOLEAN NOT NULL DEFAULT false,
+    endpoint_reason TEXT,
+    remark TEXT,
+    vet_read BOOLEAN NOT NULL DEFAULT false,
+    vet_read_at TIMESTAMPTZ,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ,
+    deletion_reason TEXT,
+    deleted_by UUID REFERENCES users(id)
+);
+
+CREATE INDEX idx_pig_welfare_scores_pig_id ON pig_welfare_scores(pig_id, assessed_at);
+
+-- ============================================
+-- 3. 版本歷史與通知類型
+-- ============================================
+
+ALTER TYPE version_record_type ADD VALUE IF NOT EXISTS 'welfare_score';
+ALTER TYPE notification_type ADD VALUE IF NOT EXISTS 'humane_endpoint';
+
+-- ============================================
+-- 完成
+-- ============================================
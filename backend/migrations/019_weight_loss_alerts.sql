@@ -0,0 +1,37 @@
+-- ============================================
+-- Migration 019: 體重生長分析與異常體重下降警示
+--
+-- 包含：
+-- 1. pig_weight_alerts 體重下降警示紀錄（同一筆體重只警示一次）
+-- 2. notification_type 新增 weight_loss_alert
+-- ============================================
+
+-- ============================================
+-- 1. 體重下降警示
+-- ============================================
+
+CREATE TABLE pig_weight_alerts (
+    id SERIAL PRIMARY KEY,
+    pig_id INTEGER NOT NULL REFERENCES pigs(id) ON DELETE CASCADE,
+    -- 觸發警示的最新體重紀錄
+    weight_id INTEGER NOT NULL REFERENCES pig_weights(id) ON DELETE CASCADE,
+    -- 比較基準：觀察期間內的最高體重
+    baseline_weight_id INTEGER NOT NULL REFERENCES pig_weights(id) ON DELETE CASCADE,
+    loss_percent NUMERIC(6, 2) NOT NULL,
+    threshold_percent NUMERIC(6, 2) NOT NULL,
+    window_days INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (pig_id, weight_id)
+);
+
+CREATE INDEX idx_pig_weight_alerts_pig_id ON pig_weight_alerts(pig_id, created_at);
+
+-- ============================================
+-- 2. 通知類型
+-- ============================================
+
+ALTER TYPE notification_type ADD VALUE IF NOT EXISTS 'weight_loss_alert';
+
+-- ============================================
+-- 完成
+-- ============================================
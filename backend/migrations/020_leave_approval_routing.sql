@@ -0,0 +1,31 @@
+-- ============================================
+-- Migration 020: 請假多層級審核路由
+--
+-- 包含：
+-- 1. leave_requests 新增審核鏈、指派時間、代理來源
+-- 2. 逾時上呈查詢索引
+-- ============================================
+
+-- ============================================
+-- 1. 審核鏈
+-- ============================================
+
+-- 送出時依假別與天數決定的審核層級，例如 {PENDING_L1,PENDING_HR}
+ALTER TABLE leave_requests ADD COLUMN IF NOT EXISTS approval_chain VARCHAR(20)[];
+-- 目前審核人被指派的時間（逾時上呈用）
+ALTER TABLE leave_requests ADD COLUMN IF NOT EXISTS approver_assigned_at TIMESTAMPTZ;
+-- 原審核人請假時由其代理人審核，記錄原審核人
+ALTER TABLE leave_requests ADD COLUMN IF NOT EXISTS delegated_from_id UUID REFERENCES users(id);
+
+-- leave_approvals.action 新增 DELEGATE（代理）、ESCALATE（逾時上呈）
+
+-- ============================================
+-- 2. 索引
+-- ============================================
+
+CREATE INDEX IF NOT EXISTS idx_leave_approver_assigned ON leave_requests(approver_assigned_at)
+    WHERE status IN ('PENDING_L1', 'PENDING_L2', 'PENDING_HR', 'PENDING_GM');
+
+-- ============================================
+-- 完成
+-- ============================================
@@ -0,0 +1,43 @@
+-- ============================================
+-- Migration 021: 盤點作業（實盤數量、差異與調整）
+--
+-- 包含：
+-- 1. documents 新增盲盤與開始盤點時間
+-- 2. stocktake_items 盤點項目（凍結帳面數量 / 實盤數量，依批號）
+-- ============================================
+
+-- ============================================
+-- 1. 盤點單設定
+-- ============================================
+
+-- 盲盤：盤點人員看不到帳面數量
+ALTER TABLE documents ADD COLUMN IF NOT EXISTS blind_count BOOLEAN NOT NULL DEFAULT false;
+-- 開始盤點時間（帳面數量於此時凍結）
+ALTER TABLE documents ADD COLUMN IF NOT EXISTS count_started_at TIMESTAMPTZ;
+
+-- ============================================
+-- 2. 盤點項目
+-- ============================================
+
+CREATE TABLE stocktake_items (
+    id UUID PRIMARY KEY,
+    document_id UUID NOT NULL REFERENCES documents(id) ON DELETE CASCADE,
+    product_id UUID NOT NULL REFERENCES products(id),
+    batch_no VARCHAR(50),
+    expiry_date DATE,
+    -- 開始盤點時凍結的帳面數量與單位成本（盤盈品項帳面為 0）
+    book_qty NUMERIC(18, 4) NOT NULL DEFAULT 0,
+    unit_cost NUMERIC(18, 4) NOT NULL DEFAULT 0,
+    counted_qty NUMERIC(18, 4),
+    counted_by UUID REFERENCES users(id),
+    counted_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT chk_stocktake_counted_qty CHECK (counted_qty IS NULL OR counted_qty >= 0),
+    CONSTRAINT uq_stocktake_items_batch UNIQUE NULLS NOT DISTINCT (document_id, product_id, batch_no)
+);
+
+CREATE INDEX idx_stocktake_items_document_id ON stocktake_items(document_id);
+
+-- ============================================
+-- 完成
+-- ============================================
@@ -0,0 +1,30 @@
+-- ============================================
+-- Migration 022: 活動日誌實體鍵值
+--
+-- 包含：
+-- 1. user_activity_logs 新增 entity_key（支援整數 ID 的實體，如動物紀錄）與請求耗時
+-- 2. 依 session 查詢活動的索引
+-- ============================================
+
+-- ============================================
+-- 1. 實體鍵值與請求耗時
+-- ============================================
+
+-- entity_id 僅能存 UUID；entity_key 保存路徑中的原始 ID（UUID 或整數）
+ALTER TABLE user_activity_logs ADD COLUMN IF NOT EXISTS entity_key VARCHAR(100);
+ALTER TABLE user_activity_logs ADD COLUMN IF NOT EXISTS duration_ms INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_activity_entity_key
+    ON user_activity_logs(entity_type, entity_key, created_at DESC);
+
+-- ============================================
+-- 2. Session 索引
+-- ============================================
+
+CREATE INDEX IF NOT EXISTS idx_activity_session
+    ON user_activity_logs(session_id, created_at DESC)
+    WHERE session_id IS NOT NULL;
+
+-- ============================================
+-- 完成
+-- ============================================
@@ -0,0 +1,22 @@
+-- ============================================
+-- Migration 023: GLP 記錄鎖定擴充
+--
+-- 包含：
+-- 1. 體重、疫苗紀錄新增鎖定欄位（與觀察、手術、犧牲紀錄一致）
+-- ============================================
+
+-- ============================================
+-- 1. 鎖定欄位
+-- ============================================
+
+ALTER TABLE pig_weights ADD COLUMN IF NOT EXISTS is_locked BOOLEAN DEFAULT false;
+ALTER TABLE pig_weights ADD COLUMN IF NOT EXISTS locked_at TIMESTAMPTZ;
+ALTER TABLE pig_weights ADD COLUMN IF NOT EXISTS locked_by UUID REFERENCES users(id);
+
+ALTER TABLE pig_vaccinations ADD COLUMN IF NOT EXISTS is_locked BOOLEAN DEFAULT false;
+ALTER TABLE pig_vaccinations ADD COLUMN IF NOT EXISTS locked_at TIMESTAMPTZ;
+ALTER TABLE pig_vaccinations ADD COLUMN IF NOT EXISTS locked_by UUID REFERENCES users(id);
+
+-- ============================================
+-- 完成
+-- ============================================
@@ -0,0 +1,40 @@
+-- ============================================
+-- Migration 024: 電子簽章意義
+--
+-- 包含：
+-- 1. electronic_signatures 新增簽章意義（撰寫、審查、核准）
+-- 2. 既有簽章依簽章類型回填
+-- 3. 簽章驗證日誌索引
+-- ============================================
+
+-- ============================================
+-- 1. 簽章意義
+-- ============================================
+
+ALTER TABLE electronic_signatures ADD COLUMN IF NOT EXISTS signature_meaning VARCHAR(20);
+
+ALTER TABLE electronic_signatures DROP CONSTRAINT IF EXISTS chk_signature_meaning;
+ALTER TABLE electronic_signatures ADD CONSTRAINT chk_signature_meaning
+    CHECK (signature_meaning IS NULL OR signature_meaning IN ('AUTHORSHIP', 'REVIEW', 'APPROVAL'));
+
+-- ============================================
+-- 2. 回填既有簽章
+-- ============================================
+
+UPDATE electronic_signatures SET signature_meaning = CASE signature_type
+    WHEN 'APPROVE' THEN 'APPROVAL'
+    WHEN 'WITNESS' THEN 'REVIEW'
+    ELSE 'AUTHORSHIP'
+END
+WHERE signature_meaning IS NULL;
+
+-- ============================================
+-- 3. 驗證日誌索引
+-- ============================================
+
+CREATE INDEX IF NOT EXISTS idx_signature_verification_logs_signature
+    ON signature_verification_logs(signature_id, verified_at DESC);
+
+-- ============================================
+-- 完成
+-- ============================================
@@ -0,0 +1,23 @@
+-- ============================================
+-- Migration 025: 附件實體鍵值
+--
+-- 包含：
+-- 1. attachments.entity_id 改為文字（豬隻、獸醫建議等實體使用整數或複合鍵值）
+-- 2. 檔案大小改為 BIGINT
+-- ============================================
+
+-- ============================================
+-- 1. 實體鍵值
+-- ============================================
+
+ALTER TABLE attachments ALTER COLUMN entity_id TYPE VARCHAR(100) USING entity_id::text;
+
+-- ============================================
+-- 2. 檔案大小
+-- ============================================
+
+ALTER TABLE attachments ALTER COLUMN file_size TYPE BIGINT;
+
+-- ============================================
+-- 完成
+-- ============================================
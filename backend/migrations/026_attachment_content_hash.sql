@@ -0,0 +1,27 @@
+-- ============================================
+-- Migration 026: 附件內容雜湊
+--
+-- 包含：
+-- 1. attachments 新增 SHA-256 內容雜湊與最近一次完整性檢查時間
+-- 2. 依雜湊查詢（內容去重、完整性掃描）索引
+-- ============================================
+
+-- ============================================
+-- 1. 內容雜湊
+-- ============================================
+
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS content_hash CHAR(64);
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS integrity_checked_at TIMESTAMPTZ;
+
+COMMENT ON COLUMN attachments.content_hash IS '上傳時計算的 SHA-256（十六進位）；既有附件於第一次完整性掃描時補上';
+
+-- ============================================
+-- 2. 索引
+-- ============================================
+
+CREATE INDEX IF NOT EXISTS idx_attachments_content_hash ON attachments(content_hash);
+CREATE INDEX IF NOT EXISTS idx_attachments_file_path ON attachments(file_path);
+
+-- ============================================
+-- 完成
+-- ============================================
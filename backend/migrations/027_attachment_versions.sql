@@ -0,0 +1,48 @@
+-- ============================================
+-- Migration 027: 附件版本與軟刪除
+--
+-- 包含：
+-- 1. 版本鏈（同一文件的各版本共用 root_id，previous_version_id 指向前一版）
+-- 2. 軟刪除（刪除時間、刪除者、原因）
+-- 3. 保存期限屆滿後的實體清除標記
+-- ============================================
+
+-- ============================================
+-- 1. 版本鏈
+-- ============================================
+
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS root_id UUID;
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS previous_version_id UUID REFERENCES attachments(id);
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS superseded_at TIMESTAMPTZ;
+
+UPDATE attachments SET root_id = id WHERE root_id IS NULL;
+ALTER TABLE attachments ALTER COLUMN root_id SET NOT NULL;
+
+-- 每個版本只能被取代一次
+CREATE UNIQUE INDEX IF NOT EXISTS idx_attachments_previous_version
+    ON attachments(previous_version_id) WHERE previous_version_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_attachments_root ON attachments(root_id, version);
+
+-- ============================================
+-- 2. 軟刪除
+-- ============================================
+
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS deleted_by UUID REFERENCES users(id);
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS deletion_reason TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_attachments_current
+    ON attachments(entity_type, entity_id)
+    WHERE superseded_at IS NULL AND deleted_at IS NULL;
+
+-- ============================================
+-- 3. 實體清除
+-- ============================================
+
+-- 檔案已依保存期限清除，紀錄保留供稽核
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS purged_at TIMESTAMPTZ;
+
+-- ============================================
+-- 完成
+-- ============================================
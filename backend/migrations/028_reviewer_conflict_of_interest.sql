@@ -0,0 +1,37 @@
+-- ============================================
+-- Migration 028: 審查委員利益衝突與盲審
+--
+-- 包含：
+-- 1. 審查委員利益衝突（COI）聲明（僅新增，保留完整歷程）
+-- 2. 計畫盲審設定（對 PI 隱藏審查委員身分）
+-- ============================================
+
+-- ============================================
+-- 1. 利益衝突聲明
+-- ============================================
+
+CREATE TABLE IF NOT EXISTS reviewer_coi_declarations (
+    id UUID PRIMARY KEY,
+    protocol_id UUID NOT NULL REFERENCES protocols(id) ON DELETE CASCADE,
+    reviewer_id UUID NOT NULL REFERENCES users(id),
+    has_conflict BOOLEAN NOT NULL,
+    description TEXT,
+    declared_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT chk_coi_description CHECK (NOT has_conflict OR description IS NOT NULL)
+);
+
+-- 以最新一筆聲明為準
+CREATE INDEX IF NOT EXISTS idx_reviewer_coi_latest
+    ON reviewer_coi_declarations(protocol_id, reviewer_id, declared_at DESC);
+
+-- ============================================
+-- 2. 盲審
+-- ============================================
+
+ALTER TABLE protocols ADD COLUMN IF NOT EXISTS blinded_review BOOLEAN NOT NULL DEFAULT false;
+
+COMMENT ON COLUMN protocols.blinded_review IS '盲審：PI 與共同編輯者查看審查意見時不顯示審查委員身分';
+
+-- ============================================
+-- 完成
+-- ============================================
@@ -0,0 +1,34 @@
+-- ============================================
+-- Migration 029: 審查委員表決
+--
+-- 包含：
+-- 1. 審查委員對各計畫版本的正式表決（核准、附條件核准、修正、否決）
+-- ============================================
+
+-- ============================================
+-- 1. 審查表決
+-- ============================================
+
+CREATE TABLE IF NOT EXISTS review_votes (
+    id UUID PRIMARY KEY,
+    protocol_id UUID NOT NULL REFERENCES protocols(id) ON DELETE CASCADE,
+    protocol_version_id UUID NOT NULL REFERENCES protocol_versions(id) ON DELETE CASCADE,
+    reviewer_id UUID NOT NULL REFERENCES users(id),
+    decision VARCHAR(30) NOT NULL,
+    conditions TEXT,
+    comment TEXT,
+    voted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT chk_review_vote_decision
+        CHECK (decision IN ('APPROVE', 'APPROVE_WITH_CONDITIONS', 'REVISE', 'REJECT')),
+    CONSTRAINT chk_review_vote_conditions
+        CHECK (decision <> 'APPROVE_WITH_CONDITIONS' OR conditions IS NOT NULL),
+    -- 每位委員對每個版本僅一票（審查期間可修改）
+    CONSTRAINT uq_review_vote UNIQUE (protocol_version_id, reviewer_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_review_votes_protocol ON review_votes(protocol_id);
+
+-- ============================================
+-- 完成
+-- ============================================
@@ -0,0 +1,63 @@
+-- ============================================
+-- Migration 030: 審查期限、提醒與逾期上呈
+--
+-- 包含：
+-- 1. 審查指派期限（依工作日計算）與提醒、上呈紀錄
+-- 2. 行政預審期限
+-- 3. notification_type 新增 review_deadline
+-- 4. 既有未完成審查補上期限（預設值：審查 10 個工作日、預審 5 個工作日）
+-- ============================================
+
+-- ============================================
+-- 1. 審查指派期限
+-- ============================================
+
+ALTER TABLE review_assignments ADD COLUMN IF NOT EXISTS due_date DATE;
+-- 最近一次寄送期限提醒的日期（每日最多提醒一次）
+ALTER TABLE review_assignments ADD COLUMN IF NOT EXISTS last_reminded_on DATE;
+-- 逾期上呈主委的時間（僅上呈一次）
+ALTER TABLE review_assignments ADD COLUMN IF NOT EXISTS escalated_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_review_assignments_open_due
+    ON review_assignments(due_date) WHERE completed_at IS NULL;
+
+-- ============================================
+-- 2. 行政預審期限
+-- ============================================
+
+ALTER TABLE protocols ADD COLUMN IF NOT EXISTS pre_review_due_date DATE;
+ALTER TABLE protocols ADD COLUMN IF NOT EXISTS pre_review_escalated_at TIMESTAMPTZ;
+
+-- ============================================
+-- 3. 通知類型
+-- ============================================
+
+ALTER TYPE notification_type ADD VALUE IF NOT EXISTS 'review_deadline';
+
+-- ============================================
+-- 4. 既有資料補上期限（僅計週一至週五）
+-- ============================================
+
+UPDATE review_assignments ra
+SET due_date = (
+    SELECT d::date
+    FROM generate_series(ra.assigned_at::date + 1, ra.assigned_at::date + 30, INTERVAL '1 day') AS d
+    WHERE EXTRACT(ISODOW FROM d) < 6
+    ORDER BY d
+    OFFSET 9 LIMIT 1
+)
+WHERE ra.completed_at IS NULL AND ra.due_date IS NULL;
+
+UPDATE protocols p
+SET pre_review_due_date = (
+    SELECT d::date
+    FROM generate_series(p.updated_at::date + 1, p.updated_at::date + 30, INTERVAL '1 day') AS d
+    WHERE EXTRACT(ISODOW FROM d) < 6
+    ORDER BY d
+    OFFSET 4 LIMIT 1
+)
+WHERE p.status = 'PRE_REVIEW' AND p.pre_review_due_date IS NULL;
+
+-- ============================================
+-- 完成
+-- ============================================
@@ -0,0 +1,74 @@
+-- ============================================
+-- Migration 031: 計畫修正案
+--
+-- 包含：
+-- 1. 核准後計畫的修正案（人員、動物數量、試驗程序變更等），沿用計畫狀態
+-- 2. 審查指派與審查意見可對應修正案（沿用 review_assignments / review_comments）
+-- 3. 修正案核准後產生的計畫版本對應修正案
+-- ============================================
+
+-- ============================================
+-- 1. 修正案
+-- ============================================
+
+CREATE TABLE IF NOT EXISTS protocol_amendments (
+    id UUID PRIMARY KEY,
+    protocol_id UUID NOT NULL REFERENCES protocols(id) ON DELETE CASCADE,
+    -- 修正案編號：{IACUC No.}-A{序號:02}，例如 PIG-115001-A01
+    amendment_no VARCHAR(60) NOT NULL UNIQUE,
+    title VARCHAR(500) NOT NULL,
+    reason TEXT NOT NULL,
+    -- 變更類別：PERSONNEL / ANIMAL_NUMBER / PROCEDURE / OTHER
+    change_types VARCHAR(30)[] NOT NULL DEFAULT '{}',
+    status protocol_status NOT NULL DEFAULT 'DRAFT',
+    -- 建立修正案時的核准版本（比較基準）
+    base_version_id UUID NOT NULL REFERENCES protocol_versions(id),
+    proposed_content JSONB NOT NULL,
+    -- 提交次數（退回修正後重送遞增）
+    revision_no INTEGER NOT NULL DEFAULT 0,
+    submitted_at TIMESTAMPTZ,
+    decided_by UUID REFERENCES users(id),
+    decided_at TIMESTAMPTZ,
+    decision_remark TEXT,
+    -- 核准後產生的計畫版本
+    effective_version_id UUID REFERENCES protocol_versions(id),
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT chk_amendment_status
+        CHECK (status IN ('DRAFT', 'SUBMITTED', 'UNDER_REVIEW', 'REVISION_REQUIRED', 'RESUBMITTED', 'APPROVED', 'REJECTED', 'DELETED'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_protocol_amendments_protocol ON protocol_amendments(protocol_id, created_at DESC);
+
+-- 每個計畫同時僅能有一件進行中的修正案（確保核准時比較基準仍為現行版本）
+CREATE UNIQUE INDEX IF NOT EXISTS uq_protocol_amendments_open
+    ON protocol_amendments(protocol_id) WHERE status NOT IN ('APPROVED', 'REJECTED', 'DELETED');
+
+-- ============================================
+-- 2. 修正案審查
+-- ============================================
+
+ALTER TABLE review_assignments ADD COLUMN IF NOT EXISTS amendment_id UUID REFERENCES protocol_amendments(id) ON DELETE CASCADE;
+
+-- 計畫審查與修正案審查各自唯一
+ALTER TABLE review_assignments DROP CONSTRAINT IF EXISTS review_assignments_protocol_id_reviewer_id_key;
+CREATE UNIQUE INDEX IF NOT EXISTS uq_review_assignments_protocol
+    ON review_assignments(protocol_id, reviewer_id) WHERE amendment_id IS NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS uq_review_assignments_amendment
+    ON review_assignments(amendment_id, reviewer_id) WHERE amendment_id IS NOT NULL;
+
+-- 修正案審查意見的 protocol_version_id 為修正案的比較基準版本
+ALTER TABLE review_comments ADD COLUMN IF NOT EXISTS amendment_id UUID REFERENCES protocol_amendments(id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS idx_review_comments_amendment ON review_comments(amendment_id) WHERE amendment_id IS NOT NULL;
+
+-- ============================================
+-- 3. 修正案產生的計畫版本
+-- ============================================
+
+ALTER TABLE protocol_versions ADD COLUMN IF NOT EXISTS amendment_id UUID REFERENCES protocol_amendments(id);
+
+-- ============================================
+-- 完成
+-- ============================================
@@ -0,0 +1,47 @@
+-- ============================================
+-- Migration 032: 醫療資料匯出記錄
+--
+-- 包含：
+-- 1. 匯出類型與格式
+-- 2. 匯出記錄（保存產生的檔案供重新下載）
+-- ============================================
+
+-- ============================================
+-- 1. 匯出類型與格式
+-- ============================================
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'export_type') THEN
+        CREATE TYPE export_type AS ENUM ('medical_summary', 'observation_records', 'surgery_records', 'experiment_records');
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'export_format') THEN
+        CREATE TYPE export_format AS ENUM ('pdf', 'excel');
+    END IF;
+END
+$$;
+
+-- ============================================
+-- 2. 匯出記錄
+-- ============================================
+
+CREATE TABLE IF NOT EXISTS pig_export_records (
+    id UUID PRIMARY KEY,
+    -- 單隻豬匯出時為豬隻 ID，計畫匯出時為 NULL
+    pig_id INTEGER REFERENCES pigs(id) ON DELETE SET NULL,
+    iacuc_no VARCHAR(20),
+    export_type export_type NOT NULL,
+    export_format export_format NOT NULL,
+    -- 儲存空間中的檔案鍵值
+    file_path VARCHAR(500),
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_pig_export_records_pig ON pig_export_records(pig_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_pig_export_records_iacuc ON pig_export_records(iacuc_no, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_pig_export_records_file_path ON pig_export_records(file_path);
+
+-- ============================================
+-- 完成
+-- ============================================
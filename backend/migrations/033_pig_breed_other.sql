@@ -0,0 +1,8 @@
+-- ============================================
+-- Migration 033: 豬隻其他品種說明
+--
+-- 包含：
+-- 1. pigs.breed_other（品種為 other 時的說明；程式已讀寫此欄位，但先前未有 migration 建立）
+-- ============================================
+
+ALTER TABLE pigs ADD COLUMN IF NOT EXISTS breed_other VARCHAR(100);
@@ -0,0 +1,10 @@
+-- ============================================
+-- Migration 034: 病理報告簽核鎖定
+--
+-- 包含：
+-- 1. 病理報告新增鎖定欄位（簽核後附件不可新增、取代或刪除，與其他動物紀錄一致）
+-- ============================================
+
+ALTER TABLE pig_pathology_reports ADD COLUMN IF NOT EXISTS is_locked BOOLEAN DEFAULT false;
+ALTER TABLE pig_pathology_reports ADD COLUMN IF NOT EXISTS locked_at TIMESTAMPTZ;
+ALTER TABLE pig_pathology_reports ADD COLUMN IF NOT EXISTS locked_by UUID REFERENCES users(id);
@@ -1,5 +1,12 @@
+use std::collections::HashMap;
+
 use anyhow::Context;
 
+use rust_decimal::Decimal;
+
+use crate::models::{CostingMethod, ReviewVoteRule, WeightLossRule};
+use crate::services::{S3Settings, StorageKind};
+
 #[derive(Clone, Debug)]
 pub struct Config {
     pub host: String,
@@ -11,6 +18,11 @@ pub struct Config {
     pub jwt_secret: String,
     pub jwt_expiration_hours: i64,
     pub jwt_refresh_expiration_days: i64,
+    // Session settings
+    pub session_idle_timeout_minutes: i64,
+    pub session_cache_seconds: u64,
+    pub max_sessions_default: i64,
+    pub max_sessions_per_role: HashMap<String, i64>,
     // Email settings
     pub smtp_host: Option<String>,
     pub smtp_port: u16,
@@ -19,6 +31,32 @@ pub struct Config {
     pub smtp_from_email: String,
     pub smtp_from_name: String,
     pub app_url: String,
+    // Inventory settings
+    pub costing_method: CostingMethod,
+    // Animal settings
+    pub weight_loss_threshold_percent: Decimal,
+    pub weight_loss_window_days: i64,
+    // AUP settings
+    pub review_vote_rule: ReviewVoteRule,
+    /// 審查委員須於指派後幾個工作日內完成審查
+    pub review_due_working_days: i64,
+    /// 行政預審須於進入預審後幾個工作日內完成
+    pub pre_review_due_working_days: i64,
+    /// 期限前幾個工作日開始每日提醒審查委員
+    pub review_reminder_working_days: i64,
+    // HR settings
+    pub leave_escalation_hours: i64,
+    // Storage settings
+    pub storage_backend: StorageKind,
+    pub upload_dir: String,
+    pub s3_endpoint: Option<String>,
+    pub s3_bucket: Option<String>,
+    pub s3_region: String,
+    pub s3_access_key_id: Option<String>,
+    pub s3_secret_access_key: Option<String>,
+    pub s3_path_style: bool,
+    /// 已刪除、已取代附件版本的檔案保存天數（0 表示永不清除）
+    pub attachment_retention_days: i64,
     // Development settings
     pub seed_dev_users: bool,
 }
@@ -55,6 +93,23 @@ impl Config {
                 .unwrap_or_else(|_| "7".to_string())
                 .parse()
                 .context("JWT_REFRESH_EXPIRATION_DAYS must be a number")?,
+            // Session settings
+            session_idle_timeout_minutes: std::env::var("SESSION_IDLE_TIMEOUT_MINUTES")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .context("SESSION_IDLE_TIMEOUT_MINUTES must be a number")?,
+            session_cache_seconds: std::env::var("SESSION_CACHE_SECONDS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .context("SESSION_CACHE_SECONDS must be a number")?,
+            max_sessions_default: std::env::var("MAX_SESSIONS_DEFAULT")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .context("MAX_SESSIONS_DEFAULT must be a number")?,
+            max_sessions_per_role: parse_role_limits(
+                &std::env::var("MAX_SESSIONS_PER_ROLE").unwrap_or_default(),
+            )
+            .context("MAX_SESSIONS_PER_ROLE must look like ROLE=N,ROLE=N")?,
             // Email settings
             smtp_host: std::env::var("SMTP_HOST").ok(),
             smtp_port: std::env::var("SMTP_PORT")
@@ -69,6 +124,61 @@ impl Config {
                 .unwrap_or_else(|_| "ERP System".to_string()),
             app_url: std::env::var("APP_URL")
                 .unwrap_or_else(|_| "http://localhost".to_string()),
+            costing_method: std::env::var("COSTING_METHOD")
+                .unwrap_or_else(|_| "fifo".to_string())
+                .parse()
+                .map_err(|e: String| anyhow::anyhow!(e))
+                .context("COSTING_METHOD must be fifo or weighted_average")?,
+            weight_loss_threshold_percent: std::env::var("WEIGHT_LOSS_THRESHOLD_PERCENT")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .context("WEIGHT_LOSS_THRESHOLD_PERCENT must be a number")?,
+            weight_loss_window_days: std::env::var("WEIGHT_LOSS_WINDOW_DAYS")
+                .unwrap_or_else(|_| "14".to_string())
+                .parse()
+                .context("WEIGHT_LOSS_WINDOW_DAYS must be a number")?,
+            review_vote_rule: std::env::var("REVIEW_VOTE_RULE")
+                .unwrap_or_else(|_| "majority".to_string())
+                .parse()
+                .map_err(|e: String| anyhow::anyhow!(e))
+                .context("REVIEW_VOTE_RULE must be unanimous or majority")?,
+            review_due_working_days: std::env::var("REVIEW_DUE_WORKING_DAYS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .context("REVIEW_DUE_WORKING_DAYS must be a number")?,
+            pre_review_due_working_days: std::env::var("PRE_REVIEW_DUE_WORKING_DAYS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .context("PRE_REVIEW_DUE_WORKING_DAYS must be a number")?,
+            review_reminder_working_days: std::env::var("REVIEW_REMINDER_WORKING_DAYS")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()
+                .context("REVIEW_REMINDER_WORKING_DAYS must be a number")?,
+            leave_escalation_hours: std::env::var("LEAVE_ESCALATION_HOURS")
+                .unwrap_or_else(|_| "48".to_string())
+                .parse()
+                .context("LEAVE_ESCALATION_HOURS must be a number")?,
+            // Storage settings
+            storage_backend: std::env::var("STORAGE_BACKEND")
+                .unwrap_or_else(|_| "local".to_string())
+                .parse()
+                .map_err(|e: String| anyhow::anyhow!(e))
+                .context("STORAGE_BACKEND must be local or s3")?,
+            upload_dir: std::env::var("UPLOAD_DIR")
+                .unwrap_or_else(|_| "./uploads".to_string()),
+            s3_endpoint: std::env::var("S3_ENDPOINT").ok().filter(|v| !v.is_empty()),
+            s3_bucket: std::env::var("S3_BUCKET").ok().filter(|v| !v.is_empty()),
+            s3_region: std::env::var("S3_REGION")
+                .unwrap_or_else(|_| "us-east-1".to_string()),
+            s3_access_key_id: std::env::var("S3_ACCESS_KEY_ID").ok().filter(|v| !v.is_empty()),
+            s3_secret_access_key: std::env::var("S3_SECRET_ACCESS_KEY").ok().filter(|v| !v.is_empty()),
+            s3_path_style: std::env::var("S3_PATH_STYLE")
+                .map(|v| v.to_lowercase() == "true" || v == "1")
+                .unwrap_or(false),
+            attachment_retention_days: std::env::var("ATTACHMENT_RETENTION_DAYS")
+                .unwrap_or_else(|_| "2555".to_string())
+                .parse()
+                .context("ATTACHMENT_RETENTION_DAYS must be a number")?,
             seed_dev_users: std::env::var("SEED_DEV_USERS")
                 .map(|v| v.to_lowercase() == "true" || v == "1")
                 .unwrap_or(false),
@@ -78,4 +188,41 @@ impl Config {
     pub fn is_email_enabled(&self) -> bool {
         self.smtp_host.is_some()
     }
+
+    pub fn weight_loss_rule(&self) -> WeightLossRule {
+        WeightLossRule {
+            threshold_percent: self.weight_loss_threshold_percent,
+            window_days: self.weight_loss_window_days,
+        }
+    }
+
+    pub fn s3_settings(&self) -> anyhow::Result<S3Settings> {
+        Ok(S3Settings {
+            endpoint: self.s3_endpoint.clone().context("S3_ENDPOINT must be set")?,
+            bucket: self.s3_bucket.clone().context("S3_BUCKET must be set")?,
+            region: self.s3_region.clone(),
+            access_key_id: self.s3_access_key_id.clone().context("S3_ACCESS_KEY_ID must be set")?,
+            secret_access_key: self
+                .s3_secret_access_key
+                .clone()
+                .context("S3_SECRET_ACCESS_KEY must be set")?,
+            path_style: self.s3_path_style,
+        })
+    }
+}
+
+/// 解析 `ROLE=N,ROLE=N` 格式的角色上限設定
+fn parse_role_limits(value: &str) -> anyhow::Result<HashMap<String, i64>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (role, limit) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("invalid entry: {}", entry))?;
+            let limit = limit.trim().parse::<i64>()?;
+            Ok((role.trim().to_string(), limit))
+        })
+        .collect()
 }
@@ -20,9 +20,17 @@ use crate::{
         UpdateVaccinationRequest, CopyRecordRequest, VersionHistoryResponse,
         CreateVetRecommendationWithAttachmentsRequest, ExportRequest, PigImportBatch, ObservationListItem, SurgeryListItem, ImportResult,
         DeleteRequest,  // GLP: 刪除請求含原因
+        PigExportRecord, PaginatedResponse, PigTimelineEvent, PigTimelineQuery,
+        CreateWelfareScoreRequest, CreateWelfareScoreSheetRequest, PigWelfareScore,
+        UpdateWelfareScoreSheetRequest, UpdateWelfareScoreWithReasonRequest, WelfareScoreSheet,
+        WelfareScoreSheetQuery, PigGrowthAnalytics, PigWeightAlert, ProtocolGrowthStats,
+        WeightLossCheckRequest, WeightLossQuery,
     },
     require_permission,
-    services::AnimalService,
+    services::{
+        AccessService, AnimalService, ExportFile, MedicalExportService, PigRecord, WeightAnalyticsService,
+        WelfareService,
+    },
     AppError, AppState, Result,
 };
 use axum::extract::Multipart;
@@ -88,32 +96,11 @@ pub async fn list_pigs(
     Extension(current_user): Extension<CurrentUser>,
     Query(query): Query<PigQuery>,
 ) -> Result<Json<Vec<PigListItem>>> {
-    // 檢查權限
-    let has_view_all = current_user.has_permission("animal.info.view_all");
-    let has_view_project = current_user.has_permission("animal.info.view_project");
-    
-    if !has_view_all && !has_view_project {
-        // 如果沒有查看權限，返回空列表
-        // 這裡不拋出錯誤，而是返回空列表，避免洩露權限資訊
-        return Ok(Json(vec![]));
-    }
-    
-    // 如果只有 view_project 權限而沒有 view_all，則只能查看有 iacuc_no 的豬
-    // 即只能查看屬於專案的豬，不能查看沒有 iacuc_no 的豬
-    let pigs = AnimalService::list(&state.db, &query).await?;
-    
-    // 如果只有 view_project 權限，則過濾出有 iacuc_no 的豬
-    // 即只返回屬於專案的豬，過濾掉沒有 iacuc_no 的豬
-    let filtered_pigs = if has_view_all {
-        pigs
-    } else {
-        // 過濾出有 iacuc_no 的豬，只顯示屬於專案的豬
-        pigs.into_iter()
-            .filter(|pig| pig.iacuc_no.is_some())
-            .collect()
-    };
-    
-    Ok(Json(filtered_pigs))
+    // 外部使用者僅能查看參與計畫的豬隻；無查看權限者回傳空列表，避免洩露權限資訊
+    let scope = AccessService::data_scope(&state.db, &current_user).await?;
+    let pigs = AnimalService::list(&state.db, &query, &scope).await?;
+
+    Ok(Json(pigs))
 }
 
 /// 按欄位列出所有豬
@@ -123,16 +110,18 @@ pub async fn list_pigs_by_pen(
 ) -> Result<Json<Vec<PigsByPen>>> {
     require_permission!(current_user, "animal.info.view_all");
     
-    let pigs = AnimalService::list_by_pen(&state.db).await?;
+    let scope = AccessService::data_scope(&state.db, &current_user).await?;
+    let pigs = AnimalService::list_by_pen(&state.db, &scope).await?;
     Ok(Json(pigs))
 }
 
 /// 取得單個豬的詳細資訊
 pub async fn get_pig(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(id): Path<i32>,
 ) -> Result<Json<Pig>> {
+    AccessService::ensure_pig(&state.db, &current_user, id).await?;
     let pig = AnimalService::get_by_id(&state.db, id).await?;
     Ok(Json(pig))
 }
@@ -186,6 +175,7 @@ pub async fn update_pig(
     Json(req): Json<UpdatePigRequest>,
 ) -> Result<Json<Pig>> {
     require_permission!(current_user, "animal.info.edit");
+    AccessService::ensure_pig(&state.db, &current_user, id).await?;
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
     
     let pig = AnimalService::update(&state.db, id, &req).await?;
@@ -200,6 +190,7 @@ pub async fn delete_pig(
     Json(req): Json<DeleteRequest>,
 ) -> Result<Json<serde_json::Value>> {
     require_permission!(current_user, "animal.info.edit");
+    AccessService::ensure_pig(&state.db, &current_user, id).await?;
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
     
     AnimalService::delete_with_reason(&state.db, id, &req.reason, current_user.id).await?;
@@ -213,6 +204,10 @@ pub async fn batch_assign_pigs(
     Json(req): Json<BatchAssignRequest>,
 ) -> Result<Json<Vec<Pig>>> {
     require_permission!(current_user, "animal.info.assign");
+    AccessService::ensure_project(&state.db, &current_user, &req.iacuc_no).await?;
+    for pig_id in &req.pig_ids {
+        AccessService::ensure_pig(&state.db, &current_user, *pig_id).await?;
+    }
     
     let pigs = AnimalService::batch_assign(&state.db, &req).await?;
     Ok(Json(pigs))
@@ -225,6 +220,9 @@ pub async fn batch_start_experiment(
     Json(req): Json<BatchStartExperimentRequest>,
 ) -> Result<Json<Vec<Pig>>> {
     require_permission!(current_user, "animal.info.edit");
+    for pig_id in &req.pig_ids {
+        AccessService::ensure_pig(&state.db, &current_user, *pig_id).await?;
+    }
     
     let pigs = AnimalService::batch_start_experiment(&state.db, &req).await?;
     Ok(Json(pigs))
@@ -237,6 +235,7 @@ pub async fn mark_pig_vet_read(
     Path(id): Path<i32>,
 ) -> Result<Json<serde_json::Value>> {
     require_permission!(current_user, "animal.vet.read");
+    AccessService::ensure_pig(&state.db, &current_user, id).await?;
     
     AnimalService::mark_vet_read(&state.db, id).await?;
     Ok(Json(serde_json::json!({ "message": "Marked as read" })))
@@ -249,9 +248,10 @@ pub async fn mark_pig_vet_read(
 /// 列出豬的所有觀察記錄
 pub async fn list_pig_observations(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(pig_id): Path<i32>,
 ) -> Result<Json<Vec<PigObservation>>> {
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
     let observations = AnimalService::list_observations(&state.db, pig_id).await?;
     Ok(Json(observations))
 }
@@ -259,9 +259,10 @@ pub async fn list_pig_observations(
 /// 列出豬的觀察記錄（包含獸醫建議）
 pub async fn list_pig_observations_with_recommendations(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(pig_id): Path<i32>,
 ) -> Result<Json<Vec<ObservationListItem>>> {
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
     let observations = AnimalService::list_observations_with_recommendations(&state.db, pig_id).await?;
     Ok(Json(observations))
 }
@@ -269,9 +270,10 @@ pub async fn list_pig_observations_with_recommendations(
 /// 取得單個觀察記錄
 pub async fn get_pig_observation(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(id): Path<i32>,
 ) -> Result<Json<PigObservation>> {
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Observation, id).await?;
     let observation = AnimalService::get_observation_by_id(&state.db, id).await?;
     Ok(Json(observation))
 }
@@ -284,6 +286,7 @@ pub async fn create_pig_observation(
     Json(req): Json<CreateObservationRequest>,
 ) -> Result<Json<PigObservation>> {
     require_permission!(current_user, "animal.record.create");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
     
     let observation = AnimalService::create_observation(&state.db, pig_id, &req, current_user.id).await?;
@@ -298,6 +301,7 @@ pub async fn update_pig_observation(
     Json(req): Json<UpdateObservationRequest>,
 ) -> Result<Json<PigObservation>> {
     require_permission!(current_user, "animal.record.edit");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Observation, id).await?;
     
     let observation = AnimalService::update_observation(&state.db, id, &req, current_user.id).await?;
     Ok(Json(observation))
@@ -311,6 +315,7 @@ pub async fn delete_pig_observation(
     Json(req): Json<DeleteRequest>,
 ) -> Result<Json<serde_json::Value>> {
     require_permission!(current_user, "animal.record.delete");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Observation, id).await?;
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
     
     AnimalService::soft_delete_observation_with_reason(&state.db, id, &req.reason, current_user.id).await?;
@@ -325,6 +330,8 @@ pub async fn copy_pig_observation(
     Json(req): Json<CopyRecordRequest>,
 ) -> Result<Json<PigObservation>> {
     require_permission!(current_user, "animal.record.copy");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Observation, req.source_id).await?;
     
     let observation = AnimalService::copy_observation(&state.db, pig_id, req.source_id, current_user.id).await?;
     Ok(Json(observation))
@@ -337,6 +344,7 @@ pub async fn mark_observation_vet_read(
     Path(id): Path<i32>,
 ) -> Result<Json<serde_json::Value>> {
     require_permission!(current_user, "animal.vet.read");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Observation, id).await?;
     
     AnimalService::mark_observation_vet_read(&state.db, id, current_user.id).await?;
     Ok(Json(serde_json::json!({ "message": "Marked as read" })))
@@ -345,9 +353,10 @@ pub async fn mark_observation_vet_read(
 /// 取得觀察記錄的版本歷史
 pub async fn get_observation_versions(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(id): Path<i32>,
 ) -> Result<Json<VersionHistoryResponse>> {
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Observation, id).await?;
     let versions = AnimalService::get_record_versions(&state.db, "observation", id).await?;
     Ok(Json(versions))
 }
@@ -359,9 +368,10 @@ pub async fn get_observation_versions(
 /// 列出豬的所有手術記錄
 pub async fn list_pig_surgeries(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(pig_id): Path<i32>,
 ) -> Result<Json<Vec<PigSurgery>>> {
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
     let surgeries = AnimalService::list_surgeries(&state.db, pig_id).await?;
     Ok(Json(surgeries))
 }
@@ -369,9 +379,10 @@ pub async fn list_pig_surgeries(
 /// 列出豬的手術記錄（包含獸醫建議）
 pub async fn list_pig_surgeries_with_recommendations(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(pig_id): Path<i32>,
 ) -> Result<Json<Vec<SurgeryListItem>>> {
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
     let surgeries = AnimalService::list_surgeries_with_recommendations(&state.db, pig_id).await?;
     Ok(Json(surgeries))
 }
@@ -379,9 +390,10 @@ pub async fn list_pig_surgeries_with_recommendations(
 /// 取得單個手術記錄
 pub async fn get_pig_surgery(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(id): Path<i32>,
 ) -> Result<Json<PigSurgery>> {
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Surgery, id).await?;
     let surgery = AnimalService::get_surgery_by_id(&state.db, id).await?;
     Ok(Json(surgery))
 }
@@ -394,6 +406,7 @@ pub async fn create_pig_surgery(
     Json(req): Json<CreateSurgeryRequest>,
 ) -> Result<Json<PigSurgery>> {
     require_permission!(current_user, "animal.record.create");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
     
     let surgery = AnimalService::create_surgery(&state.db, pig_id, &req, current_user.id).await?;
@@ -408,6 +421,7 @@ pub async fn update_pig_surgery(
     Json(req): Json<UpdateSurgeryRequest>,
 ) -> Result<Json<PigSurgery>> {
     require_permission!(current_user, "animal.record.edit");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Surgery, id).await?;
     
     let surgery = AnimalService::update_surgery(&state.db, id, &req, current_user.id).await?;
     Ok(Json(surgery))
@@ -421,6 +435,7 @@ pub async fn delete_pig_surgery(
     Json(req): Json<DeleteRequest>,
 ) -> Result<Json<serde_json::Value>> {
     require_permission!(current_user, "animal.record.delete");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Surgery, id).await?;
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
     
     AnimalService::soft_delete_surgery_with_reason(&state.db, id, &req.reason, current_user.id).await?;
@@ -435,6 +450,8 @@ pub async fn copy_pig_surgery(
     Json(req): Json<CopyRecordRequest>,
 ) -> Result<Json<PigSurgery>> {
     require_permission!(current_user, "animal.record.copy");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Surgery, req.source_id).await?;
     
     let surgery = AnimalService::copy_surgery(&state.db, pig_id, req.source_id, current_user.id).await?;
     Ok(Json(surgery))
@@ -447,6 +464,7 @@ pub async fn mark_surgery_vet_read(
     Path(id): Path<i32>,
 ) -> Result<Json<serde_json::Value>> {
     require_permission!(current_user, "animal.vet.read");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Surgery, id).await?;
     
     AnimalService::mark_surgery_vet_read(&state.db, id, current_user.id).await?;
     Ok(Json(serde_json::json!({ "message": "Marked as read" })))
@@ -455,9 +473,10 @@ pub async fn mark_surgery_vet_read(
 /// 取得手術記錄的版本歷史
 pub async fn get_surgery_versions(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(id): Path<i32>,
 ) -> Result<Json<VersionHistoryResponse>> {
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Surgery, id).await?;
     let versions = AnimalService::get_record_versions(&state.db, "surgery", id).await?;
     Ok(Json(versions))
 }
@@ -469,9 +488,10 @@ pub async fn get_surgery_versions(
 /// 列出豬的所有體重記錄
 pub async fn list_pig_weights(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(pig_id): Path<i32>,
 ) -> Result<Json<Vec<PigWeight>>> {
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
     let weights = AnimalService::list_weights(&state.db, pig_id).await?;
     Ok(Json(weights))
 }
@@ -484,8 +504,15 @@ pub async fn create_pig_weight(
     Json(req): Json<CreateWeightRequest>,
 ) -> Result<Json<PigWeight>> {
     require_permission!(current_user, "animal.record.create");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
     
     let weight = AnimalService::create_weight(&state.db, pig_id, &req, current_user.id).await?;
+
+    // 新增體重後檢查是否異常下降（警示失敗不影響紀錄建立）
+    if let Err(e) = WeightAnalyticsService::check_weight_loss(&state.db, &[pig_id], state.config.weight_loss_rule()).await {
+        tracing::warn!("Weight loss check failed for pig {}: {}", pig_id, e);
+    }
+
     Ok(Json(weight))
 }
 
@@ -497,8 +524,15 @@ pub async fn update_pig_weight(
     Json(req): Json<UpdateWeightRequest>,
 ) -> Result<Json<PigWeight>> {
     require_permission!(current_user, "animal.record.edit");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Weight, id).await?;
     
     let weight = AnimalService::update_weight(&state.db, id, &req).await?;
+
+    // 修正體重後重新檢查警示（警示失敗不影響紀錄更新）
+    if let Err(e) = WeightAnalyticsService::recheck_weight(&state.db, weight.pig_id, weight.id, state.config.weight_loss_rule()).await {
+        tracing::warn!("Weight loss check failed for pig {}: {}", weight.pig_id, e);
+    }
+
     Ok(Json(weight))
 }
 
@@ -510,12 +544,72 @@ pub async fn delete_pig_weight(
     Json(req): Json<DeleteRequest>,
 ) -> Result<Json<serde_json::Value>> {
     require_permission!(current_user, "animal.record.delete");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Weight, id).await?;
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
     
     AnimalService::soft_delete_weight_with_reason(&state.db, id, &req.reason, current_user.id).await?;
     Ok(Json(serde_json::json!({ "message": "Weight record deleted successfully" })))
 }
 
+/// 取得豬隻生長分析（平均日增重、進場體重變化、群組偏差）
+pub async fn get_pig_growth(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(pig_id): Path<i32>,
+    Query(query): Query<WeightLossQuery>,
+) -> Result<Json<PigGrowthAnalytics>> {
+    require_permission!(current_user, "animal.record.view");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
+
+    let rule = query.rule_or(state.config.weight_loss_rule());
+    let growth = WeightAnalyticsService::pig_growth(&state.db, pig_id, rule).await?;
+    Ok(Json(growth))
+}
+
+/// 取得計畫生長統計
+pub async fn get_project_growth(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(iacuc_no): Path<String>,
+    Query(query): Query<WeightLossQuery>,
+) -> Result<Json<ProtocolGrowthStats>> {
+    require_permission!(current_user, "animal.record.view");
+    AccessService::ensure_project(&state.db, &current_user, &iacuc_no).await?;
+
+    let rule = query.rule_or(state.config.weight_loss_rule());
+    let stats = WeightAnalyticsService::protocol_growth(&state.db, &iacuc_no, rule).await?;
+    Ok(Json(stats))
+}
+
+/// 列出豬隻體重下降警示
+pub async fn list_pig_weight_alerts(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(pig_id): Path<i32>,
+) -> Result<Json<Vec<PigWeightAlert>>> {
+    require_permission!(current_user, "animal.record.view");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
+
+    let alerts = WeightAnalyticsService::list_alerts(&state.db, pig_id).await?;
+    Ok(Json(alerts))
+}
+
+/// 手動執行體重下降檢查（新警示會通知獸醫師）
+pub async fn check_weight_loss(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Json(req): Json<WeightLossCheckRequest>,
+) -> Result<Json<Vec<PigWeightAlert>>> {
+    require_permission!(current_user, "animal.record.edit");
+    // 外部使用者須指定參與中的計畫
+    AccessService::data_scope(&state.db, &current_user)
+        .await?
+        .ensure(req.iacuc_no.as_deref(), "Project not found")?;
+
+    let alerts = WeightAnalyticsService::check_all(&state.db, req.iacuc_no.as_deref(), state.config.weight_loss_rule()).await?;
+    Ok(Json(alerts))
+}
+
 // ============================================
 // 疫苗接種記錄管理
 // ============================================
@@ -523,9 +617,10 @@ pub async fn delete_pig_weight(
 /// 列出豬的所有疫苗接種記錄
 pub async fn list_pig_vaccinations(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(pig_id): Path<i32>,
 ) -> Result<Json<Vec<PigVaccination>>> {
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
     let vaccinations = AnimalService::list_vaccinations(&state.db, pig_id).await?;
     Ok(Json(vaccinations))
 }
@@ -538,6 +633,7 @@ pub async fn create_pig_vaccination(
     Json(req): Json<CreateVaccinationRequest>,
 ) -> Result<Json<PigVaccination>> {
     require_permission!(current_user, "animal.record.create");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
     
     let vaccination = AnimalService::create_vaccination(&state.db, pig_id, &req, current_user.id).await?;
     Ok(Json(vaccination))
@@ -551,6 +647,7 @@ pub async fn update_pig_vaccination(
     Json(req): Json<UpdateVaccinationRequest>,
 ) -> Result<Json<PigVaccination>> {
     require_permission!(current_user, "animal.record.edit");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Vaccination, id).await?;
     
     let vaccination = AnimalService::update_vaccination(&state.db, id, &req).await?;
     Ok(Json(vaccination))
@@ -564,6 +661,7 @@ pub async fn delete_pig_vaccination(
     Json(req): Json<DeleteRequest>,
 ) -> Result<Json<serde_json::Value>> {
     require_permission!(current_user, "animal.record.delete");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Vaccination, id).await?;
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
     
     AnimalService::soft_delete_vaccination_with_reason(&state.db, id, &req.reason, current_user.id).await?;
@@ -577,9 +675,10 @@ pub async fn delete_pig_vaccination(
 /// 取得豬的犧牲記錄
 pub async fn get_pig_sacrifice(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(pig_id): Path<i32>,
 ) -> Result<Json<Option<PigSacrifice>>> {
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
     let sacrifice = AnimalService::get_sacrifice(&state.db, pig_id).await?;
     Ok(Json(sacrifice))
 }
@@ -592,6 +691,7 @@ pub async fn upsert_pig_sacrifice(
     Json(req): Json<CreateSacrificeRequest>,
 ) -> Result<Json<PigSacrifice>> {
     require_permission!(current_user, "animal.record.create");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
     
     let sacrifice = AnimalService::upsert_sacrifice(&state.db, pig_id, &req, current_user.id).await?;
     Ok(Json(sacrifice))
@@ -609,6 +709,7 @@ pub async fn add_observation_vet_recommendation(
     Json(req): Json<CreateVetRecommendationRequest>,
 ) -> Result<Json<VetRecommendation>> {
     require_permission!(current_user, "animal.vet.recommend");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Observation, id).await?;
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
     
     let recommendation = AnimalService::add_vet_recommendation(&state.db, VetRecordType::Observation, id, &req, current_user.id).await?;
@@ -623,6 +724,7 @@ pub async fn add_surgery_vet_recommendation(
     Json(req): Json<CreateVetRecommendationRequest>,
 ) -> Result<Json<VetRecommendation>> {
     require_permission!(current_user, "animal.vet.recommend");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Surgery, id).await?;
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
     
     let recommendation = AnimalService::add_vet_recommendation(&state.db, VetRecordType::Surgery, id, &req, current_user.id).await?;
@@ -638,6 +740,7 @@ pub async fn add_observation_vet_recommendation_with_attachments(
 ) -> Result<Json<VetRecommendation>> {
     require_permission!(current_user, "animal.vet.recommend");
     require_permission!(current_user, "animal.vet.upload_attachment");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Observation, id).await?;
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
     
     let recommendation = AnimalService::add_vet_recommendation_with_attachments(&state.db, VetRecordType::Observation, id, &req, current_user.id).await?;
@@ -653,6 +756,7 @@ pub async fn add_surgery_vet_recommendation_with_attachments(
 ) -> Result<Json<VetRecommendation>> {
     require_permission!(current_user, "animal.vet.recommend");
     require_permission!(current_user, "animal.vet.upload_attachment");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Surgery, id).await?;
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
     
     let recommendation = AnimalService::add_vet_recommendation_with_attachments(&state.db, VetRecordType::Surgery, id, &req, current_user.id).await?;
@@ -662,9 +766,10 @@ pub async fn add_surgery_vet_recommendation_with_attachments(
 /// 取得觀察記錄的所有獸醫建議
 pub async fn get_observation_vet_recommendations(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(id): Path<i32>,
 ) -> Result<Json<Vec<VetRecommendation>>> {
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Observation, id).await?;
     let recommendations = AnimalService::get_vet_recommendations(&state.db, VetRecordType::Observation, id).await?;
     Ok(Json(recommendations))
 }
@@ -672,76 +777,319 @@ pub async fn get_observation_vet_recommendations(
 /// 取得手術記錄的所有獸醫建議
 pub async fn get_surgery_vet_recommendations(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(id): Path<i32>,
 ) -> Result<Json<Vec<VetRecommendation>>> {
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::Surgery, id).await?;
     let recommendations = AnimalService::get_vet_recommendations(&state.db, VetRecordType::Surgery, id).await?;
     Ok(Json(recommendations))
 }
 
+// ============================================
+// 疼痛 / 福祉評分
+// ============================================
+
+/// 列出福祉評分表
+pub async fn list_welfare_score_sheets(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Query(query): Query<WelfareScoreSheetQuery>,
+) -> Result<Json<Vec<WelfareScoreSheet>>> {
+    require_permission!(current_user, "animal.record.view");
+
+    let scope = AccessService::data_scope(&state.db, &current_user).await?;
+    let sheets = WelfareService::list_sheets(&state.db, &query, &scope).await?;
+    Ok(Json(sheets))
+}
+
+/// 取得單一福祉評分表
+pub async fn get_welfare_score_sheet(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<WelfareScoreSheet>> {
+    require_permission!(current_user, "animal.record.view");
+    AccessService::ensure_welfare_sheet(&state.db, &current_user, id, true).await?;
+
+    let sheet = WelfareService::get_sheet(&state.db, id).await?;
+    Ok(Json(sheet))
+}
+
+/// 建立福祉評分表
+pub async fn create_welfare_score_sheet(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Json(req): Json<CreateWelfareScoreSheetRequest>,
+) -> Result<Json<WelfareScoreSheet>> {
+    require_permission!(current_user, "animal.welfare.manage");
+    AccessService::ensure_sheet_protocol(&state.db, &current_user, req.protocol_id, false).await?;
+    req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let sheet = WelfareService::create_sheet(&state.db, &req, current_user.id).await?;
+    Ok(Json(sheet))
+}
+
+/// 更新福祉評分表
+pub async fn update_welfare_score_sheet(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<UpdateWelfareScoreSheetRequest>,
+) -> Result<Json<WelfareScoreSheet>> {
+    require_permission!(current_user, "animal.welfare.manage");
+    AccessService::ensure_welfare_sheet(&state.db, &current_user, id, false).await?;
+    req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let sheet = WelfareService::update_sheet(&state.db, id, &req).await?;
+    Ok(Json(sheet))
+}
+
+/// 停用福祉評分表
+pub async fn deactivate_welfare_score_sheet(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    require_permission!(current_user, "animal.welfare.manage");
+    AccessService::ensure_welfare_sheet(&state.db, &current_user, id, false).await?;
+
+    WelfareService::deactivate_sheet(&state.db, id).await?;
+    Ok(Json(serde_json::json!({ "message": "Welfare score sheet deactivated" })))
+}
+
+/// 列出豬的福祉評分紀錄
+pub async fn list_pig_welfare_scores(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(pig_id): Path<i32>,
+) -> Result<Json<Vec<PigWelfareScore>>> {
+    require_permission!(current_user, "animal.record.view");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
+
+    let scores = WelfareService::list_by_pig(&state.db, pig_id).await?;
+    Ok(Json(scores))
+}
+
+/// 取得單個福祉評分紀錄
+pub async fn get_pig_welfare_score(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<i32>,
+) -> Result<Json<PigWelfareScore>> {
+    require_permission!(current_user, "animal.record.view");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::WelfareScore, id).await?;
+
+    let score = WelfareService::get_by_id(&state.db, id).await?;
+    Ok(Json(score))
+}
+
+/// 建立福祉評分紀錄（達人道終點時通知獸醫師與 PI）
+pub async fn create_pig_welfare_score(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(pig_id): Path<i32>,
+    Json(req): Json<CreateWelfareScoreRequest>,
+) -> Result<Json<PigWelfareScore>> {
+    require_permission!(current_user, "animal.record.create");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
+    req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let score = WelfareService::create_score(&state.db, pig_id, &req, current_user.id).await?;
+    Ok(Json(score))
+}
+
+/// 更新福祉評分紀錄（須填寫變更原因）- GLP 合規
+pub async fn update_pig_welfare_score(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<i32>,
+    Json(req): Json<UpdateWelfareScoreWithReasonRequest>,
+) -> Result<Json<PigWelfareScore>> {
+    require_permission!(current_user, "animal.record.edit");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::WelfareScore, id).await?;
+    req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let score = WelfareService::update_score(&state.db, id, &req.data, &req.change_reason, current_user.id).await?;
+    Ok(Json(score))
+}
+
+/// 刪除福祉評分紀錄（軟刪除 + 刪除原因）- GLP 合規
+pub async fn delete_pig_welfare_score(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<i32>,
+    Json(req): Json<DeleteRequest>,
+) -> Result<Json<serde_json::Value>> {
+    require_permission!(current_user, "animal.record.delete");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::WelfareScore, id).await?;
+    req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+    WelfareService::soft_delete_with_reason(&state.db, id, &req.reason, current_user.id).await?;
+    Ok(Json(serde_json::json!({ "message": "Welfare score deleted successfully" })))
+}
+
+/// 標記福祉評分為獸醫已讀
+pub async fn mark_welfare_score_vet_read(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<i32>,
+) -> Result<Json<serde_json::Value>> {
+    require_permission!(current_user, "animal.vet.read");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::WelfareScore, id).await?;
+
+    WelfareService::mark_vet_read(&state.db, id).await?;
+    Ok(Json(serde_json::json!({ "message": "Marked as read" })))
+}
+
+/// 取得福祉評分的版本歷史
+pub async fn get_welfare_score_versions(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<i32>,
+) -> Result<Json<VersionHistoryResponse>> {
+    require_permission!(current_user, "animal.record.view");
+    AccessService::ensure_pig_record(&state.db, &current_user, PigRecord::WelfareScore, id).await?;
+
+    let versions = AnimalService::get_record_versions(&state.db, "welfare_score", id).await?;
+    Ok(Json(versions))
+}
+
+// ============================================
+// 臨床時間軸
+// ============================================
+
+/// 取得豬隻臨床時間軸
+pub async fn get_pig_timeline(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<i32>,
+    Query(query): Query<PigTimelineQuery>,
+) -> Result<Json<PaginatedResponse<PigTimelineEvent>>> {
+    require_permission!(current_user, "animal.record.view");
+    AccessService::ensure_pig(&state.db, &current_user, id).await?;
+    
+    let timeline = AnimalService::get_timeline(&state.db, id, &query).await?;
+    Ok(Json(timeline))
+}
+
 // ============================================
 // 匯入匯出
 // ============================================
 
-/// 匯出豬的醫療資料
+/// 匯出豬的醫療資料（後端產生 PDF / Excel 並存檔）
 pub async fn export_pig_medical_data(
     State(state): State<AppState>,
     Extension(current_user): Extension<CurrentUser>,
     Path(pig_id): Path<i32>,
     Json(req): Json<ExportRequest>,
-) -> Result<Json<serde_json::Value>> {
+) -> Result<Response> {
     require_permission!(current_user, "animal.export.medical");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
     
-    // 取得醫療資料
-    let data = AnimalService::get_pig_medical_data(&state.db, pig_id).await?;
-    
-    // 建立匯出記錄
-    let _record = AnimalService::create_export_record(
+    let file = MedicalExportService::export_pig(
         &state.db,
-        Some(pig_id),
-        None,
+        pig_id,
         req.export_type,
         req.format,
-        None,
         current_user.id,
     ).await?;
     
-    // 返回資料，前端負責轉換為 PDF/Excel 格式並下載
-    Ok(Json(serde_json::json!({
-        "data": data,
-        "format": req.format,
-        "export_type": req.export_type,
-    })))
+    export_file_response(file)
 }
 
-/// 匯出專案的醫療資料
+/// 匯出專案的醫療資料（後端產生 PDF / Excel 並存檔）
 pub async fn export_project_medical_data(
     State(state): State<AppState>,
     Extension(current_user): Extension<CurrentUser>,
     Path(iacuc_no): Path<String>,
     Json(req): Json<ExportRequest>,
-) -> Result<Json<serde_json::Value>> {
+) -> Result<Response> {
     require_permission!(current_user, "animal.export.medical");
+    AccessService::ensure_project(&state.db, &current_user, &iacuc_no).await?;
     
-    // 取得專案下所有豬的醫療資料
-    let data = AnimalService::get_project_medical_data(&state.db, &iacuc_no).await?;
-    
-    // 建立匯出記錄
-    let _record = AnimalService::create_export_record(
+    let file = MedicalExportService::export_project(
         &state.db,
-        None,
-        Some(&iacuc_no),
+        &iacuc_no,
         req.export_type,
         req.format,
-        None,
         current_user.id,
     ).await?;
     
-    Ok(Json(serde_json::json!({
-        "data": data,
-        "format": req.format,
-        "export_type": req.export_type,
-    })))
+    export_file_response(file)
+}
+
+/// 列出豬的匯出記錄
+pub async fn list_pig_export_records(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(pig_id): Path<i32>,
+) -> Result<Json<Vec<PigExportRecord>>> {
+    require_permission!(current_user, "animal.export.medical");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
+    
+    let records = AnimalService::list_export_records(&state.db, Some(pig_id), None).await?;
+    Ok(Json(records))
+}
+
+/// 列出專案的匯出記錄
+pub async fn list_project_export_records(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(iacuc_no): Path<String>,
+) -> Result<Json<Vec<PigExportRecord>>> {
+    require_permission!(current_user, "animal.export.medical");
+    AccessService::ensure_project(&state.db, &current_user, &iacuc_no).await?;
+    
+    let records = AnimalService::list_export_records(&state.db, None, Some(&iacuc_no)).await?;
+    Ok(Json(records))
+}
+
+/// 重新下載匯出檔案
+pub async fn download_export_record(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Response> {
+    require_permission!(current_user, "animal.export.medical");
+
+    let record = AnimalService::get_export_record(&state.db, id).await?;
+    match (&record.iacuc_no, record.pig_id) {
+        (Some(iacuc_no), _) => AccessService::ensure_project(&state.db, &current_user, iacuc_no).await?,
+        (None, Some(pig_id)) => AccessService::ensure_pig(&state.db, &current_user, pig_id).await?,
+        (None, None) => AccessService::data_scope(&state.db, &current_user)
+            .await?
+            .ensure(None, "Export record not found")?,
+    }
+    
+    let file = MedicalExportService::download(&state.db, id).await?;
+    let mut builder = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, file.mime_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename*=UTF-8''{}", urlencoding::encode(&file.file_name)),
+        )
+        .header("X-Export-Record-Id", file.record.id.to_string());
+    if let Some(size) = file.object.size {
+        builder = builder.header(header::CONTENT_LENGTH, size);
+    }
+    builder
+        .body(Body::from_stream(file.object.stream))
+        .map_err(|e| AppError::Internal(format!("Failed to build response: {}", e)))
+}
+
+/// 匯出檔案回應（附匯出記錄 ID）
+fn export_file_response(file: ExportFile) -> Result<Response> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, file.mime_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename*=UTF-8''{}", urlencoding::encode(&file.file_name)),
+        )
+        .header("X-Export-Record-Id", file.record.id.to_string())
+        .body(Body::from(file.data))
+        .map_err(|e| AppError::Internal(format!("Failed to build response: {}", e)))
 }
 
 /// 列出所有匯入批次
@@ -750,7 +1098,8 @@ pub async fn list_import_batches(
     Extension(current_user): Extension<CurrentUser>,
 ) -> Result<Json<Vec<PigImportBatch>>> {
     require_permission!(current_user, "animal.info.import");
-    
+    AccessService::ensure_unrestricted(&state.db, &current_user).await?;
+
     let batches = AnimalService::list_import_batches(&state.db, 50).await?;
     Ok(Json(batches))
 }
@@ -834,6 +1183,7 @@ pub async fn import_basic_data(
     mut multipart: Multipart,
 ) -> Result<Json<ImportResult>> {
     require_permission!(current_user, "animal.info.import");
+    AccessService::ensure_unrestricted(&state.db, &current_user).await?;
 
     let mut file_data: Option<Vec<u8>> = None;
     let mut file_name = String::from("unknown");
@@ -884,6 +1234,7 @@ pub async fn import_weight_data(
     mut multipart: Multipart,
 ) -> Result<Json<ImportResult>> {
     require_permission!(current_user, "animal.info.import");
+    AccessService::ensure_unrestricted(&state.db, &current_user).await?;
 
     let mut file_data: Option<Vec<u8>> = None;
     let mut file_name = String::from("unknown");
@@ -921,6 +1272,7 @@ pub async fn import_weight_data(
         &file_data,
         &file_name,
         current_user.id,
+        state.config.weight_loss_rule(),
     )
     .await?;
 
@@ -938,6 +1290,7 @@ pub async fn get_pig_pathology_report(
     Path(pig_id): Path<i32>,
 ) -> Result<Json<Option<crate::models::PigPathologyReport>>> {
     require_permission!(current_user, "animal.pathology.view");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
     
     let report = AnimalService::get_pathology_report(&state.db, pig_id).await?;
     Ok(Json(report))
@@ -950,6 +1303,7 @@ pub async fn upsert_pig_pathology_report(
     Path(pig_id): Path<i32>,
 ) -> Result<Json<crate::models::PigPathologyReport>> {
     require_permission!(current_user, "animal.pathology.upload");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
     
     let report = AnimalService::upsert_pathology_report(&state.db, pig_id, current_user.id).await?;
     Ok(Json(report))
@@ -962,15 +1316,16 @@ pub async fn upsert_pig_pathology_report(
 /// 取得最近的獸醫師評論（儀表板用）
 pub async fn get_vet_comments(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Result<Json<serde_json::Value>> {
     let limit: i64 = params
         .get("per_page")
         .and_then(|s| s.parse().ok())
         .unwrap_or(10);
+    let scope = AccessService::data_scope(&state.db, &current_user).await?;
     
-    // 查詢最近的獸醫建議
+    // 查詢最近的獸醫建議（限使用者資料範圍內的豬隻）
     let comments = sqlx::query_as::<_, (i32, i32, String, Option<String>, String, chrono::NaiveDateTime, String)>(
         r#"
         SELECT 
@@ -985,11 +1340,13 @@ pub async fn get_vet_comments(
         INNER JOIN pig_observations po ON vr.record_type = 'observation'::vet_record_type AND vr.record_id = po.id
         INNER JOIN pigs p ON po.pig_id = p.id
         INNER JOIN users u ON vr.created_by = u.id
+        WHERE ($2::text[] IS NULL OR p.iacuc_no = ANY($2))
         ORDER BY vr.created_at DESC
         LIMIT $1
         "#
     )
     .bind(limit)
+    .bind(scope.iacuc_filter())
     .fetch_all(&state.db)
     .await?;
     
@@ -1011,3 +1368,60 @@ pub async fn get_vet_comments(
     Ok(Json(serde_json::json!({ "data": data })))
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{app_state, scope_fixture};
+
+    // 外部使用者資料範圍：範圍外的動物、紀錄、匯出記錄一律回應 404
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_external_user_gets_404_for_other_protocols_animal(db: sqlx::PgPool) {
+        let fixture = scope_fixture(&db).await;
+        AccessService::ensure_pig(&db, &fixture.external_user, fixture.own_pig).await.unwrap();
+        let state = app_state(db);
+
+        let other = get_pig(State(state), Extension(fixture.external_user), Path(fixture.other_pig)).await;
+        assert!(matches!(other, Err(AppError::NotFound(_))));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_external_user_gets_404_for_other_protocols_records(db: sqlx::PgPool) {
+        let fixture = scope_fixture(&db).await;
+        let state = app_state(db);
+
+        let observation = get_pig_observation(
+            State(state.clone()),
+            Extension(fixture.external_user.clone()),
+            Path(fixture.other_observation),
+        )
+        .await;
+        assert!(matches!(observation, Err(AppError::NotFound(_))));
+
+        let surgery = get_pig_surgery(
+            State(state),
+            Extension(fixture.external_user),
+            Path(fixture.other_surgery),
+        )
+        .await;
+        assert!(matches!(surgery, Err(AppError::NotFound(_))));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_external_user_gets_404_for_other_protocols_export(db: sqlx::PgPool) {
+        let fixture = scope_fixture(&db).await;
+        let state = app_state(db);
+
+        let result = download_export_record(
+            State(state),
+            Extension(fixture.external_user),
+            Path(fixture.other_export),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+}
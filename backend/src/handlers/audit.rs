@@ -10,13 +10,14 @@ use uuid::Uuid;
 
 use crate::{
     middleware::CurrentUser,
+    require_permission,
     models::{
         ActivityLogQuery, AuditDashboardStats, AuditLogQuery, AuditLogWithActor,
         ForceLogoutRequest, LoginEventQuery, LoginEventWithUser, PaginatedResponse,
         ResolveAlertRequest, SecurityAlert, SecurityAlertQuery, SessionQuery, SessionWithUser,
         UserActivityLog,
     },
-    services::AuditService,
+    services::{AuditService, IntegrityScanSummary, IntegrityService},
     AppState, Result,
 };
 
@@ -184,6 +185,16 @@ pub async fn resolve_security_alert(
     Ok(Json(alert))
 }
 
+/// 立即執行附件完整性掃描（排程每日 03:30 亦會執行）
+pub async fn run_integrity_scan(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+) -> Result<Json<IntegrityScanSummary>> {
+    require_permission!(current_user, "audit.alerts.manage");
+    let summary = IntegrityService::scan(&state.db).await?;
+    Ok(Json(summary))
+}
+
 // ============================================
 // Dashboard
 // ============================================
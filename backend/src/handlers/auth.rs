@@ -1,8 +1,13 @@
-use axum::{extract::State, Extension, Json};
+use axum::{
+    extract::{ConnectInfo, State},
+    http::HeaderMap,
+    Extension, Json,
+};
+use std::net::SocketAddr;
 use validator::Validate;
 
 use crate::{
-    middleware::CurrentUser,
+    middleware::{client_ip, CurrentUser},
     models::{
         ChangeOwnPasswordRequest, ForgotPasswordRequest, LoginRequest, LoginResponse,
         RefreshTokenRequest, ResetPasswordWithTokenRequest, User, UserResponse,
@@ -14,20 +19,38 @@ use crate::{
 /// 登入
 pub async fn login(
     State(state): State<AppState>,
+    headers: HeaderMap,
+    peer: Option<ConnectInfo<SocketAddr>>,
     Json(req): Json<LoginRequest>,
 ) -> Result<Json<LoginResponse>> {
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
     
-    let response = AuthService::login(&state.db, &state.config, &req).await?;
+    let response = AuthService::login(
+        &state.db,
+        &state.config,
+        &req,
+        client_ip(&headers, peer.map(|ConnectInfo(addr)| addr)).as_deref(),
+        user_agent(&headers),
+    )
+    .await?;
     Ok(Json(response))
 }
 
 /// 重新整理 Token
 pub async fn refresh_token(
     State(state): State<AppState>,
+    headers: HeaderMap,
+    peer: Option<ConnectInfo<SocketAddr>>,
     Json(req): Json<RefreshTokenRequest>,
 ) -> Result<Json<LoginResponse>> {
-    let response = AuthService::refresh_token(&state.db, &state.config, &req.refresh_token).await?;
+    let response = AuthService::refresh_token(
+        &state.db,
+        &state.config,
+        &req.refresh_token,
+        client_ip(&headers, peer.map(|ConnectInfo(addr)| addr)).as_deref(),
+        user_agent(&headers),
+    )
+    .await?;
     Ok(Json(response))
 }
 
@@ -36,7 +59,7 @@ pub async fn logout(
     State(state): State<AppState>,
     Extension(current_user): Extension<CurrentUser>,
 ) -> Result<Json<serde_json::Value>> {
-    AuthService::logout(&state.db, current_user.id).await?;
+    AuthService::logout(&state.db, current_user.id, current_user.session_id).await?;
     Ok(Json(serde_json::json!({ "message": "Logged out successfully" })))
 }
 
@@ -84,6 +107,7 @@ pub async fn change_own_password(
     AuthService::change_own_password(
         &state.db,
         current_user.id,
+        current_user.session_id,
         &req.current_password,
         &req.new_password,
     ).await?;
@@ -136,3 +160,9 @@ pub async fn reset_password_with_token(
     
     Ok(Json(serde_json::json!({ "message": "Password reset successfully" })))
 }
+
+fn user_agent(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(axum::http::header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+}
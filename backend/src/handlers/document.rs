@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Multipart, Path, Query, State},
     Extension, Json,
 };
 use uuid::Uuid;
@@ -8,11 +8,12 @@ use validator::Validate;
 use crate::{
     middleware::CurrentUser,
     models::{
-        CreateDocumentRequest, DocumentListItem, DocumentQuery, DocumentWithLines,
-        UpdateDocumentRequest,
+        ApproveDocumentRequest, CreateDocumentRequest, DocumentListItem, DocumentQuery, DocumentWithLines,
+        ReverseDocumentRequest, StartStocktakeRequest, StocktakeCountRequest, StocktakeImportResult,
+        StocktakeSheet, StocktakeVarianceReport, UpdateDocumentRequest,
     },
     require_permission,
-    services::DocumentService,
+    services::{AuthService, DocumentService, StocktakeService},
     AppError, AppState, Result,
 };
 
@@ -51,7 +52,10 @@ pub async fn get_document(
 ) -> Result<Json<DocumentWithLines>> {
     require_permission!(current_user, "erp.document.view");
     
-    let document = DocumentService::get_by_id(&state.db, id).await?;
+    let mut document = DocumentService::get_by_id(&state.db, id).await?;
+    if !current_user.has_permission("erp.document.approve") {
+        StocktakeService::mask_book_quantities(&mut document);
+    }
     Ok(Json(document))
 }
 
@@ -86,6 +90,7 @@ pub async fn approve_document(
     State(state): State<AppState>,
     Extension(current_user): Extension<CurrentUser>,
     Path(id): Path<Uuid>,
+    Json(req): Json<ApproveDocumentRequest>,
 ) -> Result<Json<DocumentWithLines>> {
     require_permission!(current_user, "erp.document.approve");
     
@@ -93,8 +98,20 @@ pub async fn approve_document(
     if !current_user.roles.contains(&"WAREHOUSE_MANAGER".to_string()) {
         return Err(AppError::Forbidden("僅倉庫管理員可核准單據".to_string()));
     }
-    
-    let document = DocumentService::approve(&state.db, id, current_user.id).await?;
+    req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+    // 電子簽章：重新驗證密碼
+    let user = AuthService::verify_password_by_id(&state.db, current_user.id, &req.signature_password)
+        .await
+        .map_err(|_| AppError::Unauthorized)?;
+
+    let document = DocumentService::approve(
+        &state.db,
+        id,
+        current_user.id,
+        &user.password_hash,
+        state.config.costing_method,
+    ).await?;
     Ok(Json(document))
 }
 
@@ -115,6 +132,31 @@ pub async fn cancel_document(
     Ok(Json(document))
 }
 
+/// 沖銷已核准文件（產生反向沖銷單）
+pub async fn reverse_document(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<ReverseDocumentRequest>,
+) -> Result<Json<DocumentWithLines>> {
+    require_permission!(current_user, "erp.document.approve");
+    req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+    
+    // 僅 WAREHOUSE_MANAGER (倉庫管理員) 可沖銷單據
+    if !current_user.roles.contains(&"WAREHOUSE_MANAGER".to_string()) {
+        return Err(AppError::Forbidden("僅倉庫管理員可沖銷單據".to_string()));
+    }
+    
+    let document = DocumentService::reverse(
+        &state.db,
+        id,
+        current_user.id,
+        &req.reason,
+        state.config.costing_method,
+    ).await?;
+    Ok(Json(document))
+}
+
 /// 刪除文件
 pub async fn delete_document(
     State(state): State<AppState>,
@@ -126,3 +168,100 @@ pub async fn delete_document(
     DocumentService::delete(&state.db, id).await?;
     Ok(Json(()))
 }
+
+// ============================================
+// 盤點作業
+// ============================================
+
+/// 開始盤點（凍結帳面數量）
+pub async fn start_stocktake(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<StartStocktakeRequest>,
+) -> Result<Json<StocktakeSheet>> {
+    require_permission!(current_user, "erp.stocktake.create");
+
+    StocktakeService::start(&state.db, id, req.blind_count).await?;
+    let sheet = StocktakeService::get_sheet(&state.db, id, current_user.has_permission("erp.document.approve")).await?;
+    Ok(Json(sheet))
+}
+
+/// 取得盤點表（盲盤時盤點人員看不到帳面數量）
+pub async fn get_stocktake_sheet(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<StocktakeSheet>> {
+    require_permission!(current_user, "erp.document.view");
+
+    let sheet = StocktakeService::get_sheet(&state.db, id, current_user.has_permission("erp.document.approve")).await?;
+    Ok(Json(sheet))
+}
+
+/// 登錄實盤數量
+pub async fn record_stocktake_counts(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<StocktakeCountRequest>,
+) -> Result<Json<serde_json::Value>> {
+    require_permission!(current_user, "erp.stocktake.create");
+
+    let count = StocktakeService::record_counts(&state.db, id, &req.counts, current_user.id).await?;
+    Ok(Json(serde_json::json!({ "recorded": count })))
+}
+
+/// 匯入實盤數量（Excel / CSV）
+pub async fn import_stocktake_counts(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> Result<Json<StocktakeImportResult>> {
+    require_permission!(current_user, "erp.stocktake.create");
+
+    let mut file_data: Option<Vec<u8>> = None;
+    let mut file_name = String::from("unknown");
+
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        AppError::Validation(format!("解析檔案欄位失敗: {}", e))
+    })? {
+        if field.name() == Some("file") {
+            file_name = field
+                .file_name()
+                .map(String::from)
+                .unwrap_or_else(|| "unknown".to_string());
+
+            let data = field.bytes().await.map_err(|e| {
+                AppError::Validation(format!("讀取檔案資料失敗: {}", e))
+            })?;
+
+            file_data = Some(data.to_vec());
+        }
+    }
+
+    let file_data = file_data.ok_or_else(|| {
+        AppError::Validation("未找到檔案".to_string())
+    })?;
+
+    // 檢查檔案大小，限制為 10MB 以內
+    if file_data.len() > 10 * 1024 * 1024 {
+        return Err(AppError::Validation("檔案大小不能超過 10MB".to_string()));
+    }
+
+    let result = StocktakeService::import_counts(&state.db, id, &file_data, &file_name, current_user.id).await?;
+    Ok(Json(result))
+}
+
+/// 盤點差異（含差異金額）
+pub async fn get_stocktake_variance(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<StocktakeVarianceReport>> {
+    require_permission!(current_user, "erp.document.approve");
+
+    let report = StocktakeService::variance(&state.db, id).await?;
+    Ok(Json(report))
+}
@@ -16,12 +16,12 @@ use crate::{
         ApproveLeaveRequest, AttendanceCorrectionRequest, AttendanceQuery, AttendanceWithUser,
         BalanceQuery, BalanceSummary, CancelLeaveRequest, ClockInRequest, ClockOutRequest,
         CompTimeBalanceView, CreateAnnualLeaveRequest, CreateLeaveRequest, CreateOvertimeRequest,
-        DashboardCalendarData, ExpiredLeaveReport, LeaveQuery, LeaveRequest, LeaveRequestWithUser, OvertimeQuery, OvertimeWithUser,
+        DashboardCalendarData, ExpiredLeaveReport, LeaveApproval, LeaveQuery, LeaveRequest, LeaveRequestWithUser, OvertimeQuery, OvertimeWithUser,
         PaginatedResponse, RejectLeaveRequest, RejectOvertimeRequest, UpdateLeaveRequest,
         UpdateOvertimeRequest,
     },
-    services::HrService,
-    AppState, Result,
+    services::{AuthService, HrService, LeaveApprovalService},
+    AppError, AppState, Result,
 };
 
 // ============================================
@@ -223,10 +223,11 @@ pub async fn list_leaves(
     
     // 如果是查待審核的請假
     if query.pending_approval.unwrap_or(false) {
-        // 對於有審核權限的人，查看所有待審核狀態的請假
-        // 不再使用 current_approver_id 過濾，因為目前沒有設定審批鏈
+        // 執行秘書 / 請假管理者可查看所有待審核請假，其他人只看指派給自己的
         query.user_id = None;
-        // 可以在這裡添加權限檢查，確保只有有審核權限的人可以看待審核
+        if !LeaveApprovalService::can_override(&current_user) {
+            query.approver_id = Some(current_user.id);
+        }
     } else {
         // "我的請假" - 永遠只看自己的請假，無論有無 view.all 權限
         // 除非 API 明確傳入 user_id 且用戶有 view.all 權限
@@ -301,15 +302,21 @@ pub async fn approve_leave(
     Path(id): Path<Uuid>,
     Json(payload): Json<ApproveLeaveRequest>,
 ) -> Result<Json<LeaveRequest>> {
-    // 僅 IACUC_STAFF (執行秘書) 可審核請假申請
-    if !current_user.roles.contains(&"IACUC_STAFF".to_string()) {
-        return Err(crate::error::AppError::Forbidden("僅執行秘書可審核請假申請".to_string()));
-    }
+    // 電子簽章：重新驗證密碼
+    let password = payload.signature_password.as_deref()
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| AppError::Validation("核准請假須輸入密碼完成電子簽章".to_string()))?;
+    let user = AuthService::verify_password_by_id(&state.db, current_user.id, password)
+        .await
+        .map_err(|_| AppError::Unauthorized)?;
+
+    // 僅目前審核人可審核，執行秘書 / 請假管理者可代為審核
     let record = HrService::approve_leave(
         &state.db,
         id,
-        current_user.id,
+        &current_user,
         payload.comments.as_deref(),
+        &user.password_hash,
     )
     .await?;
     Ok(Json(record))
@@ -322,15 +329,31 @@ pub async fn reject_leave(
     Path(id): Path<Uuid>,
     Json(payload): Json<RejectLeaveRequest>,
 ) -> Result<Json<LeaveRequest>> {
-    // 僅 IACUC_STAFF (執行秘書) 可審核請假申請
-    if !current_user.roles.contains(&"IACUC_STAFF".to_string()) {
-        return Err(crate::error::AppError::Forbidden("僅執行秘書可審核請假申請".to_string()));
-    }
+    // 僅目前審核人可審核，執行秘書 / 請假管理者可代為審核
     let record =
-        HrService::reject_leave(&state.db, id, current_user.id, &payload.reason).await?;
+        HrService::reject_leave(&state.db, id, &current_user, &payload.reason).await?;
     Ok(Json(record))
 }
 
+/// 取得請假審核紀錄（核准、駁回、代理、逾時上呈）
+pub async fn list_leave_approvals(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<LeaveApproval>>> {
+    let leave = HrService::get_leave(&state.db, id, &current_user).await?;
+    if leave.user_id != current_user.id
+        && leave.current_approver_id != Some(current_user.id)
+        && !LeaveApprovalService::can_override(&current_user)
+        && !current_user.has_permission("hr.leave.view_all")
+    {
+        return Err(crate::error::AppError::Forbidden("無權查看此請假的審核紀錄".to_string()));
+    }
+
+    let approvals = HrService::list_leave_approvals(&state.db, id).await?;
+    Ok(Json(approvals))
+}
+
 /// 取消請假
 pub async fn cancel_leave(
     State(state): State<AppState>,
@@ -1,6 +1,8 @@
 use axum::{
+    body::Body,
     extract::{Path, Query, State, Extension},
-    http::StatusCode,
+    http::{header, StatusCode},
+    response::Response,
     Json,
 };
 use uuid::Uuid;
@@ -8,6 +10,7 @@ use uuid::Uuid;
 use crate::{
     error::AppError,
     middleware::CurrentUser,
+    require_permission,
     models::{
         NotificationQuery, UpdateNotificationSettingsRequest, MarkNotificationsReadRequest,
         PaginationQuery, PaginatedResponse, NotificationItem, NotificationSettings,
@@ -15,7 +18,7 @@ use crate::{
         ScheduledReport, CreateScheduledReportRequest, UpdateScheduledReportRequest,
         ReportHistory,
     },
-    services::NotificationService,
+    services::{FileService, NotificationService, ScheduledReportRunner},
     AppState,
 };
 
@@ -135,8 +138,9 @@ pub async fn list_expiry_alerts(
 /// 列出所有排程報表
 pub async fn list_scheduled_reports(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
 ) -> Result<Json<Vec<ScheduledReport>>, AppError> {
+    require_permission!(current_user, "report.schedule");
     let service = NotificationService::new(state.db.clone());
     let reports = service.list_scheduled_reports().await?;
     Ok(Json(reports))
@@ -145,9 +149,10 @@ pub async fn list_scheduled_reports(
 /// 取得單個排程報表
 pub async fn get_scheduled_report(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ScheduledReport>, AppError> {
+    require_permission!(current_user, "report.schedule");
     let service = NotificationService::new(state.db.clone());
     let report = service.get_scheduled_report(id).await?;
     Ok(Json(report))
@@ -159,6 +164,7 @@ pub async fn create_scheduled_report(
     Extension(current_user): Extension<CurrentUser>,
     Json(request): Json<CreateScheduledReportRequest>,
 ) -> Result<(StatusCode, Json<ScheduledReport>), AppError> {
+    require_permission!(current_user, "report.schedule");
     let service = NotificationService::new(state.db.clone());
     let report = service
         .create_scheduled_report(request, current_user.id)
@@ -169,10 +175,11 @@ pub async fn create_scheduled_report(
 /// 更新排程報表
 pub async fn update_scheduled_report(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(id): Path<Uuid>,
     Json(request): Json<UpdateScheduledReportRequest>,
 ) -> Result<Json<ScheduledReport>, AppError> {
+    require_permission!(current_user, "report.schedule");
     let service = NotificationService::new(state.db.clone());
     let report = service.update_scheduled_report(id, request).await?;
     Ok(Json(report))
@@ -181,9 +188,10 @@ pub async fn update_scheduled_report(
 /// 刪除排程報表
 pub async fn delete_scheduled_report(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(id): Path<Uuid>,
 ) -> Result<StatusCode, AppError> {
+    require_permission!(current_user, "report.schedule");
     let service = NotificationService::new(state.db.clone());
     service.delete_scheduled_report(id).await?;
     Ok(StatusCode::NO_CONTENT)
@@ -192,9 +200,10 @@ pub async fn delete_scheduled_report(
 /// 列出報表歷史記錄
 pub async fn list_report_history(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Query(pagination): Query<PaginationQuery>,
 ) -> Result<Json<PaginatedResponse<ReportHistory>>, AppError> {
+    require_permission!(current_user, "report.download");
     let service = NotificationService::new(state.db.clone());
     let result = service
         .list_report_history(pagination.page, pagination.per_page)
@@ -202,15 +211,43 @@ pub async fn list_report_history(
     Ok(Json(result))
 }
 
+/// 立即執行排程報表
+pub async fn run_scheduled_report(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<ReportHistory>), AppError> {
+    require_permission!(current_user, "report.schedule");
+    let service = NotificationService::new(state.db.clone());
+    let report = service.get_scheduled_report(id).await?;
+    let history = ScheduledReportRunner::run_now(&state.db, &state.config, &report, current_user.id).await?;
+    Ok((StatusCode::CREATED, Json(history)))
+}
+
 /// 下載報表檔案
 pub async fn download_report(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(id): Path<Uuid>,
-) -> Result<Json<ReportHistory>, AppError> {
+) -> Result<Response, AppError> {
+    require_permission!(current_user, "report.download");
     let service = NotificationService::new(state.db.clone());
     let report = service.get_report_history(id).await?;
-    Ok(Json(report))
+    let (object, mime_type) = FileService::open(&report.file_path).await?;
+
+    let mut builder = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, mime_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename*=UTF-8''{}", urlencoding::encode(&report.file_name)),
+        );
+    if let Some(size) = object.size {
+        builder = builder.header(header::CONTENT_LENGTH, size);
+    }
+    builder
+        .body(Body::from_stream(object.stream))
+        .map_err(|e| AppError::Internal(format!("Failed to build response: {}", e)))
 }
 
 // ============================================
@@ -269,3 +306,71 @@ pub async fn trigger_notification_cleanup(
         }))),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        services::AuthService,
+        test_support::{app_state, init_test_storage, insert_user},
+    };
+
+    /// 依啟動時的角色權限設定建立使用者（與登入時取得的權限相同）
+    async fn user_with_role(db: &sqlx::PgPool, email: &str, role: &str) -> CurrentUser {
+        crate::ensure_all_role_permissions(db).await.unwrap();
+        let id = insert_user(db, email, true).await;
+        sqlx::query("INSERT INTO user_roles (user_id, role_id) SELECT $1, id FROM roles WHERE code = $2")
+            .bind(id)
+            .bind(role)
+            .execute(db)
+            .await
+            .unwrap();
+        let (roles, permissions) = AuthService::get_user_roles_permissions(db, id).await.unwrap();
+        CurrentUser { id, email: email.to_string(), roles, permissions, session_id: Uuid::new_v4() }
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_report_roles_can_schedule_and_download_reports(db: sqlx::PgPool) {
+        init_test_storage();
+        let state = app_state(db.clone());
+        let manager = user_with_role(&db, "warehouse@example.com", "WAREHOUSE_MANAGER").await;
+        let purchaser = user_with_role(&db, "purchasing@example.com", "PURCHASING").await;
+
+        let request = CreateScheduledReportRequest {
+            report_type: "stock_on_hand".to_string(),
+            schedule_type: "daily".to_string(),
+            day_of_week: None,
+            day_of_month: None,
+            hour_of_day: 6,
+            parameters: None,
+            recipients: vec![purchaser.id],
+        };
+        let (_, Json(report)) =
+            create_scheduled_report(State(state.clone()), Extension(manager.clone()), Json(request))
+                .await
+                .unwrap();
+
+        let Json(reports) = list_scheduled_reports(State(state.clone()), Extension(manager.clone()))
+            .await
+            .unwrap();
+        assert!(reports.iter().any(|r| r.id == report.id));
+
+        let (_, Json(history)) =
+            run_scheduled_report(State(state.clone()), Extension(manager), Path(report.id))
+                .await
+                .unwrap();
+
+        // 收件者（非管理員）可列出並下載報表
+        let pagination = PaginationQuery { page: 1, per_page: 20 };
+        let Json(page) = list_report_history(State(state.clone()), Extension(purchaser.clone()), Query(pagination))
+            .await
+            .unwrap();
+        assert!(page.data.iter().any(|h| h.id == history.id));
+
+        let response = download_report(State(state), Extension(purchaser), Path(history.id))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -11,13 +11,20 @@ use crate::{
     middleware::CurrentUser,
     models::{
         AssignReviewerRequest, AssignCoEditorRequest, ChangeStatusRequest, CreateCommentRequest, CreateProtocolRequest,
-        CoEditorAssignmentResponse,
+        CoEditorAssignmentResponse, DeclareCoiRequest, ReviewerCoiDeclaration, SetBlindedReviewRequest,
+        OverdueReview, ReviewVote, ReviewVoteSummary, SubmitReviewVoteRequest,
         Protocol, ProtocolListItem, ProtocolQuery, ProtocolResponse, ProtocolStatusHistory,
         ProtocolVersion, ReplyCommentRequest, ReviewAssignment, ReviewComment, ReviewCommentResponse,
-        UpdateProtocolRequest, UserProtocol,
+        UpdateProtocolRequest, UserProtocol, ProtocolTransition, ProtocolTransitionOption,
+        ProtocolVersionDiff, ProtocolVersionDiffQuery, PROTOCOL_TRANSITIONS,
+        CreateAmendmentCommentRequest, CreateAmendmentRequest, ProtocolAmendment, ProtocolAmendmentDiff,
+        UpdateAmendmentRequest, AMENDMENT_TRANSITIONS,
     },
     require_permission,
-    services::{ProtocolService, PdfService},
+    services::{
+        AccessService, ProtocolService, PdfService, ProtocolAmendmentService, ReviewDeadlineService,
+        ReviewVoteService, ReviewerConflictService,
+    },
     AppError, AppState, Result,
 };
 
@@ -214,10 +221,71 @@ pub async fn change_protocol_status(
 ) -> Result<Json<Protocol>> {
     require_permission!(current_user, "aup.protocol.change_status");
     
-    let protocol = ProtocolService::change_status(&state.db, id, &req, current_user.id).await?;
+    let protocol = ProtocolService::change_status(&state.db, id, &req, &current_user, &state.config).await?;
     Ok(Json(protocol))
 }
 
+/// 取得目前使用者可執行的狀態轉移
+pub async fn get_protocol_transitions(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ProtocolTransitionOption>>> {
+    require_permission!(current_user, "aup.protocol.view_own");
+    
+    let transitions = ProtocolService::get_available_transitions(&state.db, id, &current_user).await?;
+    Ok(Json(transitions))
+}
+
+/// 取得完整狀態轉移表
+pub async fn get_protocol_transition_table(
+    Extension(current_user): Extension<CurrentUser>,
+) -> Result<Json<&'static [ProtocolTransition]>> {
+    require_permission!(current_user, "aup.protocol.view_own");
+    
+    Ok(Json(PROTOCOL_TRANSITIONS))
+}
+
+/// 審查委員完成審查
+pub async fn complete_protocol_review(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ReviewAssignment>> {
+    require_permission!(current_user, "aup.review.comment");
+    
+    let assignment = ProtocolService::complete_review(&state.db, id, current_user.id).await?;
+    Ok(Json(assignment))
+}
+
+/// 審查委員提交表決（核准、附條件核准、修正、否決）
+pub async fn submit_review_vote(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<SubmitReviewVoteRequest>,
+) -> Result<Json<ReviewVote>> {
+    require_permission!(current_user, "aup.review.comment");
+    req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let vote = ReviewVoteService::submit(&state.db, id, current_user.id, &req).await?;
+    Ok(Json(vote))
+}
+
+/// 取得表決彙整與建議狀態（主委、執行秘書）
+pub async fn get_review_vote_summary(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ReviewVoteSummary>> {
+    if !AccessService::can_view_all_protocols(&current_user) {
+        return Err(AppError::Forbidden("Only IACUC staff and the chair can view vote summaries".to_string()));
+    }
+
+    let summary = ReviewVoteService::summary(&state.db, id, state.config.review_vote_rule).await?;
+    Ok(Json(summary))
+}
+
 /// 列出專案所有版本
 pub async fn get_protocol_versions(
     State(state): State<AppState>,
@@ -230,6 +298,19 @@ pub async fn get_protocol_versions(
     Ok(Json(versions))
 }
 
+/// 比較專案兩個版本的內容差異
+pub async fn get_protocol_version_diff(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ProtocolVersionDiffQuery>,
+) -> Result<Json<ProtocolVersionDiff>> {
+    require_permission!(current_user, "aup.protocol.view_own");
+    
+    let diff = ProtocolService::diff_versions(&state.db, id, query.from, query.to).await?;
+    Ok(Json(diff))
+}
+
 /// 列出專案狀態變更歷史
 pub async fn get_protocol_status_history(
     State(state): State<AppState>,
@@ -250,7 +331,7 @@ pub async fn assign_reviewer(
 ) -> Result<Json<ReviewAssignment>> {
     require_permission!(current_user, "aup.review.assign");
     
-    let assignment = ProtocolService::assign_reviewer(&state.db, &req, current_user.id).await?;
+    let assignment = ProtocolService::assign_reviewer(&state.db, &req, current_user.id, state.config.review_due_working_days).await?;
     Ok(Json(assignment))
 }
 
@@ -302,16 +383,70 @@ pub async fn list_review_assignments(
     let protocol_id = query.protocol_id
         .ok_or_else(|| AppError::Validation("protocol_id is required".to_string()))?;
     
-    let assignments: Vec<ReviewAssignment> = sqlx::query_as(
-        "SELECT * FROM review_assignments WHERE protocol_id = $1"
+    let mut assignments: Vec<ReviewAssignment> = sqlx::query_as(
+        "SELECT * FROM review_assignments WHERE protocol_id = $1 AND amendment_id IS NULL"
     )
     .bind(protocol_id)
     .fetch_all(&state.db)
     .await?;
+    ReviewerConflictService::blind_assignments(&state.db, &current_user, protocol_id, &mut assignments).await?;
     
     Ok(Json(assignments))
 }
 
+/// 審查委員聲明利益衝突
+/// 聲明有衝突時自動撤銷該委員尚未完成的審查指派
+pub async fn declare_reviewer_coi(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<DeclareCoiRequest>,
+) -> Result<Json<ReviewerCoiDeclaration>> {
+    require_permission!(current_user, "aup.protocol.review");
+    req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let declaration = ReviewerConflictService::declare(&state.db, id, current_user.id, &req).await?;
+    Ok(Json(declaration))
+}
+
+/// 列出計畫的利益衝突聲明
+pub async fn list_reviewer_coi_declarations(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ReviewerCoiDeclaration>>> {
+    require_permission!(current_user, "aup.review.assign");
+
+    let declarations = ReviewerConflictService::list(&state.db, id).await?;
+    Ok(Json(declarations))
+}
+
+/// 設定計畫是否盲審（對 PI 隱藏審查委員身分）
+pub async fn set_protocol_blinded_review(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<SetBlindedReviewRequest>,
+) -> Result<Json<Protocol>> {
+    require_permission!(current_user, "aup.review.assign");
+
+    let protocol = ReviewerConflictService::set_blinded_review(&state.db, id, req.blinded_review, current_user.id).await?;
+    Ok(Json(protocol))
+}
+
+/// 逾期審查看板（主委、執行秘書）
+pub async fn list_overdue_reviews(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+) -> Result<Json<Vec<OverdueReview>>> {
+    if !AccessService::can_view_all_protocols(&current_user) {
+        return Err(AppError::Forbidden("Only IACUC staff and the chair can view overdue reviews".to_string()));
+    }
+
+    let overdue = ReviewDeadlineService::list_overdue(&state.db).await?;
+    Ok(Json(overdue))
+}
+
 #[derive(Debug, serde::Deserialize)]
 pub struct ProtocolIdQuery {
     pub protocol_id: Option<Uuid>,
@@ -342,7 +477,8 @@ pub async fn list_review_comments(
     let protocol_version_id = query.protocol_version_id
         .ok_or_else(|| AppError::Validation("protocol_version_id is required".to_string()))?;
     
-    let comments = ProtocolService::get_comments(&state.db, protocol_version_id).await?;
+    let mut comments = ProtocolService::get_comments(&state.db, protocol_version_id).await?;
+    ReviewerConflictService::blind_comments(&state.db, &current_user, protocol_version_id, &mut comments).await?;
     Ok(Json(comments))
 }
 
@@ -405,6 +541,7 @@ pub async fn get_protocol_animal_stats(
             "remaining_count": 0
         }))),
     };
+    AccessService::ensure_project(&state.db, &current_user, &iacuc_no).await?;
     
     // 統計動物數量
     let stats: (i64, i64, i64) = sqlx::query_as(
@@ -518,3 +655,281 @@ pub async fn export_protocol_pdf(
         pdf_bytes,
     ))
 }
+
+// ============================================
+// 修正案
+// ============================================
+
+/// 確認使用者具指定權限，且為計畫的 PI / co-editor 或 IACUC 人員
+async fn ensure_protocol_applicant(
+    state: &AppState,
+    current_user: &CurrentUser,
+    protocol_id: Uuid,
+    permission: &str,
+) -> Result<()> {
+    require_permission!(current_user, permission);
+    if AccessService::can_view_all_protocols(current_user) {
+        return Ok(());
+    }
+
+    let is_authorized: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM protocols p
+            WHERE p.id = $1
+            AND (
+                p.pi_user_id = $2
+                OR EXISTS(
+                    SELECT 1 FROM user_protocols up
+                    WHERE up.protocol_id = p.id
+                    AND up.user_id = $2
+                    AND up.role_in_protocol IN ('PI', 'CO_EDITOR')
+                )
+            )
+        )
+        "#,
+    )
+    .bind(protocol_id)
+    .bind(current_user.id)
+    .fetch_one(&state.db)
+    .await?;
+
+    if !is_authorized {
+        return Err(AppError::Forbidden("Only the protocol PI or co-editors can manage amendments".to_string()));
+    }
+    Ok(())
+}
+
+/// 確認使用者可查看計畫（含其修正案）
+async fn ensure_protocol_viewer(state: &AppState, current_user: &CurrentUser, protocol_id: Uuid) -> Result<()> {
+    if !AccessService::can_view_protocol(&state.db, current_user, protocol_id).await? {
+        return Err(AppError::Forbidden("You don't have permission to view this protocol".to_string()));
+    }
+    Ok(())
+}
+
+/// 提出修正案（已核准計畫的 PI 或 co-editor）
+pub async fn create_protocol_amendment(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<CreateAmendmentRequest>,
+) -> Result<Json<ProtocolAmendment>> {
+    ensure_protocol_applicant(&state, &current_user, id, "aup.protocol.edit").await?;
+    req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let amendment = ProtocolAmendmentService::create(&state.db, id, &req, current_user.id).await?;
+    Ok(Json(amendment))
+}
+
+/// 列出計畫的修正案歷程
+pub async fn list_protocol_amendments(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ProtocolAmendment>>> {
+    require_permission!(current_user, "aup.protocol.view_own");
+    ensure_protocol_viewer(&state, &current_user, id).await?;
+
+    let amendments = ProtocolAmendmentService::list_by_protocol(&state.db, id).await?;
+    Ok(Json(amendments))
+}
+
+/// 取得修正案
+pub async fn get_protocol_amendment(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ProtocolAmendment>> {
+    require_permission!(current_user, "aup.protocol.view_own");
+
+    let amendment = ProtocolAmendmentService::get(&state.db, id).await?;
+    ensure_protocol_viewer(&state, &current_user, amendment.protocol_id).await?;
+    Ok(Json(amendment))
+}
+
+/// 更新修正案（草稿或需修訂）
+pub async fn update_protocol_amendment(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<UpdateAmendmentRequest>,
+) -> Result<Json<ProtocolAmendment>> {
+    let amendment = ProtocolAmendmentService::get(&state.db, id).await?;
+    ensure_protocol_applicant(&state, &current_user, amendment.protocol_id, "aup.protocol.edit").await?;
+    req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let amendment = ProtocolAmendmentService::update(&state.db, id, &req, current_user.id).await?;
+    Ok(Json(amendment))
+}
+
+/// 提交修正案
+pub async fn submit_protocol_amendment(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ProtocolAmendment>> {
+    let amendment = ProtocolAmendmentService::get(&state.db, id).await?;
+    ensure_protocol_applicant(&state, &current_user, amendment.protocol_id, "aup.protocol.submit").await?;
+
+    let amendment = ProtocolAmendmentService::submit(&state.db, id, current_user.id).await?;
+    Ok(Json(amendment))
+}
+
+/// 撤回修正案（草稿或需修訂）
+pub async fn withdraw_protocol_amendment(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ProtocolAmendment>> {
+    let amendment = ProtocolAmendmentService::get(&state.db, id).await?;
+    ensure_protocol_applicant(&state, &current_user, amendment.protocol_id, "aup.protocol.submit").await?;
+
+    let amendment = ProtocolAmendmentService::withdraw(&state.db, id, current_user.id).await?;
+    Ok(Json(amendment))
+}
+
+/// 變更修正案狀態（送審、退回、核准、否決）
+pub async fn change_protocol_amendment_status(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<ChangeStatusRequest>,
+) -> Result<Json<ProtocolAmendment>> {
+    require_permission!(current_user, "aup.protocol.change_status");
+
+    let amendment = ProtocolAmendmentService::change_status(&state.db, id, &req, &current_user, &state.config).await?;
+    Ok(Json(amendment))
+}
+
+/// 取得目前使用者可執行的修正案狀態轉移
+pub async fn get_protocol_amendment_transitions(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ProtocolTransitionOption>>> {
+    require_permission!(current_user, "aup.protocol.view_own");
+
+    let transitions = ProtocolAmendmentService::get_available_transitions(&state.db, id, &current_user).await?;
+    Ok(Json(transitions))
+}
+
+/// 取得修正案狀態轉移表
+pub async fn get_amendment_transition_table(
+    Extension(current_user): Extension<CurrentUser>,
+) -> Result<Json<&'static [ProtocolTransition]>> {
+    require_permission!(current_user, "aup.protocol.view_own");
+
+    Ok(Json(AMENDMENT_TRANSITIONS))
+}
+
+/// 比較修正案與核准版本
+pub async fn get_protocol_amendment_diff(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ProtocolAmendmentDiff>> {
+    require_permission!(current_user, "aup.protocol.view_own");
+
+    let amendment = ProtocolAmendmentService::get(&state.db, id).await?;
+    ensure_protocol_viewer(&state, &current_user, amendment.protocol_id).await?;
+
+    let diff = ProtocolAmendmentService::diff(&state.db, id).await?;
+    Ok(Json(diff))
+}
+
+/// 列出修正案審查指派
+pub async fn list_amendment_review_assignments(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ReviewAssignment>>> {
+    require_permission!(current_user, "aup.protocol.view_own");
+
+    let amendment = ProtocolAmendmentService::get(&state.db, id).await?;
+    ensure_protocol_viewer(&state, &current_user, amendment.protocol_id).await?;
+
+    let mut assignments = ProtocolAmendmentService::list_assignments(&state.db, id).await?;
+    ReviewerConflictService::blind_assignments(&state.db, &current_user, amendment.protocol_id, &mut assignments).await?;
+    Ok(Json(assignments))
+}
+
+/// 審查委員完成修正案審查
+pub async fn complete_amendment_review(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ReviewAssignment>> {
+    require_permission!(current_user, "aup.review.comment");
+
+    let assignment = ProtocolAmendmentService::complete_review(&state.db, id, current_user.id).await?;
+    Ok(Json(assignment))
+}
+
+/// 新增修正案審查意見
+pub async fn create_amendment_review_comment(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<CreateAmendmentCommentRequest>,
+) -> Result<Json<ReviewComment>> {
+    require_permission!(current_user, "aup.review.comment");
+    req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let comment = ProtocolAmendmentService::add_comment(&state.db, id, &req, current_user.id).await?;
+    Ok(Json(comment))
+}
+
+/// 列出修正案審查意見
+pub async fn list_amendment_review_comments(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ReviewCommentResponse>>> {
+    require_permission!(current_user, "aup.protocol.view_own");
+
+    let amendment = ProtocolAmendmentService::get(&state.db, id).await?;
+    ensure_protocol_viewer(&state, &current_user, amendment.protocol_id).await?;
+
+    let mut comments = ProtocolAmendmentService::get_comments(&state.db, id).await?;
+    ReviewerConflictService::blind_comments(&state.db, &current_user, amendment.base_version_id, &mut comments).await?;
+    Ok(Json(comments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{app_state, current_user, insert_protocol, insert_user};
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_only_protocol_members_or_staff_manage_amendments(db: sqlx::PgPool) {
+        let pi = insert_user(&db, "pi@example.com", false).await;
+        let co_editor = insert_user(&db, "co-editor@example.com", false).await;
+        let other_pi = insert_user(&db, "other-pi@example.com", false).await;
+        let staff = insert_user(&db, "iacuc-staff@example.com", true).await;
+        let protocol_id = insert_protocol(&db, pi, "PIG-APPLICANT").await;
+        sqlx::query("INSERT INTO user_protocols (user_id, protocol_id, role_in_protocol) VALUES ($1, $2, 'CO_EDITOR')")
+            .bind(co_editor)
+            .bind(protocol_id)
+            .execute(&db)
+            .await
+            .unwrap();
+        let state = app_state(db);
+        let perms = ["aup.protocol.edit", "aup.protocol.submit"];
+
+        for (user_id, roles) in [(pi, &["PI"][..]), (co_editor, &["PI"][..]), (staff, &["IACUC_STAFF"][..])] {
+            let user = current_user(user_id, "member@example.com", roles, &perms);
+            ensure_protocol_applicant(&state, &user, protocol_id, "aup.protocol.edit").await.unwrap();
+        }
+
+        // 其他計畫的 PI 同樣具編輯權限，但不可管理此計畫的修正案
+        let outsider = current_user(other_pi, "other-pi@example.com", &["PI"], &perms);
+        let result = ensure_protocol_applicant(&state, &outsider, protocol_id, "aup.protocol.edit").await;
+        assert!(matches!(result, Err(AppError::Forbidden(_))));
+
+        let without_permission = current_user(pi, "pi@example.com", &["PI"], &[]);
+        let result = ensure_protocol_applicant(&state, &without_permission, protocol_id, "aup.protocol.edit").await;
+        assert!(matches!(result, Err(AppError::Forbidden(_))));
+    }
+}
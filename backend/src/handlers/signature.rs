@@ -11,7 +11,10 @@ use validator::Validate;
 use crate::{
     middleware::CurrentUser,
     require_permission,
-    services::{SignatureService, AnnotationService, AnnotationType, SignatureType, AuthService},
+    services::{
+        AccessService, AnnotationService, AnnotationType, AuthService, EntitySignatureVerification,
+        LeaveApprovalService, PigRecord, SignatureMeaning, SignatureService, SignatureType, UnlockSigner,
+    },
     AppError, AppState, Result,
 };
 
@@ -24,6 +27,8 @@ pub struct SignRecordRequest {
     #[validate(length(min = 1, message = "密碼為必填"))]
     pub password: String,
     pub signature_type: Option<String>,
+    /// 簽章意義，未提供時依簽章類型決定
+    pub signature_meaning: Option<SignatureMeaning>,
 }
 
 #[derive(Debug, Serialize)]
@@ -33,6 +38,27 @@ pub struct SignRecordResponse {
     pub is_locked: bool,
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct UnlockRecordRequest {
+    #[validate(length(min = 1, message = "解鎖原因為必填"))]
+    pub reason: String,
+    #[validate(length(min = 1, message = "密碼為必填"))]
+    pub password: String,
+    /// 共同簽署者（須為解鎖者以外、未簽署原記錄且具解鎖權限的使用者）
+    #[validate(email(message = "共同簽署者 Email 格式不正確"))]
+    pub co_signer_email: String,
+    #[validate(length(min = 1, message = "共同簽署者密碼為必填"))]
+    pub co_signer_password: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnlockRecordResponse {
+    pub signature_id: Uuid,
+    pub co_signature_id: Uuid,
+    pub unlocked_at: String,
+    pub is_locked: bool,
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct CreateAnnotationRequest {
     #[validate(length(min = 1, message = "內容為必填"))]
@@ -52,32 +78,58 @@ pub struct AnnotationResponse {
 }
 
 #[derive(Debug, Serialize)]
-pub struct SignatureStatusResponse {
+pub struct EntitySignaturesResponse {
+    pub entity_type: String,
+    pub entity_id: String,
     pub is_signed: bool,
     pub is_locked: bool,
-    pub signatures: Vec<SignatureInfo>,
-}
-
-#[derive(Debug, Serialize)]
-pub struct SignatureInfo {
-    pub id: Uuid,
-    pub signature_type: String,
-    pub signer_name: Option<String>,
-    pub signed_at: String,
+    /// 所有有效簽章的內容雜湊皆與目前記錄一致
+    pub all_valid: bool,
+    pub signatures: Vec<EntitySignatureVerification>,
 }
 
 // ============================================
-// Sacrifice Record Signature
+// Record Signature
 // ============================================
 
-/// 為犧牲記錄簽章
-pub async fn sign_sacrifice_record(
+/// 為動物紀錄簽章（犧牲、觀察、手術紀錄與病理報告簽章後鎖定）
+///
+/// 計畫、單據與請假的簽章於各自核准流程中完成。
+pub async fn sign_entity_record(
     State(state): State<AppState>,
     Extension(current_user): Extension<CurrentUser>,
-    Path(sacrifice_id): Path<i32>,
+    Path((entity_type, entity_id)): Path<(String, String)>,
     Json(req): Json<SignRecordRequest>,
 ) -> Result<Json<SignRecordResponse>> {
-    require_permission!(current_user, "animal.record.sacrifice");
+    let permission = match entity_type.as_str() {
+        "sacrifice" => "animal.record.sacrifice",
+        "observation" => "animal.record.view",
+        "surgery" => "animal.record.surgery",
+        "pathology" => "animal.pathology.upload",
+        "protocol" | "document" | "leave" => {
+            return Err(AppError::BusinessRule("此類型須於核准流程中完成電子簽章".to_string()));
+        }
+        _ => {
+            return Err(AppError::Validation(format!("不支援的簽章實體類型: {}", entity_type)));
+        }
+    };
+    require_permission!(current_user, permission);
+
+    let record_id = entity_id.parse::<i32>()
+        .map_err(|_| AppError::Validation(format!("無效的記錄 ID: {}", entity_id)))?;
+    ensure_record_scope(&state, &current_user, &entity_type, record_id).await?;
+    let response = sign_record(&state, &current_user, &entity_type, record_id, &req).await?;
+    Ok(Json(response))
+}
+
+/// 驗證密碼後依目前記錄內容簽章並鎖定記錄
+async fn sign_record(
+    state: &AppState,
+    current_user: &CurrentUser,
+    record_type: &str,
+    record_id: i32,
+    req: &SignRecordRequest,
+) -> Result<SignRecordResponse> {
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
 
     // 驗證密碼
@@ -85,140 +137,204 @@ pub async fn sign_sacrifice_record(
         .await
         .map_err(|_| AppError::Unauthorized)?;
 
-    // 取得犧牲記錄內容用於生成雜湊
-    let sacrifice_content: Option<String> = sqlx::query_scalar(
-        r#"
-        SELECT CONCAT(
-            'sacrifice_id:', id::text, 
-            ',pig_id:', pig_id::text, 
-            ',date:', COALESCE(sacrifice_date::text, ''),
-            ',confirmed:', confirmed_sacrifice::text
-        ) FROM pig_sacrifices WHERE id = $1
-        "#
-    )
-    .bind(sacrifice_id)
-    .fetch_optional(&state.db)
-    .await?;
-
-    let content = sacrifice_content
-        .ok_or_else(|| AppError::NotFound("找不到犧牲記錄".into()))?;
-
     let sig_type = match req.signature_type.as_deref() {
         Some("WITNESS") => SignatureType::Witness,
         Some("APPROVE") => SignatureType::Approve,
         _ => SignatureType::Confirm,
     };
-
-    // 建立簽章
-    let signature = SignatureService::sign(
-        &state.db,
-        "sacrifice",
-        &sacrifice_id.to_string(),
+    let meaning = req.signature_meaning.unwrap_or_else(|| sig_type.default_meaning());
+
+    // 鎖住記錄後簽章並鎖定，三者於同一交易，避免簽章後、鎖定前記錄被修改
+    let mut tx = state.db.begin().await?;
+    SignatureService::lock_for_signing(&mut tx, record_type, record_id).await?;
+    let signature = SignatureService::sign_entity(
+        &mut tx,
+        record_type,
+        &record_id.to_string(),
         current_user.id,
         &user.password_hash,
         sig_type,
-        &content,
-        None,
-        None,
+        meaning,
     ).await?;
+    SignatureService::lock_record(&mut tx, record_type, record_id, current_user.id).await?;
+    tx.commit().await?;
 
-    // 鎖定記錄
-    SignatureService::lock_record(&state.db, "sacrifice", sacrifice_id, current_user.id).await?;
-
-    Ok(Json(SignRecordResponse {
+    Ok(SignRecordResponse {
         signature_id: signature.id,
         signed_at: signature.signed_at.to_rfc3339(),
         is_locked: true,
-    }))
+    })
 }
 
-/// 取得犧牲記錄簽章狀態
-pub async fn get_sacrifice_signature_status(
-    State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
-    Path(sacrifice_id): Path<i32>,
-) -> Result<Json<SignatureStatusResponse>> {
-    let is_signed = SignatureService::is_signed(&state.db, "sacrifice", &sacrifice_id.to_string()).await?;
-    let is_locked = SignatureService::is_locked(&state.db, "sacrifice", sacrifice_id).await?;
-    
-    let signatures = SignatureService::get_signatures(&state.db, "sacrifice", &sacrifice_id.to_string()).await?;
-    
-    let mut signature_infos = Vec::new();
-    for sig in signatures {
-        let signer_name: Option<String> = sqlx::query_scalar(
-            "SELECT display_name FROM users WHERE id = $1"
-        )
-        .bind(sig.signer_id)
-        .fetch_optional(&state.db)
-        .await?;
+// ============================================
+// Generic Signature Verification
+// ============================================
 
-        signature_infos.push(SignatureInfo {
-            id: sig.id,
-            signature_type: sig.signature_type,
-            signer_name,
-            signed_at: sig.signed_at.to_rfc3339(),
-        });
-    }
+/// 列出實體簽章並以目前內容驗證
+pub async fn get_entity_signatures(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path((entity_type, entity_id)): Path<(String, String)>,
+) -> Result<Json<EntitySignaturesResponse>> {
+    ensure_signature_access(&state, &current_user, &entity_type, &entity_id).await?;
+
+    let signatures = SignatureService::verify_entity(&state.db, &entity_type, &entity_id, current_user.id).await?;
+    let is_signed = signatures.iter().any(|s| s.is_valid);
+    let all_valid = is_signed
+        && signatures.iter().filter(|s| s.is_valid).all(|s| s.content_matches);
+
+    let is_locked = match (entity_type.as_str(), entity_id.parse::<i32>()) {
+        ("sacrifice" | "observation" | "surgery" | "pathology", Ok(record_id)) => {
+            SignatureService::is_locked(&state.db, &entity_type, record_id).await?
+        }
+        _ => false,
+    };
 
-    Ok(Json(SignatureStatusResponse {
+    Ok(Json(EntitySignaturesResponse {
+        entity_type,
+        entity_id,
         is_signed,
         is_locked,
-        signatures: signature_infos,
+        all_valid,
+        signatures,
     }))
 }
 
+/// 依實體類型檢查查看簽章的權限
+async fn ensure_signature_access(
+    state: &AppState,
+    current_user: &CurrentUser,
+    entity_type: &str,
+    entity_id: &str,
+) -> Result<()> {
+    match entity_type {
+        "sacrifice" | "observation" | "surgery" | "pathology" => {
+            let permission = if entity_type == "pathology" {
+                "animal.pathology.view"
+            } else {
+                "animal.record.view"
+            };
+            require_permission!(current_user, permission);
+
+            let record_id = entity_id.parse::<i32>()
+                .map_err(|_| AppError::Validation(format!("無效的記錄 ID: {}", entity_id)))?;
+            ensure_record_scope(state, current_user, entity_type, record_id).await?;
+        }
+        "protocol" | "protocol_amendment" => {
+            require_permission!(current_user, "aup.protocol.view_own");
+
+            let id = entity_id.parse::<Uuid>()
+                .map_err(|_| AppError::Validation(format!("無效的記錄 ID: {}", entity_id)))?;
+            let protocol_id = if entity_type == "protocol" {
+                Some(id)
+            } else {
+                sqlx::query_scalar("SELECT protocol_id FROM protocol_amendments WHERE id = $1")
+                    .bind(id)
+                    .fetch_optional(&state.db)
+                    .await?
+            };
+            let protocol_id = protocol_id
+                .ok_or_else(|| AppError::NotFound("Protocol not found".to_string()))?;
+            AccessService::ensure_protocol(&state.db, current_user, protocol_id).await?;
+        }
+        "document" => {
+            require_permission!(current_user, "erp.document.view");
+        }
+        "leave" => {
+            let leave_id = entity_id.parse::<Uuid>()
+                .map_err(|_| AppError::Validation(format!("無效的記錄 ID: {}", entity_id)))?;
+            let leave: Option<(Uuid, Option<Uuid>)> = sqlx::query_as(
+                "SELECT user_id, current_approver_id FROM leave_requests WHERE id = $1"
+            )
+            .bind(leave_id)
+            .fetch_optional(&state.db)
+            .await?;
+            let (owner_id, approver_id) = leave
+                .ok_or_else(|| AppError::NotFound("找不到請假申請".to_string()))?;
+
+            if owner_id != current_user.id
+                && approver_id != Some(current_user.id)
+                && !LeaveApprovalService::can_override(current_user)
+                && !current_user.has_permission("hr.leave.view_all")
+            {
+                return Err(AppError::Forbidden("無權查看此請假的簽章".to_string()));
+            }
+        }
+        _ => {
+            return Err(AppError::Validation(format!("不支援的簽章實體類型: {}", entity_type)));
+        }
+    }
+
+    Ok(())
+}
+
+/// 確認動物紀錄所屬豬隻在使用者的資料範圍內，範圍外回應 404
+async fn ensure_record_scope(
+    state: &AppState,
+    current_user: &CurrentUser,
+    record_type: &str,
+    record_id: i32,
+) -> Result<()> {
+    let record = match record_type {
+        "sacrifice" => PigRecord::Sacrifice,
+        "observation" => PigRecord::Observation,
+        "surgery" => PigRecord::Surgery,
+        "weight" => PigRecord::Weight,
+        "vaccination" => PigRecord::Vaccination,
+        "pathology" => PigRecord::Pathology,
+        _ => return Err(AppError::Validation(format!("不支援的記錄類型: {}", record_type))),
+    };
+    AccessService::ensure_pig_record(&state.db, current_user, record, record_id).await
+}
+
 // ============================================
-// Observation Record Signature
+// Record Unlock
 // ============================================
 
-/// 為觀察記錄簽章
-pub async fn sign_observation_record(
+/// 解除已鎖定的紀錄（需說明原因，並由解鎖者與共同簽署者以密碼簽章）
+pub async fn unlock_record(
     State(state): State<AppState>,
     Extension(current_user): Extension<CurrentUser>,
-    Path(observation_id): Path<i32>,
-    Json(req): Json<SignRecordRequest>,
-) -> Result<Json<SignRecordResponse>> {
-    require_permission!(current_user, "animal.record.view");
+    Path((record_type, record_id)): Path<(String, i32)>,
+    Json(req): Json<UnlockRecordRequest>,
+) -> Result<Json<UnlockRecordResponse>> {
+    require_permission!(current_user, "animal.record.unlock");
+    ensure_record_scope(&state, &current_user, &record_type, record_id).await?;
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
 
     let user = AuthService::verify_password_by_id(&state.db, current_user.id, &req.password)
         .await
         .map_err(|_| AppError::Unauthorized)?;
+    let co_signer = AuthService::verify_password_by_email(&state.db, &req.co_signer_email, &req.co_signer_password)
+        .await
+        .map_err(|_| AppError::Unauthorized)?;
+
+    let (roles, permissions) = AuthService::get_user_roles_permissions(&state.db, co_signer.id).await?;
+    let co_signer_user = CurrentUser {
+        id: co_signer.id,
+        email: co_signer.email.clone(),
+        roles,
+        permissions,
+        session_id: Uuid::nil(),
+    };
+    if !co_signer_user.has_permission("animal.record.unlock") {
+        return Err(AppError::Forbidden("共同簽署者無解鎖權限".to_string()));
+    }
 
-    let content: Option<String> = sqlx::query_scalar(
-        r#"
-        SELECT CONCAT(
-            'observation_id:', id::text,
-            ',pig_id:', pig_id::text,
-            ',date:', event_date::text,
-            ',content:', content
-        ) FROM pig_observations WHERE id = $1
-        "#
-    )
-    .bind(observation_id)
-    .fetch_optional(&state.db)
-    .await?;
-
-    let content = content.ok_or_else(|| AppError::NotFound("找不到觀察記錄".into()))?;
-
-    let signature = SignatureService::sign(
+    let unlock = SignatureService::unlock_record(
         &state.db,
-        "observation",
-        &observation_id.to_string(),
-        current_user.id,
-        &user.password_hash,
-        SignatureType::Confirm,
-        &content,
-        None,
-        None,
+        &record_type,
+        record_id,
+        UnlockSigner { user_id: current_user.id, password_hash: &user.password_hash },
+        UnlockSigner { user_id: co_signer.id, password_hash: &co_signer.password_hash },
+        &req.reason,
     ).await?;
 
-    SignatureService::lock_record(&state.db, "observation", observation_id, current_user.id).await?;
-
-    Ok(Json(SignRecordResponse {
-        signature_id: signature.id,
-        signed_at: signature.signed_at.to_rfc3339(),
-        is_locked: true,
+    Ok(Json(UnlockRecordResponse {
+        signature_id: unlock.signature.id,
+        co_signature_id: unlock.co_signature.id,
+        unlocked_at: unlock.signature.signed_at.to_rfc3339(),
+        is_locked: false,
     }))
 }
 
@@ -233,6 +349,8 @@ pub async fn add_record_annotation(
     Path((record_type, record_id)): Path<(String, i32)>,
     Json(req): Json<CreateAnnotationRequest>,
 ) -> Result<Json<AnnotationResponse>> {
+    require_permission!(current_user, "animal.record.view");
+    ensure_record_scope(&state, &current_user, &record_type, record_id).await?;
     req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
 
     // 檢查記錄是否已鎖定
@@ -296,9 +414,12 @@ pub async fn add_record_annotation(
 /// 取得記錄的所有附註
 pub async fn get_record_annotations(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path((record_type, record_id)): Path<(String, i32)>,
 ) -> Result<Json<Vec<AnnotationResponse>>> {
+    require_permission!(current_user, "animal.record.view");
+    ensure_record_scope(&state, &current_user, &record_type, record_id).await?;
+
     let annotations = AnnotationService::get_by_record(&state.db, &record_type, record_id).await?;
 
     let mut responses = Vec::new();
@@ -5,9 +5,12 @@ use axum::{
 
 use crate::{
     middleware::CurrentUser,
-    models::{InventoryOnHand, InventoryQuery, LowStockAlert, StockLedgerDetail, StockLedgerQuery},
+    models::{
+        CostLayerQuery, InventoryByLocation, InventoryOnHand, InventoryQuery, LowStockAlert, StockCostLayer,
+        StockLedgerDetail, StockLedgerQuery,
+    },
     require_permission,
-    services::StockService,
+    services::{CostingService, StockService},
     AppState, Result,
 };
 
@@ -23,6 +26,18 @@ pub async fn get_inventory_on_hand(
     Ok(Json(inventory))
 }
 
+/// 取得庫存現況（依儲位）
+pub async fn get_inventory_by_location(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Query(query): Query<InventoryQuery>,
+) -> Result<Json<Vec<InventoryByLocation>>> {
+    require_permission!(current_user, "erp.stock.view");
+    
+    let inventory = StockService::get_on_hand_by_location(&state.db, &query).await?;
+    Ok(Json(inventory))
+}
+
 /// 取得庫存流水
 pub async fn get_stock_ledger(
     State(state): State<AppState>,
@@ -45,3 +60,15 @@ pub async fn get_low_stock_alerts(
     let alerts = StockService::get_low_stock_alerts(&state.db).await?;
     Ok(Json(alerts))
 }
+
+/// 取得庫存成本層
+pub async fn get_cost_layers(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Query(query): Query<CostLayerQuery>,
+) -> Result<Json<Vec<StockCostLayer>>> {
+    require_permission!(current_user, "erp.stock.view");
+    
+    let layers = CostingService::list_layers(&state.db, &query).await?;
+    Ok(Json(layers))
+}
@@ -6,14 +6,19 @@ use axum::{
     Extension, Json,
 };
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
+use validator::Validate;
 
 use crate::{
     error::AppError,
     middleware::CurrentUser,
+    models::{Attachment, DeleteRequest, NewAttachment},
     require_permission,
-    services::{FileCategory, FileService, UploadResult},
+    services::{
+        AccessService, AttachmentService, FileCategory, FileService, IntegrityProblem, IntegrityService,
+        LeaveApprovalService, PigRecord, UploadResult,
+    },
     AppState, Result,
 };
 
@@ -25,6 +30,7 @@ pub struct UploadResponse {
     pub file_path: String,
     pub file_size: i64,
     pub mime_type: String,
+    pub content_hash: String,
 }
 
 impl From<UploadResult> for UploadResponse {
@@ -35,24 +41,11 @@ impl From<UploadResult> for UploadResponse {
             file_path: result.file_path,
             file_size: result.file_size,
             mime_type: result.mime_type,
+            content_hash: result.content_hash,
         }
     }
 }
 
-/// 附件資料結構
-#[derive(Debug, Serialize, sqlx::FromRow)]
-pub struct Attachment {
-    pub id: Uuid,
-    pub entity_type: String,
-    pub entity_id: String,
-    pub file_name: String,
-    pub file_path: String,
-    pub file_size: i64,
-    pub mime_type: String,
-    pub uploaded_by: Uuid,
-    pub created_at: chrono::DateTime<chrono::Utc>,
-}
-
 /// 上傳查詢參數
 #[derive(Debug, Deserialize)]
 pub struct UploadQuery {
@@ -68,6 +61,9 @@ pub async fn upload_protocol_attachment(
     mut multipart: Multipart,
 ) -> Result<Json<Vec<UploadResponse>>> {
     require_permission!(current_user, "aup.protocol.edit");
+    if !AccessService::can_view_protocol(&state.db, &current_user, protocol_id).await? {
+        return Err(AppError::Forbidden("You don't have permission to access this protocol".to_string()));
+    }
 
     let mut results = Vec::new();
 
@@ -84,29 +80,29 @@ pub async fn upload_protocol_attachment(
             .map(String::from)
             .unwrap_or_else(|| "application/octet-stream".to_string());
 
-        let data = field.bytes().await.map_err(|e| {
-            AppError::Validation(format!("Failed to read file data: {}", e))
-        })?;
-
         // 上傳檔案
-        let upload_result = FileService::upload(
+        let mut stored = FileService::upload_stream(
+            &state.db,
             FileCategory::ProtocolAttachment,
             &file_name,
             &content_type,
-            &data,
-            Some(&protocol_id.to_string()),
+            field,
         ).await?;
 
         // 儲存附件記錄到資料庫
-        save_attachment(
-            &state.db,
+        let attachment_id = save_attachment(
+            &mut stored.tx,
             "protocol",
             &protocol_id.to_string(),
-            &upload_result,
+            &stored.result,
             current_user.id,
+            FileCategory::ProtocolAttachment,
         ).await?;
+        stored.tx.commit().await?;
 
-        results.push(UploadResponse::from(upload_result));
+        let mut response = UploadResponse::from(stored.result);
+        response.id = attachment_id.to_string();
+        results.push(response);
     }
 
     if results.is_empty() {
@@ -124,6 +120,7 @@ pub async fn upload_pig_photo(
     mut multipart: Multipart,
 ) -> Result<Json<Vec<UploadResponse>>> {
     require_permission!(current_user, "animal.info.edit");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
 
     let mut results = Vec::new();
 
@@ -140,27 +137,27 @@ pub async fn upload_pig_photo(
             .map(String::from)
             .unwrap_or_else(|| "application/octet-stream".to_string());
 
-        let data = field.bytes().await.map_err(|e| {
-            AppError::Validation(format!("Failed to read file data: {}", e))
-        })?;
-
-        let upload_result = FileService::upload(
+        let mut stored = FileService::upload_stream(
+            &state.db,
             FileCategory::PigPhoto,
             &file_name,
             &content_type,
-            &data,
-            Some(&pig_id.to_string()),
+            field,
         ).await?;
 
-        save_attachment(
-            &state.db,
+        let attachment_id = save_attachment(
+            &mut stored.tx,
             "pig",
             &pig_id.to_string(),
-            &upload_result,
+            &stored.result,
             current_user.id,
+            FileCategory::PigPhoto,
         ).await?;
+        stored.tx.commit().await?;
 
-        results.push(UploadResponse::from(upload_result));
+        let mut response = UploadResponse::from(stored.result);
+        response.id = attachment_id.to_string();
+        results.push(response);
     }
 
     if results.is_empty() {
@@ -178,6 +175,7 @@ pub async fn upload_pathology_report(
     mut multipart: Multipart,
 ) -> Result<Json<Vec<UploadResponse>>> {
     require_permission!(current_user, "animal.info.edit");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
 
     let mut results = Vec::new();
 
@@ -194,27 +192,27 @@ pub async fn upload_pathology_report(
             .map(String::from)
             .unwrap_or_else(|| "application/octet-stream".to_string());
 
-        let data = field.bytes().await.map_err(|e| {
-            AppError::Validation(format!("Failed to read file data: {}", e))
-        })?;
-
-        let upload_result = FileService::upload(
+        let mut stored = FileService::upload_stream(
+            &state.db,
             FileCategory::PathologyReport,
             &file_name,
             &content_type,
-            &data,
-            Some(&pig_id.to_string()),
+            field,
         ).await?;
 
-        save_attachment(
-            &state.db,
+        let attachment_id = save_attachment(
+            &mut stored.tx,
             "pathology",
             &pig_id.to_string(),
-            &upload_result,
+            &stored.result,
             current_user.id,
+            FileCategory::PathologyReport,
         ).await?;
+        stored.tx.commit().await?;
 
-        results.push(UploadResponse::from(upload_result));
+        let mut response = UploadResponse::from(stored.result);
+        response.id = attachment_id.to_string();
+        results.push(response);
     }
 
     if results.is_empty() {
@@ -232,6 +230,9 @@ pub async fn upload_vet_recommendation_attachment(
     mut multipart: Multipart,
 ) -> Result<Json<Vec<UploadResponse>>> {
     require_permission!(current_user, "animal.vet.upload_attachment");
+    let record = vet_record(&record_type)
+        .ok_or_else(|| AppError::Validation(format!("Invalid record type: {}", record_type)))?;
+    AccessService::ensure_pig_record(&state.db, &current_user, record, record_id).await?;
 
     let mut results = Vec::new();
 
@@ -248,28 +249,28 @@ pub async fn upload_vet_recommendation_attachment(
             .map(String::from)
             .unwrap_or_else(|| "application/octet-stream".to_string());
 
-        let data = field.bytes().await.map_err(|e| {
-            AppError::Validation(format!("Failed to read file data: {}", e))
-        })?;
-
         let entity_id = format!("{}_{}", record_type, record_id);
-        let upload_result = FileService::upload(
+        let mut stored = FileService::upload_stream(
+            &state.db,
             FileCategory::VetRecommendation,
             &file_name,
             &content_type,
-            &data,
-            Some(&entity_id),
+            field,
         ).await?;
 
-        save_attachment(
-            &state.db,
+        let attachment_id = save_attachment(
+            &mut stored.tx,
             "vet_recommendation",
             &entity_id,
-            &upload_result,
+            &stored.result,
             current_user.id,
+            FileCategory::VetRecommendation,
         ).await?;
+        stored.tx.commit().await?;
 
-        results.push(UploadResponse::from(upload_result));
+        let mut response = UploadResponse::from(stored.result);
+        response.id = attachment_id.to_string();
+        results.push(response);
     }
 
     if results.is_empty() {
@@ -300,29 +301,29 @@ pub async fn upload_leave_attachment(
             .map(String::from)
             .unwrap_or_else(|| "application/octet-stream".to_string());
 
-        let data = field.bytes().await.map_err(|e| {
-            AppError::Validation(format!("Failed to read file data: {}", e))
-        })?;
-
         // 上傳檔案到 leave-attachments 目錄
-        let upload_result = FileService::upload(
+        let mut stored = FileService::upload_stream(
+            &state.db,
             FileCategory::LeaveAttachment,
             &file_name,
             &content_type,
-            &data,
-            Some(&current_user.id.to_string()),
+            field,
         ).await?;
 
         // 儲存附件記錄到資料庫
-        save_attachment(
-            &state.db,
+        let attachment_id = save_attachment(
+            &mut stored.tx,
             "leave_request",
             &current_user.id.to_string(),
-            &upload_result,
+            &stored.result,
             current_user.id,
+            FileCategory::LeaveAttachment,
         ).await?;
+        stored.tx.commit().await?;
 
-        results.push(UploadResponse::from(upload_result));
+        let mut response = UploadResponse::from(stored.result);
+        response.id = attachment_id.to_string();
+        results.push(response);
     }
 
     if results.is_empty() {
@@ -340,6 +341,7 @@ pub async fn upload_sacrifice_photo(
     mut multipart: Multipart,
 ) -> Result<Json<Vec<UploadResponse>>> {
     require_permission!(current_user, "animal.record.create");
+    AccessService::ensure_pig(&state.db, &current_user, pig_id).await?;
 
     // 檢查犧牲記錄是否存在
     let sacrifice_exists: Option<i32> = sqlx::query_scalar(
@@ -368,28 +370,25 @@ pub async fn upload_sacrifice_photo(
             .map(String::from)
             .unwrap_or_else(|| "application/octet-stream".to_string());
 
-        let data = field.bytes().await.map_err(|e| {
-            AppError::Validation(format!("Failed to read file data: {}", e))
-        })?;
-
-        let upload_result = FileService::upload(
+        let mut stored = FileService::upload_stream(
+            &state.db,
             FileCategory::PigPhoto,
             &file_name,
             &content_type,
-            &data,
-            Some(&pig_id.to_string()),
+            field,
         ).await?;
 
         // 儲存到 pig_record_attachments 表
         save_pig_record_attachment(
-            &state.db,
+            &mut stored.tx,
             "sacrifice",
             sacrifice_id,
             "photo",
-            &upload_result,
+            &stored.result,
         ).await?;
+        stored.tx.commit().await?;
 
-        results.push(UploadResponse::from(upload_result));
+        results.push(UploadResponse::from(stored.result));
     }
 
     if results.is_empty() {
@@ -401,7 +400,7 @@ pub async fn upload_sacrifice_photo(
 
 /// 儲存豬隻記錄附件到 pig_record_attachments 表
 async fn save_pig_record_attachment(
-    db: &PgPool,
+    conn: &mut PgConnection,
     record_type: &str,
     record_id: i32,
     file_type: &str,
@@ -421,128 +420,358 @@ async fn save_pig_record_attachment(
     .bind(&upload_result.file_path)
     .bind(upload_result.file_size)
     .bind(&upload_result.mime_type)
-    .fetch_one(db)
+    .fetch_one(conn)
     .await?;
 
     Ok(id.0)
 }
 
-/// 列出附件清單
+/// 列出附件清單（需可存取所屬實體）
 pub async fn list_attachments(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Query(query): Query<UploadQuery>,
 ) -> Result<Json<Vec<Attachment>>> {
-    let entity_type = query.entity_type.unwrap_or_default();
-    let entity_id = query.entity_id.unwrap_or_default();
+    let (Some(entity_type), Some(entity_id)) = (query.entity_type, query.entity_id) else {
+        return Err(AppError::Validation("entity_type and entity_id are required".to_string()));
+    };
 
-    let attachments: Vec<Attachment> = sqlx::query_as(
-        r#"
-        SELECT id, entity_type, entity_id, file_name, file_path, 
-               file_size, mime_type, uploaded_by, created_at
-        FROM attachments
-        WHERE entity_type = $1 AND entity_id = $2
-        ORDER BY created_at DESC
-        "#,
+    if !can_access_entity(&state.db, &current_user, &entity_type, &entity_id).await? {
+        return Err(AppError::NotFound("Attachments not found".to_string()));
+    }
+
+    let attachments = AttachmentService::list_current(&state.db, &entity_type, &entity_id).await?;
+
+    Ok(Json(attachments))
+}
+
+/// 列出附件版本歷史（含已取代、已刪除的版本）
+pub async fn list_attachment_history(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Query(query): Query<UploadQuery>,
+) -> Result<Json<Vec<Attachment>>> {
+    let (Some(entity_type), Some(entity_id)) = (query.entity_type, query.entity_id) else {
+        return Err(AppError::Validation("entity_type and entity_id are required".to_string()));
+    };
+
+    if !can_access_entity(&state.db, &current_user, &entity_type, &entity_id).await? {
+        return Err(AppError::NotFound("Attachments not found".to_string()));
+    }
+
+    let attachments = AttachmentService::list_history(&state.db, &entity_type, &entity_id).await?;
+    Ok(Json(attachments))
+}
+
+/// 上傳附件新版本（取代目前版本，舊版本保留）
+///
+/// multipart 欄位：一個檔案，及選填的 `reason` 文字欄位。
+pub async fn upload_attachment_version(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> Result<Json<Attachment>> {
+    let previous = find_accessible_attachment(&state.db, &current_user, id).await?;
+
+    // 與刪除相同，只有上傳者或管理員可以取代
+    if previous.uploaded_by != current_user.id && !is_admin(&current_user) {
+        return Err(AppError::Forbidden("You can only replace your own attachments".to_string()));
+    }
+
+    let category = match previous.entity_type.as_str() {
+        "protocol" => {
+            require_permission!(current_user, "aup.protocol.edit");
+            FileCategory::ProtocolAttachment
+        }
+        "pig" => {
+            require_permission!(current_user, "animal.info.edit");
+            FileCategory::PigPhoto
+        }
+        "pathology" => {
+            require_permission!(current_user, "animal.info.edit");
+            FileCategory::PathologyReport
+        }
+        "vet_recommendation" => {
+            require_permission!(current_user, "animal.vet.upload_attachment");
+            FileCategory::VetRecommendation
+        }
+        "leave_request" if previous.entity_id == current_user.id.to_string() => FileCategory::LeaveAttachment,
+        _ => {
+            return Err(AppError::Forbidden("You don't have permission to replace this attachment".to_string()));
+        }
+    };
+
+    let mut reason = None;
+    let mut upload_result = None;
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        AppError::Validation(format!("Failed to read multipart field: {}", e))
+    })? {
+        if field.name() == Some("reason") {
+            let text = field.text().await.map_err(|e| {
+                AppError::Validation(format!("Failed to read multipart field: {}", e))
+            })?;
+            reason = Some(text.trim().to_string()).filter(|r| !r.is_empty());
+            continue;
+        }
+        if upload_result.is_some() {
+            return Err(AppError::Validation("Only one file can be uploaded as a new version".to_string()));
+        }
+
+        let file_name = field
+            .file_name()
+            .map(String::from)
+            .unwrap_or_else(|| previous.file_name.clone());
+
+        let content_type = field
+            .content_type()
+            .map(String::from)
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+
+        upload_result = Some(FileService::upload_stream(&state.db, category, &file_name, &content_type, field).await?);
+    }
+
+    let mut stored = upload_result.ok_or_else(|| AppError::Validation("No files uploaded".to_string()))?;
+    let attachment = AttachmentService::replace(
+        &mut stored.tx,
+        previous.id,
+        &new_attachment(&previous.entity_type, &previous.entity_id, &stored.result, current_user.id, category),
+        reason.as_deref().unwrap_or("上傳新版本"),
     )
-    .bind(&entity_type)
-    .bind(&entity_id)
-    .fetch_all(&state.db)
     .await?;
+    stored.tx.commit().await?;
 
-    Ok(Json(attachments))
+    Ok(Json(attachment))
 }
 
 /// 下載附件
 pub async fn download_attachment(
     State(state): State<AppState>,
-    Extension(_current_user): Extension<CurrentUser>,
+    Extension(current_user): Extension<CurrentUser>,
     Path(id): Path<Uuid>,
 ) -> Result<Response> {
-    // 從資料庫查詢附件資訊
-    let attachment: Attachment = sqlx::query_as(
-        r#"SELECT * FROM attachments WHERE id = $1"#,
-    )
-    .bind(id)
-    .fetch_optional(&state.db)
-    .await?
-    .ok_or_else(|| AppError::NotFound("Attachment not found".to_string()))?;
+    let attachment = find_accessible_attachment(&state.db, &current_user, id).await?;
+    if attachment.purged_at.is_some() {
+        return Err(AppError::NotFound("Attachment file has been purged under the retention policy".to_string()));
+    }
 
-    // 讀取檔案資料
-    let (data, _) = FileService::read(&attachment.file_path).await?;
+    // 開啟檔案串流；有記錄雜湊者於傳輸時驗證，不符則中斷下載並建立安全警報
+    let object = match attachment.content_hash.as_deref() {
+        Some(expected) => {
+            let db = state.db.clone();
+            let attachment_id = attachment.id;
+            let file_path = attachment.file_path.clone();
+            let expected_hash = expected.to_string();
+            let user_id = current_user.id;
+            FileService::open_verified(&attachment.file_path, expected, move |actual| {
+                tokio::spawn(async move {
+                    let problem = IntegrityProblem::Tampered { expected: expected_hash, actual };
+                    if let Err(e) = IntegrityService::raise_alert(&db, attachment_id, &file_path, &problem, Some(user_id)).await {
+                        tracing::error!("[Integrity] Failed to raise alert: {}", e);
+                    }
+                });
+            })
+            .await
+        }
+        None => FileService::open(&attachment.file_path).await.map(|(object, _)| object),
+    };
+    let object = match object {
+        Err(AppError::NotFound(_)) => {
+            IntegrityService::raise_alert(
+                &state.db,
+                attachment.id,
+                &attachment.file_path,
+                &IntegrityProblem::Missing,
+                Some(current_user.id),
+            )
+            .await?;
+            return Err(AppError::NotFound("Attachment file is missing".to_string()));
+        }
+        other => other?,
+    };
 
     // 建立回應
-    let response = Response::builder()
+    let mut builder = Response::builder()
         .status(StatusCode::OK)
         .header(header::CONTENT_TYPE, &attachment.mime_type)
         .header(
             header::CONTENT_DISPOSITION,
             format!("attachment; filename=\"{}\"", attachment.file_name),
-        )
-        .body(Body::from(data))
+        );
+    if let Some(size) = object.size {
+        builder = builder.header(header::CONTENT_LENGTH, size);
+    }
+    let response = builder
+        .body(Body::from_stream(object.stream))
         .map_err(|e| AppError::Internal(format!("Failed to build response: {}", e)))?;
 
     Ok(response)
 }
 
-/// 刪除附件
+/// 刪除附件（軟刪除 + 刪除原因）- GLP 合規
 pub async fn delete_attachment(
     State(state): State<AppState>,
     Extension(current_user): Extension<CurrentUser>,
     Path(id): Path<Uuid>,
+    Json(req): Json<DeleteRequest>,
 ) -> Result<StatusCode> {
-    // 查詢附件資訊
-    let attachment: Attachment = sqlx::query_as(
-        r#"SELECT * FROM attachments WHERE id = $1"#,
-    )
-    .bind(id)
-    .fetch_optional(&state.db)
-    .await?
-    .ok_or_else(|| AppError::NotFound("Attachment not found".to_string()))?;
+    req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+    let attachment = find_accessible_attachment(&state.db, &current_user, id).await?;
 
     // 檢查權限，只有上傳者或管理員可以刪除
-    let is_admin = current_user.roles.contains(&"SYSTEM_ADMIN".to_string())
-        || current_user.roles.contains(&"admin".to_string());
-    if attachment.uploaded_by != current_user.id && !is_admin {
+    if attachment.uploaded_by != current_user.id && !is_admin(&current_user) {
         return Err(AppError::Forbidden("You can only delete your own attachments".to_string()));
     }
 
-    // 刪除檔案
-    FileService::delete(&attachment.file_path).await?;
-
-    // 從資料庫刪除記錄
-    sqlx::query(r#"DELETE FROM attachments WHERE id = $1"#)
-        .bind(id)
-        .execute(&state.db)
-        .await?;
+    AttachmentService::delete_with_reason(&state.db, id, &req.reason, current_user.id).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
-/// 儲存附件記錄到資料庫
+/// 查詢附件並檢查所屬實體的存取權限；無權限時視同不存在，避免洩漏附件 ID
+async fn find_accessible_attachment(db: &PgPool, user: &CurrentUser, id: Uuid) -> Result<Attachment> {
+    let attachment = AttachmentService::get(db, id).await?;
+
+    match attachment {
+        Some(attachment) if can_access_entity(db, user, &attachment.entity_type, &attachment.entity_id).await? => {
+            Ok(attachment)
+        }
+        _ => Err(AppError::NotFound("Attachment not found".to_string())),
+    }
+}
+
+/// 依附件所屬實體判斷存取權限
+///
+/// - protocol：PI、共同編輯者、審查委員或 IACUC 人員
+/// - pig / pathology：可查看該豬隻（依 iacuc_no 所屬計畫）
+/// - vet_recommendation：可查看該紀錄所屬豬隻
+/// - leave_request：上傳者本人、其請假的審核人或請假管理者
+async fn can_access_entity(db: &PgPool, user: &CurrentUser, entity_type: &str, entity_id: &str) -> Result<bool> {
+    match entity_type {
+        "protocol" => match entity_id.parse::<Uuid>() {
+            Ok(protocol_id) => AccessService::can_view_protocol(db, user, protocol_id).await,
+            Err(_) => Ok(false),
+        },
+        "pig" => match entity_id.parse::<i32>() {
+            Ok(pig_id) => AccessService::can_view_pig(db, user, pig_id).await,
+            Err(_) => Ok(false),
+        },
+        "pathology" => match entity_id.parse::<i32>() {
+            Ok(pig_id) if user.has_permission("animal.pathology.view") => {
+                AccessService::can_view_pig(db, user, pig_id).await
+            }
+            _ => Ok(false),
+        },
+        "vet_recommendation" => {
+            // entity_id 格式為 {record_type}_{record_id}
+            let Some((record_type, record_id)) = entity_id.rsplit_once('_') else {
+                return Ok(false);
+            };
+            let (Some(record), Ok(record_id)) = (vet_record(record_type), record_id.parse::<i32>()) else {
+                return Ok(false);
+            };
+
+            match AccessService::ensure_pig_record(db, user, record, record_id).await {
+                Ok(()) => Ok(true),
+                Err(AppError::NotFound(_)) => Ok(false),
+                Err(e) => Err(e),
+            }
+        }
+        "leave_request" => {
+            let Ok(owner_id) = entity_id.parse::<Uuid>() else {
+                return Ok(false);
+            };
+            if owner_id == user.id
+                || LeaveApprovalService::can_override(user)
+                || user.has_permission("hr.leave.view_all")
+            {
+                return Ok(true);
+            }
+
+            let is_approver: bool = sqlx::query_scalar(
+                "SELECT EXISTS(SELECT 1 FROM leave_requests WHERE user_id = $1 AND current_approver_id = $2)"
+            )
+            .bind(owner_id)
+            .bind(user.id)
+            .fetch_one(db)
+            .await?;
+
+            Ok(is_approver)
+        }
+        _ => Ok(is_admin(user)),
+    }
+}
+
+/// 獸醫建議附件的紀錄類型
+fn vet_record(record_type: &str) -> Option<PigRecord> {
+    match record_type {
+        "observation" => Some(PigRecord::Observation),
+        "surgery" => Some(PigRecord::Surgery),
+        _ => None,
+    }
+}
+
+fn is_admin(user: &CurrentUser) -> bool {
+    user.roles.contains(&"SYSTEM_ADMIN".to_string())
+        || user.roles.contains(&"admin".to_string())
+}
+
+/// 新增附件紀錄的欄位
+fn new_attachment<'a>(
+    entity_type: &'a str,
+    entity_id: &'a str,
+    upload_result: &'a UploadResult,
+    uploaded_by: Uuid,
+    category: FileCategory,
+) -> NewAttachment<'a> {
+    NewAttachment {
+        category: category.subdirectory(),
+        entity_type,
+        entity_id,
+        file_name: &upload_result.file_name,
+        file_path: &upload_result.file_path,
+        file_size: upload_result.file_size,
+        mime_type: &upload_result.mime_type,
+        content_hash: &upload_result.content_hash,
+        uploaded_by,
+    }
+}
+
+/// 儲存附件記錄到資料庫（於上傳回傳的交易內）
 async fn save_attachment(
-    db: &PgPool,
+    conn: &mut PgConnection,
     entity_type: &str,
     entity_id: &str,
     upload_result: &UploadResult,
     uploaded_by: Uuid,
+    category: FileCategory,
 ) -> Result<Uuid> {
-    let id: (Uuid,) = sqlx::query_as(
-        r#"
-        INSERT INTO attachments (id, entity_type, entity_id, file_name, file_path, file_size, mime_type, uploaded_by)
-        VALUES (gen_random_uuid(), $1, $2, $3, $4, $5, $6, $7)
-        RETURNING id
-        "#,
+    let attachment = AttachmentService::create(
+        conn,
+        &new_attachment(entity_type, entity_id, upload_result, uploaded_by, category),
     )
-    .bind(entity_type)
-    .bind(entity_id)
-    .bind(&upload_result.file_name)
-    .bind(&upload_result.file_path)
-    .bind(upload_result.file_size)
-    .bind(&upload_result.mime_type)
-    .bind(uploaded_by)
-    .fetch_one(db)
     .await?;
 
-    Ok(id.0)
+    Ok(attachment.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{app_state, scope_fixture};
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_external_user_gets_404_for_other_protocols_attachment(db: sqlx::PgPool) {
+        let fixture = scope_fixture(&db).await;
+        let state = app_state(db);
+
+        let result = download_attachment(
+            State(state),
+            Extension(fixture.external_user),
+            Path(fixture.other_attachment),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
 }
@@ -7,9 +7,13 @@ use validator::Validate;
 
 use crate::{
     middleware::CurrentUser,
-    models::{CreateWarehouseRequest, UpdateWarehouseRequest, Warehouse, WarehouseQuery},
+    models::{
+        CreateStorageLocationRequest, CreateWarehouseRequest, StorageLocation,
+        StorageLocationQuery, UpdateStorageLocationRequest, UpdateWarehouseRequest, Warehouse,
+        WarehouseQuery,
+    },
     require_permission,
-    services::WarehouseService,
+    services::{StorageLocationService, WarehouseService},
     AppError, AppState, Result,
 };
 
@@ -75,3 +79,68 @@ pub async fn delete_warehouse(
     WarehouseService::delete(&state.db, id).await?;
     Ok(Json(serde_json::json!({ "message": "Warehouse deleted successfully" })))
 }
+
+/// 建立倉庫儲位
+pub async fn create_storage_location(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(warehouse_id): Path<Uuid>,
+    Json(req): Json<CreateStorageLocationRequest>,
+) -> Result<Json<StorageLocation>> {
+    require_permission!(current_user, "erp.warehouse.edit");
+    req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+    
+    let location = StorageLocationService::create(&state.db, warehouse_id, &req).await?;
+    Ok(Json(location))
+}
+
+/// 列出倉庫儲位
+pub async fn list_storage_locations(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(warehouse_id): Path<Uuid>,
+    Query(query): Query<StorageLocationQuery>,
+) -> Result<Json<Vec<StorageLocation>>> {
+    require_permission!(current_user, "erp.warehouse.view");
+    
+    let locations = StorageLocationService::list(&state.db, warehouse_id, &query).await?;
+    Ok(Json(locations))
+}
+
+/// 取得單個儲位
+pub async fn get_storage_location(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<StorageLocation>> {
+    require_permission!(current_user, "erp.warehouse.view");
+    
+    let location = StorageLocationService::get_by_id(&state.db, id).await?;
+    Ok(Json(location))
+}
+
+/// 更新儲位
+pub async fn update_storage_location(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<UpdateStorageLocationRequest>,
+) -> Result<Json<StorageLocation>> {
+    require_permission!(current_user, "erp.warehouse.edit");
+    req.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+    
+    let location = StorageLocationService::update(&state.db, id, &req).await?;
+    Ok(Json(location))
+}
+
+/// 刪除儲位
+pub async fn delete_storage_location(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    require_permission!(current_user, "erp.warehouse.edit");
+    
+    StorageLocationService::delete(&state.db, id).await?;
+    Ok(Json(serde_json::json!({ "message": "Storage location deleted successfully" })))
+}
@@ -1,5 +1,7 @@
 use std::sync::Arc;
 
+use anyhow::Context;
+
 use axum::{
     http::{header, HeaderValue, Method},
 };
@@ -18,6 +20,8 @@ mod middleware;
 mod models;
 mod routes;
 mod services;
+#[cfg(test)]
+mod test_support;
 
 use services::scheduler::SchedulerService;
 use std::time::Duration;
@@ -161,6 +165,8 @@ async fn ensure_required_permissions(pool: &sqlx::PgPool) -> Result<()> {
     // 需要確保存在的權限清單
     let required_permissions = vec![
         ("animal.source.manage", "管理動物來源", "animal", "可管理動物來源資料"),
+        ("animal.welfare.manage", "管理福祉評分表", "animal", "可設定疼痛 / 福祉評分表與人道終點"),
+        ("animal.record.unlock", "解除紀錄鎖定", "animal", "可於說明原因並簽章後解除已鎖定的 GLP 紀錄"),
     ];
     
     for (code, name, module, description) in required_permissions {
@@ -210,6 +216,8 @@ async fn ensure_all_role_permissions(pool: &sqlx::PgPool) -> Result<()> {
             "erp.stocktake.create",
             // 報表
             "erp.report.view", "erp.report.export", "erp.report.download",
+            // 排程報表
+            "report.schedule", "report.download",
         ]),
         
         // ============================================
@@ -230,6 +238,8 @@ async fn ensure_all_role_permissions(pool: &sqlx::PgPool) -> Result<()> {
             "erp.stock.view",
             // 報表
             "erp.report.view",
+            // 排程報表（收件者可下載）
+            "report.download",
         ]),
         
         // ============================================
@@ -267,8 +277,11 @@ async fn ensure_all_role_permissions(pool: &sqlx::PgPool) -> Result<()> {
             // 動物管理
             "animal.info.view_all", "animal.info.edit", "animal.info.assign",
             "animal.record.view", "animal.record.create", "animal.record.edit",
+            // GLP 紀錄解鎖（需另一位具此權限者共同簽章）
+            "animal.record.unlock",
             // 獸醫師功能
             "animal.vet.recommend", "animal.vet.read",
+            "animal.welfare.manage",
             // 匯出
             "animal.export.medical", "animal.export.observation", "animal.export.surgery", "animal.export.experiment",
         ]),
@@ -306,6 +319,8 @@ async fn ensure_all_role_permissions(pool: &sqlx::PgPool) -> Result<()> {
             // 動物管理 - 僅查看，不含來源管理
             "animal.info.view_all",
             "animal.record.view",
+            // GLP 紀錄解鎖
+            "animal.record.unlock",
         ]),
         
         // ============================================
@@ -325,8 +340,11 @@ async fn ensure_all_role_permissions(pool: &sqlx::PgPool) -> Result<()> {
             "animal.info.view_all", "animal.info.create", "animal.info.edit", 
             "animal.info.assign", "animal.info.import", "animal.info.delete",
             "animal.record.view", "animal.record.create", "animal.record.edit", "animal.record.delete",
+            "animal.record.unlock",
             // 動物來源管理
             "animal.source.manage",
+            // 福祉評分表
+            "animal.welfare.manage",
             // 匯出
             "animal.export.medical", "animal.export.observation", "animal.export.surgery", "animal.export.experiment",
         ]),
@@ -667,10 +685,50 @@ async fn create_database_pool_with_retry(
     ))
 }
 
+/// 執行 `migrate-storage --from <local|s3> --to <local|s3> [--delete-source]`
+async fn run_storage_migration(config: &config::Config, args: &[String]) -> anyhow::Result<()> {
+    let mut from = None;
+    let mut to = None;
+    let mut delete_source = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--from" => from = iter.next().map(|v| v.parse::<services::StorageKind>()),
+            "--to" => to = iter.next().map(|v| v.parse::<services::StorageKind>()),
+            "--delete-source" => delete_source = true,
+            other => anyhow::bail!("Unknown argument: {}", other),
+        }
+    }
+    let usage = "usage: erp-backend migrate-storage --from <local|s3> --to <local|s3> [--delete-source]";
+    let from = from.context(usage)?.map_err(|e| anyhow::anyhow!(e))?;
+    let to = to.context(usage)?.map_err(|e| anyhow::anyhow!(e))?;
+    if from == to {
+        anyhow::bail!("Source and target storage backends must differ");
+    }
+
+    let pool = create_database_pool_with_retry(config).await?;
+    let source = services::build_storage(from, config)?;
+    let target = services::build_storage(to, config)?;
+    let summary = services::migrate_storage(&pool, source.as_ref(), target.as_ref(), delete_source).await?;
+
+    tracing::info!(
+        "[Storage] Migration finished: copied={}, skipped={}, missing={}, failed={}",
+        summary.copied,
+        summary.skipped,
+        summary.missing,
+        summary.failed
+    );
+    if summary.failed > 0 {
+        anyhow::bail!("{} objects failed to migrate", summary.failed);
+    }
+    Ok(())
+}
+
 #[derive(Clone)]
 pub struct AppState {
     pub db: sqlx::PgPool,
     pub config: Arc<config::Config>,
+    pub activity_log: services::ActivityLogWriter,
 }
 
 #[tokio::main]
@@ -691,6 +749,15 @@ async fn main() -> anyhow::Result<()> {
     let config = config::Config::from_env()?;
     let config = Arc::new(config);
 
+    // 檔案儲存後端
+    services::init_storage(services::build_storage(config.storage_backend, &config)?);
+
+    // 子命令：在儲存後端之間搬移檔案
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("migrate-storage") {
+        return run_storage_migration(&config, &args[1..]).await;
+    }
+
     // Create database pool with retry logic
     let pool = match create_database_pool_with_retry(&config).await {
         Ok(pool) => pool,
@@ -775,6 +842,7 @@ async fn main() -> anyhow::Result<()> {
 
     // Create app state
     let state = AppState {
+        activity_log: services::ActivityLogWriter::spawn(pool.clone()),
         db: pool,
         config: config.clone(),
     };
@@ -804,7 +872,7 @@ async fn main() -> anyhow::Result<()> {
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     tracing::info!("Server listening on {}", addr);
 
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()).await?;
 
     Ok(())
 }
@@ -2,9 +2,9 @@
 // 自動記錄 API 請求到 user_activity_logs
 
 use axum::{
-    body::Body,
+    body::{Body, HttpBody},
     extract::{ConnectInfo, State},
-    http::{Method, Request},
+    http::{header, HeaderMap, Method, Request},
     middleware::Next,
     response::Response,
 };
@@ -12,113 +12,217 @@ use chrono::Utc;
 use std::net::SocketAddr;
 use uuid::Uuid;
 
-use crate::AppState;
+use crate::{
+    middleware::CurrentUser,
+    services::{ActivityLogEntry, ActivityLogService},
+    AppState,
+};
+
+/// 擷取新建實體 ID 時可緩衝的最大回應大小
+const MAX_CAPTURE_BYTES: u64 = 1024 * 1024;
 
 /// 活動記錄 Middleware
-/// 自動記錄重要 API 操作到 user_activity_logs 表
+/// 自動記錄重要 API 操作到 user_activity_logs 表（背景批次寫入），
+/// GLP 實體的修改操作另記錄前後快照
 pub async fn activity_logger_middleware(
     State(state): State<AppState>,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     request: Request<Body>,
     next: Next,
 ) -> Response {
     let start = std::time::Instant::now();
-    
+    let created_at = Utc::now();
+
     // 提取請求資訊
     let method = request.method().clone();
     let path = request.uri().path().to_string();
+
+    // 判斷是否需要記錄
+    if !should_log_request(&method, &path) {
+        return next.run(request).await;
+    }
+
     let user_agent = request
         .headers()
-        .get("user-agent")
+        .get(header::USER_AGENT)
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
-    let ip_address = addr.ip().to_string();
-    
-    // 從 extension 取得 user_id（如果有的話）
-    let user_id = request
+    let peer = request
         .extensions()
-        .get::<Uuid>()
-        .cloned();
-    
-    // 決定事件類別和類型
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
+    let ip_address = client_ip(request.headers(), peer);
+
+    // 決定事件類別、類型與實體
     let (event_category, event_type, entity_type) = categorize_request(&method, &path);
-    
-    // 判斷是否需要記錄
-    let should_log = should_log_request(&method, &path);
-    
+    let mut entity_key = entity_key_from_path(&path);
+
+    // GLP 實體的修改操作：記錄修改前快照
+    let capture = method != Method::GET
+        && entity_type.as_deref().is_some_and(ActivityLogService::is_glp_entity);
+    let before_data = match (&entity_type, &entity_key) {
+        (Some(entity_type), Some(key)) if capture => snapshot(&state, entity_type, key).await,
+        _ => None,
+    };
+
     // 執行請求
-    let response = next.run(request).await;
-    
-    // 如果需要記錄，異步寫入資料庫
-    if should_log {
-        let duration_ms = start.elapsed().as_millis() as i32;
-        let status_code = response.status().as_u16() as i32;
-        let is_success = response.status().is_success();
-        let is_suspicious = check_suspicious(&method, &path, status_code);
-        
-        let pool = state.db.clone();
-        let partition_date = Utc::now().date_naive();
-        
-        // 異步記錄，不阻塞請求
-        tokio::spawn(async move {
-            let log_id = Uuid::new_v4();
-            let _ = sqlx::query(
-                r#"
-                INSERT INTO user_activity_logs (
-                    id, partition_date, actor_user_id,
-                    event_category, event_type, event_severity,
-                    entity_type, request_path, request_method, response_status,
-                    ip_address, user_agent, duration_ms,
-                    is_suspicious, created_at
-                ) VALUES (
-                    $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, NOW()
-                )
-                "#,
-            )
-            .bind(log_id)
-            .bind(partition_date)
-            .bind(user_id)
-            .bind(&event_category)
-            .bind(&event_type)
-            .bind(if is_success { "info" } else { "warning" })
-            .bind(&entity_type)
-            .bind(&path)
-            .bind(method.as_str())
-            .bind(status_code)
-            .bind(&ip_address)
-            .bind(&user_agent)
-            .bind(duration_ms)
-            .bind(is_suspicious)
-            .execute(&pool)
-            .await;
-        });
+    let mut response = next.run(request).await;
+
+    let duration_ms = start.elapsed().as_millis() as i32;
+    let status_code = response.status().as_u16() as i32;
+    let is_success = response.status().is_success();
+
+    // 新建時由回應取得 ID，再記錄修改後快照
+    let mut after_data = None;
+    if capture && is_success {
+        if entity_key.is_none() && method == Method::POST {
+            let (parts, body) = response.into_parts();
+            let (body, created_key) = created_entity_key(body).await;
+            response = Response::from_parts(parts, body);
+            entity_key = created_key;
+        }
+        if method != Method::DELETE {
+            if let (Some(entity_type), Some(key)) = (&entity_type, &entity_key) {
+                after_data = snapshot(&state, entity_type, key).await;
+            }
+        }
     }
-    
+
+    // 操作者由 auth middleware 附加於回應
+    let actor = response.extensions().get::<CurrentUser>();
+
+    state.activity_log.record(ActivityLogEntry {
+        created_at,
+        actor_user_id: actor.map(|u| u.id),
+        actor_email: actor.map(|u| u.email.clone()),
+        actor_roles: actor.map(|u| serde_json::json!(u.roles)),
+        session_id: actor.map(|u| u.session_id),
+        event_category,
+        event_type,
+        event_severity: if is_success { "info" } else { "warning" }.to_string(),
+        entity_type,
+        entity_key,
+        before_data,
+        after_data,
+        ip_address,
+        user_agent,
+        is_suspicious: check_suspicious(&method, &path, status_code),
+        request_path: path,
+        request_method: method.as_str().to_string(),
+        response_status: status_code,
+        duration_ms,
+    });
+
     response
 }
 
+/// 取得用戶端 IP：優先採用反向代理設定的 X-Real-IP（nginx 以 $remote_addr 覆寫），
+/// 否則使用連線來源位址。X-Forwarded-For 第一筆可由用戶端偽造，不予採用
+pub fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<String> {
+    headers
+        .get("x-real-ip")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .or_else(|| peer.map(|addr| addr.ip().to_string()))
+}
+
+/// 取得 GLP 實體快照；失敗僅記錄警告，不影響請求
+async fn snapshot(state: &AppState, entity_type: &str, key: &str) -> Option<serde_json::Value> {
+    match ActivityLogService::snapshot(&state.db, entity_type, key).await {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::warn!("Failed to snapshot {} {}: {}", entity_type, key, e);
+            None
+        }
+    }
+}
+
+/// 由新建操作的 JSON 回應取得實體 ID，並還原回應內容
+async fn created_entity_key(body: Body) -> (Body, Option<String>) {
+    let small_enough = body
+        .size_hint()
+        .upper()
+        .is_some_and(|size| size <= MAX_CAPTURE_BYTES);
+    if !small_enough {
+        return (body, None);
+    }
+
+    match axum::body::to_bytes(body, MAX_CAPTURE_BYTES as usize).await {
+        Ok(bytes) => {
+            let key = serde_json::from_slice::<serde_json::Value>(&bytes)
+                .ok()
+                .and_then(|v| match v.get("id") {
+                    Some(serde_json::Value::String(id)) => Some(id.clone()),
+                    Some(serde_json::Value::Number(id)) => Some(id.to_string()),
+                    _ => None,
+                });
+            (Body::from(bytes), key)
+        }
+        Err(e) => {
+            tracing::warn!("Failed to buffer response for activity log: {}", e);
+            (Body::empty(), None)
+        }
+    }
+}
+
+/// 動物底下的子紀錄集合（路徑中的 ID 為動物 ID，非子紀錄 ID）
+const NESTED_COLLECTIONS: [&str; 5] = ["observations", "surgeries", "weights", "vaccinations", "welfare-scores"];
+
+/// 路徑中的實體 ID（UUID 或整數），如 /api/pigs/12/sacrifice → "12"；
+/// 子紀錄集合（如 /api/pigs/12/observations）忽略動物 ID，新建時改由回應取得
+fn entity_key_from_path(path: &str) -> Option<String> {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let resource_index = if segments.get(1) == Some(&"hr") { 2 } else { 1 };
+
+    let nested = segments.get(resource_index) == Some(&"pigs")
+        && segments
+            .get(resource_index + 2)
+            .is_some_and(|s| NESTED_COLLECTIONS.contains(s));
+    let key_index = if nested { resource_index + 3 } else { resource_index + 1 };
+
+    segments
+        .get(key_index)
+        .filter(|s| s.parse::<Uuid>().is_ok() || s.parse::<i64>().is_ok())
+        .map(|s| s.to_string())
+}
+
 /// 分類請求
 fn categorize_request(method: &Method, path: &str) -> (String, String, Option<String>) {
     // 從路徑解析實體類型
     let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
     
-    let entity_type = if segments.len() >= 2 {
-        match segments.get(1) {
-            Some(&"users") => Some("user".to_string()),
-            Some(&"roles") => Some("role".to_string()),
-            Some(&"products") => Some("product".to_string()),
-            Some(&"partners") => Some("partner".to_string()),
-            Some(&"documents") => Some("document".to_string()),
-            Some(&"protocols") => Some("protocol".to_string()),
-            Some(&"pigs") => Some("pig".to_string()),
-            Some(&"leaves") => Some("leave_request".to_string()),
-            Some(&"overtime") => Some("overtime".to_string()),
-            Some(&"attendance") => Some("attendance".to_string()),
-            _ => None,
-        }
-    } else {
-        None
-    };
+    let resource_index = if segments.get(1) == Some(&"hr") { 2 } else { 1 };
+    let resource = segments.get(resource_index).copied();
+    let sub_resource = segments.get(resource_index + 2).copied();
+    
+    let entity_type = match (resource, sub_resource) {
+        // 動物子紀錄（犧牲、病理以動物 ID 為鍵；其餘新建時由回應取得 ID）
+        (Some("pigs"), Some("sacrifice")) => Some("pig_sacrifice"),
+        (Some("pigs"), Some("pathology")) => Some("pig_pathology"),
+        (Some("pigs"), Some("observations")) => Some("pig_observation"),
+        (Some("pigs"), Some("surgeries")) => Some("pig_surgery"),
+        (Some("pigs"), Some("weights")) => Some("pig_weight"),
+        (Some("pigs"), Some("vaccinations")) => Some("pig_vaccination"),
+        (Some("pigs"), Some("welfare-scores")) => Some("pig_welfare_score"),
+        (Some("users"), _) => Some("user"),
+        (Some("roles"), _) => Some("role"),
+        (Some("products"), _) => Some("product"),
+        (Some("partners"), _) => Some("partner"),
+        (Some("warehouses"), _) => Some("warehouse"),
+        (Some("documents"), _) => Some("document"),
+        (Some("protocols"), _) => Some("protocol"),
+        (Some("pigs"), _) => Some("pig"),
+        (Some("observations"), _) => Some("pig_observation"),
+        (Some("surgeries"), _) => Some("pig_surgery"),
+        (Some("weights"), _) => Some("pig_weight"),
+        (Some("vaccinations"), _) => Some("pig_vaccination"),
+        (Some("welfare-scores"), _) => Some("pig_welfare_score"),
+        (Some("leaves"), _) => Some("leave_request"),
+        (Some("overtime"), _) => Some("overtime"),
+        (Some("attendance"), _) => Some("attendance"),
+        _ => None,
+    }
+    .map(str::to_string);
     
     // 決定事件類別
     let event_category = match segments.get(1) {
@@ -128,8 +232,9 @@ fn categorize_request(method: &Method, path: &str) -> (String, String, Option<St
         Some(&"users") | Some(&"roles") => "user_management",
         Some(&"products") | Some(&"partners") | Some(&"warehouses") => "master_data",
         Some(&"documents") | Some(&"inventory") => "transaction",
-        Some(&"protocols") => "aup",
-        Some(&"pigs") => "animal_management",
+        Some(&"protocols") | Some(&"reviews") => "aup",
+        Some(&"pigs") | Some(&"observations") | Some(&"surgeries") | Some(&"weights")
+        | Some(&"vaccinations") | Some(&"welfare-scores") => "animal_management",
         Some(&"reports") => "report",
         Some(&"notifications") => "notification",
         _ => "system",
@@ -212,3 +317,64 @@ fn check_suspicious(method: &Method, path: &str, status_code: i32) -> bool {
     
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entity_key_for_pig_keyed_records() {
+        assert_eq!(entity_key_from_path("/api/pigs/12/sacrifice"), Some("12".to_string()));
+        assert_eq!(entity_key_from_path("/api/observations/34"), Some("34".to_string()));
+        assert_eq!(entity_key_from_path("/api/hr/leaves/56/approve"), Some("56".to_string()));
+    }
+
+    #[test]
+    fn test_nested_post_ignores_parent_id() {
+        let (_, event_type, entity_type) = categorize_request(&Method::POST, "/api/pigs/12/observations");
+        assert_eq!(event_type, "create");
+        assert_eq!(entity_type.as_deref(), Some("pig_observation"));
+        // 不可誤用動物 ID 作為觀察紀錄 ID，需由回應取得新建 ID
+        assert_eq!(entity_key_from_path("/api/pigs/12/observations"), None);
+        assert_eq!(entity_key_from_path("/api/pigs/12/welfare-scores"), None);
+        assert_eq!(entity_key_from_path("/api/pigs/12/surgeries/copy"), None);
+    }
+
+    #[test]
+    fn test_record_update_uses_record_id() {
+        // 子紀錄以紀錄 ID 更新（/weights/:id、/welfare-scores/:id），不經動物路徑
+        let (category, event_type, entity_type) = categorize_request(&Method::PUT, "/api/weights/34");
+        assert_eq!((category.as_str(), event_type.as_str()), ("animal_management", "update"));
+        assert_eq!(entity_type.as_deref(), Some("pig_weight"));
+        assert_eq!(entity_key_from_path("/api/weights/34"), Some("34".to_string()));
+
+        let (_, event_type, entity_type) = categorize_request(&Method::PUT, "/api/welfare-scores/56");
+        assert_eq!(event_type, "update");
+        assert_eq!(entity_type.as_deref(), Some("pig_welfare_score"));
+        assert_eq!(entity_key_from_path("/api/welfare-scores/56"), Some("56".to_string()));
+
+        let (_, _, entity_type) = categorize_request(&Method::POST, "/api/observations/78/vet-read");
+        assert_eq!(entity_type.as_deref(), Some("pig_observation"));
+        assert_eq!(entity_key_from_path("/api/observations/78/vet-read"), Some("78".to_string()));
+    }
+
+    #[test]
+    fn test_client_ip_ignores_forwarded_for() {
+        let peer: SocketAddr = "10.0.0.5:40000".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "1.2.3.4, 203.0.113.9".parse().unwrap());
+        assert_eq!(client_ip(&headers, Some(peer)), Some("10.0.0.5".to_string()));
+
+        headers.insert("x-real-ip", "203.0.113.9".parse().unwrap());
+        assert_eq!(client_ip(&headers, Some(peer)), Some("203.0.113.9".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_created_entity_key_from_response() {
+        let body = Body::from(r#"{"id":78,"pig_id":12}"#);
+        let (body, key) = created_entity_key(body).await;
+        assert_eq!(key, Some("78".to_string()));
+        let bytes = axum::body::to_bytes(body, 1024).await.unwrap();
+        assert_eq!(&bytes[..], br#"{"id":78,"pig_id":12}"#);
+    }
+}
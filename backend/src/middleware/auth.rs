@@ -8,7 +8,7 @@ use jsonwebtoken::{decode, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{AppError, AppState, Result};
+use crate::{services::SessionManager, AppError, AppState, Result};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
@@ -18,6 +18,8 @@ pub struct Claims {
     pub permissions: Vec<String>,
     pub exp: i64,
     pub iat: i64,
+    #[serde(default)]
+    pub sid: Option<Uuid>, // session_id
 }
 
 #[derive(Debug, Clone)]
@@ -27,6 +29,7 @@ pub struct CurrentUser {
     pub email: String,
     pub roles: Vec<String>,
     pub permissions: Vec<String>,
+    pub session_id: Uuid,
 }
 
 impl CurrentUser {
@@ -70,16 +73,34 @@ pub async fn auth_middleware(
     )
     .map_err(|_| AppError::Unauthorized)?;
 
+    // 確認 Session 仍有效（未登出、未被強制登出、未閒置逾時）
+    let session_id = token_data.claims.sid.ok_or(AppError::Unauthorized)?;
+    let valid = SessionManager::validate(
+        &state.db,
+        session_id,
+        token_data.claims.sub,
+        state.config.session_idle_timeout_minutes,
+        state.config.session_cache_seconds,
+    )
+    .await?;
+    if !valid {
+        return Err(AppError::Unauthorized);
+    }
+
     let current_user = CurrentUser {
         id: token_data.claims.sub,
         email: token_data.claims.email,
         roles: token_data.claims.roles,
         permissions: token_data.claims.permissions,
+        session_id,
     };
 
-    request.extensions_mut().insert(current_user);
+    request.extensions_mut().insert(current_user.clone());
 
-    Ok(next.run(request).await)
+    // 附加於回應，供活動日誌 middleware 取得操作者
+    let mut response = next.run(request).await;
+    response.extensions_mut().insert(current_user);
+    Ok(response)
 }
 
 /// 權限檢查巨集
@@ -1,5 +1,5 @@
 mod auth;
-#[allow(dead_code)]
 mod activity_logger;
 
 pub use auth::*;
+pub use activity_logger::{activity_logger_middleware, client_ip};
@@ -549,6 +549,22 @@ pub enum ExportFormat {
     Excel,
 }
 
+impl ExportFormat {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Pdf => "application/pdf",
+            ExportFormat::Excel => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Pdf => "pdf",
+            ExportFormat::Excel => "xlsx",
+        }
+    }
+}
+
 /// 匯出類型
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
 #[sqlx(type_name = "export_type", rename_all = "snake_case")]
@@ -559,6 +575,17 @@ pub enum ExportType {
     ExperimentRecords,
 }
 
+impl ExportType {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ExportType::MedicalSummary => "病歷總覽",
+            ExportType::ObservationRecords => "觀察紀錄",
+            ExportType::SurgeryRecords => "手術紀錄",
+            ExportType::ExperimentRecords => "試驗紀錄",
+        }
+    }
+}
+
 /// 匯入批次記錄
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct PigImportBatch {
@@ -617,6 +644,9 @@ pub struct ImportResult {
     pub success_count: i32,
     pub error_count: i32,
     pub errors: Vec<ImportErrorDetail>,
+    /// 體重匯入後觸發的體重下降警示
+    #[serde(default)]
+    pub weight_loss_alerts: Vec<PigWeightAlert>,
 }
 
 // ============================================
@@ -908,3 +938,310 @@ pub struct UpdateVaccinationWithReasonRequest {
     pub change_reason: String,
 }
 
+
+// ============================================
+// 臨床時間軸
+// ============================================
+
+/// 時間軸事件類型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEventType {
+    Observation,
+    Surgery,
+    Weight,
+    Vaccination,
+    VetRecommendation,
+    Sacrifice,
+    Pathology,
+    WelfareScore,
+    Annotation,
+    Signature,
+}
+
+impl TimelineEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimelineEventType::Observation => "observation",
+            TimelineEventType::Surgery => "surgery",
+            TimelineEventType::Weight => "weight",
+            TimelineEventType::Vaccination => "vaccination",
+            TimelineEventType::VetRecommendation => "vet_recommendation",
+            TimelineEventType::Sacrifice => "sacrifice",
+            TimelineEventType::Pathology => "pathology",
+            TimelineEventType::WelfareScore => "welfare_score",
+            TimelineEventType::Annotation => "annotation",
+            TimelineEventType::Signature => "signature",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [
+            TimelineEventType::Observation,
+            TimelineEventType::Surgery,
+            TimelineEventType::Weight,
+            TimelineEventType::Vaccination,
+            TimelineEventType::VetRecommendation,
+            TimelineEventType::Sacrifice,
+            TimelineEventType::Pathology,
+            TimelineEventType::WelfareScore,
+            TimelineEventType::Annotation,
+            TimelineEventType::Signature,
+        ]
+        .into_iter()
+        .find(|t| t.as_str() == value)
+    }
+}
+
+/// 時間軸事件
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PigTimelineEvent {
+    pub event_type: TimelineEventType,
+    /// 紀錄 ID（數字或 UUID，以字串表示）
+    pub record_id: String,
+    /// 建議、附註、簽章所屬的紀錄
+    pub related_record_type: Option<String>,
+    pub related_record_id: Option<i32>,
+    pub event_date: NaiveDate,
+    pub occurred_at: DateTime<Utc>,
+    pub summary: Option<String>,
+    /// 獸醫是否已讀（不適用的事件為 null）
+    pub vet_read: Option<bool>,
+    pub version_count: i64,
+    pub is_locked: bool,
+    pub is_signed: bool,
+    pub created_by: Option<Uuid>,
+    pub created_by_name: Option<String>,
+}
+
+/// 時間軸查詢參數
+#[derive(Debug, Deserialize)]
+pub struct PigTimelineQuery {
+    /// 事件類型（逗號分隔，例如 observation,surgery）
+    pub event_types: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub vet_read: Option<bool>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+// ============================================
+// 疼痛 / 福祉評分
+// ============================================
+
+/// 評分項目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WelfareCriterion {
+    pub code: String,
+    pub name: String,
+    /// 加權係數
+    pub weight: rust_decimal::Decimal,
+    /// 單項最高分（0 ~ max_score）
+    pub max_score: i32,
+    pub description: Option<String>,
+}
+
+/// 福祉評分表
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WelfareScoreSheet {
+    pub id: Uuid,
+    pub name: String,
+    pub protocol_id: Option<Uuid>,
+    pub species: Option<String>,
+    pub criteria: serde_json::Value,
+    pub endpoint_total: Option<rust_decimal::Decimal>,
+    pub endpoint_trend_increase: Option<rust_decimal::Decimal>,
+    pub is_active: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 建立評分表請求
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateWelfareScoreSheetRequest {
+    #[validate(length(min = 1, max = 200, message = "Name must be 1-200 characters"))]
+    pub name: String,
+    pub protocol_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 50, message = "Species must be 1-50 characters"))]
+    pub species: Option<String>,
+    #[validate(length(min = 1, message = "At least one criterion is required"))]
+    pub criteria: Vec<WelfareCriterion>,
+    pub endpoint_total: Option<rust_decimal::Decimal>,
+    pub endpoint_trend_increase: Option<rust_decimal::Decimal>,
+}
+
+/// 更新評分表請求
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateWelfareScoreSheetRequest {
+    #[validate(length(min = 1, max = 200, message = "Name must be 1-200 characters"))]
+    pub name: Option<String>,
+    #[validate(length(min = 1, message = "At least one criterion is required"))]
+    pub criteria: Option<Vec<WelfareCriterion>>,
+    pub endpoint_total: Option<rust_decimal::Decimal>,
+    pub endpoint_trend_increase: Option<rust_decimal::Decimal>,
+    pub is_active: Option<bool>,
+}
+
+/// 評分表查詢參數
+#[derive(Debug, Deserialize)]
+pub struct WelfareScoreSheetQuery {
+    pub protocol_id: Option<Uuid>,
+    pub species: Option<String>,
+    pub is_active: Option<bool>,
+}
+
+/// 豬隻福祉評分紀錄
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PigWelfareScore {
+    pub id: i32,
+    pub pig_id: i32,
+    pub score_sheet_id: Uuid,
+    pub assessed_at: DateTime<Utc>,
+    pub scores: serde_json::Value,
+    pub total_score: rust_decimal::Decimal,
+    pub endpoint_reached: bool,
+    pub endpoint_reason: Option<String>,
+    pub remark: Option<String>,
+    pub vet_read: bool,
+    pub vet_read_at: Option<DateTime<Utc>>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deletion_reason: Option<String>,
+    pub deleted_by: Option<Uuid>,
+}
+
+/// 建立福祉評分請求
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateWelfareScoreRequest {
+    /// 未指定時依豬隻所屬計畫或物種選用評分表
+    pub score_sheet_id: Option<Uuid>,
+    pub assessed_at: Option<DateTime<Utc>>,
+    /// 各項評分：{criterion_code: score}
+    pub scores: std::collections::HashMap<String, i32>,
+    pub remark: Option<String>,
+}
+
+/// 更新福祉評分請求
+#[derive(Debug, Deserialize)]
+pub struct UpdateWelfareScoreRequest {
+    pub assessed_at: Option<DateTime<Utc>>,
+    pub scores: Option<std::collections::HashMap<String, i32>>,
+    pub remark: Option<String>,
+}
+
+/// 帶變更原因的更新福祉評分請求
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateWelfareScoreWithReasonRequest {
+    #[serde(flatten)]
+    pub data: UpdateWelfareScoreRequest,
+    #[validate(length(min = 1, message = "變更原因為必填"))]
+    pub change_reason: String,
+}
+
+// ============================================
+// 體重生長分析
+// ============================================
+
+/// 體重下降警示規則
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WeightLossRule {
+    /// 下降百分比門檻（相對觀察期間內最高體重）
+    pub threshold_percent: rust_decimal::Decimal,
+    /// 觀察期間（天）
+    pub window_days: i64,
+}
+
+/// 體重下降警示規則覆寫參數（未指定時使用系統設定）
+#[derive(Debug, Deserialize)]
+pub struct WeightLossQuery {
+    pub threshold_percent: Option<rust_decimal::Decimal>,
+    pub window_days: Option<i64>,
+}
+
+impl WeightLossQuery {
+    pub fn rule_or(&self, default: WeightLossRule) -> WeightLossRule {
+        WeightLossRule {
+            threshold_percent: self.threshold_percent.unwrap_or(default.threshold_percent),
+            window_days: self.window_days.unwrap_or(default.window_days),
+        }
+    }
+}
+
+/// 手動執行體重下降檢查請求（警示一律依系統設定的規則建立）
+#[derive(Debug, Deserialize)]
+pub struct WeightLossCheckRequest {
+    /// 指定計畫；未指定時檢查所有未刪除的豬隻
+    pub iacuc_no: Option<String>,
+}
+
+/// 體重下降偵測結果
+#[derive(Debug, Clone, Serialize)]
+pub struct WeightLossFinding {
+    pub baseline_weight_id: i32,
+    pub baseline_date: NaiveDate,
+    pub baseline_weight: rust_decimal::Decimal,
+    pub weight_id: i32,
+    pub measure_date: NaiveDate,
+    pub weight: rust_decimal::Decimal,
+    pub loss_percent: rust_decimal::Decimal,
+}
+
+/// 體重下降警示紀錄
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PigWeightAlert {
+    pub id: i32,
+    pub pig_id: i32,
+    pub weight_id: i32,
+    pub baseline_weight_id: i32,
+    pub loss_percent: rust_decimal::Decimal,
+    pub threshold_percent: rust_decimal::Decimal,
+    pub window_days: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 豬隻生長分析
+#[derive(Debug, Clone, Serialize)]
+pub struct PigGrowthAnalytics {
+    pub pig_id: i32,
+    pub ear_tag: String,
+    pub breed: PigBreed,
+    pub iacuc_no: Option<String>,
+    pub entry_date: NaiveDate,
+    pub entry_weight: Option<rust_decimal::Decimal>,
+    pub measurement_count: usize,
+    pub latest_weight: Option<rust_decimal::Decimal>,
+    pub latest_measure_date: Option<NaiveDate>,
+    /// 最新測量時的日齡（需出生日期）
+    pub age_days: Option<i64>,
+    /// 平均日增重（kg/天）
+    pub average_daily_gain: Option<rust_decimal::Decimal>,
+    /// 相對進場體重的變化百分比
+    pub percent_change_since_entry: Option<rust_decimal::Decimal>,
+    /// 同品種、同日齡群組的平均體重
+    pub cohort_expected_weight: Option<rust_decimal::Decimal>,
+    pub cohort_size: i64,
+    /// 相對群組平均體重的偏差百分比
+    pub cohort_deviation_percent: Option<rust_decimal::Decimal>,
+    pub weight_loss: Option<WeightLossFinding>,
+}
+
+/// 計畫生長統計
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtocolGrowthStats {
+    pub iacuc_no: String,
+    pub pig_count: usize,
+    pub measured_pig_count: usize,
+    pub latest_weight_mean: Option<rust_decimal::Decimal>,
+    pub latest_weight_min: Option<rust_decimal::Decimal>,
+    pub latest_weight_max: Option<rust_decimal::Decimal>,
+    pub latest_weight_stddev: Option<rust_decimal::Decimal>,
+    pub average_daily_gain_mean: Option<rust_decimal::Decimal>,
+    pub percent_change_mean: Option<rust_decimal::Decimal>,
+    pub weight_loss_count: usize,
+    pub pigs: Vec<PigGrowthAnalytics>,
+}
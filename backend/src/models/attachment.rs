@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// 附件（含版本與軟刪除資訊）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub file_name: String,
+    pub file_path: String,
+    pub file_size: i64,
+    pub mime_type: String,
+    pub content_hash: Option<String>,
+    /// 同一文件各版本共用的識別碼（第一版的 ID）
+    pub root_id: Uuid,
+    pub version: i32,
+    pub previous_version_id: Option<Uuid>,
+    pub superseded_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deleted_by: Option<Uuid>,
+    pub deletion_reason: Option<String>,
+    pub purged_at: Option<DateTime<Utc>>,
+    pub uploaded_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Attachment {
+    /// 是否為最新且未刪除的版本
+    pub fn is_current(&self) -> bool {
+        self.superseded_at.is_none() && self.deleted_at.is_none()
+    }
+}
+
+/// 新增附件紀錄
+#[derive(Debug, Clone)]
+pub struct NewAttachment<'a> {
+    pub category: &'a str,
+    pub entity_type: &'a str,
+    pub entity_id: &'a str,
+    pub file_name: &'a str,
+    pub file_path: &'a str,
+    pub file_size: i64,
+    pub mime_type: &'a str,
+    pub content_hash: &'a str,
+    pub uploaded_by: Uuid,
+}
@@ -51,6 +51,7 @@ pub struct UserActivityLog {
     pub event_severity: String,
     pub entity_type: Option<String>,
     pub entity_id: Option<Uuid>,
+    pub entity_key: Option<String>,
     pub entity_display_name: Option<String>,
     pub before_data: Option<serde_json::Value>,
     pub after_data: Option<serde_json::Value>,
@@ -60,6 +61,7 @@ pub struct UserActivityLog {
     pub request_path: Option<String>,
     pub request_method: Option<String>,
     pub response_status: Option<i32>,
+    pub duration_ms: Option<i32>,
     pub is_suspicious: bool,
     pub suspicious_reason: Option<String>,
     pub created_at: DateTime<Utc>,
@@ -164,6 +166,8 @@ pub enum AuditAction {
     StatusChange,
     Assign,
     Unassign,
+    AssignRejected,
+    Override,
 }
 
 impl AuditAction {
@@ -182,6 +186,8 @@ impl AuditAction {
             AuditAction::StatusChange => "STATUS_CHANGE",
             AuditAction::Assign => "ASSIGN",
             AuditAction::Unassign => "UNASSIGN",
+            AuditAction::AssignRejected => "ASSIGN_REJECTED",
+            AuditAction::Override => "OVERRIDE",
         }
     }
 }
@@ -204,6 +210,9 @@ pub struct ActivityLogQuery {
     pub event_type: Option<String>,
     pub entity_type: Option<String>,
     pub entity_id: Option<Uuid>,
+    /// 實體原始 ID（整數 ID 的實體，如動物紀錄）
+    pub entity_key: Option<String>,
+    pub session_id: Option<Uuid>,
     pub is_suspicious: Option<bool>,
     pub from: Option<NaiveDate>,
     pub to: Option<NaiveDate>,
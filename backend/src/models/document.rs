@@ -28,6 +28,8 @@ pub enum DocType {
     ADJ,
     /// 退料單 Return Material
     RM,
+    /// 庫內上架 / 移動單 Putaway / Move
+    MV,
 }
 
 impl DocType {
@@ -42,12 +44,13 @@ impl DocType {
             DocType::STK => "STK",
             DocType::ADJ => "ADJ",
             DocType::RM => "RM",
+            DocType::MV => "MV",
         }
     }
 
     /// 是否影響庫存
     pub fn affects_stock(&self) -> bool {
-        matches!(self, DocType::GRN | DocType::PR | DocType::DO | DocType::TR | DocType::ADJ)
+        matches!(self, DocType::GRN | DocType::PR | DocType::DO | DocType::TR | DocType::ADJ | DocType::MV)
     }
 }
 
@@ -60,6 +63,8 @@ pub enum DocStatus {
     Submitted,
     Approved,
     Cancelled,
+    /// 已沖銷（已產生沖銷單）
+    Reversed,
 }
 
 /// 單據頭
@@ -86,6 +91,12 @@ pub struct Document {
     pub receipt_status: Option<String>,
     /// 盤點範圍設定（循環盤點用）
     pub stocktake_scope: Option<serde_json::Value>,
+    /// 是否為沖銷單（source_doc_id 指向被沖銷的原單據）
+    pub is_reversal: bool,
+    /// 盲盤（盤點人員看不到帳面數量，僅盤點單使用）
+    pub blind_count: bool,
+    /// 開始盤點時間（帳面數量於此時凍結）
+    pub count_started_at: Option<DateTime<Utc>>,
 }
 
 /// 單據明細
@@ -101,6 +112,10 @@ pub struct DocumentLine {
     pub batch_no: Option<String>,
     pub expiry_date: Option<NaiveDate>,
     pub remark: Option<String>,
+    /// 來源儲位（出庫 / 移出）
+    pub from_location_id: Option<Uuid>,
+    /// 目的儲位（入庫 / 移入）
+    pub to_location_id: Option<Uuid>,
 }
 
 /// 建立單據請求
@@ -129,6 +144,10 @@ pub struct DocumentLineInput {
     pub batch_no: Option<String>,
     pub expiry_date: Option<NaiveDate>,
     pub remark: Option<String>,
+    /// 來源儲位（出庫 / 移出）
+    pub from_location_id: Option<Uuid>,
+    /// 目的儲位（入庫 / 移入）
+    pub to_location_id: Option<Uuid>,
 }
 
 /// 更新單據請求 (僅 Draft 狀態可更新)
@@ -143,6 +162,20 @@ pub struct UpdateDocumentRequest {
     pub lines: Option<Vec<DocumentLineInput>>,
 }
 
+/// 核准單據請求（需電子簽章）
+#[derive(Debug, Deserialize, Validate)]
+pub struct ApproveDocumentRequest {
+    #[validate(length(min = 1, message = "核准單據須輸入密碼完成電子簽章"))]
+    pub signature_password: String,
+}
+
+/// 沖銷單據請求
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReverseDocumentRequest {
+    #[validate(length(min = 1, max = 500, message = "Reason must be 1-500 characters"))]
+    pub reason: String,
+}
+
 /// 查詢單據
 #[derive(Debug, Deserialize)]
 pub struct DocumentQuery {
@@ -183,6 +216,10 @@ pub struct DocumentLineWithProduct {
     pub batch_no: Option<String>,
     pub expiry_date: Option<NaiveDate>,
     pub remark: Option<String>,
+    pub from_location_id: Option<Uuid>,
+    pub from_location_code: Option<String>,
+    pub to_location_id: Option<Uuid>,
+    pub to_location_code: Option<String>,
 }
 
 /// 單據列表項
@@ -256,6 +293,82 @@ pub struct StocktakeResultInput {
     pub actual_qty: Decimal,
 }
 
+/// 開始盤點請求
+#[derive(Debug, Deserialize)]
+pub struct StartStocktakeRequest {
+    /// 盲盤
+    #[serde(default)]
+    pub blind_count: bool,
+}
+
+/// 登錄實盤數量請求
+#[derive(Debug, Deserialize)]
+pub struct StocktakeCountRequest {
+    pub counts: Vec<StocktakeResultInput>,
+}
+
+/// 盤點項目（凍結帳面數量 / 實盤數量）
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct StocktakeItem {
+    pub id: Uuid,
+    pub document_id: Uuid,
+    pub product_id: Uuid,
+    pub product_sku: String,
+    pub product_name: String,
+    pub uom: String,
+    pub batch_no: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    /// 帳面數量（盲盤時對盤點人員隱藏）
+    pub book_qty: Option<Decimal>,
+    pub unit_cost: Option<Decimal>,
+    pub counted_qty: Option<Decimal>,
+    pub counted_by: Option<Uuid>,
+    pub counted_by_name: Option<String>,
+    pub counted_at: Option<DateTime<Utc>>,
+}
+
+/// 盤點表
+#[derive(Debug, Serialize)]
+pub struct StocktakeSheet {
+    pub document_id: Uuid,
+    pub doc_no: String,
+    pub status: DocStatus,
+    pub warehouse_id: Option<Uuid>,
+    pub blind_count: bool,
+    pub count_started_at: Option<DateTime<Utc>>,
+    pub items: Vec<StocktakeItem>,
+}
+
+/// 盤點匯入錯誤
+#[derive(Debug, Serialize)]
+pub struct StocktakeImportError {
+    pub row: i32,
+    pub sku: Option<String>,
+    pub error: String,
+}
+
+/// 盤點匯入結果
+#[derive(Debug, Serialize)]
+pub struct StocktakeImportResult {
+    pub total_rows: i32,
+    pub success_count: i32,
+    pub error_count: i32,
+    pub errors: Vec<StocktakeImportError>,
+}
+
+/// 盤點差異報表
+#[derive(Debug, Serialize)]
+pub struct StocktakeVarianceReport {
+    pub document_id: Uuid,
+    pub doc_no: String,
+    pub count_started_at: Option<DateTime<Utc>>,
+    pub items: Vec<StocktakeDifferenceItem>,
+    /// 尚未盤點的項目數
+    pub uncounted_count: i64,
+    /// 差異金額合計（盤盈為正、盤虧為負）
+    pub total_value_impact: Decimal,
+}
+
 /// 盤點差異項目
 #[derive(Debug, Serialize)]
 pub struct StocktakeDifferenceItem {
@@ -271,4 +384,8 @@ pub struct StocktakeDifferenceItem {
     /// 差異 (actual - system)
     pub difference: Decimal,
     pub uom: String,
+    /// 凍結時的單位成本
+    pub unit_cost: Decimal,
+    /// 差異金額 (difference * unit_cost)
+    pub value_impact: Decimal,
 }
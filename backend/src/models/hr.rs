@@ -196,7 +196,7 @@ impl LeaveType {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
 #[sqlx(type_name = "leave_status", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum LeaveStatus {
     Draft,
@@ -224,6 +224,34 @@ impl LeaveStatus {
             LeaveStatus::Revoked => "已銷假",
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LeaveStatus::Draft => "DRAFT",
+            LeaveStatus::PendingL1 => "PENDING_L1",
+            LeaveStatus::PendingL2 => "PENDING_L2",
+            LeaveStatus::PendingHr => "PENDING_HR",
+            LeaveStatus::PendingGm => "PENDING_GM",
+            LeaveStatus::Approved => "APPROVED",
+            LeaveStatus::Rejected => "REJECTED",
+            LeaveStatus::Cancelled => "CANCELLED",
+            LeaveStatus::Revoked => "REVOKED",
+        }
+    }
+
+    /// 審核中的層級（依審核順序）
+    pub fn pending_levels() -> [LeaveStatus; 4] {
+        [
+            LeaveStatus::PendingL1,
+            LeaveStatus::PendingL2,
+            LeaveStatus::PendingHr,
+            LeaveStatus::PendingGm,
+        ]
+    }
+
+    pub fn parse_pending(value: &str) -> Option<Self> {
+        Self::pending_levels().into_iter().find(|s| s.as_str() == value)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -287,6 +315,8 @@ pub struct LeaveQuery {
     pub from: Option<NaiveDate>,
     pub to: Option<NaiveDate>,
     pub pending_approval: Option<bool>,
+    /// 僅列出指派給此審核人的請假
+    pub approver_id: Option<Uuid>,
     pub page: Option<i64>,
     pub per_page: Option<i64>,
 }
@@ -322,6 +352,8 @@ pub struct UpdateLeaveRequest {
 #[derive(Debug, Deserialize)]
 pub struct ApproveLeaveRequest {
     pub comments: Option<String>,
+    /// 電子簽章密碼
+    pub signature_password: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
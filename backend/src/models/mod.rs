@@ -15,6 +15,7 @@ mod notification;
 mod hr;
 mod facility;
 mod calendar;
+mod attachment;
 pub mod user_preferences;
 
 pub use user::*;
@@ -32,6 +33,7 @@ pub use notification::*;
 pub use hr::*;
 pub use facility::*;
 pub use calendar::*;
+pub use attachment::*;
 
 use serde::{Deserialize, Serialize};
 
@@ -16,6 +16,9 @@ pub enum NotificationType {
     ReviewAssignment,
     ReviewComment,
     VetRecommendation,
+    HumaneEndpoint,
+    WeightLossAlert,
+    ReviewDeadline,
     SystemAlert,
     MonthlyReport,
 }
@@ -31,6 +34,9 @@ impl NotificationType {
             NotificationType::ReviewAssignment => "review_assignment",
             NotificationType::ReviewComment => "review_comment",
             NotificationType::VetRecommendation => "vet_recommendation",
+            NotificationType::HumaneEndpoint => "humane_endpoint",
+            NotificationType::WeightLossAlert => "weight_loss_alert",
+            NotificationType::ReviewDeadline => "review_deadline",
             NotificationType::SystemAlert => "system_alert",
             NotificationType::MonthlyReport => "monthly_report",
         }
@@ -149,7 +155,7 @@ pub struct ExpiryAlert {
 // ============================================
 
 /// 排程類型
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, sqlx::Type)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, sqlx::Type)]
 #[sqlx(type_name = "schedule_type", rename_all = "lowercase")]
 pub enum ScheduleType {
     Daily,
@@ -157,8 +163,19 @@ pub enum ScheduleType {
     Monthly,
 }
 
+impl ScheduleType {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "daily" => Some(ScheduleType::Daily),
+            "weekly" => Some(ScheduleType::Weekly),
+            "monthly" => Some(ScheduleType::Monthly),
+            _ => None,
+        }
+    }
+}
+
 /// 報表類型
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, sqlx::Type)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, sqlx::Type)]
 #[sqlx(type_name = "report_type", rename_all = "snake_case")]
 pub enum ReportType {
     StockOnHand,
@@ -169,6 +186,56 @@ pub enum ReportType {
     LowStockReport,
 }
 
+impl ReportType {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "stock_on_hand" => Some(ReportType::StockOnHand),
+            "stock_ledger" => Some(ReportType::StockLedger),
+            "purchase_summary" => Some(ReportType::PurchaseSummary),
+            "cost_summary" => Some(ReportType::CostSummary),
+            "expiry_report" => Some(ReportType::ExpiryReport),
+            "low_stock_report" => Some(ReportType::LowStockReport),
+            _ => None,
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ReportType::StockOnHand => "庫存現況報表",
+            ReportType::StockLedger => "庫存流水報表",
+            ReportType::PurchaseSummary => "採購明細報表",
+            ReportType::CostSummary => "成本摘要報表",
+            ReportType::ExpiryReport => "效期報表",
+            ReportType::LowStockReport => "低庫存報表",
+        }
+    }
+}
+
+/// 報表輸出格式（scheduled_reports.parameters.format）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFileFormat {
+    #[default]
+    Xlsx,
+    Csv,
+}
+
+impl ReportFileFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFileFormat::Xlsx => "xlsx",
+            ReportFileFormat::Csv => "csv",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ReportFileFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ReportFileFormat::Csv => "text/csv",
+        }
+    }
+}
+
 /// 定期報表設定
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct ScheduledReport {
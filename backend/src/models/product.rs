@@ -38,6 +38,19 @@ pub enum StorageCondition {
     Dry,              // 乾燥
 }
 
+impl StorageCondition {
+    /// 資料庫儲存的代碼
+    pub fn code(&self) -> &'static str {
+        match self {
+            StorageCondition::RoomTemperature => "RT",
+            StorageCondition::Refrigerated => "RF",
+            StorageCondition::Frozen => "FZ",
+            StorageCondition::Dark => "DK",
+            StorageCondition::Dry => "DY",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Product {
     pub id: Uuid,
@@ -62,6 +62,166 @@ impl ProtocolStatus {
     }
 }
 
+/// 狀態轉移前置條件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransitionPrecondition {
+    /// 須選定 2-3 位審查委員（由請求提供）
+    ReviewersSelected,
+    /// 所有審查委員皆已完成審查
+    AllReviewsCompleted,
+    /// 無未解決的審查意見
+    NoUnresolvedComments,
+    /// 須有核准電子簽章（可由請求提供密碼當場簽章）
+    ElectronicSignature,
+    /// 須填寫備註說明（由請求提供）
+    RemarkRequired,
+}
+
+impl TransitionPrecondition {
+    pub fn description(&self) -> &'static str {
+        match self {
+            TransitionPrecondition::ReviewersSelected => "必須選擇 2-3 位不同的審查委員",
+            TransitionPrecondition::AllReviewsCompleted => "所有審查委員須完成審查",
+            TransitionPrecondition::NoUnresolvedComments => "審查意見須全部解決",
+            TransitionPrecondition::ElectronicSignature => "須完成電子簽章",
+            TransitionPrecondition::RemarkRequired => "須填寫備註說明",
+        }
+    }
+
+    /// 是否由請求內容提供（而非計畫目前的資料狀態）
+    pub fn is_request_input(&self) -> bool {
+        matches!(
+            self,
+            TransitionPrecondition::ReviewersSelected
+                | TransitionPrecondition::ElectronicSignature
+                | TransitionPrecondition::RemarkRequired
+        )
+    }
+}
+
+/// 計畫狀態轉移規則
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ProtocolTransition {
+    pub from: ProtocolStatus,
+    pub to: ProtocolStatus,
+    /// 可執行的角色（系統管理員一律可執行）
+    pub roles: &'static [&'static str],
+    pub preconditions: &'static [TransitionPrecondition],
+}
+
+const STAFF: &[&str] = &["IACUC_STAFF"];
+const STAFF_OR_CHAIR: &[&str] = &["IACUC_STAFF", "CHAIR"];
+const CHAIR: &[&str] = &["CHAIR"];
+
+/// 計畫狀態轉移表（未列出的轉移一律不合法）
+pub const PROTOCOL_TRANSITIONS: &[ProtocolTransition] = {
+    use ProtocolStatus::*;
+    use TransitionPrecondition::*;
+    &[
+        // 執行秘書代為提交 / 刪除草稿
+        ProtocolTransition { from: Draft, to: Submitted, roles: STAFF, preconditions: &[] },
+        ProtocolTransition { from: Draft, to: Deleted, roles: STAFF, preconditions: &[] },
+        // 行政預審
+        ProtocolTransition { from: Submitted, to: PreReview, roles: STAFF, preconditions: &[] },
+        ProtocolTransition { from: Submitted, to: RevisionRequired, roles: STAFF, preconditions: &[RemarkRequired] },
+        ProtocolTransition { from: Resubmitted, to: PreReview, roles: STAFF, preconditions: &[] },
+        ProtocolTransition { from: PreReview, to: RevisionRequired, roles: STAFF, preconditions: &[RemarkRequired] },
+        // 送委員審查
+        ProtocolTransition { from: PreReview, to: UnderReview, roles: STAFF_OR_CHAIR, preconditions: &[ReviewersSelected] },
+        ProtocolTransition { from: Resubmitted, to: UnderReview, roles: STAFF_OR_CHAIR, preconditions: &[ReviewersSelected] },
+        ProtocolTransition { from: Deferred, to: UnderReview, roles: STAFF_OR_CHAIR, preconditions: &[ReviewersSelected] },
+        // 審查決議
+        ProtocolTransition { from: UnderReview, to: RevisionRequired, roles: STAFF_OR_CHAIR, preconditions: &[AllReviewsCompleted] },
+        ProtocolTransition {
+            from: UnderReview,
+            to: Approved,
+            roles: CHAIR,
+            preconditions: &[AllReviewsCompleted, NoUnresolvedComments, ElectronicSignature],
+        },
+        ProtocolTransition {
+            from: UnderReview,
+            to: ApprovedWithConditions,
+            roles: CHAIR,
+            preconditions: &[AllReviewsCompleted, ElectronicSignature, RemarkRequired],
+        },
+        ProtocolTransition { from: UnderReview, to: Deferred, roles: CHAIR, preconditions: &[RemarkRequired] },
+        ProtocolTransition { from: UnderReview, to: Rejected, roles: CHAIR, preconditions: &[AllReviewsCompleted, RemarkRequired] },
+        ProtocolTransition {
+            from: ApprovedWithConditions,
+            to: Approved,
+            roles: CHAIR,
+            preconditions: &[NoUnresolvedComments, ElectronicSignature],
+        },
+        // 核准後管理
+        ProtocolTransition { from: Approved, to: Suspended, roles: STAFF_OR_CHAIR, preconditions: &[RemarkRequired] },
+        ProtocolTransition { from: ApprovedWithConditions, to: Suspended, roles: STAFF_OR_CHAIR, preconditions: &[RemarkRequired] },
+        ProtocolTransition { from: Suspended, to: Approved, roles: CHAIR, preconditions: &[ElectronicSignature, RemarkRequired] },
+        ProtocolTransition { from: Approved, to: Closed, roles: STAFF_OR_CHAIR, preconditions: &[] },
+        ProtocolTransition { from: ApprovedWithConditions, to: Closed, roles: STAFF_OR_CHAIR, preconditions: &[] },
+        ProtocolTransition { from: Suspended, to: Closed, roles: STAFF_OR_CHAIR, preconditions: &[] },
+        ProtocolTransition { from: Rejected, to: Closed, roles: STAFF, preconditions: &[] },
+    ]
+};
+
+/// 修正案狀態轉移表（提交、重送與撤回由申請方透過各自端點執行，不列於此表）
+pub const AMENDMENT_TRANSITIONS: &[ProtocolTransition] = {
+    use ProtocolStatus::*;
+    use TransitionPrecondition::*;
+    &[
+        // 行政審查後送委員審查或退回
+        ProtocolTransition { from: Submitted, to: UnderReview, roles: STAFF_OR_CHAIR, preconditions: &[ReviewersSelected] },
+        ProtocolTransition { from: Resubmitted, to: UnderReview, roles: STAFF_OR_CHAIR, preconditions: &[ReviewersSelected] },
+        ProtocolTransition { from: Submitted, to: RevisionRequired, roles: STAFF, preconditions: &[RemarkRequired] },
+        ProtocolTransition { from: Resubmitted, to: RevisionRequired, roles: STAFF, preconditions: &[RemarkRequired] },
+        // 審查決議
+        ProtocolTransition { from: UnderReview, to: RevisionRequired, roles: STAFF_OR_CHAIR, preconditions: &[AllReviewsCompleted] },
+        ProtocolTransition {
+            from: UnderReview,
+            to: Approved,
+            roles: CHAIR,
+            preconditions: &[AllReviewsCompleted, NoUnresolvedComments, ElectronicSignature],
+        },
+        ProtocolTransition { from: UnderReview, to: Rejected, roles: CHAIR, preconditions: &[AllReviewsCompleted, RemarkRequired] },
+    ]
+};
+
+impl ProtocolTransition {
+    /// 查詢轉移規則
+    pub fn find(from: ProtocolStatus, to: ProtocolStatus) -> Option<&'static ProtocolTransition> {
+        Self::find_in(PROTOCOL_TRANSITIONS, from, to)
+    }
+
+    /// 目前狀態可轉移的規則
+    pub fn from_status(from: ProtocolStatus) -> impl Iterator<Item = &'static ProtocolTransition> {
+        Self::from_status_in(PROTOCOL_TRANSITIONS, from)
+    }
+
+    /// 於指定轉移表查詢轉移規則（計畫或修正案）
+    pub fn find_in(
+        table: &'static [ProtocolTransition],
+        from: ProtocolStatus,
+        to: ProtocolStatus,
+    ) -> Option<&'static ProtocolTransition> {
+        table.iter().find(|t| t.from == from && t.to == to)
+    }
+
+    /// 指定轉移表中目前狀態可轉移的規則
+    pub fn from_status_in(
+        table: &'static [ProtocolTransition],
+        from: ProtocolStatus,
+    ) -> impl Iterator<Item = &'static ProtocolTransition> {
+        table.iter().filter(move |t| t.from == from)
+    }
+
+    /// 角色是否可執行此轉移
+    pub fn allows_roles(&self, roles: &[String]) -> bool {
+        roles.iter().any(|r| {
+            r == "SYSTEM_ADMIN" || r.eq_ignore_ascii_case("admin") || self.roles.contains(&r.as_str())
+        })
+    }
+}
+
 /// 計畫書主表
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Protocol {
@@ -77,6 +237,12 @@ pub struct Protocol {
     pub created_by: Uuid,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// 盲審：對 PI 隱藏審查委員身分
+    #[sqlx(default)]
+    pub blinded_review: bool,
+    /// 行政預審期限
+    #[sqlx(default)]
+    pub pre_review_due_date: Option<NaiveDate>,
 }
 
 /// 計畫版本快照
@@ -88,6 +254,9 @@ pub struct ProtocolVersion {
     pub content_snapshot: serde_json::Value,
     pub submitted_at: DateTime<Utc>,
     pub submitted_by: Uuid,
+    /// 由修正案核准產生的版本
+    #[sqlx(default)]
+    pub amendment_id: Option<Uuid>,
 }
 
 /// 計畫狀態歷程
@@ -111,6 +280,46 @@ pub struct ReviewAssignment {
     pub assigned_by: Uuid,
     pub assigned_at: DateTime<Utc>,
     pub completed_at: Option<DateTime<Utc>>,
+    /// 審查期限（指派日起算工作日）
+    #[sqlx(default)]
+    pub due_date: Option<NaiveDate>,
+    /// 逾期上呈主委的時間
+    #[sqlx(default)]
+    pub escalated_at: Option<DateTime<Utc>>,
+    /// 修正案審查時為修正案 ID；計畫審查時為 null
+    #[sqlx(default)]
+    pub amendment_id: Option<Uuid>,
+}
+
+/// 審查階段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewStage {
+    /// 行政預審（執行秘書）
+    PreReview,
+    /// 委員審查
+    Review,
+}
+
+/// 逾期未完成的審查（逾期審查看板）
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct OverdueReview {
+    pub stage: ReviewStage,
+    pub protocol_id: Uuid,
+    pub protocol_no: String,
+    pub title: String,
+    pub pi_name: Option<String>,
+    /// 修正案審查時為修正案；計畫審查時為 null
+    pub amendment_id: Option<Uuid>,
+    pub amendment_no: Option<String>,
+    /// 委員審查時為審查委員；行政預審時為 null
+    pub reviewer_id: Option<Uuid>,
+    pub reviewer_name: Option<String>,
+    pub due_date: NaiveDate,
+    pub days_overdue: i32,
+    /// 已上呈主委的時間
+    pub escalated_at: Option<DateTime<Utc>>,
 }
 
 /// 審查意見
@@ -125,6 +334,12 @@ pub struct ReviewComment {
     pub resolved_at: Option<DateTime<Utc>>,
     pub parent_comment_id: Option<Uuid>,
     pub replied_by: Option<Uuid>,
+    /// 對應 working_content 章節（例如 design）
+    #[sqlx(default)]
+    pub section: Option<String>,
+    /// 修正案審查意見（protocol_version_id 為修正案的比較基準版本）
+    #[sqlx(default)]
+    pub amendment_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -191,6 +406,20 @@ pub struct ChangeStatusRequest {
     pub remark: Option<String>,
     /// 審查委員 ID 列表（當目標狀態為 UNDER_REVIEW 時必填 2-3 位）
     pub reviewer_ids: Option<Vec<Uuid>>,
+    /// 電子簽章密碼（核准類狀態需簽章時提供）
+    pub signature_password: Option<String>,
+}
+
+/// 可執行的狀態轉移（供前端顯示下一步操作）
+#[derive(Debug, Serialize)]
+pub struct ProtocolTransitionOption {
+    pub to_status: ProtocolStatus,
+    pub to_status_display: String,
+    /// 執行時須於請求中提供的資料
+    pub required_inputs: Vec<TransitionPrecondition>,
+    /// 尚未滿足的前置條件說明
+    pub unmet_preconditions: Vec<String>,
+    pub can_transition: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -199,6 +428,178 @@ pub struct AssignReviewerRequest {
     pub reviewer_id: Uuid,
 }
 
+/// 審查委員利益衝突聲明
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ReviewerCoiDeclaration {
+    pub id: Uuid,
+    pub protocol_id: Uuid,
+    pub reviewer_id: Uuid,
+    pub has_conflict: bool,
+    pub description: Option<String>,
+    pub declared_at: DateTime<Utc>,
+    #[sqlx(default)]
+    pub reviewer_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct DeclareCoiRequest {
+    pub has_conflict: bool,
+    #[validate(length(min = 1, max = 2000, message = "Description must be 1-2000 characters"))]
+    pub description: Option<String>,
+}
+
+/// 不得指派為審查委員的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewerConflict {
+    /// 審查委員即為計畫主持人
+    IsPrincipalInvestigator,
+    /// 與 PI 同部門
+    SameDepartment,
+    /// 與 PI 同機構
+    SameOrganization,
+    /// 列名為計畫的共同編輯者
+    CoEditor,
+    /// 審查委員已聲明利益衝突
+    DeclaredConflict,
+}
+
+impl ReviewerConflict {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ReviewerConflict::IsPrincipalInvestigator => "審查委員為計畫主持人",
+            ReviewerConflict::SameDepartment => "審查委員與計畫主持人同部門",
+            ReviewerConflict::SameOrganization => "審查委員與計畫主持人同機構",
+            ReviewerConflict::CoEditor => "審查委員為計畫共同編輯者",
+            ReviewerConflict::DeclaredConflict => "審查委員已聲明利益衝突",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetBlindedReviewRequest {
+    pub blinded_review: bool,
+}
+
+/// 審查委員表決
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReviewDecision {
+    Approve,
+    ApproveWithConditions,
+    Revise,
+    Reject,
+}
+
+impl ReviewDecision {
+    pub const ALL: [ReviewDecision; 4] = [
+        ReviewDecision::Approve,
+        ReviewDecision::ApproveWithConditions,
+        ReviewDecision::Revise,
+        ReviewDecision::Reject,
+    ];
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ReviewDecision::Approve => "核准",
+            ReviewDecision::ApproveWithConditions => "附條件核准",
+            ReviewDecision::Revise => "修正後再審",
+            ReviewDecision::Reject => "否決",
+        }
+    }
+
+    /// 表決對應的計畫狀態
+    pub fn status(&self) -> ProtocolStatus {
+        match self {
+            ReviewDecision::Approve => ProtocolStatus::Approved,
+            ReviewDecision::ApproveWithConditions => ProtocolStatus::ApprovedWithConditions,
+            ReviewDecision::Revise => ProtocolStatus::RevisionRequired,
+            ReviewDecision::Reject => ProtocolStatus::Rejected,
+        }
+    }
+}
+
+/// 表決彙整規則（REVIEW_VOTE_RULE）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewVoteRule {
+    /// 全體一致才有建議結果，意見分歧時建議暫緩（提會討論）
+    Unanimous,
+    /// 過半數決定，未過半時建議暫緩；主委可推翻建議結果
+    Majority,
+}
+
+impl ReviewVoteRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewVoteRule::Unanimous => "unanimous",
+            ReviewVoteRule::Majority => "majority",
+        }
+    }
+}
+
+impl std::str::FromStr for ReviewVoteRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "unanimous" => Ok(ReviewVoteRule::Unanimous),
+            "majority" => Ok(ReviewVoteRule::Majority),
+            other => Err(format!("Unknown review vote rule: {}", other)),
+        }
+    }
+}
+
+/// 審查委員對計畫版本的表決
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ReviewVote {
+    pub id: Uuid,
+    pub protocol_id: Uuid,
+    pub protocol_version_id: Uuid,
+    pub reviewer_id: Uuid,
+    pub decision: ReviewDecision,
+    pub conditions: Option<String>,
+    pub comment: Option<String>,
+    pub voted_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[sqlx(default)]
+    pub reviewer_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SubmitReviewVoteRequest {
+    pub decision: ReviewDecision,
+    #[validate(length(max = 5000, message = "Conditions must be at most 5000 characters"))]
+    pub conditions: Option<String>,
+    #[validate(length(max = 5000, message = "Comment must be at most 5000 characters"))]
+    pub comment: Option<String>,
+}
+
+/// 各表決選項票數
+#[derive(Debug, Clone, Serialize)]
+pub struct ReviewVoteTally {
+    pub decision: ReviewDecision,
+    pub decision_display: String,
+    pub count: i64,
+}
+
+/// 表決彙整（供主委決定計畫狀態前參考）
+#[derive(Debug, Serialize)]
+pub struct ReviewVoteSummary {
+    pub protocol_id: Uuid,
+    /// 表決所針對的版本（最新提交版本）
+    pub protocol_version_id: Option<Uuid>,
+    pub rule: ReviewVoteRule,
+    pub votes: Vec<ReviewVote>,
+    pub tally: Vec<ReviewVoteTally>,
+    pub assigned_reviewers: i64,
+    pub pending_reviewers: i64,
+    /// 建議狀態；尚有委員未表決時為 null
+    pub suggested_status: Option<ProtocolStatus>,
+    pub suggested_status_display: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct AssignCoEditorRequest {
     pub protocol_id: Uuid,
@@ -210,6 +611,9 @@ pub struct CreateCommentRequest {
     pub protocol_version_id: Uuid,
     #[validate(length(min = 1, message = "Content is required"))]
     pub content: String,
+    /// 意見所屬章節（working_content 第一層欄位）
+    #[validate(length(min = 1, max = 100, message = "Section must be 1-100 characters"))]
+    pub section: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -275,6 +679,8 @@ pub struct ReviewCommentResponse {
     pub replied_by_name: Option<String>,
     #[sqlx(default)]
     pub replied_by_email: Option<String>,
+    #[sqlx(default)]
+    pub section: Option<String>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -292,4 +698,152 @@ pub struct CoEditorAssignmentResponse {
     pub user_email: String,
     #[sqlx(default)]
     pub granted_by_name: Option<String>,
-}
\ No newline at end of file
+}
+// ============================================
+// 版本比較
+// ============================================
+
+#[derive(Debug, Deserialize)]
+pub struct ProtocolVersionDiffQuery {
+    /// 比較基準版本號
+    pub from: i32,
+    /// 比較目標版本號
+    pub to: i32,
+}
+
+/// 欄位變更類型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldChangeType {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// 單一欄位變更
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProtocolFieldChange {
+    /// JSON 路徑，例如 design.procedures[0].name
+    pub path: String,
+    pub change_type: FieldChangeType,
+    pub old_value: Option<serde_json::Value>,
+    pub new_value: Option<serde_json::Value>,
+}
+
+/// 章節變更
+#[derive(Debug, Serialize)]
+pub struct ProtocolSectionDiff {
+    pub section: String,
+    pub changes: Vec<ProtocolFieldChange>,
+    /// 該章節未解決的審查意見數
+    pub open_comment_count: i64,
+    pub has_open_comments: bool,
+}
+
+/// 計畫書版本比較結果
+#[derive(Debug, Serialize)]
+pub struct ProtocolVersionDiff {
+    pub protocol_id: Uuid,
+    pub from_version: i32,
+    pub to_version: i32,
+    pub from_submitted_at: DateTime<Utc>,
+    pub to_submitted_at: DateTime<Utc>,
+    pub total_changes: usize,
+    pub sections: Vec<ProtocolSectionDiff>,
+}
+
+// ============================================
+// 修正案
+// ============================================
+
+/// 修正案變更類別
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AmendmentChangeType {
+    /// 人員異動
+    Personnel,
+    /// 動物數量增加
+    AnimalNumber,
+    /// 試驗程序變更
+    Procedure,
+    Other,
+}
+
+impl AmendmentChangeType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AmendmentChangeType::Personnel => "PERSONNEL",
+            AmendmentChangeType::AnimalNumber => "ANIMAL_NUMBER",
+            AmendmentChangeType::Procedure => "PROCEDURE",
+            AmendmentChangeType::Other => "OTHER",
+        }
+    }
+}
+
+/// 計畫修正案（核准後計畫的變更申請）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ProtocolAmendment {
+    pub id: Uuid,
+    pub protocol_id: Uuid,
+    pub amendment_no: String,
+    pub title: String,
+    pub reason: String,
+    pub change_types: Vec<String>,
+    /// 沿用計畫狀態：DRAFT → SUBMITTED → UNDER_REVIEW → APPROVED / REJECTED
+    pub status: ProtocolStatus,
+    /// 比較基準（建立時的核准版本）
+    pub base_version_id: Uuid,
+    pub proposed_content: serde_json::Value,
+    pub revision_no: i32,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub decided_by: Option<Uuid>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub decision_remark: Option<String>,
+    /// 核准後產生的計畫版本
+    pub effective_version_id: Option<Uuid>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateAmendmentRequest {
+    #[validate(length(min = 1, max = 500, message = "Title must be 1-500 characters"))]
+    pub title: String,
+    #[validate(length(min = 1, max = 5000, message = "Reason must be 1-5000 characters"))]
+    pub reason: String,
+    #[validate(length(min = 1, message = "At least one change type is required"))]
+    pub change_types: Vec<AmendmentChangeType>,
+    /// 修正後的計畫內容；未提供時以核准版本內容為起點
+    pub proposed_content: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateAmendmentRequest {
+    #[validate(length(min = 1, max = 500, message = "Title must be 1-500 characters"))]
+    pub title: Option<String>,
+    #[validate(length(min = 1, max = 5000, message = "Reason must be 1-5000 characters"))]
+    pub reason: Option<String>,
+    #[validate(length(min = 1, message = "At least one change type is required"))]
+    pub change_types: Option<Vec<AmendmentChangeType>>,
+    pub proposed_content: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateAmendmentCommentRequest {
+    #[validate(length(min = 1, message = "Content is required"))]
+    pub content: String,
+    #[validate(length(min = 1, max = 100, message = "Section must be 1-100 characters"))]
+    pub section: Option<String>,
+}
+
+/// 修正案與核准版本的比較結果
+#[derive(Debug, Serialize)]
+pub struct ProtocolAmendmentDiff {
+    pub amendment_id: Uuid,
+    pub protocol_id: Uuid,
+    pub base_version: i32,
+    pub base_submitted_at: DateTime<Utc>,
+    pub total_changes: usize,
+    pub sections: Vec<ProtocolSectionDiff>,
+}
@@ -19,6 +19,56 @@ pub enum StockDirection {
     AdjustOut,
 }
 
+impl StockDirection {
+    /// 是否為入庫方向
+    pub fn is_inbound(&self) -> bool {
+        matches!(self, StockDirection::In | StockDirection::TransferIn | StockDirection::AdjustIn)
+    }
+
+    /// 沖銷用的反向方向
+    pub fn reversed(&self) -> StockDirection {
+        match self {
+            StockDirection::In => StockDirection::Out,
+            StockDirection::Out => StockDirection::In,
+            StockDirection::TransferIn => StockDirection::TransferOut,
+            StockDirection::TransferOut => StockDirection::TransferIn,
+            StockDirection::AdjustIn => StockDirection::AdjustOut,
+            StockDirection::AdjustOut => StockDirection::AdjustIn,
+        }
+    }
+}
+
+/// 成本計價方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostingMethod {
+    /// 先進先出
+    Fifo,
+    /// 移動加權平均
+    WeightedAverage,
+}
+
+impl CostingMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CostingMethod::Fifo => "fifo",
+            CostingMethod::WeightedAverage => "weighted_average",
+        }
+    }
+}
+
+impl std::str::FromStr for CostingMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fifo" => Ok(CostingMethod::Fifo),
+            "weighted_average" | "moving_average" => Ok(CostingMethod::WeightedAverage),
+            other => Err(format!("Unknown costing method: {}", other)),
+        }
+    }
+}
+
 /// 庫存流水紀錄
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct StockLedger {
@@ -33,9 +83,12 @@ pub struct StockLedger {
     pub direction: StockDirection,
     pub qty_base: Decimal,
     pub unit_cost: Option<Decimal>,
+    pub total_cost: Option<Decimal>,
     pub batch_no: Option<String>,
     pub expiry_date: Option<NaiveDate>,
     pub created_at: DateTime<Utc>,
+    /// 儲位（None 表示未指定儲位）
+    pub location_id: Option<Uuid>,
 }
 
 /// 庫存現況
@@ -54,6 +107,49 @@ pub struct InventoryOnHand {
     pub reorder_point: Option<Decimal>,
 }
 
+/// 庫存現況（依儲位）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct InventoryByLocation {
+    pub warehouse_id: Uuid,
+    pub warehouse_code: String,
+    pub warehouse_name: String,
+    /// 儲位（None 表示未指定儲位的庫存）
+    pub location_id: Option<Uuid>,
+    pub location_code: Option<String>,
+    pub location_name: Option<String>,
+    pub product_id: Uuid,
+    pub product_sku: String,
+    pub product_name: String,
+    pub base_uom: String,
+    pub qty_on_hand: Decimal,
+}
+
+/// 庫存成本層（倉庫 / 產品 / 批號）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct StockCostLayer {
+    pub id: Uuid,
+    pub warehouse_id: Uuid,
+    pub product_id: Uuid,
+    pub batch_no: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    pub source_ledger_id: Option<Uuid>,
+    pub layer_date: DateTime<Utc>,
+    pub original_qty: Decimal,
+    pub remaining_qty: Decimal,
+    pub unit_cost: Decimal,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 成本層查詢
+#[derive(Debug, Deserialize)]
+pub struct CostLayerQuery {
+    pub warehouse_id: Option<Uuid>,
+    pub product_id: Option<Uuid>,
+    pub batch_no: Option<String>,
+    /// 是否包含已耗用完畢的成本層
+    pub include_depleted: Option<bool>,
+}
+
 /// 庫存快照（可選快取表）
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct InventorySnapshot {
@@ -73,6 +169,7 @@ pub struct StockLedgerQuery {
     pub date_to: Option<NaiveDate>,
     pub doc_type: Option<DocType>,
     pub batch_no: Option<String>,
+    pub location_id: Option<Uuid>,
 }
 
 /// 庫存現況查詢
@@ -83,6 +180,8 @@ pub struct InventoryQuery {
     pub keyword: Option<String>,
     pub batch_no: Option<String>,
     pub low_stock_only: Option<bool>,
+    /// 依儲位篩選（僅儲位庫存查詢使用）
+    pub location_id: Option<Uuid>,
 }
 
 /// 庫存流水詳情（含關聯名稱）
@@ -101,8 +200,11 @@ pub struct StockLedgerDetail {
     pub direction: StockDirection,
     pub qty_base: Decimal,
     pub unit_cost: Option<Decimal>,
+    pub total_cost: Option<Decimal>,
     pub batch_no: Option<String>,
     pub expiry_date: Option<NaiveDate>,
+    pub location_id: Option<Uuid>,
+    pub location_code: Option<String>,
     pub running_balance: Option<Decimal>,
 }
 
@@ -1,9 +1,12 @@
 use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 use validator::Validate;
 
+use super::StorageCondition;
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Warehouse {
     pub id: Uuid,
@@ -35,3 +38,81 @@ pub struct WarehouseQuery {
     pub keyword: Option<String>,
     pub is_active: Option<bool>,
 }
+
+/// 儲位層級
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum StorageLocationType {
+    /// 區
+    Zone,
+    /// 架
+    Rack,
+    /// 格
+    Bin,
+}
+
+impl StorageLocationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StorageLocationType::Zone => "zone",
+            StorageLocationType::Rack => "rack",
+            StorageLocationType::Bin => "bin",
+        }
+    }
+
+    /// 上層儲位應有的層級（區為最上層）
+    pub fn parent_type(&self) -> Option<StorageLocationType> {
+        match self {
+            StorageLocationType::Zone => None,
+            StorageLocationType::Rack => Some(StorageLocationType::Zone),
+            StorageLocationType::Bin => Some(StorageLocationType::Rack),
+        }
+    }
+}
+
+/// 倉庫儲位（區 / 架 / 格）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct StorageLocation {
+    pub id: Uuid,
+    pub warehouse_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub code: String,
+    pub name: String,
+    pub location_type: StorageLocationType,
+    /// 容量（基本單位數量，None 表示不限）
+    pub capacity: Option<Decimal>,
+    /// 保存條件代碼（RT/RF/FZ/DK/DY）
+    pub storage_condition: Option<String>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateStorageLocationRequest {
+    pub parent_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 50, message = "Code must be 1-50 characters"))]
+    pub code: String,
+    #[validate(length(min = 1, max = 200, message = "Name must be 1-200 characters"))]
+    pub name: String,
+    pub location_type: StorageLocationType,
+    pub capacity: Option<Decimal>,
+    pub storage_condition: Option<StorageCondition>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateStorageLocationRequest {
+    #[validate(length(min = 1, max = 200, message = "Name must be 1-200 characters"))]
+    pub name: Option<String>,
+    pub capacity: Option<Decimal>,
+    pub storage_condition: Option<StorageCondition>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StorageLocationQuery {
+    pub location_type: Option<StorageLocationType>,
+    pub parent_id: Option<Uuid>,
+    pub is_active: Option<bool>,
+}
@@ -1,10 +1,18 @@
 use axum::{
+    extract::DefaultBodyLimit,
     middleware,
     routing::{get, post, put, delete},
     Router,
 };
 
-use crate::{handlers, middleware::auth_middleware, AppState};
+use crate::{
+    handlers,
+    middleware::{activity_logger_middleware, auth_middleware},
+    AppState,
+};
+
+/// 上傳路由的請求大小上限；各類檔案的單檔上限由 FileService 於串流寫入時檢查
+const UPLOAD_BODY_LIMIT: usize = 100 * 1024 * 1024;
 
 pub fn api_routes(state: AppState) -> Router {
     // Public routes (no auth required) - 移除公開註冊，改為私域註冊
@@ -35,6 +43,8 @@ pub fn api_routes(state: AppState) -> Router {
         // Warehouses
         .route("/warehouses", get(handlers::list_warehouses).post(handlers::create_warehouse))
         .route("/warehouses/:id", get(handlers::get_warehouse).put(handlers::update_warehouse).delete(handlers::delete_warehouse))
+        .route("/warehouses/:id/locations", get(handlers::list_storage_locations).post(handlers::create_storage_location))
+        .route("/storage-locations/:id", get(handlers::get_storage_location).put(handlers::update_storage_location).delete(handlers::delete_storage_location))
         // Products
         .route("/products", get(handlers::list_products).post(handlers::create_product))
         .route("/products/:id", get(handlers::get_product).put(handlers::update_product).delete(handlers::delete_product))
@@ -56,10 +66,18 @@ pub fn api_routes(state: AppState) -> Router {
         .route("/documents/:id/submit", post(handlers::submit_document))
         .route("/documents/:id/approve", post(handlers::approve_document))
         .route("/documents/:id/cancel", post(handlers::cancel_document))
+        .route("/documents/:id/reverse", post(handlers::reverse_document))
+        .route("/documents/:id/stocktake", get(handlers::get_stocktake_sheet))
+        .route("/documents/:id/stocktake/start", post(handlers::start_stocktake))
+        .route("/documents/:id/stocktake/counts", put(handlers::record_stocktake_counts))
+        .route("/documents/:id/stocktake/import", post(handlers::import_stocktake_counts))
+        .route("/documents/:id/stocktake/variance", get(handlers::get_stocktake_variance))
         // Inventory
         .route("/inventory/on-hand", get(handlers::get_inventory_on_hand))
+        .route("/inventory/on-hand/by-location", get(handlers::get_inventory_by_location))
         .route("/inventory/ledger", get(handlers::get_stock_ledger))
         .route("/inventory/low-stock", get(handlers::get_low_stock_alerts))
+        .route("/inventory/cost-layers", get(handlers::get_cost_layers))
         // Audit Logs
         .route("/audit-logs", get(handlers::list_audit_logs))
         // Reports
@@ -72,19 +90,39 @@ pub fn api_routes(state: AppState) -> Router {
         .route("/protocols", get(handlers::list_protocols).post(handlers::create_protocol))
         .route("/protocols/:id", get(handlers::get_protocol).put(handlers::update_protocol))
         .route("/protocols/:id/submit", post(handlers::submit_protocol))
+        .route("/protocols/status-transitions", get(handlers::get_protocol_transition_table))
         .route("/protocols/:id/status", post(handlers::change_protocol_status))
+        .route("/protocols/:id/transitions", get(handlers::get_protocol_transitions))
+        .route("/protocols/:id/reviews/complete", post(handlers::complete_protocol_review))
+        .route("/protocols/:id/votes", get(handlers::get_review_vote_summary).post(handlers::submit_review_vote))
         .route("/protocols/:id/versions", get(handlers::get_protocol_versions))
+        .route("/protocols/:id/versions/diff", get(handlers::get_protocol_version_diff))
         .route("/protocols/:id/status-history", get(handlers::get_protocol_status_history))
         .route("/protocols/:id/animal-stats", get(handlers::get_protocol_animal_stats))
         .route("/protocols/:id/export-pdf", get(handlers::export_protocol_pdf))
+        // Protocol Amendments
+        .route("/protocols/amendment-transitions", get(handlers::get_amendment_transition_table))
+        .route("/protocols/:id/amendments", get(handlers::list_protocol_amendments).post(handlers::create_protocol_amendment))
+        .route("/amendments/:id", get(handlers::get_protocol_amendment).put(handlers::update_protocol_amendment))
+        .route("/amendments/:id/submit", post(handlers::submit_protocol_amendment))
+        .route("/amendments/:id/withdraw", post(handlers::withdraw_protocol_amendment))
+        .route("/amendments/:id/status", post(handlers::change_protocol_amendment_status))
+        .route("/amendments/:id/transitions", get(handlers::get_protocol_amendment_transitions))
+        .route("/amendments/:id/diff", get(handlers::get_protocol_amendment_diff))
+        .route("/amendments/:id/reviews/assignments", get(handlers::list_amendment_review_assignments))
+        .route("/amendments/:id/reviews/complete", post(handlers::complete_amendment_review))
+        .route("/amendments/:id/comments", get(handlers::list_amendment_review_comments).post(handlers::create_amendment_review_comment))
         // Review
         .route("/reviews/assignments", get(handlers::list_review_assignments).post(handlers::assign_reviewer))
+        .route("/reviews/overdue", get(handlers::list_overdue_reviews))
         .route("/reviews/comments", get(handlers::list_review_comments).post(handlers::create_review_comment))
         .route("/reviews/comments/:id/resolve", post(handlers::resolve_review_comment))
         .route("/reviews/comments/reply", post(handlers::reply_review_comment))
         // Co-Editor Assignment
         .route("/protocols/:id/co-editors", get(handlers::list_co_editors).post(handlers::assign_co_editor))
         .route("/protocols/:id/co-editors/:user_id", delete(handlers::remove_co_editor))
+        .route("/protocols/:id/coi-declarations", get(handlers::list_reviewer_coi_declarations).post(handlers::declare_reviewer_coi))
+        .route("/protocols/:id/blinded-review", put(handlers::set_protocol_blinded_review))
         // My Projects
         .route("/my-projects", get(handlers::get_my_protocols))
         // Pig Sources
@@ -98,6 +136,7 @@ pub fn api_routes(state: AppState) -> Router {
         .route("/pigs/vet-comments", get(handlers::get_vet_comments))
         .route("/pigs/:id", get(handlers::get_pig).put(handlers::update_pig).delete(handlers::delete_pig))
         .route("/pigs/:id/vet-read", post(handlers::mark_pig_vet_read))
+        .route("/pigs/:id/timeline", get(handlers::get_pig_timeline))
         // Pig Records - Observations
         .route("/pigs/:id/observations", get(handlers::list_pig_observations).post(handlers::create_pig_observation))
         .route("/pigs/:id/observations/with-recommendations", get(handlers::list_pig_observations_with_recommendations))
@@ -115,6 +154,10 @@ pub fn api_routes(state: AppState) -> Router {
         // Pig Records - Weights
         .route("/pigs/:id/weights", get(handlers::list_pig_weights).post(handlers::create_pig_weight))
         .route("/weights/:id", put(handlers::update_pig_weight).delete(handlers::delete_pig_weight))
+        .route("/pigs/:id/growth", get(handlers::get_pig_growth))
+        .route("/pigs/:id/weight-alerts", get(handlers::list_pig_weight_alerts))
+        .route("/pigs/weight-loss/check", post(handlers::check_weight_loss))
+        .route("/projects/:iacuc_no/growth", get(handlers::get_project_growth))
         // Pig Records - Vaccinations
         .route("/pigs/:id/vaccinations", get(handlers::list_pig_vaccinations).post(handlers::create_pig_vaccination))
         .route("/vaccinations/:id", put(handlers::update_pig_vaccination).delete(handlers::delete_pig_vaccination))
@@ -122,6 +165,13 @@ pub fn api_routes(state: AppState) -> Router {
         .route("/pigs/:id/sacrifice", get(handlers::get_pig_sacrifice).post(handlers::upsert_pig_sacrifice))
         // Pig Records - Pathology
         .route("/pigs/:id/pathology", get(handlers::get_pig_pathology_report).post(handlers::upsert_pig_pathology_report))
+        // Pig Records - Welfare Scores
+        .route("/welfare-score-sheets", get(handlers::list_welfare_score_sheets).post(handlers::create_welfare_score_sheet))
+        .route("/welfare-score-sheets/:id", get(handlers::get_welfare_score_sheet).put(handlers::update_welfare_score_sheet).delete(handlers::deactivate_welfare_score_sheet))
+        .route("/pigs/:id/welfare-scores", get(handlers::list_pig_welfare_scores).post(handlers::create_pig_welfare_score))
+        .route("/welfare-scores/:id", get(handlers::get_pig_welfare_score).put(handlers::update_pig_welfare_score).delete(handlers::delete_pig_welfare_score))
+        .route("/welfare-scores/:id/vet-read", post(handlers::mark_welfare_score_vet_read))
+        .route("/welfare-scores/:id/versions", get(handlers::get_welfare_score_versions))
         // Vet Recommendations
         .route("/observations/:id/recommendations", get(handlers::get_observation_vet_recommendations).post(handlers::add_observation_vet_recommendation))
         .route("/observations/:id/recommendations/with-attachments", post(handlers::add_observation_vet_recommendation_with_attachments))
@@ -129,7 +179,10 @@ pub fn api_routes(state: AppState) -> Router {
         .route("/surgeries/:id/recommendations/with-attachments", post(handlers::add_surgery_vet_recommendation_with_attachments))
         // Pig Export
         .route("/pigs/:id/export", post(handlers::export_pig_medical_data))
+        .route("/pigs/:id/exports", get(handlers::list_pig_export_records))
         .route("/projects/:iacuc_no/export", post(handlers::export_project_medical_data))
+        .route("/projects/:iacuc_no/exports", get(handlers::list_project_export_records))
+        .route("/exports/:id/download", get(handlers::download_export_record))
         // Import Batches
         .route("/pigs/import/batches", get(handlers::list_import_batches))
         .route("/pigs/import/template/basic", get(handlers::download_basic_import_template))
@@ -153,15 +206,18 @@ pub fn api_routes(state: AppState) -> Router {
         // Scheduled Reports
         .route("/scheduled-reports", get(handlers::list_scheduled_reports).post(handlers::create_scheduled_report))
         .route("/scheduled-reports/:id", get(handlers::get_scheduled_report).put(handlers::update_scheduled_report).delete(handlers::delete_scheduled_report))
+        .route("/scheduled-reports/:id/run", post(handlers::run_scheduled_report))
         .route("/report-history", get(handlers::list_report_history))
         .route("/report-history/:id/download", get(handlers::download_report))
         // File Upload
-        .route("/protocols/:id/attachments", post(handlers::upload_protocol_attachment))
-        .route("/pigs/:id/photos", post(handlers::upload_pig_photo))
-        .route("/pigs/:id/pathology/attachments", post(handlers::upload_pathology_report))
-        .route("/pigs/:id/sacrifice/photos", post(handlers::upload_sacrifice_photo))
-        .route("/vet-recommendations/:record_type/:record_id/attachments", post(handlers::upload_vet_recommendation_attachment))
+        .route("/protocols/:id/attachments", post(handlers::upload_protocol_attachment).layer(DefaultBodyLimit::max(UPLOAD_BODY_LIMIT)))
+        .route("/pigs/:id/photos", post(handlers::upload_pig_photo).layer(DefaultBodyLimit::max(UPLOAD_BODY_LIMIT)))
+        .route("/pigs/:id/pathology/attachments", post(handlers::upload_pathology_report).layer(DefaultBodyLimit::max(UPLOAD_BODY_LIMIT)))
+        .route("/pigs/:id/sacrifice/photos", post(handlers::upload_sacrifice_photo).layer(DefaultBodyLimit::max(UPLOAD_BODY_LIMIT)))
+        .route("/vet-recommendations/:record_type/:record_id/attachments", post(handlers::upload_vet_recommendation_attachment).layer(DefaultBodyLimit::max(UPLOAD_BODY_LIMIT)))
         .route("/attachments", get(handlers::list_attachments))
+        .route("/attachments/history", get(handlers::list_attachment_history))
+        .route("/attachments/:id/versions", post(handlers::upload_attachment_version).layer(DefaultBodyLimit::max(UPLOAD_BODY_LIMIT)))
         .route("/attachments/:id", get(handlers::download_attachment).delete(handlers::delete_attachment))
         // ============================================
         // Admin Audit Trail (新增)
@@ -174,6 +230,7 @@ pub fn api_routes(state: AppState) -> Router {
         .route("/admin/audit/sessions/:id/logout", post(handlers::force_logout_session))
         .route("/admin/audit/alerts", get(handlers::list_security_alerts))
         .route("/admin/audit/alerts/:id/resolve", post(handlers::resolve_security_alert))
+        .route("/admin/audit/integrity-scan", post(handlers::run_integrity_scan))
         .route("/admin/audit/dashboard", get(handlers::get_audit_dashboard))
         // ============================================
         // HR Attendance (新增)
@@ -199,8 +256,9 @@ pub fn api_routes(state: AppState) -> Router {
         .route("/hr/leaves/:id/submit", post(handlers::submit_leave))
         .route("/hr/leaves/:id/approve", post(handlers::approve_leave))
         .route("/hr/leaves/:id/reject", post(handlers::reject_leave))
+        .route("/hr/leaves/:id/approvals", get(handlers::list_leave_approvals))
         .route("/hr/leaves/:id/cancel", post(handlers::cancel_leave))
-        .route("/hr/leaves/attachments", post(handlers::upload_leave_attachment))
+        .route("/hr/leaves/attachments", post(handlers::upload_leave_attachment).layer(DefaultBodyLimit::max(UPLOAD_BODY_LIMIT)))
         // ============================================
         // HR Balances (新增)
         // ============================================
@@ -250,12 +308,13 @@ pub fn api_routes(state: AppState) -> Router {
         // ============================================
         // Electronic Signatures & Annotations (GLP Compliance)
         // ============================================
-        .route("/signatures/sacrifice/:id", post(handlers::sign_sacrifice_record).get(handlers::get_sacrifice_signature_status))
-        .route("/signatures/observation/:id", post(handlers::sign_observation_record))
+        .route("/signatures/:entity_type/:entity_id", get(handlers::get_entity_signatures).post(handlers::sign_entity_record))
+        .route("/records/:record_type/:record_id/unlock", post(handlers::unlock_record))
         .route("/annotations/:record_type/:record_id", get(handlers::get_record_annotations).post(handlers::add_record_annotation))
         .route_layer(middleware::from_fn_with_state(state.clone(), auth_middleware))
-        .with_state(state);
+        .with_state(state.clone());
 
     Router::new()
         .nest("/api", public_routes.merge(protected_routes))
+        .layer(middleware::from_fn_with_state(state, activity_logger_middleware))
 }
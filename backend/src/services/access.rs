@@ -0,0 +1,334 @@
+// 資料存取範圍
+// 依所屬實體（計畫、豬隻）判斷使用者可否存取相關資料
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    middleware::CurrentUser,
+    models::VetRecordType,
+    services::AnimalService,
+    AppError, Result,
+};
+
+/// 動物資料存取範圍
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataScope {
+    /// 內部人員：可存取所有動物資料
+    Staff,
+    /// 僅可存取 iacuc_no 屬於這些計畫的豬隻（PI、共同編輯者或審查委員）
+    Protocols(Vec<String>),
+}
+
+impl DataScope {
+    /// 豬隻（依其 iacuc_no）是否在範圍內；未分配計畫的豬隻僅內部人員可見
+    pub fn allows(&self, iacuc_no: Option<&str>) -> bool {
+        match self {
+            DataScope::Staff => true,
+            DataScope::Protocols(iacuc_nos) => {
+                iacuc_no.is_some_and(|no| iacuc_nos.iter().any(|allowed| allowed == no))
+            }
+        }
+    }
+
+    /// 範圍外的資料一律回應 404，不透露資料是否存在
+    pub fn ensure(&self, iacuc_no: Option<&str>, not_found: &str) -> Result<()> {
+        if self.allows(iacuc_no) {
+            Ok(())
+        } else {
+            Err(AppError::NotFound(not_found.to_string()))
+        }
+    }
+
+    /// 供 SQL 篩選使用：`($n::text[] IS NULL OR p.iacuc_no = ANY($n))`
+    pub fn iacuc_filter(&self) -> Option<&[String]> {
+        match self {
+            DataScope::Staff => None,
+            DataScope::Protocols(iacuc_nos) => Some(iacuc_nos),
+        }
+    }
+}
+
+/// 依紀錄 ID 反查所屬豬隻的紀錄類型
+#[derive(Debug, Clone, Copy)]
+pub enum PigRecord {
+    Observation,
+    Surgery,
+    Weight,
+    Vaccination,
+    WelfareScore,
+    Sacrifice,
+    Pathology,
+}
+
+impl PigRecord {
+    fn table(self) -> &'static str {
+        match self {
+            PigRecord::Observation => "pig_observations",
+            PigRecord::Surgery => "pig_surgeries",
+            PigRecord::Weight => "pig_weights",
+            PigRecord::Vaccination => "pig_vaccinations",
+            PigRecord::WelfareScore => "pig_welfare_scores",
+            PigRecord::Sacrifice => "pig_sacrifices",
+            PigRecord::Pathology => "pig_pathology_reports",
+        }
+    }
+
+    fn not_found(self) -> &'static str {
+        match self {
+            PigRecord::Observation => "Observation not found",
+            PigRecord::Surgery => "Surgery not found",
+            PigRecord::Weight => "Weight record not found",
+            PigRecord::Vaccination => "Vaccination record not found",
+            PigRecord::WelfareScore => "Welfare score not found",
+            PigRecord::Sacrifice => "Sacrifice record not found",
+            PigRecord::Pathology => "Pathology report not found",
+        }
+    }
+}
+
+impl From<VetRecordType> for PigRecord {
+    fn from(record_type: VetRecordType) -> Self {
+        match record_type {
+            VetRecordType::Observation => PigRecord::Observation,
+            VetRecordType::Surgery => PigRecord::Surgery,
+        }
+    }
+}
+
+pub struct AccessService;
+
+impl AccessService {
+    /// 可查看所有計畫（IACUC 主委、執行秘書、系統管理員）
+    pub fn can_view_all_protocols(user: &CurrentUser) -> bool {
+        user.has_permission("aup.protocol.view_all")
+            || user.roles.iter().any(|r| r == "CHAIR" || r == "IACUC_STAFF")
+    }
+
+    /// 可否查看計畫：PI、共同編輯者、已指派審查委員或 IACUC 人員
+    pub async fn can_view_protocol(pool: &PgPool, user: &CurrentUser, protocol_id: Uuid) -> Result<bool> {
+        if Self::can_view_all_protocols(user) {
+            return Ok(true);
+        }
+
+        let allowed: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM protocols p
+                WHERE p.id = $1
+                  AND (
+                      p.pi_user_id = $2
+                      OR EXISTS(
+                          SELECT 1 FROM user_protocols up
+                          WHERE up.protocol_id = p.id AND up.user_id = $2
+                            AND up.role_in_protocol IN ('PI', 'CLIENT', 'CO_EDITOR')
+                      )
+                      OR EXISTS(
+                          SELECT 1 FROM review_assignments ra
+                          WHERE ra.protocol_id = p.id AND ra.reviewer_id = $2
+                      )
+                  )
+            )
+            "#
+        )
+        .bind(protocol_id)
+        .bind(user.id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(allowed)
+    }
+
+    /// 取得使用者的動物資料範圍
+    ///
+    /// 可查看所有計畫者（IACUC 主委、執行秘書）與具 animal.info.view_all 的內部使用者不受限；
+    /// 外部使用者（users.is_internal = false）即使角色具 view_all 也只能存取參與計畫的豬隻。
+    pub async fn data_scope(pool: &PgPool, user: &CurrentUser) -> Result<DataScope> {
+        if Self::can_view_all_protocols(user) {
+            return Ok(DataScope::Staff);
+        }
+
+        let has_view_all = user.has_permission("animal.info.view_all");
+        if has_view_all {
+            let is_internal: bool = sqlx::query_scalar("SELECT is_internal FROM users WHERE id = $1")
+                .bind(user.id)
+                .fetch_optional(pool)
+                .await?
+                .unwrap_or(false);
+            if is_internal {
+                return Ok(DataScope::Staff);
+            }
+        } else if !user.has_permission("animal.info.view_project") {
+            return Ok(DataScope::Protocols(Vec::new()));
+        }
+
+        let iacuc_nos = AnimalService::get_user_iacuc_nos(pool, user.id).await?;
+        Ok(DataScope::Protocols(iacuc_nos))
+    }
+
+    /// 可否查看豬隻
+    pub async fn can_view_pig(pool: &PgPool, user: &CurrentUser, pig_id: i32) -> Result<bool> {
+        match Self::ensure_pig(pool, user, pig_id).await {
+            Ok(()) => Ok(true),
+            Err(AppError::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 確認豬隻存在且在使用者的資料範圍內，否則回應 404
+    pub async fn ensure_pig(pool: &PgPool, user: &CurrentUser, pig_id: i32) -> Result<()> {
+        let iacuc_no: Option<Option<String>> =
+            sqlx::query_scalar("SELECT iacuc_no FROM pigs WHERE id = $1 AND deleted_at IS NULL")
+                .bind(pig_id)
+                .fetch_optional(pool)
+                .await?;
+        let Some(iacuc_no) = iacuc_no else {
+            return Err(AppError::NotFound("Pig not found".to_string()));
+        };
+
+        Self::data_scope(pool, user)
+            .await?
+            .ensure(iacuc_no.as_deref(), "Pig not found")
+    }
+
+    /// 確認豬隻紀錄所屬豬隻在使用者的資料範圍內，否則回應 404
+    pub async fn ensure_pig_record(pool: &PgPool, user: &CurrentUser, record: PigRecord, id: i32) -> Result<()> {
+        let iacuc_no: Option<Option<String>> = sqlx::query_scalar(&format!(
+            "SELECT g.iacuc_no FROM {} r JOIN pigs g ON g.id = r.pig_id WHERE r.id = $1",
+            record.table()
+        ))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+        let Some(iacuc_no) = iacuc_no else {
+            return Err(AppError::NotFound(record.not_found().to_string()));
+        };
+
+        Self::data_scope(pool, user)
+            .await?
+            .ensure(iacuc_no.as_deref(), record.not_found())
+    }
+
+    /// 確認計畫（IACUC 編號）在使用者的資料範圍內，否則回應 404
+    pub async fn ensure_project(pool: &PgPool, user: &CurrentUser, iacuc_no: &str) -> Result<()> {
+        Self::data_scope(pool, user)
+            .await?
+            .ensure(Some(iacuc_no), "Project not found")
+    }
+
+    /// 確認使用者可查看計畫，否則回應 404
+    pub async fn ensure_protocol(pool: &PgPool, user: &CurrentUser, protocol_id: Uuid) -> Result<()> {
+        if Self::can_view_protocol(pool, user, protocol_id).await? {
+            Ok(())
+        } else {
+            Err(AppError::NotFound("Protocol not found".to_string()))
+        }
+    }
+
+    /// 確認評分表所屬計畫在使用者的資料範圍內，否則回應 404
+    ///
+    /// 未綁定計畫的共用評分表：`allow_shared` 時皆可存取，否則僅限內部人員（如修改）。
+    pub async fn ensure_sheet_protocol(
+        pool: &PgPool,
+        user: &CurrentUser,
+        protocol_id: Option<Uuid>,
+        allow_shared: bool,
+    ) -> Result<()> {
+        const NOT_FOUND: &str = "Welfare score sheet not found";
+
+        let scope = Self::data_scope(pool, user).await?;
+        match protocol_id {
+            None if allow_shared => Ok(()),
+            None => scope.ensure(None, NOT_FOUND),
+            Some(protocol_id) => {
+                let iacuc_no: Option<String> =
+                    sqlx::query_scalar("SELECT iacuc_no FROM protocols WHERE id = $1")
+                        .bind(protocol_id)
+                        .fetch_optional(pool)
+                        .await?
+                        .flatten();
+                scope.ensure(iacuc_no.as_deref(), NOT_FOUND)
+            }
+        }
+    }
+
+    /// 確認福祉評分表在使用者的資料範圍內，否則回應 404
+    pub async fn ensure_welfare_sheet(
+        pool: &PgPool,
+        user: &CurrentUser,
+        sheet_id: Uuid,
+        allow_shared: bool,
+    ) -> Result<()> {
+        let protocol_id: Option<Option<Uuid>> =
+            sqlx::query_scalar("SELECT protocol_id FROM welfare_score_sheets WHERE id = $1")
+                .bind(sheet_id)
+                .fetch_optional(pool)
+                .await?;
+        let Some(protocol_id) = protocol_id else {
+            return Err(AppError::NotFound("Welfare score sheet not found".to_string()));
+        };
+
+        Self::ensure_sheet_protocol(pool, user, protocol_id, allow_shared).await
+    }
+
+    /// 確認使用者不受計畫範圍限制（跨計畫的批次作業，如匯入）
+    pub async fn ensure_unrestricted(pool: &PgPool, user: &CurrentUser) -> Result<()> {
+        match Self::data_scope(pool, user).await? {
+            DataScope::Staff => Ok(()),
+            DataScope::Protocols(_) => Err(AppError::Forbidden(
+                "Batch operations are limited to internal staff".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staff_scope_allows_everything() {
+        assert!(DataScope::Staff.allows(Some("IACUC-001")));
+        assert!(DataScope::Staff.allows(None));
+        assert!(DataScope::Staff.iacuc_filter().is_none());
+    }
+
+    #[test]
+    fn test_protocol_scope_hides_other_protocols_as_not_found() {
+        let scope = DataScope::Protocols(vec!["IACUC-001".to_string()]);
+
+        assert!(scope.ensure(Some("IACUC-001"), "Pig not found").is_ok());
+        assert!(matches!(
+            scope.ensure(Some("IACUC-002"), "Pig not found"),
+            Err(AppError::NotFound(_))
+        ));
+        // 未分配計畫的豬隻不屬於任何外部使用者
+        assert!(matches!(scope.ensure(None, "Pig not found"), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_empty_scope_allows_nothing() {
+        let scope = DataScope::Protocols(Vec::new());
+        assert!(!scope.allows(Some("IACUC-001")));
+        assert_eq!(scope.iacuc_filter(), Some(&[][..]));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_iacuc_staff_keep_access_to_all_protocols_animals(db: PgPool) {
+        let fixture = crate::test_support::scope_fixture(&db).await;
+        let staff_id = crate::test_support::insert_user(&db, "iacuc-staff@example.com", true).await;
+        let staff = crate::test_support::current_user(
+            staff_id,
+            "iacuc-staff@example.com",
+            &["IACUC_STAFF"],
+            &["animal.info.view_project"],
+        );
+
+        AccessService::ensure_pig(&db, &staff, fixture.other_pig).await.unwrap();
+        assert!(matches!(
+            AccessService::ensure_pig(&db, &fixture.external_user, fixture.other_pig).await,
+            Err(AppError::NotFound(_))
+        ));
+    }
+}
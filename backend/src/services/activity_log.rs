@@ -0,0 +1,294 @@
+// Activity Log Service
+// 批次寫入 user_activity_logs、GLP 實體前後快照與分區維護
+
+use std::net::IpAddr;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::Result;
+
+/// 寫入佇列容量；佇列滿時丟棄新記錄，避免拖慢請求
+const QUEUE_CAPACITY: usize = 10_000;
+/// 單次批次寫入的最大筆數
+const MAX_BATCH_SIZE: usize = 200;
+/// 批次等待時間
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 需記錄前後快照的 GLP 實體：(entity_type, 資料表, 鍵值欄位, 鍵值型別)
+const GLP_ENTITIES: &[(&str, &str, &str, &str)] = &[
+    ("protocol", "protocols", "id", "uuid"),
+    ("pig", "pigs", "id", "integer"),
+    ("pig_observation", "pig_observations", "id", "integer"),
+    ("pig_surgery", "pig_surgeries", "id", "integer"),
+    ("pig_weight", "pig_weights", "id", "integer"),
+    ("pig_vaccination", "pig_vaccinations", "id", "integer"),
+    ("pig_welfare_score", "pig_welfare_scores", "id", "integer"),
+    ("pig_sacrifice", "pig_sacrifices", "pig_id", "integer"),
+    ("pig_pathology", "pig_pathology_reports", "pig_id", "integer"),
+];
+
+/// 一筆待寫入的活動記錄
+#[derive(Debug, Clone)]
+pub struct ActivityLogEntry {
+    pub created_at: DateTime<Utc>,
+    pub actor_user_id: Option<Uuid>,
+    pub actor_email: Option<String>,
+    pub actor_roles: Option<serde_json::Value>,
+    pub session_id: Option<Uuid>,
+    pub event_category: String,
+    pub event_type: String,
+    pub event_severity: String,
+    pub entity_type: Option<String>,
+    pub entity_key: Option<String>,
+    pub before_data: Option<serde_json::Value>,
+    pub after_data: Option<serde_json::Value>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_path: String,
+    pub request_method: String,
+    pub response_status: i32,
+    pub duration_ms: i32,
+    pub is_suspicious: bool,
+}
+
+/// 活動記錄寫入器（背景批次寫入）
+#[derive(Clone)]
+pub struct ActivityLogWriter {
+    tx: mpsc::Sender<ActivityLogEntry>,
+}
+
+impl ActivityLogWriter {
+    /// 啟動背景寫入工作
+    pub fn spawn(pool: PgPool) -> Self {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(ActivityLogService::run_writer(pool, rx));
+        Self { tx }
+    }
+
+    /// 排入一筆記錄（不等待寫入）
+    pub fn record(&self, entry: ActivityLogEntry) {
+        if let Err(e) = self.tx.try_send(entry) {
+            tracing::warn!("Activity log dropped: {}", e);
+        }
+    }
+}
+
+pub struct ActivityLogService;
+
+impl ActivityLogService {
+    /// 背景迴圈：累積至批次上限或等待逾時後一次寫入
+    async fn run_writer(pool: PgPool, mut rx: mpsc::Receiver<ActivityLogEntry>) {
+        while let Some(first) = rx.recv().await {
+            let mut batch = vec![first];
+            let deadline = tokio::time::sleep(FLUSH_INTERVAL);
+            tokio::pin!(deadline);
+
+            while batch.len() < MAX_BATCH_SIZE {
+                tokio::select! {
+                    entry = rx.recv() => match entry {
+                        Some(entry) => batch.push(entry),
+                        None => break,
+                    },
+                    _ = &mut deadline => break,
+                }
+            }
+
+            if let Err(e) = Self::insert_batch(&pool, &batch).await {
+                tracing::error!("Failed to write {} activity logs: {}", batch.len(), e);
+            }
+        }
+    }
+
+    /// 批次寫入活動記錄
+    async fn insert_batch(pool: &PgPool, entries: &[ActivityLogEntry]) -> Result<()> {
+        let mut builder: QueryBuilder<Postgres> = QueryBuilder::new(
+            r#"
+            INSERT INTO user_activity_logs (
+                id, partition_date, created_at,
+                actor_user_id, actor_email, actor_roles, session_id,
+                event_category, event_type, event_severity,
+                entity_type, entity_id, entity_key,
+                before_data, after_data, changed_fields,
+                ip_address, user_agent, request_path, request_method,
+                response_status, duration_ms, is_suspicious
+            )
+            "#,
+        );
+
+        builder.push_values(entries, |mut row, entry| {
+            let changed = match (&entry.before_data, &entry.after_data) {
+                (Some(before), Some(after)) => Some(changed_fields(before, after)),
+                _ => None,
+            };
+            let ip = entry
+                .ip_address
+                .as_deref()
+                .filter(|ip| ip.parse::<IpAddr>().is_ok());
+
+            row.push_bind(Uuid::new_v4())
+                .push_bind(entry.created_at.date_naive())
+                .push_bind(entry.created_at)
+                .push_bind(entry.actor_user_id)
+                .push_bind(entry.actor_email.as_deref())
+                .push_bind(entry.actor_roles.as_ref())
+                .push_bind(entry.session_id)
+                .push_bind(entry.event_category.as_str())
+                .push_bind(entry.event_type.as_str())
+                .push_bind(entry.event_severity.as_str())
+                .push_bind(entry.entity_type.as_deref())
+                .push_bind(entry.entity_key.as_deref().and_then(|k| k.parse::<Uuid>().ok()))
+                .push_bind(entry.entity_key.as_deref())
+                .push_bind(entry.before_data.as_ref())
+                .push_bind(entry.after_data.as_ref())
+                .push_bind(changed)
+                .push_bind(ip)
+                .push_unseparated("::inet")
+                .push_bind(entry.user_agent.as_deref())
+                .push_bind(entry.request_path.as_str())
+                .push_bind(entry.request_method.as_str())
+                .push_bind(entry.response_status)
+                .push_bind(entry.duration_ms)
+                .push_bind(entry.is_suspicious);
+        });
+
+        builder.build().execute(pool).await?;
+        Ok(())
+    }
+
+    /// 是否為需記錄前後快照的 GLP 實體
+    pub fn is_glp_entity(entity_type: &str) -> bool {
+        GLP_ENTITIES.iter().any(|(t, ..)| *t == entity_type)
+    }
+
+    /// 取得 GLP 實體目前的資料列（JSON）
+    pub async fn snapshot(
+        pool: &PgPool,
+        entity_type: &str,
+        key: &str,
+    ) -> Result<Option<serde_json::Value>> {
+        let Some((_, table, key_column, key_type)) =
+            GLP_ENTITIES.iter().find(|(t, ..)| *t == entity_type)
+        else {
+            return Ok(None);
+        };
+
+        let valid_key = match *key_type {
+            "uuid" => key.parse::<Uuid>().is_ok(),
+            _ => key.parse::<i32>().is_ok(),
+        };
+        if !valid_key {
+            return Ok(None);
+        }
+
+        let row: Option<serde_json::Value> = sqlx::query_scalar(&format!(
+            "SELECT to_jsonb(t) FROM {table} t WHERE t.{key_column} = $1::{key_type}"
+        ))
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// 確保目前及未來數季的分區存在，回傳新建的分區數
+    pub async fn ensure_partitions(pool: &PgPool, today: NaiveDate, quarters_ahead: u32) -> Result<usize> {
+        let mut created = 0;
+
+        for (name, from, to) in quarter_partitions(today, quarters_ahead + 1) {
+            let exists: bool = sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL")
+                .bind(&name)
+                .fetch_one(pool)
+                .await?;
+            if exists {
+                continue;
+            }
+
+            // 預設分區已有同區間資料時會失敗，記錄後繼續處理其他季
+            let result = sqlx::query(&format!(
+                "CREATE TABLE IF NOT EXISTS {name} PARTITION OF user_activity_logs \
+                 FOR VALUES FROM ('{from}') TO ('{to}')"
+            ))
+            .execute(pool)
+            .await;
+
+            match result {
+                Ok(_) => created += 1,
+                Err(e) => tracing::error!("Failed to create partition {}: {}", name, e),
+            }
+        }
+
+        Ok(created)
+    }
+}
+
+/// 比較前後快照，回傳有變動的欄位
+pub fn changed_fields(before: &serde_json::Value, after: &serde_json::Value) -> Vec<String> {
+    let (Some(before), Some(after)) = (before.as_object(), after.as_object()) else {
+        return Vec::new();
+    };
+
+    let mut fields: Vec<String> = before
+        .keys()
+        .chain(after.keys())
+        .filter(|k| before.get(*k) != after.get(*k))
+        .cloned()
+        .collect();
+    fields.sort();
+    fields.dedup();
+    fields
+}
+
+/// 由所在季起算 `count` 季的分區名稱與區間
+fn quarter_partitions(today: NaiveDate, count: u32) -> Vec<(String, NaiveDate, NaiveDate)> {
+    let quarter_month = (today.month0() / 3) * 3 + 1;
+    let Some(mut start) = NaiveDate::from_ymd_opt(today.year(), quarter_month, 1) else {
+        return Vec::new();
+    };
+
+    let mut partitions = Vec::new();
+    for _ in 0..count {
+        let Some(end) = start.checked_add_months(Months::new(3)) else {
+            break;
+        };
+        let name = format!(
+            "user_activity_logs_{}_q{}",
+            start.year(),
+            start.month0() / 3 + 1
+        );
+        partitions.push((name, start, end));
+        start = end;
+    }
+    partitions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_quarter_partitions_roll_over_year() {
+        let today = NaiveDate::from_ymd_opt(2026, 11, 17).unwrap();
+        let partitions = quarter_partitions(today, 3);
+
+        let names: Vec<&str> = partitions.iter().map(|(n, ..)| n.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["user_activity_logs_2026_q4", "user_activity_logs_2027_q1", "user_activity_logs_2027_q2"]
+        );
+        assert_eq!(partitions[0].1, NaiveDate::from_ymd_opt(2026, 10, 1).unwrap());
+        assert_eq!(partitions[0].2, NaiveDate::from_ymd_opt(2027, 1, 1).unwrap());
+    }
+
+    #[test]
+    fn test_changed_fields_lists_modified_added_and_removed_keys() {
+        let before = json!({ "id": 1, "status": "draft", "note": "a" });
+        let after = json!({ "id": 1, "status": "submitted", "vet_read": true });
+
+        assert_eq!(changed_fields(&before, &after), vec!["note", "status", "vet_read"]);
+    }
+}
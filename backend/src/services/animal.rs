@@ -1,4 +1,4 @@
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
 use crate::{
@@ -13,7 +13,9 @@ use crate::{
         PigImportBatch, ImportStatus, ImportType, ImportResult, ImportErrorDetail,
         PigExportRecord, ExportType, ExportFormat, CreateVetRecommendationWithAttachmentsRequest,
         ObservationListItem, SurgeryListItem, PigImportRow, WeightImportRow, PigBreed, PigGender,
+        PaginatedResponse, PigTimelineEvent, PigTimelineQuery, TimelineEventType, WeightLossRule,
     },
+    services::{DataScope, SignatureService, WeightAnalyticsService},
     AppError, Result,
 };
 use calamine::{Reader, Xlsx, Xls, open_workbook_from_rs, Data};
@@ -22,8 +24,43 @@ use std::io::Cursor;
 pub struct AnimalService;
 
 impl AnimalService {
+    /// GLP 鎖定檢查：已簽章鎖定的紀錄不可修改或刪除，僅能以附註更正
+    async fn ensure_unlocked(pool: &PgPool, record_type: &str, id: i32) -> Result<()> {
+        if SignatureService::is_locked(pool, record_type, id).await? {
+            return Err(Self::record_locked());
+        }
+        Ok(())
+    }
+
+    fn record_locked() -> AppError {
+        AppError::BusinessRule("紀錄已簽章鎖定，無法修改或刪除；如需更正請新增附註".to_string())
+    }
+
+    /// 豬隻有已鎖定的紀錄時不可刪除
+    async fn ensure_pig_records_unlocked(pool: &PgPool, pig_id: i32) -> Result<()> {
+        let has_locked: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (SELECT 1 FROM pig_observations WHERE pig_id = $1 AND is_locked AND deleted_at IS NULL)
+                OR EXISTS (SELECT 1 FROM pig_surgeries WHERE pig_id = $1 AND is_locked AND deleted_at IS NULL)
+                OR EXISTS (SELECT 1 FROM pig_weights WHERE pig_id = $1 AND is_locked AND deleted_at IS NULL)
+                OR EXISTS (SELECT 1 FROM pig_vaccinations WHERE pig_id = $1 AND is_locked AND deleted_at IS NULL)
+                OR EXISTS (SELECT 1 FROM pig_sacrifices WHERE pig_id = $1 AND is_locked AND deleted_at IS NULL)
+            "#
+        )
+        .bind(pig_id)
+        .fetch_one(pool)
+        .await?;
+
+        if has_locked {
+            return Err(AppError::BusinessRule(
+                "此豬隻有已簽章鎖定的紀錄，無法刪除".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// 格式化耳號：如果是數字且 < 100，則補零至三位數
-    fn format_ear_tag(ear_tag: &str) -> String {
+    pub(crate) fn format_ear_tag(ear_tag: &str) -> String {
         if let Ok(num) = ear_tag.parse::<u32>() {
             if num < 100 {
                 format!("{:03}", num)
@@ -114,7 +151,7 @@ impl AnimalService {
     // ============================================
 
     /// 取得豬隻列表
-    pub async fn list(pool: &PgPool, query: &PigQuery) -> Result<Vec<PigListItem>> {
+    pub async fn list(pool: &PgPool, query: &PigQuery, scope: &DataScope) -> Result<Vec<PigListItem>> {
         // Build query with proper parameterized queries
         let mut query_builder = sqlx::QueryBuilder::new(
             r#"
@@ -179,6 +216,11 @@ impl AnimalService {
             query_builder.push(" AND p.iacuc_no = ");
             query_builder.push_bind(iacuc_no);
         }
+        if let Some(iacuc_nos) = scope.iacuc_filter() {
+            query_builder.push(" AND p.iacuc_no = ANY(");
+            query_builder.push_bind(iacuc_nos.to_vec());
+            query_builder.push(")");
+        }
         if let Some(keyword) = &query.keyword {
             let keyword_pattern = format!("%{}%", keyword);
             query_builder.push(" AND (p.ear_tag ILIKE ");
@@ -223,14 +265,25 @@ impl AnimalService {
     }
 
     /// 取得使用者關聯計畫的 IACUC 編號清單
-    /// 透過 user_protocols 表查詢使用者作為 PI/CO_EDITOR/CLIENT 的計畫
+    /// 使用者為計畫主持人、user_protocols 中的 PI/CO_EDITOR/CLIENT 或已指派審查委員的計畫
     pub async fn get_user_iacuc_nos(pool: &PgPool, user_id: Uuid) -> Result<Vec<String>> {
         let iacuc_nos: Vec<String> = sqlx::query_scalar(
             r#"
             SELECT DISTINCT p.iacuc_no
             FROM protocols p
-            INNER JOIN user_protocols up ON up.protocol_id = p.id
-            WHERE up.user_id = $1 AND p.iacuc_no IS NOT NULL
+            WHERE p.iacuc_no IS NOT NULL
+              AND (
+                  p.pi_user_id = $1
+                  OR EXISTS(
+                      SELECT 1 FROM user_protocols up
+                      WHERE up.protocol_id = p.id AND up.user_id = $1
+                        AND up.role_in_protocol IN ('PI', 'CLIENT', 'CO_EDITOR')
+                  )
+                  OR EXISTS(
+                      SELECT 1 FROM review_assignments ra
+                      WHERE ra.protocol_id = p.id AND ra.reviewer_id = $1
+                  )
+              )
             "#
         )
         .bind(user_id)
@@ -241,7 +294,7 @@ impl AnimalService {
     }
 
     /// 依欄位分組取得豬隻
-    pub async fn list_by_pen(pool: &PgPool) -> Result<Vec<PigsByPen>> {
+    pub async fn list_by_pen(pool: &PgPool, scope: &DataScope) -> Result<Vec<PigsByPen>> {
         let mut pigs = sqlx::query_as::<_, PigListItem>(
             r#"
             SELECT 
@@ -252,9 +305,11 @@ impl AnimalService {
             LEFT JOIN pig_sources s ON p.source_id = s.id
             WHERE p.pen_location IS NOT NULL
             AND p.deleted_at IS NULL
+            AND ($1::text[] IS NULL OR p.iacuc_no = ANY($1))
             ORDER BY p.pen_location, p.id
             "#
         )
+        .bind(scope.iacuc_filter())
         .fetch_all(pool)
         .await?;
 
@@ -423,6 +478,8 @@ impl AnimalService {
 
     /// 軟刪除豬隻
     pub async fn delete(pool: &PgPool, id: i32) -> Result<()> {
+        Self::ensure_pig_records_unlocked(pool, id).await?;
+
         sqlx::query("UPDATE pigs SET deleted_at = NOW(), updated_at = NOW() WHERE id = $1 AND deleted_at IS NULL")
             .bind(id)
             .execute(pool)
@@ -433,6 +490,8 @@ impl AnimalService {
 
     /// 軟刪除豬隻（含刪除原因）- GLP 合規
     pub async fn delete_with_reason(pool: &PgPool, id: i32, reason: &str, deleted_by: Uuid) -> Result<()> {
+        Self::ensure_pig_records_unlocked(pool, id).await?;
+
         // 記錄到 change_reasons 表
         sqlx::query(
             r#"
@@ -618,11 +677,12 @@ impl AnimalService {
         req: &UpdateObservationRequest,
         updated_by: Uuid,
     ) -> Result<PigObservation> {
+        Self::ensure_unlocked(pool, "observation", id).await?;
         // 先取得原始紀錄用於版本歷史
         let original = Self::get_observation_by_id(pool, id).await?;
         
         // 保存版本歷史
-        Self::save_record_version(pool, "observation", id, &original, updated_by).await?;
+        Self::save_record_version(&mut *pool.acquire().await?, "observation", id, &original, updated_by).await?;
 
         let observation = sqlx::query_as::<_, PigObservation>(
             r#"
@@ -659,6 +719,7 @@ impl AnimalService {
 
     /// 刪除觀察紀錄
     pub async fn soft_delete_observation(pool: &PgPool, id: i32) -> Result<()> {
+        Self::ensure_unlocked(pool, "observation", id).await?;
         sqlx::query(
             "DELETE FROM pig_observations WHERE id = $1"
         )
@@ -671,6 +732,7 @@ impl AnimalService {
 
     /// 軟刪除觀察紀錄（含刪除原因）- GLP 合規
     pub async fn soft_delete_observation_with_reason(pool: &PgPool, id: i32, reason: &str, deleted_by: Uuid) -> Result<()> {
+        Self::ensure_unlocked(pool, "observation", id).await?;
         // 記錄到 change_reasons 表
         sqlx::query(
             r#"
@@ -863,11 +925,12 @@ impl AnimalService {
         req: &UpdateSurgeryRequest,
         updated_by: Uuid,
     ) -> Result<PigSurgery> {
+        Self::ensure_unlocked(pool, "surgery", id).await?;
         // 先取得原始紀錄用於版本歷史
         let original = Self::get_surgery_by_id(pool, id).await?;
         
         // 保存版本歷史
-        Self::save_record_version(pool, "surgery", id, &original, updated_by).await?;
+        Self::save_record_version(&mut *pool.acquire().await?, "surgery", id, &original, updated_by).await?;
 
         let surgery = sqlx::query_as::<_, PigSurgery>(
             r#"
@@ -914,6 +977,7 @@ impl AnimalService {
 
     /// 刪除手術紀錄
     pub async fn soft_delete_surgery(pool: &PgPool, id: i32) -> Result<()> {
+        Self::ensure_unlocked(pool, "surgery", id).await?;
         sqlx::query(
             "DELETE FROM pig_surgeries WHERE id = $1"
         )
@@ -926,6 +990,7 @@ impl AnimalService {
 
     /// 軟刪除手術紀錄（含刪除原因）- GLP 合規
     pub async fn soft_delete_surgery_with_reason(pool: &PgPool, id: i32, reason: &str, deleted_by: Uuid) -> Result<()> {
+        Self::ensure_unlocked(pool, "surgery", id).await?;
         sqlx::query(
             r#"
             INSERT INTO change_reasons (entity_type, entity_id, change_type, reason, changed_by)
@@ -1031,7 +1096,7 @@ impl AnimalService {
     /// 取得體重紀錄列表（排除已刪除）
     pub async fn list_weights(pool: &PgPool, pig_id: i32) -> Result<Vec<PigWeight>> {
         let weights = sqlx::query_as::<_, PigWeight>(
-            "SELECT * FROM pig_weights WHERE pig_id = $1 ORDER BY measure_date DESC"
+            "SELECT * FROM pig_weights WHERE pig_id = $1 AND deleted_at IS NULL ORDER BY measure_date DESC"
         )
         .bind(pig_id)
         .fetch_all(pool)
@@ -1043,7 +1108,7 @@ impl AnimalService {
     /// 取得最新體重
     pub async fn get_latest_weight(pool: &PgPool, pig_id: i32) -> Result<Option<PigWeight>> {
         let weight = sqlx::query_as::<_, PigWeight>(
-            "SELECT * FROM pig_weights WHERE pig_id = $1 ORDER BY measure_date DESC LIMIT 1"
+            "SELECT * FROM pig_weights WHERE pig_id = $1 AND deleted_at IS NULL ORDER BY measure_date DESC LIMIT 1"
         )
         .bind(pig_id)
         .fetch_optional(pool)
@@ -1081,6 +1146,7 @@ impl AnimalService {
         id: i32,
         req: &UpdateWeightRequest,
     ) -> Result<PigWeight> {
+        Self::ensure_unlocked(pool, "weight", id).await?;
         let weight = sqlx::query_as::<_, PigWeight>(
             r#"
             UPDATE pig_weights SET
@@ -1101,6 +1167,7 @@ impl AnimalService {
 
     /// 刪除體重紀錄
     pub async fn soft_delete_weight(pool: &PgPool, id: i32) -> Result<()> {
+        Self::ensure_unlocked(pool, "weight", id).await?;
         sqlx::query("DELETE FROM pig_weights WHERE id = $1")
             .bind(id)
             .execute(pool)
@@ -1111,6 +1178,7 @@ impl AnimalService {
 
     /// 軟刪除體重紀錄（含刪除原因）- GLP 合規
     pub async fn soft_delete_weight_with_reason(pool: &PgPool, id: i32, reason: &str, deleted_by: Uuid) -> Result<()> {
+        Self::ensure_unlocked(pool, "weight", id).await?;
         sqlx::query(
             r#"
             INSERT INTO change_reasons (entity_type, entity_id, change_type, reason, changed_by)
@@ -1187,6 +1255,7 @@ impl AnimalService {
         id: i32,
         req: &UpdateVaccinationRequest,
     ) -> Result<PigVaccination> {
+        Self::ensure_unlocked(pool, "vaccination", id).await?;
         let vaccination = sqlx::query_as::<_, PigVaccination>(
             r#"
             UPDATE pig_vaccinations SET
@@ -1209,6 +1278,7 @@ impl AnimalService {
 
     /// 刪除疫苗紀錄
     pub async fn soft_delete_vaccination(pool: &PgPool, id: i32) -> Result<()> {
+        Self::ensure_unlocked(pool, "vaccination", id).await?;
         sqlx::query("DELETE FROM pig_vaccinations WHERE id = $1")
             .bind(id)
             .execute(pool)
@@ -1219,6 +1289,7 @@ impl AnimalService {
 
     /// 軟刪除疫苗紀錄（含刪除原因）- GLP 合規
     pub async fn soft_delete_vaccination_with_reason(pool: &PgPool, id: i32, reason: &str, deleted_by: Uuid) -> Result<()> {
+        Self::ensure_unlocked(pool, "vaccination", id).await?;
         sqlx::query(
             r#"
             INSERT INTO change_reasons (entity_type, entity_id, change_type, reason, changed_by)
@@ -1270,6 +1341,7 @@ impl AnimalService {
         req: &CreateSacrificeRequest,
         created_by: Uuid,
     ) -> Result<PigSacrifice> {
+        // 鎖定檢查與更新於同一語句完成：已鎖定的紀錄不更新、不回傳資料列
         let sacrifice = sqlx::query_as::<_, PigSacrifice>(
             r#"
             INSERT INTO pig_sacrifices (
@@ -1289,6 +1361,7 @@ impl AnimalService {
                 blood_volume_ml = EXCLUDED.blood_volume_ml,
                 confirmed_sacrifice = EXCLUDED.confirmed_sacrifice,
                 updated_at = NOW()
+            WHERE NOT COALESCE(pig_sacrifices.is_locked, false)
             RETURNING *
             "#
         )
@@ -1303,8 +1376,9 @@ impl AnimalService {
         .bind(req.blood_volume_ml)
         .bind(req.confirmed_sacrifice)
         .bind(created_by)
-        .fetch_one(pool)
-        .await?;
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(Self::record_locked)?;
 
         // Note: The database trigger will automatically handle pen_location removal and status update
         // 注意：資料庫觸發器會自動處理欄位編號移除和狀態更新
@@ -1408,8 +1482,8 @@ impl AnimalService {
     // ============================================
 
     /// 保存紀錄版本歷史
-    async fn save_record_version<T: serde::Serialize>(
-        pool: &PgPool,
+    pub(crate) async fn save_record_version<T: serde::Serialize>(
+        conn: &mut PgConnection,
         record_type: &str,
         record_id: i32,
         snapshot: &T,
@@ -1417,11 +1491,11 @@ impl AnimalService {
     ) -> Result<()> {
         // 取得當前最大版本號
         let max_version: Option<i32> = sqlx::query_scalar(
-            "SELECT MAX(version_no) FROM record_versions WHERE record_type = $1 AND record_id = $2"
+            "SELECT MAX(version_no) FROM record_versions WHERE record_type = $1::version_record_type AND record_id = $2"
         )
         .bind(record_type)
         .bind(record_id)
-        .fetch_one(pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         let next_version = max_version.unwrap_or(0) + 1;
@@ -1430,7 +1504,7 @@ impl AnimalService {
         sqlx::query(
             r#"
             INSERT INTO record_versions (record_type, record_id, version_no, snapshot, changed_by, changed_at)
-            VALUES ($1, $2, $3, $4, $5, NOW())
+            VALUES ($1::version_record_type, $2, $3, $4, $5, NOW())
             "#
         )
         .bind(record_type)
@@ -1438,7 +1512,7 @@ impl AnimalService {
         .bind(next_version)
         .bind(snapshot_json)
         .bind(changed_by)
-        .execute(pool)
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
@@ -1451,7 +1525,13 @@ impl AnimalService {
         record_id: i32,
     ) -> Result<VersionHistoryResponse> {
         let versions = sqlx::query_as::<_, RecordVersion>(
-            "SELECT * FROM record_versions WHERE record_type = $1 AND record_id = $2 ORDER BY version_no DESC"
+            r#"
+            SELECT id, record_type::text AS record_type, record_id, version_no, snapshot,
+                   NULL::text AS diff_summary, changed_by, changed_at
+            FROM record_versions
+            WHERE record_type = $1::version_record_type AND record_id = $2
+            ORDER BY version_no DESC
+            "#
         )
         .bind(record_type)
         .bind(record_id)
@@ -1562,7 +1642,7 @@ impl AnimalService {
 
     /// 建立匯出記錄
     pub async fn create_export_record(
-        pool: &PgPool,
+        conn: &mut PgConnection,
         pig_id: Option<i32>,
         iacuc_no: Option<&str>,
         export_type: ExportType,
@@ -1584,12 +1664,47 @@ impl AnimalService {
         .bind(export_format)
         .bind(file_path)
         .bind(created_by)
-        .fetch_one(pool)
+        .fetch_one(conn)
         .await?;
 
         Ok(record)
     }
 
+    /// 取得匯出記錄
+    pub async fn get_export_record(pool: &PgPool, id: Uuid) -> Result<PigExportRecord> {
+        let record = sqlx::query_as::<_, PigExportRecord>(
+            "SELECT * FROM pig_export_records WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Export record not found".to_string()))?;
+
+        Ok(record)
+    }
+
+    /// 列出匯出記錄（依豬隻或計畫）
+    pub async fn list_export_records(
+        pool: &PgPool,
+        pig_id: Option<i32>,
+        iacuc_no: Option<&str>,
+    ) -> Result<Vec<PigExportRecord>> {
+        let records = sqlx::query_as::<_, PigExportRecord>(
+            r#"
+            SELECT * FROM pig_export_records
+            WHERE ($1::int IS NULL OR pig_id = $1)
+              AND ($2::varchar IS NULL OR iacuc_no = $2)
+            ORDER BY created_at DESC
+            "#
+        )
+        .bind(pig_id)
+        .bind(iacuc_no)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(records)
+    }
+
     /// 取得豬隻完整病歷資料（用於匯出）
     pub async fn get_pig_medical_data(pool: &PgPool, pig_id: i32) -> Result<serde_json::Value> {
         let pig = Self::get_by_id(pool, pig_id).await?;
@@ -1632,6 +1747,208 @@ impl AnimalService {
         }))
     }
 
+    // ============================================
+    // 臨床時間軸
+    // ============================================
+
+    /// 取得豬隻臨床時間軸（整合各類紀錄，依日期新到舊排序）
+    pub async fn get_timeline(
+        pool: &PgPool,
+        pig_id: i32,
+        query: &PigTimelineQuery,
+    ) -> Result<PaginatedResponse<PigTimelineEvent>> {
+        // 確認豬隻存在
+        Self::get_by_id(pool, pig_id).await?;
+
+        let event_types = match query.event_types.as_deref().filter(|s| !s.trim().is_empty()) {
+            Some(raw) => {
+                let mut types = Vec::new();
+                for value in raw.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+                    let event_type = TimelineEventType::parse(value)
+                        .ok_or_else(|| AppError::Validation(format!("Unknown timeline event type: {}", value)))?;
+                    types.push(event_type.as_str().to_string());
+                }
+                Some(types)
+            }
+            None => None,
+        };
+
+        let page = query.page.unwrap_or(1).max(1);
+        let per_page = query.per_page.unwrap_or(50).clamp(1, 500);
+        let offset = (page - 1) * per_page;
+
+        // 各類事件以 UNION ALL 整合；獸醫已讀：體重 / 疫苗 / 犧牲依豬隻的獸醫檢視時間判斷
+        const EVENTS_CTE: &str = r#"
+            WITH pig AS (
+                SELECT id, vet_weight_viewed_at, vet_vaccine_viewed_at, vet_sacrifice_viewed_at
+                FROM pigs WHERE id = $1
+            ),
+            events AS (
+                SELECT 'observation'::varchar AS event_type, o.id::text AS record_id,
+                    NULL::varchar AS related_record_type, NULL::int AS related_record_id,
+                    o.event_date, o.created_at AS occurred_at, LEFT(o.content, 200) AS summary,
+                    o.vet_read AS vet_read,
+                    (SELECT COUNT(*) FROM record_versions rv
+                     WHERE rv.record_type = 'observation' AND rv.record_id = o.id) AS version_count,
+                    COALESCE(o.is_locked, false) AS is_locked,
+                    EXISTS(SELECT 1 FROM electronic_signatures es
+                           WHERE es.entity_type = 'observation' AND es.entity_id = o.id::text AND es.is_valid = true) AS is_signed,
+                    o.created_by
+                FROM pig_observations o
+                WHERE o.pig_id = $1 AND o.deleted_at IS NULL
+
+                UNION ALL
+                SELECT 'surgery', s.id::text, NULL, NULL,
+                    s.surgery_date, s.created_at, s.surgery_site,
+                    s.vet_read,
+                    (SELECT COUNT(*) FROM record_versions rv
+                     WHERE rv.record_type = 'surgery' AND rv.record_id = s.id),
+                    COALESCE(s.is_locked, false),
+                    EXISTS(SELECT 1 FROM electronic_signatures es
+                           WHERE es.entity_type = 'surgery' AND es.entity_id = s.id::text AND es.is_valid = true),
+                    s.created_by
+                FROM pig_surgeries s
+                WHERE s.pig_id = $1 AND s.deleted_at IS NULL
+
+                UNION ALL
+                SELECT 'weight', w.id::text, NULL, NULL,
+                    w.measure_date, w.created_at, w.weight::text || ' kg',
+                    COALESCE(w.created_at <= pig.vet_weight_viewed_at, false),
+                    (SELECT COUNT(*) FROM record_versions rv
+                     WHERE rv.record_type = 'weight' AND rv.record_id = w.id),
+                    false, false,
+                    w.created_by
+                FROM pig_weights w CROSS JOIN pig
+                WHERE w.pig_id = $1 AND w.deleted_at IS NULL
+
+                UNION ALL
+                SELECT 'vaccination', v.id::text, NULL, NULL,
+                    v.administered_date, v.created_at,
+                    CONCAT_WS(' / ', NULLIF(v.vaccine, ''), NULLIF(v.deworming_dose, '')),
+                    COALESCE(v.created_at <= pig.vet_vaccine_viewed_at, false),
+                    (SELECT COUNT(*) FROM record_versions rv
+                     WHERE rv.record_type = 'vaccination' AND rv.record_id = v.id),
+                    false, false,
+                    v.created_by
+                FROM pig_vaccinations v CROSS JOIN pig
+                WHERE v.pig_id = $1 AND v.deleted_at IS NULL
+
+                UNION ALL
+                SELECT 'sacrifice', sc.id::text, NULL, NULL,
+                    COALESCE(sc.sacrifice_date, sc.created_at::date), sc.created_at,
+                    CASE WHEN sc.confirmed_sacrifice THEN '已確認犧牲' ELSE '犧牲 / 採樣紀錄' END,
+                    COALESCE(sc.updated_at <= pig.vet_sacrifice_viewed_at, false),
+                    (SELECT COUNT(*) FROM record_versions rv
+                     WHERE rv.record_type = 'sacrifice' AND rv.record_id = sc.id),
+                    COALESCE(sc.is_locked, false),
+                    EXISTS(SELECT 1 FROM electronic_signatures es
+                           WHERE es.entity_type = 'sacrifice' AND es.entity_id = sc.id::text AND es.is_valid = true),
+                    sc.created_by
+                FROM pig_sacrifices sc CROSS JOIN pig
+                WHERE sc.pig_id = $1 AND sc.deleted_at IS NULL
+
+                UNION ALL
+                SELECT 'welfare_score', ws.id::text, NULL, NULL,
+                    ws.assessed_at::date, ws.assessed_at,
+                    CASE WHEN ws.endpoint_reached
+                        THEN '福祉評分 ' || ws.total_score::text || '（達人道終點）'
+                        ELSE '福祉評分 ' || ws.total_score::text END,
+                    ws.vet_read,
+                    (SELECT COUNT(*) FROM record_versions rv
+                     WHERE rv.record_type = 'welfare_score' AND rv.record_id = ws.id),
+                    false, false,
+                    ws.created_by
+                FROM pig_welfare_scores ws
+                WHERE ws.pig_id = $1 AND ws.deleted_at IS NULL
+
+                UNION ALL
+                SELECT 'pathology', pr.id::text, NULL, NULL,
+                    pr.created_at::date, pr.created_at, '病理組織報告',
+                    NULL::boolean,
+                    (SELECT COUNT(*) FROM record_versions rv
+                     WHERE rv.record_type = 'pathology' AND rv.record_id = pr.id),
+                    false, false,
+                    pr.created_by
+                FROM pig_pathology_reports pr
+                WHERE pr.pig_id = $1
+
+                UNION ALL
+                SELECT 'vet_recommendation', vr.id::text, vr.record_type::text, vr.record_id,
+                    vr.created_at::date, vr.created_at, LEFT(vr.content, 200),
+                    NULL::boolean, 0::bigint, false, false,
+                    vr.created_by
+                FROM vet_recommendations vr
+                WHERE vr.deleted_at IS NULL
+                  AND (
+                      (vr.record_type = 'observation' AND vr.record_id IN (SELECT id FROM pig_observations WHERE pig_id = $1))
+                      OR (vr.record_type = 'surgery' AND vr.record_id IN (SELECT id FROM pig_surgeries WHERE pig_id = $1))
+                  )
+
+                UNION ALL
+                SELECT 'annotation', ra.id::text, ra.record_type::text, ra.record_id,
+                    ra.created_at::date, ra.created_at, ra.annotation_type || ': ' || LEFT(ra.content, 200),
+                    NULL::boolean, 0::bigint, false, ra.signature_id IS NOT NULL,
+                    ra.created_by
+                FROM record_annotations ra
+                WHERE (ra.record_type = 'observation' AND ra.record_id IN (SELECT id FROM pig_observations WHERE pig_id = $1))
+                   OR (ra.record_type = 'surgery' AND ra.record_id IN (SELECT id FROM pig_surgeries WHERE pig_id = $1))
+                   OR (ra.record_type = 'sacrifice' AND ra.record_id IN (SELECT id FROM pig_sacrifices WHERE pig_id = $1))
+
+                UNION ALL
+                SELECT 'signature', es.id::text, es.entity_type::text, es.entity_id::int,
+                    es.signed_at::date, es.signed_at, es.signature_type,
+                    NULL::boolean, 0::bigint, false, COALESCE(es.is_valid, false),
+                    es.signer_id
+                FROM electronic_signatures es
+                WHERE es.signed_at IS NOT NULL
+                  AND (
+                      (es.entity_type = 'observation' AND es.entity_id IN (SELECT id::text FROM pig_observations WHERE pig_id = $1))
+                      OR (es.entity_type = 'surgery' AND es.entity_id IN (SELECT id::text FROM pig_surgeries WHERE pig_id = $1))
+                      OR (es.entity_type = 'sacrifice' AND es.entity_id IN (SELECT id::text FROM pig_sacrifices WHERE pig_id = $1))
+                  )
+            )
+        "#;
+        const FILTER: &str = r#"
+            WHERE ($2::varchar[] IS NULL OR e.event_type = ANY($2))
+              AND ($3::date IS NULL OR e.event_date >= $3)
+              AND ($4::date IS NULL OR e.event_date <= $4)
+              AND ($5::boolean IS NULL OR e.vet_read = $5)
+        "#;
+
+        let total: i64 = sqlx::query_scalar(&format!("{} SELECT COUNT(*) FROM events e {}", EVENTS_CTE, FILTER))
+            .bind(pig_id)
+            .bind(&event_types)
+            .bind(query.from)
+            .bind(query.to)
+            .bind(query.vet_read)
+            .fetch_one(pool)
+            .await?;
+
+        let events = sqlx::query_as::<_, PigTimelineEvent>(&format!(
+            r#"
+            {}
+            SELECT e.*, u.display_name AS created_by_name
+            FROM events e
+            LEFT JOIN users u ON e.created_by = u.id
+            {}
+            ORDER BY e.event_date DESC, e.occurred_at DESC
+            LIMIT $6 OFFSET $7
+            "#,
+            EVENTS_CTE, FILTER
+        ))
+        .bind(pig_id)
+        .bind(&event_types)
+        .bind(query.from)
+        .bind(query.to)
+        .bind(query.vet_read)
+        .bind(per_page)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(PaginatedResponse::new(events, total, page, per_page))
+    }
+
     // ============================================
     // 病理報告功能
     // ============================================
@@ -2117,6 +2434,7 @@ impl AnimalService {
             success_count,
             error_count,
             errors,
+            weight_loss_alerts: Vec::new(),
         })
     }
 
@@ -2126,6 +2444,7 @@ impl AnimalService {
         file_data: &[u8],
         file_name: &str,
         created_by: Uuid,
+        weight_loss_rule: WeightLossRule,
     ) -> Result<ImportResult> {
         // 根據檔案副檔名判斷格式
         let is_excel = file_name.ends_with(".xlsx") || file_name.ends_with(".xls");
@@ -2162,6 +2481,7 @@ impl AnimalService {
         let mut success_count = 0;
         let mut error_count = 0;
         let mut errors = Vec::new();
+        let mut imported_pig_ids = Vec::new();
 
         for (index, row) in rows.iter().enumerate() {
             let row_number = (index + 2) as i32;
@@ -2244,6 +2564,9 @@ impl AnimalService {
             match Self::create_weight(pool, pig_id, &create_req, created_by).await {
                 Ok(_) => {
                     success_count += 1;
+                    if !imported_pig_ids.contains(&pig_id) {
+                        imported_pig_ids.push(pig_id);
+                    }
                 }
                 Err(e) => {
                     errors.push(ImportErrorDetail {
@@ -2272,12 +2595,28 @@ impl AnimalService {
         )
         .await?;
 
+        // 匯入後檢查體重下降（警示失敗不影響匯入結果）
+        let weight_loss_alerts = match WeightAnalyticsService::check_weight_loss(
+            pool,
+            &imported_pig_ids,
+            weight_loss_rule,
+        )
+        .await
+        {
+            Ok(alerts) => alerts,
+            Err(e) => {
+                tracing::warn!("Weight loss check after import failed: {}", e);
+                Vec::new()
+            }
+        };
+
         Ok(ImportResult {
             batch_id: batch.id,
             total_rows: rows.len() as i32,
             success_count,
             error_count,
             errors,
+            weight_loss_alerts,
         })
     }
 
@@ -2504,3 +2843,33 @@ impl AnimalService {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{services::AccessService, test_support::scope_fixture};
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_list_excludes_other_protocols_pigs(db: PgPool) {
+        let fixture = scope_fixture(&db).await;
+        let scope = AccessService::data_scope(&db, &fixture.external_user).await.unwrap();
+        let query = PigQuery {
+            status: None,
+            breed: None,
+            gender: None,
+            iacuc_no: None,
+            pen_location: None,
+            keyword: None,
+            is_on_medication: None,
+        };
+
+        let pigs = AnimalService::list(&db, &query, &scope).await.unwrap();
+        let ids: Vec<i32> = pigs.iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![fixture.own_pig]);
+
+        // 指定其他計畫的 IACUC 編號也查不到
+        let query = PigQuery { iacuc_no: Some("PIG-SCOPE-B".to_string()), ..query };
+        assert!(AnimalService::list(&db, &query, &scope).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,486 @@
+// 附件紀錄
+// 版本鏈、軟刪除與依保存期限清除檔案
+
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    models::{Attachment, NewAttachment},
+    services::FileService,
+    AppError, Result,
+};
+
+const ATTACHMENT_COLUMNS: &str = r#"
+    id, entity_type, entity_id, file_name, file_path, file_size, mime_type, content_hash,
+    root_id, version, previous_version_id, superseded_at,
+    deleted_at, deleted_by, deletion_reason, purged_at, uploaded_by, created_at
+"#;
+
+pub struct AttachmentService;
+
+impl AttachmentService {
+    /// 取得附件（含已取代、已刪除的版本）
+    pub async fn get(pool: &PgPool, id: Uuid) -> Result<Option<Attachment>> {
+        let attachment = sqlx::query_as::<_, Attachment>(&format!(
+            "SELECT {} FROM attachments WHERE id = $1",
+            ATTACHMENT_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(attachment)
+    }
+
+    /// 列出實體目前的附件（各文件的最新未刪除版本）
+    pub async fn list_current(pool: &PgPool, entity_type: &str, entity_id: &str) -> Result<Vec<Attachment>> {
+        let attachments = sqlx::query_as::<_, Attachment>(&format!(
+            r#"
+            SELECT {} FROM attachments
+            WHERE entity_type = $1 AND entity_id = $2
+              AND superseded_at IS NULL AND deleted_at IS NULL
+            ORDER BY created_at DESC
+            "#,
+            ATTACHMENT_COLUMNS
+        ))
+        .bind(entity_type)
+        .bind(entity_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(attachments)
+    }
+
+    /// 列出實體所有附件的完整版本歷史（依文件分組，版本由新到舊）
+    pub async fn list_history(pool: &PgPool, entity_type: &str, entity_id: &str) -> Result<Vec<Attachment>> {
+        let attachments = sqlx::query_as::<_, Attachment>(&format!(
+            r#"
+            SELECT {} FROM attachments a
+            WHERE entity_type = $1 AND entity_id = $2
+            ORDER BY (SELECT MIN(r.created_at) FROM attachments r WHERE r.root_id = a.root_id) DESC,
+                     root_id, version DESC
+            "#,
+            ATTACHMENT_COLUMNS
+        ))
+        .bind(entity_type)
+        .bind(entity_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(attachments)
+    }
+
+    /// 新增第一版附件（於上傳回傳的交易內寫入）
+    pub async fn create(conn: &mut PgConnection, new: &NewAttachment<'_>) -> Result<Attachment> {
+        let id = Uuid::new_v4();
+        Self::insert(conn, id, new, id, None).await
+    }
+
+    /// 上傳新版本取代目前版本
+    ///
+    /// 舊版本標記為已取代但保留檔案與紀錄；僅能取代最新且未刪除的版本。
+    /// 須於上傳回傳的交易內呼叫，由呼叫端提交。
+    pub async fn replace(
+        conn: &mut PgConnection,
+        previous_id: Uuid,
+        new: &NewAttachment<'_>,
+        reason: &str,
+    ) -> Result<Attachment> {
+        let previous = sqlx::query_as::<_, Attachment>(&format!(
+            "SELECT {} FROM attachments WHERE id = $1 FOR UPDATE",
+            ATTACHMENT_COLUMNS
+        ))
+        .bind(previous_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Attachment not found".to_string()))?;
+
+        if !previous.is_current() {
+            return Err(AppError::BusinessRule("只能取代最新且未刪除的附件版本".to_string()));
+        }
+
+        sqlx::query("UPDATE attachments SET superseded_at = NOW() WHERE id = $1")
+            .bind(previous_id)
+            .execute(&mut *conn)
+            .await?;
+
+        let attachment = Self::insert(conn, Uuid::new_v4(), new, previous.root_id, Some(&previous)).await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO change_reasons (entity_type, entity_id, change_type, reason, old_values, new_values, changed_by)
+            VALUES ('attachment', $1::text, 'UPDATE', $2, $3, $4, $5)
+            "#,
+        )
+        .bind(previous.root_id)
+        .bind(reason)
+        .bind(serde_json::json!({
+            "attachment_id": previous.id,
+            "version": previous.version,
+            "file_name": previous.file_name,
+            "content_hash": previous.content_hash,
+        }))
+        .bind(serde_json::json!({
+            "attachment_id": attachment.id,
+            "version": attachment.version,
+            "file_name": attachment.file_name,
+            "content_hash": attachment.content_hash,
+        }))
+        .bind(new.uploaded_by)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(attachment)
+    }
+
+    async fn insert(
+        conn: &mut PgConnection,
+        id: Uuid,
+        new: &NewAttachment<'_>,
+        root_id: Uuid,
+        previous: Option<&Attachment>,
+    ) -> Result<Attachment> {
+        Self::ensure_entity_unlocked(&mut *conn, new.entity_type, new.entity_id).await?;
+
+        let attachment = sqlx::query_as::<_, Attachment>(&format!(
+            r#"
+            INSERT INTO attachments (
+                id, category, entity_type, entity_id, file_name, file_path, file_size, mime_type,
+                content_hash, uploaded_by, root_id, version, previous_version_id
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            RETURNING {}
+            "#,
+            ATTACHMENT_COLUMNS
+        ))
+        .bind(id)
+        .bind(new.category)
+        .bind(new.entity_type)
+        .bind(new.entity_id)
+        .bind(new.file_name)
+        .bind(new.file_path)
+        .bind(new.file_size)
+        .bind(new.mime_type)
+        .bind(new.content_hash)
+        .bind(new.uploaded_by)
+        .bind(root_id)
+        .bind(previous.map_or(1, |p| p.version + 1))
+        .bind(previous.map(|p| p.id))
+        .fetch_one(conn)
+        .await?;
+
+        Ok(attachment)
+    }
+
+    /// 軟刪除附件（含刪除原因）- GLP 合規
+    ///
+    /// 僅能刪除最新版本；檔案與所有舊版本皆保留，待保存期限屆滿後才清除。
+    pub async fn delete_with_reason(pool: &PgPool, id: Uuid, reason: &str, deleted_by: Uuid) -> Result<()> {
+        let mut tx = pool.begin().await?;
+
+        let row: Option<(bool, Uuid, String, String)> = sqlx::query_as(
+            r#"
+            SELECT superseded_at IS NOT NULL, root_id, entity_type, entity_id
+            FROM attachments WHERE id = $1 AND deleted_at IS NULL FOR UPDATE
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let root_id = match row {
+            None => return Err(AppError::NotFound("Attachment not found".to_string())),
+            Some((true, ..)) => {
+                return Err(AppError::BusinessRule("已被新版本取代的附件無法單獨刪除".to_string()));
+            }
+            Some((false, root_id, entity_type, entity_id)) => {
+                Self::ensure_entity_unlocked(&mut tx, &entity_type, &entity_id).await?;
+                root_id
+            }
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO change_reasons (entity_type, entity_id, change_type, reason, new_values, changed_by)
+            VALUES ('attachment', $1::text, 'DELETE', $2, $3, $4)
+            "#,
+        )
+        .bind(root_id)
+        .bind(reason)
+        .bind(serde_json::json!({ "attachment_id": id }))
+        .bind(deleted_by)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE attachments SET
+                deleted_at = NOW(),
+                deletion_reason = $2,
+                deleted_by = $3
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(reason)
+        .bind(deleted_by)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// 病理報告簽核後鎖定，其附件不可新增、取代或刪除
+    ///
+    /// 以共享鎖讀取報告，與簽章時的 FOR UPDATE 互斥，避免簽章與附件異動交錯。
+    async fn ensure_entity_unlocked(conn: &mut PgConnection, entity_type: &str, entity_id: &str) -> Result<()> {
+        if entity_type != "pathology" {
+            return Ok(());
+        }
+        let locked: Option<bool> = sqlx::query_scalar(
+            "SELECT COALESCE(is_locked, false) FROM pig_pathology_reports WHERE pig_id::text = $1 FOR SHARE",
+        )
+        .bind(entity_id)
+        .fetch_optional(conn)
+        .await?;
+        if locked == Some(true) {
+            return Err(AppError::BusinessRule(
+                "病理報告已簽章鎖定，無法新增、取代或刪除附件；如需更正請先解除鎖定".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 檔案是否仍被其他紀錄參照
+    ///
+    /// 內容去重後同一檔案可能同時被附件、動物紀錄附件、匯出記錄與報表參照；`excluding` 為即將清除的附件本身。
+    pub async fn is_file_referenced(conn: &mut PgConnection, file_path: &str, excluding: Uuid) -> Result<bool> {
+        let referenced = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM attachments WHERE file_path = $1 AND id <> $2 AND purged_at IS NULL)
+                OR EXISTS(SELECT 1 FROM pig_record_attachments WHERE file_path = $1)
+                OR EXISTS(SELECT 1 FROM pig_export_records WHERE file_path = $1)
+                OR EXISTS(SELECT 1 FROM report_history WHERE file_path = $1)
+                OR EXISTS(SELECT 1 FROM protocol_attachments WHERE file_path = $1)
+            "#,
+        )
+        .bind(file_path)
+        .bind(excluding)
+        .fetch_one(conn)
+        .await?;
+        Ok(referenced)
+    }
+
+    /// 清除保存期限已屆滿的已刪除、已取代版本檔案
+    ///
+    /// 紀錄保留並標記 purged_at；內容去重後仍被其他紀錄參照的檔案不刪除。
+    /// 每個檔案於鎖定後才標記並重新確認參照；上傳相同內容時物件鎖持有至參照紀錄寫入，兩者互斥。
+    /// 標記提交後才刪除檔案，刪除失敗僅留下未被參照的檔案並繼續清除其餘檔案。
+    pub async fn purge_expired(pool: &PgPool, retention_days: i64) -> Result<u64> {
+        if retention_days <= 0 {
+            return Ok(0);
+        }
+
+        let expired: Vec<(Uuid, String)> = sqlx::query_as(
+            r#"
+            SELECT id, file_path FROM attachments
+            WHERE purged_at IS NULL
+              AND COALESCE(deleted_at, superseded_at) < NOW() - make_interval(days => $1::int)
+            "#,
+        )
+        .bind(retention_days)
+        .fetch_all(pool)
+        .await?;
+
+        let mut purged = 0;
+        for (id, file_path) in expired {
+            let mut tx = pool.begin().await?;
+            FileService::lock_object(&mut tx, &file_path).await?;
+
+            let marked = sqlx::query("UPDATE attachments SET purged_at = NOW() WHERE id = $1 AND purged_at IS NULL")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            if marked.rows_affected() == 0 {
+                continue;
+            }
+            tx.commit().await?;
+            purged += 1;
+
+            if let Err(e) = Self::delete_if_unreferenced(pool, &file_path, id).await {
+                tracing::warn!("[Attachment] Failed to delete purged file {}: {}", file_path, e);
+            }
+        }
+
+        Ok(purged)
+    }
+
+    /// 鎖定物件後重新確認無其他參照才刪除檔案；等待鎖的上傳於鎖釋放後會重新寫入
+    async fn delete_if_unreferenced(pool: &PgPool, file_path: &str, purged_id: Uuid) -> Result<()> {
+        let mut tx = pool.begin().await?;
+        FileService::lock_object(&mut tx, file_path).await?;
+        if !Self::is_file_referenced(&mut tx, file_path, purged_id).await? {
+            FileService::delete(file_path).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{storage::storage, FileCategory, UploadResult};
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_file_referenced_by_other_tables(db: PgPool) {
+        let user_id = crate::test_support::insert_user(&db, "owner@example.com", true).await;
+
+        let attachment_id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO attachments (id, root_id, entity_type, entity_id, category, file_name, file_path, file_size, mime_type, uploaded_by)
+            VALUES ($1, $1, 'pig', '1', 'pig_photo', 'a.pdf', 'exports/ab/shared.pdf', 1, 'application/pdf', $2)
+            RETURNING id
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+
+        // 只有附件本身參照時可清除
+        let mut conn = db.acquire().await.unwrap();
+        assert!(!AttachmentService::is_file_referenced(&mut conn, "exports/ab/shared.pdf", attachment_id).await.unwrap());
+
+        sqlx::query(
+            r#"
+            INSERT INTO pig_export_records (id, export_type, export_format, file_path, created_by)
+            VALUES ($1, 'medical_summary', 'pdf', 'exports/ab/shared.pdf', $2)
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .execute(&db)
+        .await
+        .unwrap();
+
+        assert!(AttachmentService::is_file_referenced(&mut conn, "exports/ab/shared.pdf", attachment_id).await.unwrap());
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_upload_waits_for_purge_of_the_same_file(db: PgPool) {
+        crate::test_support::init_test_storage();
+        let content = format!("%PDF-{}", Uuid::new_v4());
+        let upload = |db: PgPool, content: String| async move {
+            let stored =
+                FileService::upload(&db, FileCategory::ProtocolAttachment, "a.pdf", "application/pdf", content.as_bytes())
+                    .await?;
+            stored.tx.commit().await?;
+            Ok::<_, AppError>(stored.result)
+        };
+        let first = upload(db.clone(), content.clone()).await.unwrap();
+
+        // 清除作業持有物件鎖並刪除檔案期間，上傳相同內容須等待，之後重新寫入而非沿用已刪除的檔案
+        let mut purge_tx = db.begin().await.unwrap();
+        FileService::lock_object(&mut purge_tx, &first.file_path).await.unwrap();
+        let pending = tokio::spawn(upload(db.clone(), content));
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        FileService::delete(&first.file_path).await.unwrap();
+        purge_tx.commit().await.unwrap();
+
+        let again = pending.await.unwrap().unwrap();
+        assert_eq!(again.file_path, first.file_path);
+        assert!(storage().exists(&first.file_path).await.unwrap());
+    }
+
+    fn new_attachment(result: &crate::services::UploadResult, uploaded_by: Uuid) -> NewAttachment<'_> {
+        NewAttachment {
+            category: "protocols",
+            entity_type: "protocol",
+            entity_id: "p1",
+            file_name: &result.file_name,
+            file_path: &result.file_path,
+            file_size: result.file_size,
+            mime_type: &result.mime_type,
+            content_hash: &result.content_hash,
+            uploaded_by,
+        }
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_purge_keeps_file_reused_by_pending_upload(db: PgPool) {
+        crate::test_support::init_test_storage();
+        let user_id = crate::test_support::insert_user(&db, "owner@example.com", true).await;
+        let content = format!("%PDF-{}", Uuid::new_v4());
+        let upload = |db: PgPool| {
+            let content = content.clone();
+            async move {
+                FileService::upload(&db, FileCategory::ProtocolAttachment, "a.pdf", "application/pdf", content.as_bytes())
+                    .await
+                    .unwrap()
+            }
+        };
+        // 已刪除且超過保存期限的舊附件
+        let mut first = upload(db.clone()).await;
+        let expired = AttachmentService::create(&mut first.tx, &new_attachment(&first.result, user_id)).await.unwrap();
+        sqlx::query("UPDATE attachments SET deleted_at = NOW() - INTERVAL '30 days' WHERE id = $1")
+            .bind(expired.id)
+            .execute(&mut *first.tx)
+            .await
+            .unwrap();
+        first.tx.commit().await.unwrap();
+
+        // 上傳相同內容後、寫入附件紀錄前執行清除：須等待紀錄寫入，之後不可刪除檔案
+        let mut second = upload(db.clone()).await;
+        let purge = tokio::spawn({
+            let db = db.clone();
+            async move { AttachmentService::purge_expired(&db, 7).await }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        AttachmentService::create(&mut second.tx, &new_attachment(&second.result, user_id)).await.unwrap();
+        second.tx.commit().await.unwrap();
+
+        assert_eq!(purge.await.unwrap().unwrap(), 1);
+        assert!(storage().exists(&second.result.file_path).await.unwrap());
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_purge_continues_past_a_failed_delete(db: PgPool) {
+        crate::test_support::init_test_storage();
+        let user_id = crate::test_support::insert_user(&db, "owner@example.com", true).await;
+        let content = format!("%PDF-{}", Uuid::new_v4());
+        let mut stored =
+            FileService::upload(&db, FileCategory::ProtocolAttachment, "a.pdf", "application/pdf", content.as_bytes())
+                .await
+                .unwrap();
+        let expired = AttachmentService::create(&mut stored.tx, &new_attachment(&stored.result, user_id)).await.unwrap();
+        stored.tx.commit().await.unwrap();
+
+        // 無法刪除的檔案（路徑位於儲存目錄外）排在前面
+        let broken = UploadResult { file_path: "..".to_string(), ..stored.result.clone() };
+        let mut conn = db.acquire().await.unwrap();
+        let broken = AttachmentService::create(&mut conn, &new_attachment(&broken, user_id)).await.unwrap();
+        sqlx::query(
+            "UPDATE attachments SET deleted_at = NOW() - INTERVAL '30 days' + (CASE WHEN id = $1 THEN INTERVAL '0' ELSE INTERVAL '1 day' END) \
+             WHERE id IN ($1, $2)",
+        )
+        .bind(broken.id)
+        .bind(expired.id)
+        .execute(&db)
+        .await
+        .unwrap();
+
+        assert_eq!(AttachmentService::purge_expired(&db, 7).await.unwrap(), 2);
+        let unpurged: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM attachments WHERE purged_at IS NULL")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(unpurged, 0);
+        assert!(!storage().exists(&stored.result.file_path).await.unwrap());
+    }
+}
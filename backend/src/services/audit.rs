@@ -12,8 +12,21 @@ use crate::{
     Result,
 };
 
+use super::session_manager::SessionManager;
+
 pub struct AuditService;
 
+/// user_activity_logs 查詢欄位（INET 轉為文字）
+const ACTIVITY_LOG_COLUMNS: &str = r#"
+    id, actor_user_id, actor_email, actor_display_name, actor_roles, session_id,
+    event_category, event_type, COALESCE(event_severity, 'info') AS event_severity,
+    entity_type, entity_id, entity_key, entity_display_name,
+    before_data, after_data, changed_fields,
+    host(ip_address) AS ip_address, user_agent, request_path, request_method,
+    response_status, duration_ms, COALESCE(is_suspicious, false) AS is_suspicious,
+    suspicious_reason, created_at, partition_date
+"#;
+
 impl AuditService {
     /// 記錄稽核日誌（原有）
     pub async fn log(
@@ -172,6 +185,8 @@ impl AuditService {
               AND ($6::bool IS NULL OR is_suspicious = $6)
               AND ($7::date IS NULL OR partition_date >= $7)
               AND ($8::date IS NULL OR partition_date <= $8)
+              AND ($9::text IS NULL OR entity_key = $9)
+              AND ($10::uuid IS NULL OR session_id = $10)
             "#,
         )
         .bind(query.user_id)
@@ -182,12 +197,14 @@ impl AuditService {
         .bind(query.is_suspicious)
         .bind(query.from)
         .bind(query.to)
+        .bind(&query.entity_key)
+        .bind(query.session_id)
         .fetch_one(pool)
         .await?;
 
-        let data = sqlx::query_as::<_, UserActivityLog>(
+        let data = sqlx::query_as::<_, UserActivityLog>(&format!(
             r#"
-            SELECT * FROM user_activity_logs
+            SELECT {ACTIVITY_LOG_COLUMNS} FROM user_activity_logs
             WHERE ($1::uuid IS NULL OR actor_user_id = $1)
               AND ($2::text IS NULL OR event_category = $2)
               AND ($3::text IS NULL OR event_type = $3)
@@ -196,10 +213,12 @@ impl AuditService {
               AND ($6::bool IS NULL OR is_suspicious = $6)
               AND ($7::date IS NULL OR partition_date >= $7)
               AND ($8::date IS NULL OR partition_date <= $8)
+              AND ($9::text IS NULL OR entity_key = $9)
+              AND ($10::uuid IS NULL OR session_id = $10)
             ORDER BY created_at DESC
-            LIMIT $9 OFFSET $10
-            "#,
-        )
+            LIMIT $11 OFFSET $12
+            "#
+        ))
         .bind(query.user_id)
         .bind(&query.event_category)
         .bind(&query.event_type)
@@ -208,6 +227,8 @@ impl AuditService {
         .bind(query.is_suspicious)
         .bind(query.from)
         .bind(query.to)
+        .bind(&query.entity_key)
+        .bind(query.session_id)
         .bind(per_page)
         .bind(offset)
         .fetch_all(pool)
@@ -358,6 +379,19 @@ impl AuditService {
         .execute(pool)
         .await?;
 
+        // 撤銷該 Session 的 refresh token，並立即讓驗證快取失效
+        sqlx::query(
+            r#"
+            UPDATE refresh_tokens SET revoked_at = NOW()
+            WHERE revoked_at IS NULL
+              AND id = (SELECT refresh_token_id FROM user_sessions WHERE id = $1)
+            "#,
+        )
+        .bind(session_id)
+        .execute(pool)
+        .await?;
+        SessionManager::invalidate(session_id);
+
         // 記錄審計日誌
         Self::log(
             pool,
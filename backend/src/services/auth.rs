@@ -14,6 +14,8 @@ use crate::{
     AppError, Result,
 };
 
+use super::session_manager::{max_sessions_for_roles, SessionManager};
+
 pub struct AuthService;
 
 impl AuthService {
@@ -22,6 +24,8 @@ impl AuthService {
         pool: &PgPool,
         config: &Config,
         req: &LoginRequest,
+        ip: Option<&str>,
+        user_agent: Option<&str>,
     ) -> Result<LoginResponse> {
         // 查詢用戶
         let user = sqlx::query_as::<_, User>(
@@ -49,11 +53,15 @@ impl AuthService {
         // 獲取角色和權限
         let (roles, permissions) = Self::get_user_roles_permissions(pool, user.id).await?;
 
+        // 建立 Session
+        let session_id = Self::start_session(pool, config, user.id, &roles, ip, user_agent).await?;
+
         // 生成 JWT
-        let (access_token, expires_in) = Self::generate_access_token(config, &user, &roles, &permissions)?;
+        let (access_token, expires_in) =
+            Self::generate_access_token(config, &user, &roles, &permissions, session_id)?;
 
         // 生成 Refresh Token
-        let refresh_token = Self::generate_refresh_token(pool, user.id, config).await?;
+        let refresh_token = Self::generate_refresh_token(pool, user.id, session_id, config).await?;
 
         Ok(LoginResponse {
             access_token,
@@ -84,6 +92,8 @@ impl AuthService {
         pool: &PgPool,
         config: &Config,
         refresh_token: &str,
+        ip: Option<&str>,
+        user_agent: Option<&str>,
     ) -> Result<LoginResponse> {
         // 計算 token hash
         let token_hash = Self::hash_token(refresh_token);
@@ -120,9 +130,24 @@ impl AuthService {
         // 獲取角色和權限
         let (roles, permissions) = Self::get_user_roles_permissions(pool, user.id).await?;
 
+        // 沿用原 Session；已被登出、撤銷或閒置逾時的 Session 不可再換發
+        let session_id = match SessionManager::find_by_refresh_token(
+            pool,
+            token_record.id,
+            config.session_idle_timeout_minutes,
+        )
+        .await?
+        {
+            Some((session_id, true)) => session_id,
+            Some(_) => return Err(AppError::Unauthorized),
+            // 尚未綁定 Session 的舊 refresh token
+            None => Self::start_session(pool, config, user.id, &roles, ip, user_agent).await?,
+        };
+
         // 生成新的 tokens
-        let (access_token, expires_in) = Self::generate_access_token(config, &user, &roles, &permissions)?;
-        let new_refresh_token = Self::generate_refresh_token(pool, user.id, config).await?;
+        let (access_token, expires_in) =
+            Self::generate_access_token(config, &user, &roles, &permissions, session_id)?;
+        let new_refresh_token = Self::generate_refresh_token(pool, user.id, session_id, config).await?;
 
         Ok(LoginResponse {
             access_token,
@@ -148,15 +173,42 @@ impl AuthService {
         })
     }
 
-    /// 登出（撤銷 refresh token）
-    pub async fn logout(pool: &PgPool, user_id: Uuid) -> Result<()> {
-        sqlx::query("UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL")
-            .bind(user_id)
-            .execute(pool)
-            .await?;
+    /// 登出（結束目前 Session 並撤銷其 refresh token）
+    pub async fn logout(pool: &PgPool, user_id: Uuid, session_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE refresh_tokens SET revoked_at = NOW()
+            WHERE user_id = $1 AND revoked_at IS NULL
+              AND id = (SELECT refresh_token_id FROM user_sessions WHERE id = $2)
+            "#
+        )
+        .bind(user_id)
+        .bind(session_id)
+        .execute(pool)
+        .await?;
+
+        SessionManager::end_session(pool, session_id).await?;
         Ok(())
     }
 
+    /// 建立新 Session（超過角色同時登入上限時先結束最舊的 Sessions）
+    async fn start_session(
+        pool: &PgPool,
+        config: &Config,
+        user_id: Uuid,
+        roles: &[String],
+        ip: Option<&str>,
+        user_agent: Option<&str>,
+    ) -> Result<Uuid> {
+        let max_sessions = max_sessions_for_roles(
+            &config.max_sessions_per_role,
+            config.max_sessions_default,
+            roles,
+        );
+        SessionManager::enforce_session_limit(pool, user_id, max_sessions).await?;
+        SessionManager::create_session(pool, user_id, ip, user_agent).await
+    }
+
     /// 獲取用戶的角色和權限
     pub async fn get_user_roles_permissions(
         pool: &PgPool,
@@ -195,6 +247,7 @@ impl AuthService {
         user: &User,
         roles: &[String],
         permissions: &[String],
+        session_id: Uuid,
     ) -> Result<(String, i64)> {
         let now = Utc::now();
         let expires_in = config.jwt_expiration_hours * 3600;
@@ -207,6 +260,7 @@ impl AuthService {
             permissions: permissions.to_vec(),
             exp: exp.timestamp(),
             iat: now.timestamp(),
+            sid: Some(session_id),
         };
 
         let token = encode(
@@ -223,8 +277,10 @@ impl AuthService {
     async fn generate_refresh_token(
         pool: &PgPool,
         user_id: Uuid,
+        session_id: Uuid,
         config: &Config,
     ) -> Result<String> {
+        let token_id = Uuid::new_v4();
         let token = Uuid::new_v4().to_string();
         let token_hash = Self::hash_token(&token);
         let expires_at = Utc::now() + Duration::days(config.jwt_refresh_expiration_days);
@@ -235,13 +291,15 @@ impl AuthService {
             VALUES ($1, $2, $3, $4, NOW())
             "#
         )
-        .bind(Uuid::new_v4())
+        .bind(token_id)
         .bind(user_id)
         .bind(&token_hash)
         .bind(expires_at)
         .execute(pool)
         .await?;
 
+        SessionManager::link_refresh_token(pool, session_id, token_id).await?;
+
         Ok(token)
     }
 
@@ -279,6 +337,7 @@ impl AuthService {
     pub async fn change_own_password(
         pool: &PgPool,
         user_id: Uuid,
+        session_id: Uuid,
         current_password: &str,
         new_password: &str,
     ) -> Result<()> {
@@ -308,14 +367,32 @@ impl AuthService {
         .execute(pool)
         .await?;
 
-        // 撤銷所有 refresh tokens（安全措施）
+        // 撤銷其他裝置的 refresh tokens 並結束其 Sessions（安全措施），保留目前 Session
         sqlx::query(
-            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL"
+            r#"
+            UPDATE refresh_tokens SET revoked_at = NOW()
+            WHERE user_id = $1 AND revoked_at IS NULL
+              AND id IS DISTINCT FROM (SELECT refresh_token_id FROM user_sessions WHERE id = $2)
+            "#
         )
         .bind(user_id)
+        .bind(session_id)
         .execute(pool)
         .await?;
 
+        sqlx::query(
+            r#"
+            UPDATE user_sessions
+            SET is_active = false, ended_at = NOW(), ended_reason = 'password_changed'
+            WHERE user_id = $1 AND id <> $2 AND is_active = true
+            "#
+        )
+        .bind(user_id)
+        .bind(session_id)
+        .execute(pool)
+        .await?;
+        SessionManager::invalidate_user(user_id);
+
         Ok(())
     }
 
@@ -340,6 +417,27 @@ impl AuthService {
         Ok(user)
     }
 
+    /// 透過 Email 驗證密碼（用於共同簽署等需他人簽章的操作）
+    pub async fn verify_password_by_email(
+        pool: &PgPool,
+        email: &str,
+        password: &str,
+    ) -> Result<User> {
+        let user = sqlx::query_as::<_, User>(
+            "SELECT * FROM users WHERE email = $1 AND is_active = true"
+        )
+        .bind(email)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(AppError::Unauthorized)?;
+
+        if !Self::verify_password(password, &user.password_hash)? {
+            return Err(AppError::Unauthorized);
+        }
+
+        Ok(user)
+    }
+
     /// Admin 重設他人密碼（不需驗證舊密碼）
     pub async fn reset_user_password(
         pool: &PgPool,
@@ -377,6 +475,7 @@ impl AuthService {
         .bind(target_user_id)
         .execute(pool)
         .await?;
+        SessionManager::end_all_sessions(pool, target_user_id, "password_reset").await?;
 
         Ok(())
     }
@@ -478,6 +577,7 @@ impl AuthService {
         .bind(token_record.user_id)
         .execute(pool)
         .await?;
+        SessionManager::end_all_sessions(pool, token_record.user_id, "password_reset").await?;
 
         Ok(())
     }
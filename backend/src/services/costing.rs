@@ -0,0 +1,481 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use sqlx::{FromRow, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    models::{CostLayerQuery, CostingMethod, StockCostLayer},
+    AppError, Result,
+};
+
+/// 成本層服務 - 維護倉庫 / 產品 / 批號的成本層，並於出庫時計算耗用成本
+pub struct CostingService;
+
+/// 尚有餘量的成本層
+#[derive(Debug, Clone, FromRow)]
+pub struct OpenLayer {
+    pub id: Uuid,
+    pub batch_no: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    pub layer_date: DateTime<Utc>,
+    pub remaining_qty: Decimal,
+    pub unit_cost: Decimal,
+}
+
+/// 單一成本層的耗用切片
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostSlice {
+    pub layer_id: Uuid,
+    pub batch_no: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    pub qty: Decimal,
+    pub unit_cost: Decimal,
+}
+
+/// 新成本層（入庫流水產生）
+#[derive(Debug, Clone)]
+pub struct NewCostLayer<'a> {
+    pub warehouse_id: Uuid,
+    pub product_id: Uuid,
+    pub batch_no: Option<&'a str>,
+    pub expiry_date: Option<NaiveDate>,
+    pub source_ledger_id: Uuid,
+    pub qty: Decimal,
+    pub unit_cost: Decimal,
+}
+
+/// 出庫耗用結果
+#[derive(Debug, Clone)]
+pub struct ConsumedCost {
+    pub slices: Vec<CostSlice>,
+    pub total_cost: Decimal,
+}
+
+/// 依先進先出順序從成本層分配出庫數量
+///
+/// `layers` 必須已依耗用順序排序；餘量不足時回傳錯誤。
+pub fn allocate_fifo(layers: &[OpenLayer], qty: Decimal) -> Result<Vec<CostSlice>> {
+    let mut remaining = qty;
+    let mut slices = Vec::new();
+
+    for layer in layers {
+        if remaining <= Decimal::ZERO {
+            break;
+        }
+        if layer.remaining_qty <= Decimal::ZERO {
+            continue;
+        }
+        let take = remaining.min(layer.remaining_qty);
+        slices.push(CostSlice {
+            layer_id: layer.id,
+            batch_no: layer.batch_no.clone(),
+            expiry_date: layer.expiry_date,
+            qty: take,
+            unit_cost: layer.unit_cost,
+        });
+        remaining -= take;
+    }
+
+    if remaining > Decimal::ZERO {
+        return Err(AppError::BusinessRule(format!(
+            "Insufficient cost layers: short by {}",
+            remaining
+        )));
+    }
+
+    Ok(slices)
+}
+
+/// 指定批號出庫：僅耗用該批號的成本層，數量不足時拒絕，不以其他批號補足
+pub fn allocate_batch(layers: &[OpenLayer], batch_no: &str, qty: Decimal) -> Result<Vec<CostSlice>> {
+    let batch_layers: Vec<OpenLayer> = layers
+        .iter()
+        .filter(|layer| layer.batch_no.as_deref() == Some(batch_no))
+        .cloned()
+        .collect();
+
+    let available: Decimal = batch_layers.iter().map(|layer| layer.remaining_qty.max(Decimal::ZERO)).sum();
+    if available < qty {
+        return Err(AppError::Validation(format!(
+            "Insufficient stock in batch '{}': requested {}, available {}",
+            batch_no, qty, available
+        )));
+    }
+
+    allocate_fifo(&batch_layers, qty)
+}
+
+/// 計算加權平均單位成本（依餘量加權）
+pub fn weighted_average_cost(layers: &[(Decimal, Decimal)]) -> Option<Decimal> {
+    let total_qty: Decimal = layers.iter().map(|(qty, _)| *qty).sum();
+    if total_qty <= Decimal::ZERO {
+        return None;
+    }
+    let total_value: Decimal = layers.iter().map(|(qty, cost)| *qty * *cost).sum();
+    Some((total_value / total_qty).round_dp(4))
+}
+
+impl CostingService {
+    /// 入庫：建立新的成本層
+    ///
+    /// 移動加權平均法下，同一倉庫 / 產品 / 批號的所有未耗盡成本層會重新計價為新的平均單價。
+    pub async fn receive(
+        tx: &mut Transaction<'_, Postgres>,
+        method: CostingMethod,
+        layer: NewCostLayer<'_>,
+    ) -> Result<Uuid> {
+        let layer_id = Uuid::new_v4();
+        sqlx::query(
+            r#"
+            INSERT INTO stock_cost_layers (
+                id, warehouse_id, product_id, batch_no, expiry_date, source_ledger_id,
+                layer_date, original_qty, remaining_qty, unit_cost, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, NOW(), $7, $7, $8, NOW())
+            "#
+        )
+        .bind(layer_id)
+        .bind(layer.warehouse_id)
+        .bind(layer.product_id)
+        .bind(layer.batch_no)
+        .bind(layer.expiry_date)
+        .bind(layer.source_ledger_id)
+        .bind(layer.qty)
+        .bind(layer.unit_cost)
+        .execute(&mut **tx)
+        .await?;
+
+        if method == CostingMethod::WeightedAverage {
+            Self::reprice_bucket(tx, layer.warehouse_id, layer.product_id, layer.batch_no).await?;
+        }
+
+        Ok(layer_id)
+    }
+
+    /// 出庫：依先進先出順序耗用成本層（指定批號時僅耗用該批號）
+    ///
+    /// 回傳耗用切片，呼叫端寫入流水後需以 `record_consumptions` 留下對應紀錄。
+    pub async fn consume(
+        tx: &mut Transaction<'_, Postgres>,
+        warehouse_id: Uuid,
+        product_id: Uuid,
+        batch_no: Option<&str>,
+        qty: Decimal,
+    ) -> Result<ConsumedCost> {
+        let batch_no = batch_no.filter(|batch| !batch.is_empty());
+        let layers = sqlx::query_as::<_, OpenLayer>(
+            r#"
+            SELECT id, batch_no, expiry_date, layer_date, remaining_qty, unit_cost
+            FROM stock_cost_layers
+            WHERE warehouse_id = $1 AND product_id = $2 AND remaining_qty > 0
+              AND ($3::varchar IS NULL OR batch_no = $3)
+            ORDER BY layer_date, created_at
+            FOR UPDATE
+            "#
+        )
+        .bind(warehouse_id)
+        .bind(product_id)
+        .bind(batch_no)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        let slices = match batch_no {
+            Some(batch_no) => allocate_batch(&layers, batch_no, qty)?,
+            None => allocate_fifo(&layers, qty)?,
+        };
+
+        for slice in &slices {
+            sqlx::query(
+                "UPDATE stock_cost_layers SET remaining_qty = remaining_qty - $1 WHERE id = $2"
+            )
+            .bind(slice.qty)
+            .bind(slice.layer_id)
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        let total_cost = slices.iter().map(|s| s.qty * s.unit_cost).sum();
+
+        Ok(ConsumedCost { slices, total_cost })
+    }
+
+    /// 記錄出庫流水耗用的成本層
+    pub async fn record_consumptions(
+        tx: &mut Transaction<'_, Postgres>,
+        ledger_id: Uuid,
+        consumed: &ConsumedCost,
+    ) -> Result<()> {
+        for slice in &consumed.slices {
+            sqlx::query(
+                r#"
+                INSERT INTO stock_cost_consumptions (id, layer_id, ledger_id, qty, unit_cost, created_at)
+                VALUES ($1, $2, $3, $4, $5, NOW())
+                "#
+            )
+            .bind(Uuid::new_v4())
+            .bind(slice.layer_id)
+            .bind(ledger_id)
+            .bind(slice.qty)
+            .bind(slice.unit_cost)
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// 沖銷入庫：釋放由指定入庫流水建立的成本層
+    ///
+    /// 成本層已被部分或全部耗用時拒絕，避免沖銷已出庫的庫存。
+    pub async fn release_ledger_layers(
+        tx: &mut Transaction<'_, Postgres>,
+        ledger_id: Uuid,
+    ) -> Result<ConsumedCost> {
+        let layers = sqlx::query_as::<_, StockCostLayer>(
+            "SELECT * FROM stock_cost_layers WHERE source_ledger_id = $1 ORDER BY created_at FOR UPDATE"
+        )
+        .bind(ledger_id)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        if layers.is_empty() {
+            return Err(AppError::BusinessRule(
+                "No cost layers recorded for this receipt; use a stock adjustment instead".to_string()
+            ));
+        }
+
+        if let Some(layer) = layers.iter().find(|l| l.remaining_qty < l.original_qty) {
+            let product_name: String = sqlx::query_scalar("SELECT name FROM products WHERE id = $1")
+                .bind(layer.product_id)
+                .fetch_one(&mut **tx)
+                .await?;
+            return Err(AppError::BusinessRule(format!(
+                "Stock for product '{}' has already been consumed. Received: {}, Remaining: {}",
+                product_name, layer.original_qty, layer.remaining_qty
+            )));
+        }
+
+        let mut slices = Vec::with_capacity(layers.len());
+        for layer in &layers {
+            sqlx::query("UPDATE stock_cost_layers SET remaining_qty = 0 WHERE id = $1")
+                .bind(layer.id)
+                .execute(&mut **tx)
+                .await?;
+            slices.push(CostSlice {
+                layer_id: layer.id,
+                batch_no: layer.batch_no.clone(),
+                expiry_date: layer.expiry_date,
+                qty: layer.original_qty,
+                unit_cost: layer.unit_cost,
+            });
+        }
+
+        let total_cost = slices.iter().map(|s| s.qty * s.unit_cost).sum();
+
+        Ok(ConsumedCost { slices, total_cost })
+    }
+
+    /// 沖銷出庫：將指定出庫流水耗用的數量歸還原成本層
+    ///
+    /// 回傳歸還的成本金額；流水無耗用紀錄（成本層建立前的舊資料）時回傳 `None`。
+    pub async fn restore_consumptions(
+        tx: &mut Transaction<'_, Postgres>,
+        method: CostingMethod,
+        ledger_id: Uuid,
+    ) -> Result<Option<Decimal>> {
+        let consumptions: Vec<(Uuid, Decimal, Decimal, Uuid, Uuid, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT c.layer_id, c.qty, c.unit_cost, l.warehouse_id, l.product_id, l.batch_no
+            FROM stock_cost_consumptions c
+            INNER JOIN stock_cost_layers l ON c.layer_id = l.id
+            WHERE c.ledger_id = $1
+            "#
+        )
+        .bind(ledger_id)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        if consumptions.is_empty() {
+            return Ok(None);
+        }
+
+        let mut total_cost = Decimal::ZERO;
+        for (layer_id, qty, unit_cost, _, _, _) in &consumptions {
+            sqlx::query(
+                "UPDATE stock_cost_layers SET remaining_qty = remaining_qty + $1 WHERE id = $2"
+            )
+            .bind(qty)
+            .bind(layer_id)
+            .execute(&mut **tx)
+            .await?;
+            total_cost += *qty * *unit_cost;
+        }
+
+        if method == CostingMethod::WeightedAverage {
+            for (_, _, _, warehouse_id, product_id, batch_no) in &consumptions {
+                Self::reprice_bucket(tx, *warehouse_id, *product_id, batch_no.as_deref()).await?;
+            }
+        }
+
+        Ok(Some(total_cost))
+    }
+
+    /// 未指定單價時的入庫成本：現有成本層平均 → 最近一次成本 → 0
+    pub async fn fallback_unit_cost(
+        tx: &mut Transaction<'_, Postgres>,
+        warehouse_id: Uuid,
+        product_id: Uuid,
+    ) -> Result<Decimal> {
+        let open: Vec<(Decimal, Decimal)> = sqlx::query_as(
+            r#"
+            SELECT remaining_qty, unit_cost FROM stock_cost_layers
+            WHERE warehouse_id = $1 AND product_id = $2 AND remaining_qty > 0
+            "#
+        )
+        .bind(warehouse_id)
+        .bind(product_id)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        if let Some(avg) = weighted_average_cost(&open) {
+            return Ok(avg);
+        }
+
+        let last_cost: Option<Decimal> = sqlx::query_scalar(
+            r#"
+            SELECT unit_cost FROM stock_cost_layers
+            WHERE product_id = $1
+            ORDER BY (warehouse_id = $2) DESC, layer_date DESC
+            LIMIT 1
+            "#
+        )
+        .bind(product_id)
+        .bind(warehouse_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(last_cost.unwrap_or(Decimal::ZERO))
+    }
+
+    /// 將同一倉庫 / 產品 / 批號的未耗盡成本層重新計價為加權平均單價
+    async fn reprice_bucket(
+        tx: &mut Transaction<'_, Postgres>,
+        warehouse_id: Uuid,
+        product_id: Uuid,
+        batch_no: Option<&str>,
+    ) -> Result<()> {
+        let open: Vec<(Decimal, Decimal)> = sqlx::query_as(
+            r#"
+            SELECT remaining_qty, unit_cost FROM stock_cost_layers
+            WHERE warehouse_id = $1 AND product_id = $2
+              AND batch_no IS NOT DISTINCT FROM $3
+              AND remaining_qty > 0
+            FOR UPDATE
+            "#
+        )
+        .bind(warehouse_id)
+        .bind(product_id)
+        .bind(batch_no)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        if let Some(avg) = weighted_average_cost(&open) {
+            sqlx::query(
+                r#"
+                UPDATE stock_cost_layers SET unit_cost = $4
+                WHERE warehouse_id = $1 AND product_id = $2
+                  AND batch_no IS NOT DISTINCT FROM $3
+                  AND remaining_qty > 0
+                "#
+            )
+            .bind(warehouse_id)
+            .bind(product_id)
+            .bind(batch_no)
+            .bind(avg)
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// 查詢成本層
+    pub async fn list_layers(pool: &PgPool, query: &CostLayerQuery) -> Result<Vec<StockCostLayer>> {
+        let layers = sqlx::query_as::<_, StockCostLayer>(
+            r#"
+            SELECT * FROM stock_cost_layers
+            WHERE ($1::uuid IS NULL OR warehouse_id = $1)
+              AND ($2::uuid IS NULL OR product_id = $2)
+              AND ($3::varchar IS NULL OR batch_no = $3)
+              AND ($4 OR remaining_qty > 0)
+            ORDER BY warehouse_id, product_id, layer_date, created_at
+            LIMIT 1000
+            "#
+        )
+        .bind(query.warehouse_id)
+        .bind(query.product_id)
+        .bind(&query.batch_no)
+        .bind(query.include_depleted.unwrap_or(false))
+        .fetch_all(pool)
+        .await?;
+
+        Ok(layers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(v: i64) -> Decimal {
+        Decimal::from(v)
+    }
+
+    fn layer(remaining: Decimal, cost: Decimal) -> OpenLayer {
+        OpenLayer {
+            id: Uuid::new_v4(),
+            batch_no: None,
+            expiry_date: None,
+            layer_date: Utc::now(),
+            remaining_qty: remaining,
+            unit_cost: cost,
+        }
+    }
+
+    #[test]
+    fn test_allocate_fifo_spans_layers() {
+        let layers = vec![layer(dec(10), dec(5)), layer(dec(20), dec(8))];
+        let slices = allocate_fifo(&layers, dec(15)).unwrap();
+        assert_eq!(slices.len(), 2);
+        assert_eq!(slices[0].qty, dec(10));
+        assert_eq!(slices[1].qty, dec(5));
+        let total: Decimal = slices.iter().map(|s| s.qty * s.unit_cost).sum();
+        assert_eq!(total, dec(90));
+    }
+
+    #[test]
+    fn test_allocate_fifo_insufficient() {
+        let layers = vec![layer(dec(3), dec(5))];
+        assert!(allocate_fifo(&layers, dec(4)).is_err());
+    }
+
+    #[test]
+    fn test_allocate_batch_does_not_draw_from_other_batches() {
+        let batch = |no: &str, remaining: Decimal| OpenLayer { batch_no: Some(no.to_string()), ..layer(remaining, dec(5)) };
+        let layers = vec![batch("B1", dec(3)), batch("B2", dec(10)), layer(dec(10), dec(5))];
+
+        let slices = allocate_batch(&layers, "B1", dec(3)).unwrap();
+        assert_eq!(slices.len(), 1);
+        assert_eq!(slices[0].batch_no.as_deref(), Some("B1"));
+
+        assert!(matches!(allocate_batch(&layers, "B1", dec(4)), Err(AppError::Validation(_))));
+        assert!(matches!(allocate_batch(&layers, "B3", dec(1)), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_weighted_average_cost() {
+        let avg = weighted_average_cost(&[(dec(10), dec(5)), (dec(30), dec(9))]).unwrap();
+        assert_eq!(avg, dec(8));
+        assert!(weighted_average_cost(&[]).is_none());
+    }
+}
@@ -5,11 +5,11 @@ use uuid::Uuid;
 
 use crate::{
     models::{
-        CreateDocumentRequest, DocStatus, DocType, Document, DocumentLine, DocumentLineInput,
-        DocumentLineWithProduct, DocumentListItem, DocumentQuery, DocumentWithLines,
+        CostingMethod, CreateDocumentRequest, DocStatus, DocType, Document, DocumentLine,
+        DocumentLineInput, DocumentLineWithProduct, DocumentListItem, DocumentQuery, DocumentWithLines,
         PoReceiptStatus, PoReceiptItem, StocktakeScope, UpdateDocumentRequest,
     },
-    services::StockService,
+    services::{SignatureMeaning, SignatureService, SignatureType, StockService, StocktakeService},
     AppError, Result,
 };
 
@@ -76,9 +76,9 @@ impl DocumentService {
                 r#"
                 INSERT INTO document_lines (
                     id, document_id, line_no, product_id, qty, uom, unit_price,
-                    batch_no, expiry_date, remark
+                    batch_no, expiry_date, remark, from_location_id, to_location_id
                 )
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
                 RETURNING *
                 "#
             )
@@ -92,6 +92,8 @@ impl DocumentService {
             .bind(&line.batch_no)
             .bind(line.expiry_date)
             .bind(&line.remark)
+            .bind(line.from_location_id)
+            .bind(line.to_location_id)
             .fetch_one(&mut *tx)
             .await?;
             lines.push(doc_line);
@@ -120,6 +122,11 @@ impl DocumentService {
             return Err(AppError::BusinessRule("Only draft documents can be updated".to_string()));
         }
 
+        // 盤點開始後帳面數量已凍結，不可再變更倉庫或範圍
+        if existing.count_started_at.is_some() {
+            return Err(AppError::BusinessRule("Stocktake cannot be updated after counting has started".to_string()));
+        }
+
         let mut tx = pool.begin().await?;
 
         // 更新單據頭
@@ -166,9 +173,9 @@ impl DocumentService {
                     r#"
                     INSERT INTO document_lines (
                         id, document_id, line_no, product_id, qty, uom, unit_price,
-                        batch_no, expiry_date, remark
+                        batch_no, expiry_date, remark, from_location_id, to_location_id
                     )
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
                     "#
                 )
                 .bind(Uuid::new_v4())
@@ -181,6 +188,8 @@ impl DocumentService {
                 .bind(&line.batch_no)
                 .bind(line.expiry_date)
                 .bind(&line.remark)
+                .bind(line.from_location_id)
+                .bind(line.to_location_id)
                 .execute(&mut *tx)
                 .await?;
             }
@@ -191,8 +200,20 @@ impl DocumentService {
         Self::get_by_id(pool, id).await
     }
 
-    /// 送審
+    /// 送審（盤點單須已完成盤點）
     pub async fn submit(pool: &PgPool, id: Uuid) -> Result<DocumentWithLines> {
+        let document = sqlx::query_as::<_, Document>(
+            "SELECT * FROM documents WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Document not found".to_string()))?;
+
+        if document.doc_type == DocType::STK {
+            StocktakeService::ensure_complete(pool, &document).await?;
+        }
+
         let result = sqlx::query(
             r#"
             UPDATE documents SET status = $1, updated_at = NOW()
@@ -213,8 +234,14 @@ impl DocumentService {
     }
 
     /// 核准（寫入庫存流水）
-    /// 採購單核准後會自動產生入庫單（草稿）
-    pub async fn approve(pool: &PgPool, id: Uuid, approved_by: Uuid) -> Result<DocumentWithLines> {
+    /// 採購單核准後會自動產生入庫單（草稿）；核准人以已驗證的密碼雜湊完成電子簽章
+    pub async fn approve(
+        pool: &PgPool,
+        id: Uuid,
+        approved_by: Uuid,
+        password_hash: &str,
+        costing_method: CostingMethod,
+    ) -> Result<DocumentWithLines> {
         let document = sqlx::query_as::<_, Document>(
             "SELECT * FROM documents WHERE id = $1"
         )
@@ -238,7 +265,12 @@ impl DocumentService {
 
         // 檢查庫存並寫入流水
         if document.doc_type.affects_stock() {
-            StockService::process_document(&mut tx, &document, &lines).await?;
+            StockService::process_document(&mut tx, &document, &lines, costing_method).await?;
+        }
+
+        // 盤點單：依盤點差異產生調整單並過帳
+        if document.doc_type == DocType::STK {
+            StocktakeService::post_adjustment(&mut tx, &document, approved_by, costing_method).await?;
         }
 
         // 更新單據狀態
@@ -258,6 +290,13 @@ impl DocumentService {
         .execute(&mut *tx)
         .await?;
 
+        // 如果是採購單關聯的入庫單，更新採購單入庫狀態
+        if document.doc_type == DocType::GRN {
+            if let Some(po_id) = document.source_doc_id {
+                Self::refresh_po_receipt_status(&mut tx, po_id).await?;
+            }
+        }
+
         // 如果是採購單，自動產生入庫單（草稿）
         if document.doc_type == DocType::PO {
             Self::create_grn_from_po(&mut tx, &document, &lines, approved_by).await?;
@@ -271,11 +310,180 @@ impl DocumentService {
             .await?;
         }
 
+        // 核准電子簽章（與核准同一交易）
+        SignatureService::sign_entity(
+            &mut tx,
+            "document",
+            &id.to_string(),
+            approved_by,
+            password_hash,
+            SignatureType::Approve,
+            SignatureMeaning::Approval,
+        ).await?;
+
         tx.commit().await?;
 
         Self::get_by_id(pool, id).await
     }
 
+    /// 沖銷已核准單據
+    /// 產生反向沖銷單（source_doc_id 指向原單據）並寫入反向庫存流水，原單據標記為已沖銷
+    pub async fn reverse(
+        pool: &PgPool,
+        id: Uuid,
+        reversed_by: Uuid,
+        reason: &str,
+        costing_method: CostingMethod,
+    ) -> Result<DocumentWithLines> {
+        let document = sqlx::query_as::<_, Document>(
+            "SELECT * FROM documents WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Document not found".to_string()))?;
+
+        if document.is_reversal {
+            return Err(AppError::BusinessRule("Reversal documents cannot be reversed".to_string()));
+        }
+
+        if !document.doc_type.affects_stock() {
+            return Err(AppError::BusinessRule("Only stock documents (GRN, PR, DO, TR, ADJ) can be reversed".to_string()));
+        }
+
+        let lines = sqlx::query_as::<_, DocumentLine>(
+            "SELECT * FROM document_lines WHERE document_id = $1 ORDER BY line_no"
+        )
+        .bind(id)
+        .fetch_all(pool)
+        .await?;
+
+        let mut tx = pool.begin().await?;
+
+        // 鎖定原單據並標記為已沖銷（同時防止重複沖銷）
+        let result = sqlx::query(
+            "UPDATE documents SET status = $1, updated_at = NOW() WHERE id = $2 AND status = $3"
+        )
+        .bind(DocStatus::Reversed)
+        .bind(id)
+        .bind(DocStatus::Approved)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::BusinessRule("Only approved documents can be reversed".to_string()));
+        }
+
+        let doc_no = Self::generate_doc_no(&mut tx, document.doc_type).await?;
+
+        // 建立沖銷單頭（直接為核准狀態）
+        let reversal = sqlx::query_as::<_, Document>(
+            r#"
+            INSERT INTO documents (
+                id, doc_type, doc_no, status, warehouse_id, warehouse_from_id, warehouse_to_id,
+                partner_id, doc_date, source_doc_id, is_reversal, remark,
+                created_by, approved_by, created_at, updated_at, approved_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, true, $11, $12, $12, NOW(), NOW(), NOW())
+            RETURNING *
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(document.doc_type)
+        .bind(&doc_no)
+        .bind(DocStatus::Approved)
+        .bind(document.warehouse_id)
+        .bind(document.warehouse_from_id)
+        .bind(document.warehouse_to_id)
+        .bind(document.partner_id)
+        .bind(Utc::now().date_naive())
+        .bind(document.id)
+        .bind(format!("沖銷單據 {}：{}", document.doc_no, reason))
+        .bind(reversed_by)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // 建立沖銷單明細（與原單據相同）
+        let mut reversal_lines = Vec::with_capacity(lines.len());
+        for line in &lines {
+            let reversal_line = sqlx::query_as::<_, DocumentLine>(
+                r#"
+                INSERT INTO document_lines (
+                    id, document_id, line_no, product_id, qty, uom, unit_price,
+                    batch_no, expiry_date, remark, from_location_id, to_location_id
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                RETURNING *
+                "#
+            )
+            .bind(Uuid::new_v4())
+            .bind(reversal.id)
+            .bind(line.line_no)
+            .bind(line.product_id)
+            .bind(line.qty)
+            .bind(&line.uom)
+            .bind(line.unit_price)
+            .bind(&line.batch_no)
+            .bind(line.expiry_date)
+            .bind(&line.remark)
+            // 沖銷單的來源 / 目的儲位與原單據對調
+            .bind(line.to_location_id)
+            .bind(line.from_location_id)
+            .fetch_one(&mut *tx)
+            .await?;
+            reversal_lines.push(reversal_line);
+        }
+
+        // 寫入反向庫存流水（庫存已被耗用時拒絕）
+        StockService::process_document(&mut tx, &reversal, &reversal_lines, costing_method).await?;
+
+        // 沖銷採購單關聯的入庫單時，回復採購單入庫狀態
+        if document.doc_type == DocType::GRN {
+            if let Some(po_id) = document.source_doc_id {
+                Self::refresh_po_receipt_status(&mut tx, po_id).await?;
+            }
+        }
+
+        tx.commit().await?;
+
+        Self::get_by_id(pool, reversal.id).await
+    }
+
+    /// 依已核准入庫單重新計算採購單入庫狀態
+    async fn refresh_po_receipt_status(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        po_id: Uuid,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE documents po SET
+                receipt_status = CASE
+                    WHEN r.received_qty <= 0 THEN 'pending'
+                    WHEN r.received_qty < o.ordered_qty THEN 'partial'
+                    ELSE 'complete'
+                END,
+                updated_at = NOW()
+            FROM
+                (SELECT COALESCE(SUM(qty), 0) as ordered_qty FROM document_lines WHERE document_id = $1) o,
+                (
+                    SELECT COALESCE(SUM(dl.qty), 0) as received_qty
+                    FROM documents d
+                    JOIN document_lines dl ON d.id = dl.document_id
+                    WHERE d.source_doc_id = $1
+                      AND d.doc_type = 'GRN'
+                      AND d.status = 'approved'
+                      AND d.is_reversal = false
+                ) r
+            WHERE po.id = $1 AND po.doc_type = 'PO'
+            "#
+        )
+        .bind(po_id)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
     /// 從採購單建立入庫單（草稿）
     async fn create_grn_from_po(
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
@@ -337,9 +545,9 @@ impl DocumentService {
                 r#"
                 INSERT INTO document_lines (
                     id, document_id, line_no, product_id, qty, uom, unit_price,
-                    batch_no, expiry_date, remark
+                    batch_no, expiry_date, remark, from_location_id, to_location_id
                 )
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
                 "#
             )
             .bind(Uuid::new_v4())
@@ -352,6 +560,8 @@ impl DocumentService {
             .bind(&line.batch_no)
             .bind(line.expiry_date)
             .bind(&line.remark)
+            .bind(line.from_location_id)
+            .bind(line.to_location_id)
             .execute(&mut **tx)
             .await?;
         }
@@ -456,9 +666,9 @@ impl DocumentService {
                 r#"
                 INSERT INTO document_lines (
                     id, document_id, line_no, product_id, qty, uom, unit_price,
-                    batch_no, expiry_date, remark
+                    batch_no, expiry_date, remark, from_location_id, to_location_id
                 )
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
                 "#
             )
             .bind(Uuid::new_v4())
@@ -471,6 +681,8 @@ impl DocumentService {
             .bind(&line.batch_no)
             .bind(line.expiry_date)
             .bind(&line.remark)
+            .bind(line.from_location_id)
+            .bind(line.to_location_id)
             .execute(&mut *tx)
             .await?;
         }
@@ -695,9 +907,13 @@ impl DocumentService {
             SELECT 
                 dl.id, dl.document_id, dl.line_no, dl.product_id,
                 p.sku as product_sku, p.name as product_name,
-                dl.qty, dl.uom, dl.unit_price, dl.batch_no, dl.expiry_date, dl.remark
+                dl.qty, dl.uom, dl.unit_price, dl.batch_no, dl.expiry_date, dl.remark,
+                dl.from_location_id, fl.code as from_location_code,
+                dl.to_location_id, tl.code as to_location_code
             FROM document_lines dl
             INNER JOIN products p ON dl.product_id = p.id
+            LEFT JOIN storage_locations fl ON dl.from_location_id = fl.id
+            LEFT JOIN storage_locations tl ON dl.to_location_id = tl.id
             WHERE dl.document_id = $1
             ORDER BY dl.line_no
             "#
@@ -772,7 +988,7 @@ impl DocumentService {
 
     /// 產生單據編號
     /// 格式：{PREFIX}-YYMMDD-{02} (例如：SO-260115-01)
-    async fn generate_doc_no(tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, doc_type: DocType) -> Result<String> {
+    pub(crate) async fn generate_doc_no(tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, doc_type: DocType) -> Result<String> {
         // 統一使用 YYMMDD 格式
         let today = Utc::now();
         let year = today.format("%y").to_string(); // 2-digit year
@@ -831,6 +1047,7 @@ impl DocumentService {
             keyword: None,
             batch_no: None,
             low_stock_only: None,
+            location_id: None,
         };
         
         // 由於我們在事務中，需要直接查詢
@@ -895,6 +1112,8 @@ impl DocumentService {
                 batch_no: None,
                 expiry_date: None,
                 remark: Some("系統庫存".to_string()),
+                from_location_id: None,
+                to_location_id: None,
             })
             .collect();
 
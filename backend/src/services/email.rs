@@ -6,6 +6,17 @@ use lettre::{
 
 use crate::config::Config;
 
+/// 附件大小上限，超過時只寄送連結
+const MAX_ATTACHMENT_SIZE: usize = 10 * 1024 * 1024;
+
+/// 郵件附件
+#[derive(Debug, Clone)]
+pub struct EmailAttachment {
+    pub file_name: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
 pub struct EmailService;
 
 impl EmailService {
@@ -668,6 +679,235 @@ impl EmailService {
         Ok(())
     }
 
+    /// 寄送審查期限提醒
+    pub async fn send_review_deadline_reminder_email(
+        config: &Config,
+        to_email: &str,
+        display_name: &str,
+        protocol_no: &str,
+        protocol_title: &str,
+        due_date: &str,
+    ) -> anyhow::Result<()> {
+        if !config.is_email_enabled() {
+            tracing::info!(
+                "Email disabled, skipping review deadline reminder email to {}",
+                to_email
+            );
+            return Ok(());
+        }
+
+        let smtp_host = config.smtp_host.as_ref().unwrap();
+        let protocol_url = format!("{}/protocols", config.app_url);
+        let logo_url = format!("{}/pigmodel-logo.png", config.app_url);
+
+        let html_body = format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <style>
+        body {{ font-family: 'Microsoft JhengHei', Arial, sans-serif; line-height: 1.6; color: #333; }}
+        .container {{ max-width: 600px; margin: 0 auto; padding: 20px; }}
+        .header {{ background: #ea580c; color: white; padding: 20px; text-align: center; border-radius: 8px 8px 0 0; }}
+        .content {{ background: #f8fafc; padding: 30px; border: 1px solid #e2e8f0; }}
+        .info-box {{ background: white; padding: 20px; border-radius: 8px; margin: 20px 0; border-left: 4px solid #ea580c; }}
+        .button {{ display: inline-block; background: #ea580c; color: white; padding: 12px 30px; text-decoration: none; border-radius: 6px; margin: 20px 0; }}
+        .footer {{ text-align: center; padding: 20px; color: #64748b; font-size: 12px; }}
+        .warning {{ color: #ea580c; font-weight: bold; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        <div class="header">
+            <div style="text-align: center; margin-bottom: 15px;">
+                <img src="{logo_url}" alt="iPig System" style="height: 50px; width: auto; background: white; padding: 5px; border-radius: 5px;">
+            </div>
+            <h1>⏰ 審查期限提醒</h1>
+        </div>
+        <div class="content">
+            <p>親愛的 <strong>{display_name}</strong>，您好！</p>
+            <p>您負責審查的計畫即將到期，請於期限內完成審查並提交表決。</p>
+
+            <div class="info-box">
+                <p><strong>計畫編號：</strong> {protocol_no}</p>
+                <p><strong>計畫名稱：</strong> {protocol_title}</p>
+                <p><strong>審查期限：</strong> <span class="warning">{due_date}</span></p>
+            </div>
+
+            <center>
+                <a href="{protocol_url}" class="button">登入系統審查</a>
+            </center>
+        </div>
+        <div class="footer">
+            <p>此信件由系統自動發送，請勿直接回覆。</p>
+            <p>© 2026 豬博士動物科技有限公司</p>
+        </div>
+    </div>
+</body>
+</html>"#,
+            display_name = display_name,
+            protocol_no = protocol_no,
+            protocol_title = protocol_title,
+            due_date = due_date,
+            protocol_url = protocol_url,
+            logo_url = logo_url,
+        );
+
+        let plain_body = format!(
+            r#"審查期限提醒
+
+親愛的 {display_name}，您好！
+
+您負責審查的計畫即將到期，請於期限內完成審查並提交表決。
+
+【計畫資訊】
+計畫編號：{protocol_no}
+計畫名稱：{protocol_title}
+審查期限：{due_date}
+
+請登入系統審查：{protocol_url}
+
+此信件由系統自動發送，請勿直接回覆。
+© 2026 豬博士動物科技有限公司"#,
+            display_name = display_name,
+            protocol_no = protocol_no,
+            protocol_title = protocol_title,
+            due_date = due_date,
+            protocol_url = protocol_url,
+        );
+
+        Self::send_email(
+            config,
+            smtp_host,
+            to_email,
+            display_name,
+            &format!("[iPig] 審查期限提醒 - {}", protocol_no),
+            &plain_body,
+            &html_body,
+        )
+        .await?;
+
+        tracing::info!("Review deadline reminder email sent to {}", to_email);
+        Ok(())
+    }
+
+    /// 寄送審查逾期上呈通知（給 IACUC 主委）
+    pub async fn send_review_overdue_escalation_email(
+        config: &Config,
+        to_email: &str,
+        display_name: &str,
+        protocol_no: &str,
+        protocol_title: &str,
+        overdue_detail: &str,
+        due_date: &str,
+    ) -> anyhow::Result<()> {
+        if !config.is_email_enabled() {
+            tracing::info!(
+                "Email disabled, skipping review overdue escalation email to {}",
+                to_email
+            );
+            return Ok(());
+        }
+
+        let smtp_host = config.smtp_host.as_ref().unwrap();
+        let protocol_url = format!("{}/protocols", config.app_url);
+        let logo_url = format!("{}/pigmodel-logo.png", config.app_url);
+
+        let html_body = format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <style>
+        body {{ font-family: 'Microsoft JhengHei', Arial, sans-serif; line-height: 1.6; color: #333; }}
+        .container {{ max-width: 600px; margin: 0 auto; padding: 20px; }}
+        .header {{ background: #dc2626; color: white; padding: 20px; text-align: center; border-radius: 8px 8px 0 0; }}
+        .content {{ background: #f8fafc; padding: 30px; border: 1px solid #e2e8f0; }}
+        .info-box {{ background: white; padding: 20px; border-radius: 8px; margin: 20px 0; border-left: 4px solid #dc2626; }}
+        .button {{ display: inline-block; background: #dc2626; color: white; padding: 12px 30px; text-decoration: none; border-radius: 6px; margin: 20px 0; }}
+        .footer {{ text-align: center; padding: 20px; color: #64748b; font-size: 12px; }}
+        .warning {{ color: #dc2626; font-weight: bold; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        <div class="header">
+            <div style="text-align: center; margin-bottom: 15px;">
+                <img src="{logo_url}" alt="iPig System" style="height: 50px; width: auto; background: white; padding: 5px; border-radius: 5px;">
+            </div>
+            <h1>⚠️ 審查逾期通知</h1>
+        </div>
+        <div class="content">
+            <p>親愛的 <strong>{display_name}</strong>，您好！</p>
+            <p>以下計畫的審查已超過期限，請協助追蹤或重新指派。</p>
+
+            <div class="info-box">
+                <p><strong>計畫編號：</strong> {protocol_no}</p>
+                <p><strong>計畫名稱：</strong> {protocol_title}</p>
+                <p><strong>逾期項目：</strong> {overdue_detail}</p>
+                <p><strong>原定期限：</strong> <span class="warning">{due_date}</span></p>
+            </div>
+
+            <center>
+                <a href="{protocol_url}" class="button">登入系統查看</a>
+            </center>
+        </div>
+        <div class="footer">
+            <p>此信件由系統自動發送，請勿直接回覆。</p>
+            <p>© 2026 豬博士動物科技有限公司</p>
+        </div>
+    </div>
+</body>
+</html>"#,
+            display_name = display_name,
+            protocol_no = protocol_no,
+            protocol_title = protocol_title,
+            overdue_detail = overdue_detail,
+            due_date = due_date,
+            protocol_url = protocol_url,
+            logo_url = logo_url,
+        );
+
+        let plain_body = format!(
+            r#"審查逾期通知
+
+親愛的 {display_name}，您好！
+
+以下計畫的審查已超過期限，請協助追蹤或重新指派。
+
+【計畫資訊】
+計畫編號：{protocol_no}
+計畫名稱：{protocol_title}
+逾期項目：{overdue_detail}
+原定期限：{due_date}
+
+請登入系統查看：{protocol_url}
+
+此信件由系統自動發送，請勿直接回覆。
+© 2026 豬博士動物科技有限公司"#,
+            display_name = display_name,
+            protocol_no = protocol_no,
+            protocol_title = protocol_title,
+            overdue_detail = overdue_detail,
+            due_date = due_date,
+            protocol_url = protocol_url,
+        );
+
+        Self::send_email(
+            config,
+            smtp_host,
+            to_email,
+            display_name,
+            &format!("[iPig] 審查逾期 - {}", protocol_no),
+            &plain_body,
+            &html_body,
+        )
+        .await?;
+
+        tracing::info!("Review overdue escalation email sent to {}", to_email);
+        Ok(())
+    }
+
     /// 寄送獸醫師建議通知
     pub async fn send_vet_recommendation_email(
         config: &Config,
@@ -978,6 +1218,103 @@ IACUC NO.：{iacuc_no}
         Ok(())
     }
 
+    /// 寄送定期報表（檔案不超過附件上限時直接附檔）
+    pub async fn send_scheduled_report_email(
+        config: &Config,
+        to_email: &str,
+        display_name: &str,
+        report_name: &str,
+        attachment: &EmailAttachment,
+    ) -> anyhow::Result<()> {
+        if !config.is_email_enabled() {
+            tracing::info!("Email disabled, skipping scheduled report email to {}", to_email);
+            return Ok(());
+        }
+
+        let smtp_host = config.smtp_host.as_ref().unwrap();
+        let reports_url = format!("{}/reports", config.app_url);
+        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        let logo_url = format!("{}/pigmodel-logo.png", config.app_url);
+        let attached = attachment.data.len() <= MAX_ATTACHMENT_SIZE;
+        let delivery_note = if attached {
+            format!("報表檔案 <strong>{}</strong> 已附於本信件。", attachment.file_name)
+        } else {
+            format!("報表檔案 <strong>{}</strong> 超過附件大小上限，請登入系統下載。", attachment.file_name)
+        };
+
+        let html_body = format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <style>
+        body {{ font-family: 'Microsoft JhengHei', Arial, sans-serif; line-height: 1.6; color: #333; }}
+        .container {{ max-width: 600px; margin: 0 auto; padding: 20px; }}
+        .header {{ background: #2563eb; color: white; padding: 20px; text-align: center; border-radius: 8px 8px 0 0; }}
+        .content {{ background: #f8fafc; padding: 30px; border: 1px solid #e2e8f0; }}
+        .button {{ display: inline-block; background: #2563eb; color: white; padding: 12px 30px; text-decoration: none; border-radius: 6px; margin: 20px 0; }}
+        .footer {{ text-align: center; padding: 20px; color: #64748b; font-size: 12px; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        <div class="header">
+            <div style="text-align: center; margin-bottom: 15px;">
+                <img src="{logo_url}" alt="iPig System" style="height: 50px; width: auto; background: white; padding: 5px; border-radius: 5px;">
+            </div>
+            <h1>📊 {report_name}</h1>
+        </div>
+        <div class="content">
+            <p>親愛的 <strong>{display_name}</strong>，您好！</p>
+            <p>您訂閱的定期報表已產生。</p>
+            <p>{delivery_note}</p>
+            
+            <center>
+                <a href="{reports_url}" class="button">登入系統查看</a>
+            </center>
+        </div>
+        <div class="footer">
+            <p>此信件由系統自動發送，請勿直接回覆。</p>
+            <p>© 2026 豬博士動物科技有限公司</p>
+        </div>
+    </div>
+</body>
+</html>"#,
+            display_name = display_name,
+            report_name = report_name,
+            delivery_note = delivery_note,
+            reports_url = reports_url,
+            logo_url = logo_url,
+        );
+
+        let plain_body = format!(
+            "{}\n\n您訂閱的定期報表已產生：{}\n\n請登入系統查看：{}",
+            report_name, attachment.file_name, reports_url
+        );
+
+        let mut body = lettre::message::MultiPart::mixed()
+            .multipart(Self::alternative_body(&plain_body, &html_body));
+        if attached {
+            body = body.singlepart(
+                lettre::message::Attachment::new(attachment.file_name.clone())
+                    .body(attachment.data.clone(), ContentType::parse(&attachment.mime_type)?),
+            );
+        }
+
+        Self::send_message(
+            config,
+            smtp_host,
+            to_email,
+            display_name,
+            &format!("[iPig] {} - {}", report_name, today),
+            body,
+        )
+        .await?;
+
+        tracing::info!("Scheduled report email sent to {}", to_email);
+        Ok(())
+    }
+
     /// 通用發送郵件方法
     async fn send_email(
         config: &Config,
@@ -987,6 +1324,41 @@ IACUC NO.：{iacuc_no}
         subject: &str,
         plain_body: &str,
         html_body: &str,
+    ) -> anyhow::Result<()> {
+        Self::send_message(
+            config,
+            smtp_host,
+            to_email,
+            to_name,
+            subject,
+            Self::alternative_body(plain_body, html_body),
+        )
+        .await
+    }
+
+    /// 純文字與 HTML 雙版本內文
+    fn alternative_body(plain_body: &str, html_body: &str) -> lettre::message::MultiPart {
+        lettre::message::MultiPart::alternative()
+            .singlepart(
+                lettre::message::SinglePart::builder()
+                    .header(ContentType::TEXT_PLAIN)
+                    .body(plain_body.to_string()),
+            )
+            .singlepart(
+                lettre::message::SinglePart::builder()
+                    .header(ContentType::TEXT_HTML)
+                    .body(html_body.to_string()),
+            )
+    }
+
+    /// 透過 SMTP 寄出郵件
+    async fn send_message(
+        config: &Config,
+        smtp_host: &str,
+        to_email: &str,
+        to_name: &str,
+        subject: &str,
+        body: lettre::message::MultiPart,
     ) -> anyhow::Result<()> {
         let from = format!("{} <{}>", config.smtp_from_name, config.smtp_from_email);
 
@@ -994,19 +1366,7 @@ IACUC NO.：{iacuc_no}
             .from(from.parse()?)
             .to(format!("{} <{}>", to_name, to_email).parse()?)
             .subject(subject)
-            .multipart(
-                lettre::message::MultiPart::alternative()
-                    .singlepart(
-                        lettre::message::SinglePart::builder()
-                            .header(ContentType::TEXT_PLAIN)
-                            .body(plain_body.to_string()),
-                    )
-                    .singlepart(
-                        lettre::message::SinglePart::builder()
-                            .header(ContentType::TEXT_HTML)
-                            .body(html_body.to_string()),
-                    ),
-            )?;
+            .multipart(body)?;
 
         // Gmail port 587 需要 STARTTLS
         let mailer = if let (Some(username), Some(password)) =
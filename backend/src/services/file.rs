@@ -1,10 +1,21 @@
-use std::path::{Path, PathBuf};
+use std::fmt::Display;
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
 use uuid::Uuid;
-use chrono::Utc;
 
 use crate::error::AppError;
+use crate::services::storage::{storage, ByteStream, StoredObject};
+use crate::services::{IntegrityProblem, IntegrityService};
 
 /// 檔案服務 - 處理檔案上傳、下載與管理
 pub struct FileService;
@@ -17,8 +28,45 @@ pub struct UploadResult {
     pub file_path: String,
     pub file_size: i64,
     pub mime_type: String,
+    /// 內容 SHA-256（十六進位）
+    pub content_hash: String,
 }
 
+/// 已寫入儲存後端、尚待建立參照紀錄的上傳
+///
+/// `tx` 持有該物件的鎖；呼叫端須於此交易內寫入參照檔案的紀錄後提交，
+/// 清除作業才不會在上傳與建立紀錄之間刪除剛沿用的物件。
+pub struct StoredUpload {
+    pub result: UploadResult,
+    pub tx: Transaction<'static, Postgres>,
+}
+
+/// 寫入儲存後端的內容來源；物件於寫入後被清除時需重新讀取
+enum UploadBody<'a> {
+    Bytes(Bytes),
+    File(&'a Path),
+}
+
+impl UploadBody<'_> {
+    async fn stream(&self) -> Result<ByteStream, AppError> {
+        match self {
+            UploadBody::Bytes(data) => {
+                let chunk = data.clone();
+                Ok(Box::pin(futures_util::stream::once(async move { Ok(chunk) })))
+            }
+            UploadBody::File(path) => {
+                let file = fs::File::open(path)
+                    .await
+                    .map_err(|e| AppError::Internal(format!("Failed to read file: {}", e)))?;
+                Ok(Box::pin(ReaderStream::new(file)))
+            }
+        }
+    }
+}
+
+/// 寫入後、鎖定前物件遭清除時的最多寫入次數
+const MAX_STORE_ATTEMPTS: usize = 3;
+
 /// 檔案類別
 #[derive(Debug, Clone, Copy)]
 pub enum FileCategory {
@@ -32,6 +80,10 @@ pub enum FileCategory {
     VetRecommendation,
     /// 請假附件
     LeaveAttachment,
+    /// 醫療資料匯出檔（GLP 留存）
+    MedicalExport,
+    /// 定期報表產出檔
+    ScheduledReport,
 }
 
 impl FileCategory {
@@ -43,6 +95,8 @@ impl FileCategory {
             FileCategory::PathologyReport => "pathology",
             FileCategory::VetRecommendation => "vet-recommendations",
             FileCategory::LeaveAttachment => "leave-attachments",
+            FileCategory::MedicalExport => "medical-exports",
+            FileCategory::ScheduledReport => "reports",
         }
     }
 
@@ -73,6 +127,14 @@ impl FileCategory {
                 "application/msword",
                 "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
             ],
+            FileCategory::MedicalExport => vec![
+                "application/pdf",
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ],
+            FileCategory::ScheduledReport => vec![
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+                "text/csv",
+            ],
         }
     }
 
@@ -84,28 +146,13 @@ impl FileCategory {
             FileCategory::PathologyReport => 30 * 1024 * 1024,    // 30 MB
             FileCategory::VetRecommendation => 10 * 1024 * 1024,  // 10 MB
             FileCategory::LeaveAttachment => 10 * 1024 * 1024,    // 10 MB
+            FileCategory::MedicalExport => 100 * 1024 * 1024,     // 100 MB
+            FileCategory::ScheduledReport => 100 * 1024 * 1024,   // 100 MB
         }
     }
 }
 
 impl FileService {
-    /// 取得上傳目錄
-    fn get_upload_dir() -> PathBuf {
-        std::env::var("UPLOAD_DIR")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| PathBuf::from("./uploads"))
-    }
-
-    /// 確保目錄存在
-    async fn ensure_dir_exists(dir: &Path) -> Result<(), AppError> {
-        if !dir.exists() {
-            fs::create_dir_all(dir).await.map_err(|e| {
-                AppError::Internal(format!("Failed to create directory: {}", e))
-            })?;
-        }
-        Ok(())
-    }
-
     /// 從 MIME 類型推斷副檔名
     fn get_extension_from_mime(mime_type: &str) -> Option<&'static str> {
         match mime_type {
@@ -119,6 +166,7 @@ impl FileService {
             "image/gif" => Some("gif"),
             "image/webp" => Some("webp"),
             "text/plain" => Some("txt"),
+            "text/csv" => Some("csv"),
             _ => None,
         }
     }
@@ -131,130 +179,240 @@ impl FileService {
             .map(|ext| ext.to_lowercase())
     }
 
-    /// 產生唯一檔名
-    fn generate_unique_filename(original_filename: &str, mime_type: &str) -> String {
-        let file_id = Uuid::new_v4().to_string();
-        let date_prefix = Utc::now().format("%Y%m%d").to_string();
-        
-        // 優先使用原始檔名的副檔名
-        let extension = Self::get_extension_from_filename(original_filename)
-            .or_else(|| Self::get_extension_from_mime(mime_type).map(String::from))
+    /// 內容定址的儲存路徑：`{類別}/{雜湊前兩碼}/{雜湊}.{副檔名}`
+    ///
+    /// 相同類別、相同類型的相同內容只會儲存一份。
+    fn content_key(category: FileCategory, original_filename: &str, mime_type: &str, content_hash: &str) -> String {
+        let extension = Self::get_extension_from_mime(mime_type)
+            .map(String::from)
+            .or_else(|| Self::get_extension_from_filename(original_filename))
             .unwrap_or_else(|| "bin".to_string());
-        
-        format!("{}_{}.{}", date_prefix, file_id, extension)
+
+        format!("{}/{}/{}.{}", category.subdirectory(), &content_hash[..2], content_hash, extension)
     }
 
-    /// 上傳檔案
-    pub async fn upload(
-        category: FileCategory,
-        original_filename: &str,
-        mime_type: &str,
-        data: &[u8],
-        entity_id: Option<&str>,
-    ) -> Result<UploadResult, AppError> {
-        // 驗證 MIME 類型
+    /// 驗證 MIME 類型
+    fn ensure_mime_allowed(category: FileCategory, mime_type: &str) -> Result<(), AppError> {
         if !category.allowed_mime_types().contains(&mime_type) {
             return Err(AppError::Validation(format!(
                 "File type '{}' is not allowed for this category",
                 mime_type
             )));
         }
+        Ok(())
+    }
 
-        // 驗證檔案大小
-        if data.len() > category.max_file_size() {
-            return Err(AppError::Validation(format!(
-                "File size exceeds maximum allowed size of {} MB",
-                category.max_file_size() / 1024 / 1024
-            )));
-        }
-
-        // 建立目錄結構
-        let base_dir = Self::get_upload_dir();
-        let category_dir = base_dir.join(category.subdirectory());
-        
-        // 如果有實體 ID，建立子目錄
-        let target_dir = if let Some(id) = entity_id {
-            category_dir.join(id)
-        } else {
-            category_dir
-        };
-        
-        Self::ensure_dir_exists(&target_dir).await?;
-
-        // 產生唯一檔名
-        let unique_filename = Self::generate_unique_filename(original_filename, mime_type);
-        let file_path = target_dir.join(&unique_filename);
-
-        // 寫入檔案
-        let mut file = fs::File::create(&file_path).await.map_err(|e| {
-            AppError::Internal(format!("Failed to create file: {}", e))
-        })?;
-        
-        file.write_all(data).await.map_err(|e| {
-            AppError::Internal(format!("Failed to write file: {}", e))
-        })?;
-
-        // 計算相對路徑
-        let relative_path = file_path
-            .strip_prefix(&base_dir)
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_else(|_| unique_filename.clone());
-
-        Ok(UploadResult {
-            file_id: Uuid::new_v4().to_string(),
-            file_name: original_filename.to_string(),
-            file_path: relative_path,
-            file_size: data.len() as i64,
-            mime_type: mime_type.to_string(),
-        })
+    fn file_too_large(category: FileCategory) -> AppError {
+        AppError::Validation(format!(
+            "File size exceeds maximum allowed size of {} MB",
+            category.max_file_size() / 1024 / 1024
+        ))
     }
 
-    /// 讀取檔案
-    pub async fn read(relative_path: &str) -> Result<(Vec<u8>, String), AppError> {
-        let base_dir = Self::get_upload_dir();
-        let file_path = base_dir.join(relative_path);
-
-        // 安全檢查：確保路徑在上傳目錄內
-        let canonical_base = base_dir.canonicalize().unwrap_or(base_dir.clone());
-        let canonical_file = file_path.canonicalize().map_err(|_| {
-            AppError::NotFound("File not found".to_string())
-        })?;
-        
-        if !canonical_file.starts_with(&canonical_base) {
-            return Err(AppError::Forbidden("Invalid file path".to_string()));
+    /// 寫入儲存後端；相同內容已存在時重新計算其雜湊，相符才沿用，
+    /// 不符（遭竄改或毀損）時先對參照該檔案的紀錄建立完整性警報再以上傳內容覆寫
+    ///
+    /// 寫入於資料庫交易外進行；回傳的交易持有物件鎖，呼叫端於其中寫入參照紀錄後提交。
+    async fn store(
+        pool: &PgPool,
+        category: FileCategory,
+        original_filename: &str,
+        mime_type: &str,
+        body: UploadBody<'_>,
+        size: u64,
+        content_hash: String,
+    ) -> Result<StoredUpload, AppError> {
+        let relative_path = Self::content_key(category, original_filename, mime_type, &content_hash);
+        let backend = storage();
+
+        for _ in 0..MAX_STORE_ATTEMPTS {
+            let stored_hash = match Self::hash_stored(&relative_path).await {
+                Ok((actual, _)) => Some(actual),
+                Err(AppError::NotFound(_)) => None,
+                Err(e) => return Err(e),
+            };
+            if stored_hash.as_deref() != Some(content_hash.as_str()) {
+                if let Some(actual) = stored_hash {
+                    Self::raise_mismatch_alerts(pool, &relative_path, &content_hash, actual).await?;
+                }
+                backend.put(&relative_path, body.stream().await?, size, mime_type).await?;
+            }
+
+            // 與清除作業互斥：鎖定後再次確認物件仍在，寫入後、鎖定前被清除時重新寫入
+            let mut tx = pool.begin().await?;
+            Self::lock_object(&mut tx, &relative_path).await?;
+            if backend.size(&relative_path).await? == Some(size) {
+                return Ok(StoredUpload {
+                    result: UploadResult {
+                        file_id: Uuid::new_v4().to_string(),
+                        file_name: original_filename.to_string(),
+                        file_path: relative_path,
+                        file_size: size as i64,
+                        mime_type: mime_type.to_string(),
+                        content_hash,
+                    },
+                    tx,
+                });
+            }
         }
 
-        let data = fs::read(&canonical_file).await.map_err(|_| {
-            AppError::NotFound("File not found".to_string())
-        })?;
+        Err(AppError::Internal(format!("Failed to store file {}", relative_path)))
+    }
+
+    /// 既有物件與其鍵不符：對參照該檔案的紀錄建立完整性警報
+    async fn raise_mismatch_alerts(
+        pool: &PgPool,
+        relative_path: &str,
+        content_hash: &str,
+        actual: String,
+    ) -> Result<(), AppError> {
+        tracing::warn!(
+            "[File] Stored object {} does not match its key (hash {}), overwriting",
+            relative_path,
+            actual
+        );
+        let problem = IntegrityProblem::Tampered { expected: content_hash.to_string(), actual };
+        IntegrityService::raise_path_alerts(pool, relative_path, &problem).await
+    }
 
-        // 推斷 MIME 類型
-        let mime_type = Self::guess_mime_type(&canonical_file);
+    /// 上傳檔案；參照紀錄須寫入回傳的交易後提交
+    pub async fn upload(
+        pool: &PgPool,
+        category: FileCategory,
+        original_filename: &str,
+        mime_type: &str,
+        data: &[u8],
+    ) -> Result<StoredUpload, AppError> {
+        Self::ensure_mime_allowed(category, mime_type)?;
+        if data.len() > category.max_file_size() {
+            return Err(Self::file_too_large(category));
+        }
 
-        Ok((data, mime_type))
+        let content_hash = hex::encode(Sha256::digest(data));
+        let body = UploadBody::Bytes(Bytes::copy_from_slice(data));
+        Self::store(pool, category, original_filename, mime_type, body, data.len() as u64, content_hash).await
     }
 
-    /// 刪除檔案
-    pub async fn delete(relative_path: &str) -> Result<(), AppError> {
-        let base_dir = Self::get_upload_dir();
-        let file_path = base_dir.join(relative_path);
-
-        // 安全檢查
-        let canonical_base = base_dir.canonicalize().unwrap_or(base_dir.clone());
-        if let Ok(canonical_file) = file_path.canonicalize() {
-            if !canonical_file.starts_with(&canonical_base) {
-                return Err(AppError::Forbidden("Invalid file path".to_string()));
+    /// 串流上傳檔案（例如 multipart 欄位）
+    ///
+    /// 內容先寫入暫存檔以檢查大小上限、取得長度並計算雜湊，再串流寫入儲存後端。
+    /// 參照紀錄須寫入回傳的交易後提交。
+    pub async fn upload_stream<S, E>(
+        pool: &PgPool,
+        category: FileCategory,
+        original_filename: &str,
+        mime_type: &str,
+        mut data: S,
+    ) -> Result<StoredUpload, AppError>
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin,
+        E: Display,
+    {
+        Self::ensure_mime_allowed(category, mime_type)?;
+
+        let tmp_path = std::env::temp_dir().join(format!("erp-upload-{}", Uuid::new_v4()));
+        let spooled = async {
+            let mut file = fs::File::create(&tmp_path).await.map_err(|e| {
+                AppError::Internal(format!("Failed to create file: {}", e))
+            })?;
+            let mut hasher = Sha256::new();
+            let mut size: usize = 0;
+            while let Some(chunk) = data.next().await {
+                let chunk = chunk.map_err(|e| {
+                    AppError::Validation(format!("Failed to read file data: {}", e))
+                })?;
+                size += chunk.len();
+                if size > category.max_file_size() {
+                    return Err(Self::file_too_large(category));
+                }
+                hasher.update(&chunk);
+                file.write_all(&chunk).await.map_err(|e| {
+                    AppError::Internal(format!("Failed to write file: {}", e))
+                })?;
             }
-            
-            fs::remove_file(canonical_file).await.map_err(|e| {
-                AppError::Internal(format!("Failed to delete file: {}", e))
+            file.flush().await.map_err(|e| {
+                AppError::Internal(format!("Failed to write file: {}", e))
             })?;
+            Ok((size as u64, hex::encode(hasher.finalize())))
         }
-        // 如果檔案不存在，靜默成功
+        .await;
 
+        let result = match spooled {
+            Ok((size, content_hash)) => {
+                let body = UploadBody::File(&tmp_path);
+                Self::store(pool, category, original_filename, mime_type, body, size, content_hash).await
+            }
+            Err(e) => Err(e),
+        };
+
+        let _ = fs::remove_file(&tmp_path).await;
+        result
+    }
+
+    /// 開啟檔案串流，回傳內容與推斷的 MIME 類型
+    pub async fn open(relative_path: &str) -> Result<(StoredObject, String), AppError> {
+        let object = storage().get(relative_path).await?;
+        Ok((object, Self::guess_mime_type(Path::new(relative_path))))
+    }
+
+    /// 開啟檔案串流並於傳輸結束時比對 SHA-256
+    ///
+    /// 不符時串流以錯誤結束（最後一個區塊不送出，接收端不會取得完整檔案），並呼叫 `on_mismatch`（參數為實際雜湊）。
+    pub async fn open_verified<F>(
+        relative_path: &str,
+        expected_hash: &str,
+        on_mismatch: F,
+    ) -> Result<StoredObject, AppError>
+    where
+        F: FnOnce(String) + Send + 'static,
+    {
+        let object = storage().get(relative_path).await?;
+        Ok(StoredObject {
+            stream: Box::pin(VerifyingStream {
+                inner: object.stream,
+                hasher: Sha256::new(),
+                expected: expected_hash.to_lowercase(),
+                held: None,
+                finished: false,
+                on_mismatch: Some(Box::new(on_mismatch)),
+            }),
+            size: object.size,
+        })
+    }
+
+    /// 鎖定儲存物件至交易結束；寫入或沿用物件與清除物件時取得，兩者互斥
+    pub async fn lock_object(conn: &mut PgConnection, relative_path: &str) -> Result<(), AppError> {
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(relative_path)
+            .execute(conn)
+            .await?;
         Ok(())
     }
 
+    /// 由內容定址的儲存路徑取出雜湊（檔名主檔名）；非內容定址的舊路徑回傳 None
+    pub fn hash_from_key(relative_path: &str) -> Option<&str> {
+        let stem = Path::new(relative_path).file_stem()?.to_str()?;
+        (stem.len() == 64 && stem.bytes().all(|b| b.is_ascii_hexdigit())).then_some(stem)
+    }
+
+    /// 重新計算已儲存檔案的 SHA-256 與大小
+    pub async fn hash_stored(relative_path: &str) -> Result<(String, u64), AppError> {
+        let mut object = storage().get(relative_path).await?;
+        let mut hasher = Sha256::new();
+        let mut size: u64 = 0;
+        while let Some(chunk) = object.stream.next().await {
+            let chunk = chunk.map_err(|e| AppError::Internal(format!("Failed to read file: {}", e)))?;
+            size += chunk.len() as u64;
+            hasher.update(&chunk);
+        }
+        Ok((hex::encode(hasher.finalize()), size))
+    }
+
+    /// 刪除檔案
+    pub async fn delete(relative_path: &str) -> Result<(), AppError> {
+        storage().delete(relative_path).await
+    }
+
     /// 推斷 MIME 類型
     fn guess_mime_type(path: &Path) -> String {
         let extension = path.extension()
@@ -272,27 +430,109 @@ impl FileService {
             Some("gif") => "image/gif".to_string(),
             Some("webp") => "image/webp".to_string(),
             Some("txt") => "text/plain".to_string(),
+            Some("csv") => "text/csv".to_string(),
             _ => "application/octet-stream".to_string(),
         }
     }
 
     /// 檢查檔案是否存在
     pub async fn exists(relative_path: &str) -> bool {
-        let base_dir = Self::get_upload_dir();
-        let file_path = base_dir.join(relative_path);
-        file_path.exists()
+        storage().exists(relative_path).await.unwrap_or(false)
+    }
+}
+
+/// 邊傳送邊計算雜湊的串流；保留最後一個區塊直到比對完成
+struct VerifyingStream {
+    inner: ByteStream,
+    hasher: Sha256,
+    expected: String,
+    held: Option<Bytes>,
+    finished: bool,
+    on_mismatch: Option<Box<dyn FnOnce(String) + Send>>,
+}
+
+impl Stream for VerifyingStream {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            match this.inner.as_mut().poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(Some(Ok(chunk))) => {
+                    this.hasher.update(&chunk);
+                    if let Some(previous) = this.held.replace(chunk) {
+                        return Poll::Ready(Some(Ok(previous)));
+                    }
+                }
+                Poll::Ready(None) => {
+                    this.finished = true;
+                    let actual = hex::encode(std::mem::take(&mut this.hasher).finalize());
+                    if actual == this.expected {
+                        return Poll::Ready(this.held.take().map(Ok));
+                    }
+                    if let Some(on_mismatch) = this.on_mismatch.take() {
+                        on_mismatch(actual);
+                    }
+                    return Poll::Ready(Some(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "file content does not match its recorded SHA-256",
+                    ))));
+                }
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures_util::TryStreamExt;
 
     #[test]
-    fn test_generate_unique_filename() {
-        let filename = FileService::generate_unique_filename("test.pdf", "application/pdf");
-        assert!(filename.ends_with(".pdf"));
-        assert!(filename.len() > 20); // 確保有日期前綴和 UUID
+    fn test_content_key() {
+        let hash = hex::encode(Sha256::digest(b"hello"));
+        let key = FileService::content_key(FileCategory::ProtocolAttachment, "test.PDF", "application/pdf", &hash);
+        assert_eq!(key, format!("protocols/2c/{}.pdf", hash));
+
+        // 相同內容不論原始檔名皆對應同一路徑
+        let again = FileService::content_key(FileCategory::ProtocolAttachment, "copy.pdf", "application/pdf", &hash);
+        assert_eq!(key, again);
+        assert_eq!(FileService::hash_from_key(&key), Some(hash.as_str()));
+
+        // 舊有非內容定址路徑無法取得雜湊
+        assert_eq!(FileService::hash_from_key("protocols/abc/20260101_x.pdf"), None);
+    }
+
+    #[tokio::test]
+    async fn test_verifying_stream_rejects_tampered_content() {
+        let verify = |data: &'static [u8], expected: String| {
+            let inner: ByteStream = Box::pin(futures_util::stream::iter(
+                data.chunks(2).map(|c| Ok(Bytes::from_static(c))).collect::<Vec<_>>(),
+            ));
+            VerifyingStream {
+                inner,
+                hasher: Sha256::new(),
+                expected,
+                held: None,
+                finished: false,
+                on_mismatch: None,
+            }
+        };
+        let expected = hex::encode(Sha256::digest(b"hello"));
+
+        let ok: Vec<Bytes> = verify(b"hello", expected.clone()).try_collect().await.unwrap();
+        assert_eq!(ok.concat(), b"hello");
+
+        let chunks: Vec<io::Result<Bytes>> = verify(b"hellO", expected).collect().await;
+        assert!(chunks.last().unwrap().is_err());
+        let delivered: usize = chunks.iter().filter_map(|c| c.as_ref().ok()).map(|b| b.len()).sum();
+        assert!(delivered < 5);
     }
 
     #[test]
@@ -300,4 +540,94 @@ mod tests {
         assert!(FileCategory::ProtocolAttachment.allowed_mime_types().contains(&"application/pdf"));
         assert!(FileCategory::PigPhoto.allowed_mime_types().contains(&"image/jpeg"));
     }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_overwriting_tampered_object_raises_alerts(db: PgPool) {
+        crate::test_support::init_test_storage();
+        let user_id = crate::test_support::insert_user(&db, "uploader@example.com", true).await;
+        let content = format!("%PDF-{}", Uuid::new_v4());
+
+        let StoredUpload { result: first, mut tx } =
+            FileService::upload(&db, FileCategory::ProtocolAttachment, "a.pdf", "application/pdf", content.as_bytes())
+                .await
+                .unwrap();
+        sqlx::query(
+            "INSERT INTO attachments (id, root_id, category, file_name, file_path, file_size, mime_type, uploaded_by, content_hash) \
+             VALUES ($1, $1, 'protocol', 'a.pdf', $2, $3, 'application/pdf', $4, $5)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(&first.file_path)
+        .bind(first.file_size)
+        .bind(user_id)
+        .bind(&first.content_hash)
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+        tx.commit().await.unwrap();
+        sqlx::query(
+            "INSERT INTO pig_export_records (id, export_type, export_format, file_path, created_by) \
+             VALUES ($1, 'medical_summary', 'pdf', $2, $3)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(&first.file_path)
+        .bind(user_id)
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let tampered = Bytes::from_static(b"tampered");
+        let body: ByteStream = Box::pin(futures_util::stream::once(async move { Ok(tampered) }));
+        storage().put(&first.file_path, body, 8, "application/pdf").await.unwrap();
+
+        // 重新上傳相同內容：覆寫前為兩筆參照紀錄留下警報，覆寫後內容恢復
+        let again = FileService::upload(&db, FileCategory::ProtocolAttachment, "b.pdf", "application/pdf", content.as_bytes())
+            .await
+            .unwrap();
+        again.tx.commit().await.unwrap();
+        let again = again.result;
+        assert_eq!(again.file_path, first.file_path);
+        assert_eq!(FileService::hash_stored(&first.file_path).await.unwrap().0, first.content_hash);
+
+        let sources: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT context_data->>'source' FROM security_alerts
+            WHERE alert_type = 'attachment_integrity' AND context_data->>'file_path' = $1
+            ORDER BY 1
+            "#,
+        )
+        .bind(&first.file_path)
+        .fetch_all(&db)
+        .await
+        .unwrap();
+        assert_eq!(sources, vec!["attachments", "pig_export_records"]);
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_same_size_tampered_object_is_not_reused(db: PgPool) {
+        crate::test_support::init_test_storage();
+        let content = format!("%PDF-{}", Uuid::new_v4());
+
+        let first = FileService::upload(&db, FileCategory::ProtocolAttachment, "a.pdf", "application/pdf", content.as_bytes())
+            .await
+            .unwrap();
+        first.tx.commit().await.unwrap();
+        let path = first.result.file_path;
+
+        // 竄改內容但維持相同大小
+        let tampered = Bytes::from(content.replace("%PDF-", "%XXX-"));
+        let len = tampered.len() as u64;
+        assert_eq!(len, content.len() as u64);
+        let body: ByteStream = Box::pin(futures_util::stream::once(async move { Ok(tampered) }));
+        storage().put(&path, body, len, "application/pdf").await.unwrap();
+
+        // 重新上傳相同內容時重新比對雜湊並覆寫，儲存內容恢復為上傳內容
+        let again = FileService::upload(&db, FileCategory::ProtocolAttachment, "b.pdf", "application/pdf", content.as_bytes())
+            .await
+            .unwrap();
+        again.tx.commit().await.unwrap();
+        assert_eq!(again.result.file_path, path);
+        assert_eq!(FileService::hash_stored(&path).await.unwrap().0, again.result.content_hash);
+    }
 }
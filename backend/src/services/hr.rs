@@ -13,9 +13,10 @@ use crate::{
         AttendanceCorrectionRequest, AttendanceQuery, AttendanceRecord, AttendanceWithUser,
         BalanceSummary, CompTimeBalanceView, CreateAnnualLeaveRequest,
         CreateLeaveRequest, CreateOvertimeRequest, DashboardCalendarData, ExpiredLeaveReport, LeaveQuery, LeaveRequest,
-        LeaveRequestWithUser, OvertimeQuery, OvertimeRecord, OvertimeWithUser, PaginatedResponse,
+        LeaveApproval, LeaveRequestWithUser, LeaveStatus, OvertimeQuery, OvertimeRecord, OvertimeWithUser, PaginatedResponse,
         TodayLeaveInfo, UpdateLeaveRequest, UpdateOvertimeRequest,
     },
+    services::LeaveApprovalService,
     Result,
 };
 
@@ -519,6 +520,7 @@ impl HrService {
               AND ($4::date IS NULL OR start_date >= $4)
               AND ($5::date IS NULL OR end_date <= $5)
               AND ($6::bool = false OR status::text LIKE 'PENDING%')
+              AND ($7::uuid IS NULL OR current_approver_id = $7)
             "#,
         )
         .bind(query.user_id)
@@ -527,6 +529,7 @@ impl HrService {
         .bind(query.from)
         .bind(query.to)
         .bind(is_pending_approval)
+        .bind(query.approver_id)
         .fetch_one(pool)
         .await?;
 
@@ -549,8 +552,9 @@ impl HrService {
               AND ($4::date IS NULL OR l.start_date >= $4)
               AND ($5::date IS NULL OR l.end_date <= $5)
               AND ($6::bool = false OR l.status::text LIKE 'PENDING%')
+              AND ($7::uuid IS NULL OR l.current_approver_id = $7)
             ORDER BY l.created_at DESC
-            LIMIT $7 OFFSET $8
+            LIMIT $8 OFFSET $9
             "#,
         )
         .bind(query.user_id)
@@ -559,6 +563,7 @@ impl HrService {
         .bind(query.from)
         .bind(query.to)
         .bind(is_pending_approval)
+        .bind(query.approver_id)
         .bind(per_page)
         .bind(offset)
         .fetch_all(pool)
@@ -585,8 +590,9 @@ impl HrService {
             "#,
         )
         .bind(id)
-        .fetch_one(pool)
-        .await?;
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("找不到請假申請".to_string()))?;
 
         Ok(record)
     }
@@ -713,184 +719,53 @@ impl HrService {
     pub async fn submit_leave(
         pool: &PgPool,
         id: Uuid,
-        _current_user: &CurrentUser,
+        current_user: &CurrentUser,
     ) -> Result<LeaveRequest> {
-        sqlx::query(
-            r#"
-            UPDATE leave_requests
-            SET status = 'PENDING_L1'::leave_status, submitted_at = NOW(), updated_at = NOW()
-            WHERE id = $1 AND status = 'DRAFT'::leave_status
-            "#,
-        )
-        .bind(id)
-        .execute(pool)
-        .await?;
+        let leave = Self::get_leave(pool, id, current_user).await?;
+        if leave.status != LeaveStatus::Draft.as_str() {
+            return Err(AppError::Validation("僅草稿狀態的請假可以送出".to_string()));
+        }
 
-        let record = sqlx::query_as::<_, LeaveRequest>(
-            r#"
-            SELECT 
-                id, user_id, proxy_user_id, leave_type::text as leave_type, start_date, end_date,
-                start_time, end_time, total_days, total_hours, reason, supporting_documents,
-                comp_time_source_ids, annual_leave_source_id, is_urgent, is_retroactive,
-                status::text as status, current_approver_id, submitted_at, approved_at,
-                rejected_at, cancelled_at, revoked_at, cancellation_reason, revocation_reason,
-                created_at, updated_at
-            FROM leave_requests WHERE id = $1
-            "#,
-        )
-        .bind(id)
-        .fetch_one(pool)
-        .await?;
+        // 依部門、假別與天數決定審核鏈並指派第一關審核人
+        LeaveApprovalService::submit(pool, &leave).await?;
 
-        Ok(record)
+        Self::get_leave(pool, id, current_user).await
     }
 
     pub async fn approve_leave(
         pool: &PgPool,
         id: Uuid,
-        approver_id: Uuid,
+        current_user: &CurrentUser,
         comments: Option<&str>,
+        password_hash: &str,
     ) -> Result<LeaveRequest> {
-        let current: LeaveRequest = sqlx::query_as(
-            r#"
-            SELECT 
-                id, user_id, proxy_user_id, leave_type::text as leave_type, start_date, end_date,
-                start_time, end_time, total_days, total_hours, reason, supporting_documents,
-                comp_time_source_ids, annual_leave_source_id, is_urgent, is_retroactive,
-                status::text as status, current_approver_id, submitted_at, approved_at,
-                rejected_at, cancelled_at, revoked_at, cancellation_reason, revocation_reason,
-                created_at, updated_at
-            FROM leave_requests WHERE id = $1
-            "#,
-        )
-            .bind(id)
-            .fetch_one(pool)
-            .await?;
-
-        let next_status = match current.status.as_str() {
-            "PENDING_L1" => "APPROVED",
-            "PENDING_L2" => "APPROVED",
-            "PENDING_HR" => "APPROVED",
-            "PENDING_GM" => "APPROVED",
-            _ => return Err(AppError::Validation("無法核准此狀態的請假".to_string())),
-        };
-
-        sqlx::query(
-            r#"
-            INSERT INTO leave_approvals (id, leave_request_id, approver_id, approval_level, action, comments)
-            VALUES ($1, $2, $3, $4, 'APPROVE', $5)
-            "#,
-        )
-        .bind(Uuid::new_v4())
-        .bind(id)
-        .bind(approver_id)
-        .bind(&current.status)
-        .bind(comments)
-        .execute(pool)
-        .await?;
+        let leave = Self::get_leave(pool, id, current_user).await?;
+        LeaveApprovalService::approve(pool, &leave, current_user, comments, password_hash).await?;
 
-        let approved_at = if next_status == "APPROVED" {
-            Some(Utc::now())
-        } else {
-            None
-        };
-
-        sqlx::query(
-            r#"
-            UPDATE leave_requests
-            SET status = $2::leave_status, approved_at = $3, current_approver_id = NULL, updated_at = NOW()
-            WHERE id = $1
-            "#,
-        )
-        .bind(id)
-        .bind(next_status)
-        .bind(approved_at)
-        .execute(pool)
-        .await?;
-
-        let record = sqlx::query_as::<_, LeaveRequest>(
-            r#"
-            SELECT 
-                id, user_id, proxy_user_id, leave_type::text as leave_type, start_date, end_date,
-                start_time, end_time, total_days, total_hours, reason, supporting_documents,
-                comp_time_source_ids, annual_leave_source_id, is_urgent, is_retroactive,
-                status::text as status, current_approver_id, submitted_at, approved_at,
-                rejected_at, cancelled_at, revoked_at, cancellation_reason, revocation_reason,
-                created_at, updated_at
-            FROM leave_requests WHERE id = $1
-            "#,
-        )
-        .bind(id)
-        .fetch_one(pool)
-        .await?;
-
-        Ok(record)
+        Self::get_leave(pool, id, current_user).await
     }
 
     pub async fn reject_leave(
         pool: &PgPool,
         id: Uuid,
-        rejecter_id: Uuid,
+        current_user: &CurrentUser,
         reason: &str,
     ) -> Result<LeaveRequest> {
-        let current: LeaveRequest = sqlx::query_as(
-            r#"
-            SELECT 
-                id, user_id, proxy_user_id, leave_type::text as leave_type, start_date, end_date,
-                start_time, end_time, total_days, total_hours, reason, supporting_documents,
-                comp_time_source_ids, annual_leave_source_id, is_urgent, is_retroactive,
-                status::text as status, current_approver_id, submitted_at, approved_at,
-                rejected_at, cancelled_at, revoked_at, cancellation_reason, revocation_reason,
-                created_at, updated_at
-            FROM leave_requests WHERE id = $1
-            "#,
-        )
-            .bind(id)
-            .fetch_one(pool)
-            .await?;
-
-        sqlx::query(
-            r#"
-            INSERT INTO leave_approvals (id, leave_request_id, approver_id, approval_level, action, comments)
-            VALUES ($1, $2, $3, $4, 'REJECT', $5)
-            "#,
-        )
-        .bind(Uuid::new_v4())
-        .bind(id)
-        .bind(rejecter_id)
-        .bind(&current.status)
-        .bind(reason)
-        .execute(pool)
-        .await?;
+        let leave = Self::get_leave(pool, id, current_user).await?;
+        LeaveApprovalService::reject(pool, &leave, current_user, reason).await?;
 
-        sqlx::query(
-            r#"
-            UPDATE leave_requests
-            SET status = 'REJECTED'::leave_status, rejected_at = NOW(), updated_at = NOW()
-            WHERE id = $1
-            "#,
-        )
-        .bind(id)
-        .execute(pool)
-        .await?;
+        Self::get_leave(pool, id, current_user).await
+    }
 
-        let record = sqlx::query_as::<_, LeaveRequest>(
-            r#"
-            SELECT 
-                id, user_id, proxy_user_id, leave_type::text as leave_type, start_date, end_date,
-                start_time, end_time, total_days, total_hours, reason, supporting_documents,
-                comp_time_source_ids, annual_leave_source_id, is_urgent, is_retroactive,
-                status::text as status, current_approver_id, submitted_at, approved_at,
-                rejected_at, cancelled_at, revoked_at, cancellation_reason, revocation_reason,
-                created_at, updated_at
-            FROM leave_requests WHERE id = $1
-            "#,
+    pub async fn list_leave_approvals(pool: &PgPool, id: Uuid) -> Result<Vec<LeaveApproval>> {
+        let approvals = sqlx::query_as::<_, LeaveApproval>(
+            "SELECT * FROM leave_approvals WHERE leave_request_id = $1 ORDER BY created_at"
         )
         .bind(id)
-        .fetch_one(pool)
+        .fetch_all(pool)
         .await?;
 
-        Ok(record)
+        Ok(approvals)
     }
 
     pub async fn cancel_leave(
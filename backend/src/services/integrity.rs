@@ -0,0 +1,235 @@
+// 附件完整性
+// 以上傳時記錄的 SHA-256 驗證附件未遭竄改，異常時建立安全警報
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{services::FileService, AppError, Result};
+
+/// 完整性異常
+#[derive(Debug, Clone)]
+pub enum IntegrityProblem {
+    /// 儲存後端找不到檔案
+    Missing,
+    /// 內容雜湊與上傳時不同
+    Tampered { expected: String, actual: String },
+}
+
+/// 完整性掃描結果
+#[derive(Debug, Default, Serialize)]
+pub struct IntegrityScanSummary {
+    pub checked: u64,
+    /// 既有附件首次掃描補上雜湊
+    pub backfilled: u64,
+    pub missing: u64,
+    pub tampered: u64,
+}
+
+pub struct IntegrityService;
+
+impl IntegrityService {
+    /// 建立附件完整性安全警報；同一附件已有未結案警報時不重複建立
+    pub async fn raise_alert(
+        pool: &PgPool,
+        attachment_id: Uuid,
+        file_path: &str,
+        problem: &IntegrityProblem,
+        user_id: Option<Uuid>,
+    ) -> Result<()> {
+        Self::raise_record_alert(pool, "attachments", attachment_id, file_path, problem, user_id).await
+    }
+
+    /// 為所有參照此檔案的附件、動物紀錄附件與匯出記錄建立完整性安全警報（例如檔案即將被覆寫前保留竄改證據）
+    pub async fn raise_path_alerts(pool: &PgPool, file_path: &str, problem: &IntegrityProblem) -> Result<()> {
+        let records: Vec<(String, Uuid)> = sqlx::query_as(
+            r#"
+            SELECT 'attachments', id FROM attachments WHERE file_path = $1 AND purged_at IS NULL
+            UNION ALL
+            SELECT 'pig_record_attachments', id FROM pig_record_attachments WHERE file_path = $1
+            UNION ALL
+            SELECT 'pig_export_records', id FROM pig_export_records WHERE file_path = $1
+            "#,
+        )
+        .bind(file_path)
+        .fetch_all(pool)
+        .await?;
+
+        for (source, id) in records {
+            Self::raise_record_alert(pool, &source, id, file_path, problem, None).await?;
+        }
+        Ok(())
+    }
+
+    /// 建立完整性安全警報，`source` 為參照該檔案的資料表
+    async fn raise_record_alert(
+        pool: &PgPool,
+        source: &str,
+        attachment_id: Uuid,
+        file_path: &str,
+        problem: &IntegrityProblem,
+        user_id: Option<Uuid>,
+    ) -> Result<()> {
+        let (title, description, context) = match problem {
+            IntegrityProblem::Missing => (
+                "附件檔案遺失",
+                format!("附件 {} 的檔案 {} 不存在於儲存空間", attachment_id, file_path),
+                serde_json::json!({
+                    "attachment_id": attachment_id,
+                    "source": source,
+                    "file_path": file_path,
+                    "problem": "missing",
+                }),
+            ),
+            IntegrityProblem::Tampered { expected, actual } => (
+                "附件內容遭竄改",
+                format!("附件 {} 的 SHA-256 與上傳時記錄不符", attachment_id),
+                serde_json::json!({
+                    "attachment_id": attachment_id,
+                    "source": source,
+                    "file_path": file_path,
+                    "problem": "tampered",
+                    "expected_hash": expected,
+                    "actual_hash": actual,
+                }),
+            ),
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO security_alerts (alert_type, severity, title, description, user_id, context_data, status)
+            SELECT 'attachment_integrity', 'critical', $1, $2, $3, $4, 'open'
+            WHERE NOT EXISTS (
+                SELECT 1 FROM security_alerts
+                WHERE alert_type = 'attachment_integrity'
+                  AND context_data->>'attachment_id' = $5
+                  AND status IN ('open', 'acknowledged', 'investigating')
+            )
+            "#,
+        )
+        .bind(title)
+        .bind(description)
+        .bind(user_id)
+        .bind(context)
+        .bind(attachment_id.to_string())
+        .execute(pool)
+        .await?;
+
+        tracing::error!("[Integrity] {}: attachment {} ({})", title, attachment_id, file_path);
+        Ok(())
+    }
+
+    /// 重新計算所有附件、動物紀錄附件與匯出記錄檔案的雜湊並與記錄比對
+    ///
+    /// 內容去重後多筆紀錄可能共用同一檔案，每個檔案只讀取一次。
+    /// 動物紀錄附件與匯出記錄未另存雜湊，以內容定址路徑中的雜湊比對。
+    pub async fn scan(pool: &PgPool) -> Result<IntegrityScanSummary> {
+        let attachments: Vec<(Uuid, String, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT id, file_path, content_hash FROM attachments
+            WHERE purged_at IS NULL
+            ORDER BY integrity_checked_at NULLS FIRST, created_at
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut summary = IntegrityScanSummary::default();
+        let mut hashes: HashMap<String, Option<String>> = HashMap::new();
+
+        for (id, file_path, recorded_hash) in attachments {
+            let Some(actual) = Self::cached_hash(&mut hashes, &file_path).await else {
+                continue;
+            };
+            summary.checked += 1;
+
+            let problem = match (actual, recorded_hash) {
+                (None, _) => Some(IntegrityProblem::Missing),
+                (Some(actual), None) => {
+                    sqlx::query("UPDATE attachments SET content_hash = $2 WHERE id = $1")
+                        .bind(id)
+                        .bind(&actual)
+                        .execute(pool)
+                        .await?;
+                    summary.backfilled += 1;
+                    None
+                }
+                (Some(actual), Some(expected)) if actual != expected => {
+                    Some(IntegrityProblem::Tampered { expected, actual })
+                }
+                _ => None,
+            };
+
+            match &problem {
+                Some(IntegrityProblem::Missing) => summary.missing += 1,
+                Some(IntegrityProblem::Tampered { .. }) => summary.tampered += 1,
+                None => {}
+            }
+            if let Some(problem) = problem {
+                Self::raise_alert(pool, id, &file_path, &problem, None).await?;
+            }
+
+            sqlx::query("UPDATE attachments SET integrity_checked_at = NOW() WHERE id = $1")
+                .bind(id)
+                .execute(pool)
+                .await?;
+        }
+
+        let records: Vec<(String, Uuid, String)> = sqlx::query_as(
+            r#"
+            SELECT 'pig_record_attachments', id, file_path FROM pig_record_attachments
+            UNION ALL
+            SELECT 'pig_export_records', id, file_path FROM pig_export_records
+            WHERE file_path IS NOT NULL
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        for (source, id, file_path) in records {
+            let Some(actual) = Self::cached_hash(&mut hashes, &file_path).await else {
+                continue;
+            };
+            summary.checked += 1;
+
+            let problem = match (actual, FileService::hash_from_key(&file_path)) {
+                (None, _) => Some(IntegrityProblem::Missing),
+                (Some(actual), Some(expected)) if actual != expected => Some(IntegrityProblem::Tampered {
+                    expected: expected.to_string(),
+                    actual,
+                }),
+                _ => None,
+            };
+
+            match &problem {
+                Some(IntegrityProblem::Missing) => summary.missing += 1,
+                Some(IntegrityProblem::Tampered { .. }) => summary.tampered += 1,
+                None => {}
+            }
+            if let Some(problem) = problem {
+                Self::raise_record_alert(pool, &source, id, &file_path, &problem, None).await?;
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// 取得檔案實際雜湊（同一檔案只讀取一次）；`Some(None)` 表示檔案不存在，讀取失敗時回傳 None
+    async fn cached_hash(hashes: &mut HashMap<String, Option<String>>, file_path: &str) -> Option<Option<String>> {
+        if let Some(actual) = hashes.get(file_path) {
+            return Some(actual.clone());
+        }
+        let actual = match FileService::hash_stored(file_path).await {
+            Ok((hash, _)) => Some(hash),
+            Err(AppError::NotFound(_)) => None,
+            Err(e) => {
+                tracing::warn!("[Integrity] Failed to read {}: {}", file_path, e);
+                return None;
+            }
+        };
+        hashes.insert(file_path.to_string(), actual.clone());
+        Some(actual)
+    }
+}
@@ -0,0 +1,701 @@
+// Leave Approval Routing
+// 依部門組織、假別與天數決定請假審核鏈，處理代理審核與逾時上呈
+
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    middleware::CurrentUser,
+    models::{LeaveRequest, LeaveStatus},
+    services::{SignatureMeaning, SignatureService, SignatureType},
+    Result,
+};
+
+/// 代理審核最多追溯層數（避免代理人互相代理造成循環）
+const MAX_DELEGATION_HOPS: usize = 3;
+
+/// 下一個審核關卡
+#[derive(Debug, Clone, Copy)]
+struct RouteTarget {
+    level: LeaveStatus,
+    approver_id: Uuid,
+    delegated_from_id: Option<Uuid>,
+}
+
+/// 逾時待審的請假
+#[derive(sqlx::FromRow)]
+struct OverdueLeave {
+    id: Uuid,
+    user_id: Uuid,
+    leave_type: String,
+    total_days: Decimal,
+    status: String,
+    current_approver_id: Uuid,
+    approval_chain: Option<Vec<String>>,
+}
+
+pub struct LeaveApprovalService;
+
+impl LeaveApprovalService {
+    /// 執行秘書或具 hr.leave.manage 權限者可代為審核任何待審請假
+    pub fn can_override(user: &CurrentUser) -> bool {
+        user.roles.iter().any(|r| r == "IACUC_STAFF") || user.has_permission("hr.leave.manage")
+    }
+
+    /// 送出請假：決定審核鏈並指派第一關審核人
+    pub async fn submit(pool: &PgPool, leave: &LeaveRequest) -> Result<()> {
+        let chain = approval_levels(&leave.leave_type, leave.total_days);
+        let target = Self::find_next(pool, leave.user_id, &chain, 0, &[leave.user_id])
+            .await?
+            .ok_or_else(|| {
+                AppError::BusinessRule("找不到可審核此請假的主管或行政人員，請確認部門主管設定".to_string())
+            })?;
+
+        let chain_codes: Vec<&str> = chain.iter().map(|s| s.as_str()).collect();
+
+        let mut tx = pool.begin().await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE leave_requests
+            SET approval_chain = $2, submitted_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND status = 'DRAFT'::leave_status
+            "#,
+        )
+        .bind(leave.id)
+        .bind(&chain_codes)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::Conflict("請假狀態已變更，請重新整理".to_string()));
+        }
+
+        Self::assign(&mut tx, leave.id, LeaveStatus::Draft, target).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// 核准目前關卡並送往下一關；已無下一關時核准完成
+    pub async fn approve(
+        pool: &PgPool,
+        leave: &LeaveRequest,
+        current_user: &CurrentUser,
+        comments: Option<&str>,
+        password_hash: &str,
+    ) -> Result<()> {
+        let level = LeaveStatus::parse_pending(&leave.status)
+            .ok_or_else(|| AppError::Validation("無法核准此狀態的請假".to_string()))?;
+        Self::ensure_current_approver(leave, current_user)?;
+
+        let chain = Self::load_chain(pool, leave).await?;
+        let from = chain.iter().position(|s| *s == level).map_or(chain.len(), |i| i + 1);
+
+        let mut exclude = Self::approved_by(pool, leave.id).await?;
+        exclude.push(leave.user_id);
+        exclude.push(current_user.id);
+        let next = Self::find_next(pool, leave.user_id, &chain, from, &exclude).await?;
+
+        let mut tx = pool.begin().await?;
+        Self::record_action(&mut tx, leave.id, current_user.id, level, "APPROVE", comments).await?;
+
+        let result = match next {
+            Some(target) => Self::assign(&mut tx, leave.id, level, target).await?,
+            None => sqlx::query(
+                r#"
+                UPDATE leave_requests
+                SET status = 'APPROVED'::leave_status, approved_at = NOW(), current_approver_id = NULL,
+                    delegated_from_id = NULL, approver_assigned_at = NULL, updated_at = NOW()
+                WHERE id = $1 AND status = $2::leave_status
+                "#,
+            )
+            .bind(leave.id)
+            .bind(level.as_str())
+            .execute(&mut *tx)
+            .await?
+            .rows_affected(),
+        };
+
+        if result == 0 {
+            return Err(AppError::Conflict("請假狀態已變更，請重新整理".to_string()));
+        }
+
+        // 每一關核准皆需電子簽章
+        SignatureService::sign_entity(
+            &mut tx,
+            "leave",
+            &leave.id.to_string(),
+            current_user.id,
+            password_hash,
+            SignatureType::Approve,
+            SignatureMeaning::Approval,
+        ).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// 駁回請假
+    pub async fn reject(
+        pool: &PgPool,
+        leave: &LeaveRequest,
+        current_user: &CurrentUser,
+        reason: &str,
+    ) -> Result<()> {
+        let level = LeaveStatus::parse_pending(&leave.status)
+            .ok_or_else(|| AppError::Validation("無法駁回此狀態的請假".to_string()))?;
+        Self::ensure_current_approver(leave, current_user)?;
+
+        let mut tx = pool.begin().await?;
+        Self::record_action(&mut tx, leave.id, current_user.id, level, "REJECT", Some(reason)).await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE leave_requests
+            SET status = 'REJECTED'::leave_status, rejected_at = NOW(), current_approver_id = NULL,
+                delegated_from_id = NULL, approver_assigned_at = NULL, updated_at = NOW()
+            WHERE id = $1 AND status = $2::leave_status
+            "#,
+        )
+        .bind(leave.id)
+        .bind(level.as_str())
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::Conflict("請假狀態已變更，請重新整理".to_string()));
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// 逾時未審核的請假自動上呈：送往審核鏈下一關，已是最後一關則改由審核人的上級主管審核
+    pub async fn escalate_overdue(pool: &PgPool, timeout_hours: i64) -> Result<i64> {
+        let overdue = sqlx::query_as::<_, OverdueLeave>(
+            r#"
+            SELECT id, user_id, leave_type::text as leave_type, total_days, status::text as status,
+                   current_approver_id, approval_chain
+            FROM leave_requests
+            WHERE status IN ('PENDING_L1', 'PENDING_L2', 'PENDING_HR', 'PENDING_GM')
+              AND current_approver_id IS NOT NULL
+              AND approver_assigned_at < NOW() - make_interval(hours => $1)
+            ORDER BY approver_assigned_at
+            "#,
+        )
+        .bind(timeout_hours as i32)
+        .fetch_all(pool)
+        .await?;
+
+        let mut escalated = 0;
+        for leave in overdue {
+            let Some(level) = LeaveStatus::parse_pending(&leave.status) else {
+                continue;
+            };
+            let chain = match &leave.approval_chain {
+                Some(codes) => parse_chain(codes),
+                None => approval_levels(&leave.leave_type, leave.total_days),
+            };
+            let from = chain.iter().position(|s| *s == level).map_or(chain.len(), |i| i + 1);
+
+            let mut exclude = Self::approved_by(pool, leave.id).await?;
+            exclude.push(leave.user_id);
+            exclude.push(leave.current_approver_id);
+
+            let mut target = Self::find_next(pool, leave.user_id, &chain, from, &exclude).await?;
+            if target.is_none() {
+                // 已是最後一關：改由目前審核人的上級主管審核
+                let superior = Self::manager_chain(pool, leave.current_approver_id)
+                    .await?
+                    .into_iter()
+                    .find(|id| !exclude.contains(id));
+                if let Some(superior) = superior {
+                    let (approver_id, delegated_from_id) =
+                        Self::apply_delegation(pool, superior, &exclude).await?;
+                    target = Some(RouteTarget { level, approver_id, delegated_from_id });
+                }
+            }
+
+            let Some(target) = target else {
+                tracing::warn!("Leave {} is overdue but has no approver to escalate to", leave.id);
+                continue;
+            };
+
+            let mut tx = pool.begin().await?;
+            let comments = format!("逾時 {} 小時未審核，自動上呈", timeout_hours);
+            Self::record_action(&mut tx, leave.id, leave.current_approver_id, level, "ESCALATE", Some(&comments)).await?;
+            if Self::assign(&mut tx, leave.id, level, target).await? == 0 {
+                continue;
+            }
+            tx.commit().await?;
+            escalated += 1;
+        }
+
+        Ok(escalated)
+    }
+
+    fn ensure_current_approver(leave: &LeaveRequest, current_user: &CurrentUser) -> Result<()> {
+        if leave.current_approver_id == Some(current_user.id) || Self::can_override(current_user) {
+            return Ok(());
+        }
+        Err(AppError::Forbidden("您不是此請假目前的審核人".to_string()))
+    }
+
+    async fn load_chain(pool: &PgPool, leave: &LeaveRequest) -> Result<Vec<LeaveStatus>> {
+        let codes: Option<Vec<String>> =
+            sqlx::query_scalar("SELECT approval_chain FROM leave_requests WHERE id = $1")
+                .bind(leave.id)
+                .fetch_one(pool)
+                .await?;
+
+        // 路由上線前送出的請假沒有審核鏈，依現行規則補算
+        Ok(match codes {
+            Some(codes) => parse_chain(&codes),
+            None => approval_levels(&leave.leave_type, leave.total_days),
+        })
+    }
+
+    /// 已核准過此請假的審核人（不重複審核）
+    async fn approved_by(pool: &PgPool, leave_id: Uuid) -> Result<Vec<Uuid>> {
+        let approvers: Vec<Uuid> = sqlx::query_scalar(
+            "SELECT approver_id FROM leave_approvals WHERE leave_request_id = $1 AND action = 'APPROVE'",
+        )
+        .bind(leave_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(approvers)
+    }
+
+    /// 從審核鏈第 from 關起，找出第一個有審核人的關卡
+    async fn find_next(
+        pool: &PgPool,
+        requester_id: Uuid,
+        chain: &[LeaveStatus],
+        from: usize,
+        exclude: &[Uuid],
+    ) -> Result<Option<RouteTarget>> {
+        let managers = Self::manager_chain(pool, requester_id).await?;
+
+        for &level in chain.iter().skip(from) {
+            let candidate = match level {
+                LeaveStatus::PendingL1 => managers.first().copied(),
+                LeaveStatus::PendingL2 => managers.get(1).copied(),
+                LeaveStatus::PendingGm => managers.last().copied(),
+                LeaveStatus::PendingHr => Self::hr_approver(pool, exclude).await?,
+                _ => None,
+            };
+
+            let Some(approver) = candidate.filter(|id| !exclude.contains(id)) else {
+                continue;
+            };
+            let (approver_id, delegated_from_id) = Self::apply_delegation(pool, approver, exclude).await?;
+            return Ok(Some(RouteTarget { level, approver_id, delegated_from_id }));
+        }
+
+        Ok(None)
+    }
+
+    /// 使用者所屬部門往上的各級主管（由近至遠，不含本人）
+    async fn manager_chain(pool: &PgPool, user_id: Uuid) -> Result<Vec<Uuid>> {
+        let rows: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            WITH RECURSIVE dept_chain AS (
+                SELECT d.id, d.parent_id, d.manager_id, COALESCE(d.is_active, true) AS is_active, 0 AS depth
+                FROM departments d
+                JOIN users u ON u.department_id = d.id
+                WHERE u.id = $1
+                UNION ALL
+                SELECT d.id, d.parent_id, d.manager_id, COALESCE(d.is_active, true), dc.depth + 1
+                FROM departments d
+                JOIN dept_chain dc ON d.id = dc.parent_id
+                WHERE dc.depth < 20
+            )
+            SELECT dc.manager_id
+            FROM dept_chain dc
+            JOIN users m ON m.id = dc.manager_id
+            WHERE dc.is_active AND m.is_active = true AND dc.manager_id <> $1
+            ORDER BY dc.depth
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        let mut managers: Vec<Uuid> = Vec::with_capacity(rows.len());
+        for id in rows {
+            if !managers.contains(&id) {
+                managers.push(id);
+            }
+        }
+        Ok(managers)
+    }
+
+    /// 行政審核人：執行秘書
+    async fn hr_approver(pool: &PgPool, exclude: &[Uuid]) -> Result<Option<Uuid>> {
+        let approver: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT u.id
+            FROM users u
+            JOIN user_roles ur ON u.id = ur.user_id
+            JOIN roles r ON ur.role_id = r.id
+            WHERE r.code = 'IACUC_STAFF' AND u.is_active = true AND u.id <> ALL($1)
+            ORDER BY u.created_at
+            LIMIT 1
+            "#,
+        )
+        .bind(exclude)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(approver)
+    }
+
+    /// 審核人今日請假中且有設定代理人時，改由代理人審核
+    async fn apply_delegation(
+        pool: &PgPool,
+        approver_id: Uuid,
+        exclude: &[Uuid],
+    ) -> Result<(Uuid, Option<Uuid>)> {
+        let mut current = approver_id;
+        let mut visited = vec![approver_id];
+
+        for _ in 0..MAX_DELEGATION_HOPS {
+            let proxy: Option<Uuid> = sqlx::query_scalar(
+                r#"
+                SELECT l.proxy_user_id
+                FROM leave_requests l
+                JOIN users p ON p.id = l.proxy_user_id
+                WHERE l.user_id = $1
+                  AND l.status = 'APPROVED'::leave_status
+                  AND CURRENT_DATE BETWEEN l.start_date AND l.end_date
+                  AND p.is_active = true
+                ORDER BY l.start_date DESC
+                LIMIT 1
+                "#,
+            )
+            .bind(current)
+            .fetch_optional(pool)
+            .await?;
+
+            match proxy {
+                Some(proxy) if !visited.contains(&proxy) && !exclude.contains(&proxy) => {
+                    visited.push(proxy);
+                    current = proxy;
+                }
+                _ => break,
+            }
+        }
+
+        let delegated_from = (current != approver_id).then_some(approver_id);
+        Ok((current, delegated_from))
+    }
+
+    /// 將請假自 `from` 狀態指派至下一審核關卡；回傳受影響筆數（0 表示請假狀態已變更）
+    async fn assign(
+        tx: &mut Transaction<'_, Postgres>,
+        leave_id: Uuid,
+        from: LeaveStatus,
+        target: RouteTarget,
+    ) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE leave_requests
+            SET status = $2::leave_status, current_approver_id = $3, delegated_from_id = $4,
+                approver_assigned_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND status = $5::leave_status
+            "#,
+        )
+        .bind(leave_id)
+        .bind(target.level.as_str())
+        .bind(target.approver_id)
+        .bind(target.delegated_from_id)
+        .bind(from.as_str())
+        .execute(&mut **tx)
+        .await?;
+
+        if let Some(original) = target.delegated_from_id {
+            Self::record_action(tx, leave_id, original, target.level, "DELEGATE", Some("審核人請假中，由代理人審核")).await?;
+        }
+
+        Ok(result.rows_affected())
+    }
+
+    async fn record_action(
+        tx: &mut Transaction<'_, Postgres>,
+        leave_id: Uuid,
+        approver_id: Uuid,
+        level: LeaveStatus,
+        action: &str,
+        comments: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO leave_approvals (id, leave_request_id, approver_id, approval_level, action, comments)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(leave_id)
+        .bind(approver_id)
+        .bind(level.as_str())
+        .bind(action)
+        .bind(comments)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+}
+
+fn parse_chain(codes: &[String]) -> Vec<LeaveStatus> {
+    codes.iter().filter_map(|c| LeaveStatus::parse_pending(c)).collect()
+}
+
+/// 依假別與天數決定審核層級：
+/// - 1 天內的病假、生理假、事假、特休、補休：直屬主管（L1）
+/// - 1 天內的其他假別：L1 → 行政
+/// - 3 天內：L1 → L2 → 行政
+/// - 超過 3 天：L1 → L2 → 行政 → 總經理
+pub fn approval_levels(leave_type: &str, total_days: Decimal) -> Vec<LeaveStatus> {
+    let short_term = matches!(
+        leave_type,
+        "SICK" | "MENSTRUAL" | "PERSONAL" | "ANNUAL" | "COMPENSATORY"
+    );
+
+    if total_days <= Decimal::ONE {
+        if short_term {
+            vec![LeaveStatus::PendingL1]
+        } else {
+            vec![LeaveStatus::PendingL1, LeaveStatus::PendingHr]
+        }
+    } else if total_days <= Decimal::from(3) {
+        vec![LeaveStatus::PendingL1, LeaveStatus::PendingL2, LeaveStatus::PendingHr]
+    } else {
+        LeaveStatus::pending_levels().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approval_levels_by_type_and_duration() {
+        assert_eq!(approval_levels("SICK", Decimal::ONE), vec![LeaveStatus::PendingL1]);
+        assert_eq!(
+            approval_levels("MARRIAGE", Decimal::ONE),
+            vec![LeaveStatus::PendingL1, LeaveStatus::PendingHr]
+        );
+        assert_eq!(approval_levels("SICK", Decimal::from(2)).len(), 3);
+        assert_eq!(
+            approval_levels("ANNUAL", Decimal::new(35, 1)).last(),
+            Some(&LeaveStatus::PendingGm)
+        );
+    }
+
+    use crate::{
+        services::HrService,
+        test_support::{current_user, insert_user},
+    };
+
+    /// 部門階層：總部（總經理）→ 研究部（二級主管）→ 實驗組（直屬主管），申請人屬實驗組；
+    /// 行政審核由執行秘書負責
+    struct Org {
+        requester: Uuid,
+        l1: Uuid,
+        l2: Uuid,
+        hr: Uuid,
+        gm: Uuid,
+    }
+
+    async fn insert_department(db: &PgPool, code: &str, parent_id: Option<Uuid>, manager_id: Uuid) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO departments (id, code, name, parent_id, manager_id) VALUES ($1, $2, $2, $3, $4) RETURNING id",
+        )
+        .bind(Uuid::new_v4())
+        .bind(code)
+        .bind(parent_id)
+        .bind(manager_id)
+        .fetch_one(db)
+        .await
+        .unwrap()
+    }
+
+    async fn set_department(db: &PgPool, user_id: Uuid, department_id: Uuid) {
+        sqlx::query("UPDATE users SET department_id = $2 WHERE id = $1")
+            .bind(user_id)
+            .bind(department_id)
+            .execute(db)
+            .await
+            .unwrap();
+    }
+
+    async fn setup_org(db: &PgPool) -> Org {
+        let requester = insert_user(db, "requester@example.com", true).await;
+        let l1 = insert_user(db, "team-lead@example.com", true).await;
+        let l2 = insert_user(db, "division-head@example.com", true).await;
+        let gm = insert_user(db, "gm@example.com", true).await;
+        let hr = insert_user(db, "iacuc-staff@example.com", true).await;
+        sqlx::query("INSERT INTO user_roles (user_id, role_id) SELECT $1, id FROM roles WHERE code = 'IACUC_STAFF'")
+            .bind(hr)
+            .execute(db)
+            .await
+            .unwrap();
+
+        let head_office = insert_department(db, "TEST-HQ", None, gm).await;
+        let division = insert_department(db, "TEST-DIV", Some(head_office), l2).await;
+        let team = insert_department(db, "TEST-TEAM", Some(division), l1).await;
+        set_department(db, requester, team).await;
+        set_department(db, l1, division).await;
+        set_department(db, l2, head_office).await;
+
+        Org { requester, l1, l2, hr, gm }
+    }
+
+    /// 建立請假；`days_from_today` 為開始日相對今日的天數
+    async fn insert_leave(
+        db: &PgPool,
+        user_id: Uuid,
+        leave_type: &str,
+        total_days: i64,
+        days_from_today: i32,
+        proxy_user_id: Option<Uuid>,
+        status: &str,
+    ) -> Uuid {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO leave_requests (id, user_id, proxy_user_id, leave_type, start_date, end_date, total_days, reason, status)
+            VALUES ($1, $2, $3, $4::leave_type, CURRENT_DATE + $5, CURRENT_DATE + $5 + $6::int - 1, $6, '測試', $7::leave_status)
+            RETURNING id
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(proxy_user_id)
+        .bind(leave_type)
+        .bind(days_from_today)
+        .bind(total_days as i32)
+        .bind(status)
+        .fetch_one(db)
+        .await
+        .unwrap()
+    }
+
+    async fn load(db: &PgPool, id: Uuid) -> LeaveRequest {
+        let viewer = current_user(Uuid::new_v4(), "viewer@example.com", &[], &[]);
+        HrService::get_leave(db, id, &viewer).await.unwrap()
+    }
+
+    async fn approve_as(db: &PgPool, leave: &LeaveRequest, approver_id: Uuid) -> Result<()> {
+        let approver = current_user(approver_id, "approver@example.com", &[], &[]);
+        LeaveApprovalService::approve(db, leave, &approver, None, "x").await
+    }
+
+    async fn actions(db: &PgPool, leave_id: Uuid) -> Vec<(String, Uuid)> {
+        sqlx::query_as(
+            "SELECT action, approver_id FROM leave_approvals WHERE leave_request_id = $1 ORDER BY created_at, action",
+        )
+        .bind(leave_id)
+        .fetch_all(db)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_long_leave_routes_through_full_chain(db: PgPool) {
+        let org = setup_org(&db).await;
+        let leave_id = insert_leave(&db, org.requester, "ANNUAL", 5, 7, None, "DRAFT").await;
+
+        LeaveApprovalService::submit(&db, &load(&db, leave_id).await).await.unwrap();
+        let mut leave = load(&db, leave_id).await;
+        assert_eq!((leave.status.as_str(), leave.current_approver_id), ("PENDING_L1", Some(org.l1)));
+
+        approve_as(&db, &leave, org.l1).await.unwrap();
+        leave = load(&db, leave_id).await;
+        assert_eq!((leave.status.as_str(), leave.current_approver_id), ("PENDING_L2", Some(org.l2)));
+
+        approve_as(&db, &leave, org.l2).await.unwrap();
+        leave = load(&db, leave_id).await;
+        assert_eq!((leave.status.as_str(), leave.current_approver_id), ("PENDING_HR", Some(org.hr)));
+
+        approve_as(&db, &leave, org.hr).await.unwrap();
+        leave = load(&db, leave_id).await;
+        assert_eq!((leave.status.as_str(), leave.current_approver_id), ("PENDING_GM", Some(org.gm)));
+
+        approve_as(&db, &leave, org.gm).await.unwrap();
+        let approved = load(&db, leave_id).await;
+        assert_eq!((approved.status.as_str(), approved.current_approver_id), ("APPROVED", None));
+
+        // 以過期的請假資料重複核准最後一關會被拒絕
+        assert!(matches!(approve_as(&db, &leave, org.gm).await, Err(AppError::Conflict(_))));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_stale_approval_does_not_skip_a_level(db: PgPool) {
+        let org = setup_org(&db).await;
+        let leave_id = insert_leave(&db, org.requester, "ANNUAL", 2, 7, None, "DRAFT").await;
+        LeaveApprovalService::submit(&db, &load(&db, leave_id).await).await.unwrap();
+
+        let stale = load(&db, leave_id).await;
+        approve_as(&db, &stale, org.l1).await.unwrap();
+
+        // 仍停在 L1 的舊資料不可再把請假送往下一關
+        assert!(matches!(approve_as(&db, &stale, org.l1).await, Err(AppError::Conflict(_))));
+        let leave = load(&db, leave_id).await;
+        assert_eq!((leave.status.as_str(), leave.current_approver_id), ("PENDING_L2", Some(org.l2)));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_absent_approver_is_delegated_to_proxy(db: PgPool) {
+        let org = setup_org(&db).await;
+        let proxy = insert_user(&db, "proxy@example.com", true).await;
+        // 二級主管今日請假中，由代理人審核
+        insert_leave(&db, org.l2, "PERSONAL", 1, 0, Some(proxy), "APPROVED").await;
+
+        let leave_id = insert_leave(&db, org.requester, "ANNUAL", 2, 7, None, "DRAFT").await;
+        LeaveApprovalService::submit(&db, &load(&db, leave_id).await).await.unwrap();
+        approve_as(&db, &load(&db, leave_id).await, org.l1).await.unwrap();
+
+        let leave = load(&db, leave_id).await;
+        assert_eq!((leave.status.as_str(), leave.current_approver_id), ("PENDING_L2", Some(proxy)));
+        let delegated_from: Option<Uuid> =
+            sqlx::query_scalar("SELECT delegated_from_id FROM leave_requests WHERE id = $1")
+                .bind(leave_id)
+                .fetch_one(&db)
+                .await
+                .unwrap();
+        assert_eq!(delegated_from, Some(org.l2));
+        assert!(actions(&db, leave_id).await.contains(&("DELEGATE".to_string(), org.l2)));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_escalate_overdue_to_superior(db: PgPool) {
+        let org = setup_org(&db).await;
+        let leave_id = insert_leave(&db, org.requester, "SICK", 1, 7, None, "DRAFT").await;
+        LeaveApprovalService::submit(&db, &load(&db, leave_id).await).await.unwrap();
+
+        assert_eq!(LeaveApprovalService::escalate_overdue(&db, 24).await.unwrap(), 0);
+
+        sqlx::query("UPDATE leave_requests SET approver_assigned_at = NOW() - INTERVAL '25 hours' WHERE id = $1")
+            .bind(leave_id)
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(LeaveApprovalService::escalate_overdue(&db, 24).await.unwrap(), 1);
+
+        // 審核鏈僅有 L1：改由直屬主管的上級審核，關卡不變
+        let leave = load(&db, leave_id).await;
+        assert_eq!((leave.status.as_str(), leave.current_approver_id), ("PENDING_L1", Some(org.l2)));
+        assert!(actions(&db, leave_id).await.contains(&("ESCALATE".to_string(), org.l1)));
+
+        // 重新指派後重新計時
+        assert_eq!(LeaveApprovalService::escalate_overdue(&db, 24).await.unwrap(), 0);
+    }
+}
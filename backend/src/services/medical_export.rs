@@ -0,0 +1,471 @@
+// 豬隻醫療資料匯出服務
+// 後端產生 PDF / Excel 檔案並存檔，匯出記錄的 file_path 即為實際提供給使用者的檔案（GLP 留存）
+
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        ExportFormat, ExportType, Pig, PigBreed, PigExportRecord, PigObservation, PigSacrifice, PigSurgery,
+        PigVaccination, PigWeight, RecordType,
+    },
+    services::{
+        storage::StoredObject, AnimalService, FileCategory, FileService, IntegrityProblem, IntegrityService,
+        PdfService,
+    },
+    AppError, Result,
+};
+
+/// 匯出表格（PDF 與 Excel 共用的資料結構）
+#[derive(Debug, Clone)]
+pub struct ExportTable {
+    pub title: String,
+    pub headers: Vec<&'static str>,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// 匯出完成的檔案
+#[derive(Debug, Clone)]
+pub struct ExportFile {
+    pub record: PigExportRecord,
+    pub file_name: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// 重新下載的匯出檔案（內容以串流提供）
+pub struct ExportDownload {
+    pub record: PigExportRecord,
+    pub file_name: String,
+    pub mime_type: String,
+    pub object: StoredObject,
+}
+
+/// 單隻豬的醫療資料
+struct PigMedicalData {
+    pig: Pig,
+    observations: Vec<PigObservation>,
+    surgeries: Vec<PigSurgery>,
+    weights: Vec<PigWeight>,
+    vaccinations: Vec<PigVaccination>,
+    sacrifice: Option<PigSacrifice>,
+}
+
+pub struct MedicalExportService;
+
+impl MedicalExportService {
+    /// 匯出單隻豬的醫療資料
+    pub async fn export_pig(
+        pool: &PgPool,
+        pig_id: i32,
+        export_type: ExportType,
+        format: ExportFormat,
+        created_by: Uuid,
+    ) -> Result<ExportFile> {
+        let data = Self::load_pig(pool, pig_id).await?;
+        Self::render_and_store(pool, ExportTarget::Pig(pig_id), &[data], export_type, format, created_by).await
+    }
+
+    /// 匯出計畫下所有豬隻的醫療資料
+    pub async fn export_project(
+        pool: &PgPool,
+        iacuc_no: &str,
+        export_type: ExportType,
+        format: ExportFormat,
+        created_by: Uuid,
+    ) -> Result<ExportFile> {
+        let pigs = sqlx::query_as::<_, Pig>(
+            "SELECT * FROM pigs WHERE iacuc_no = $1 AND deleted_at IS NULL ORDER BY id"
+        )
+        .bind(iacuc_no)
+        .fetch_all(pool)
+        .await?;
+
+        if pigs.is_empty() {
+            return Err(AppError::NotFound(format!("No pigs found for project {}", iacuc_no)));
+        }
+
+        let mut data = Vec::with_capacity(pigs.len());
+        for pig in pigs {
+            data.push(Self::load_pig(pool, pig.id).await?);
+        }
+
+        Self::render_and_store(pool, ExportTarget::Project(iacuc_no), &data, export_type, format, created_by).await
+    }
+
+    /// 重新下載已匯出的檔案（串流傳送；內容定址的檔案於傳輸時驗證雜湊，不符則中斷並建立安全警報）
+    pub async fn download(pool: &PgPool, record_id: Uuid) -> Result<ExportDownload> {
+        let record = AnimalService::get_export_record(pool, record_id).await?;
+        let file_path = record.file_path.clone()
+            .ok_or_else(|| AppError::NotFound("Export file not available".to_string()))?;
+
+        let object = match FileService::hash_from_key(&file_path) {
+            Some(expected) => {
+                let db = pool.clone();
+                let path = file_path.clone();
+                let expected_hash = expected.to_string();
+                FileService::open_verified(&file_path, expected, move |actual| {
+                    tokio::spawn(async move {
+                        let problem = IntegrityProblem::Tampered { expected: expected_hash, actual };
+                        if let Err(e) = IntegrityService::raise_path_alerts(&db, &path, &problem).await {
+                            tracing::error!("[Integrity] Failed to raise alert: {}", e);
+                        }
+                    });
+                })
+                .await
+            }
+            None => FileService::open(&file_path).await.map(|(object, _)| object),
+        };
+        let object = match object {
+            Err(AppError::NotFound(_)) => {
+                IntegrityService::raise_path_alerts(pool, &file_path, &IntegrityProblem::Missing).await?;
+                return Err(AppError::NotFound("Export file is missing".to_string()));
+            }
+            other => other?,
+        };
+
+        let label = match (&record.iacuc_no, record.pig_id) {
+            (Some(iacuc_no), _) => iacuc_no.clone(),
+            (None, Some(pig_id)) => sqlx::query_scalar::<_, String>("SELECT ear_tag FROM pigs WHERE id = $1")
+                .bind(pig_id)
+                .fetch_optional(pool)
+                .await?
+                .unwrap_or_else(|| pig_id.to_string()),
+            (None, None) => record.id.to_string(),
+        };
+
+        Ok(ExportDownload {
+            file_name: Self::file_name(record.export_type, record.export_format, &label, record.created_at),
+            mime_type: record.export_format.mime_type().to_string(),
+            object,
+            record,
+        })
+    }
+
+    async fn load_pig(pool: &PgPool, pig_id: i32) -> Result<PigMedicalData> {
+        Ok(PigMedicalData {
+            pig: AnimalService::get_by_id(pool, pig_id).await?,
+            observations: AnimalService::list_observations(pool, pig_id).await?,
+            surgeries: AnimalService::list_surgeries(pool, pig_id).await?,
+            weights: AnimalService::list_weights(pool, pig_id).await?,
+            vaccinations: AnimalService::list_vaccinations(pool, pig_id).await?,
+            sacrifice: AnimalService::get_sacrifice(pool, pig_id).await?,
+        })
+    }
+
+    /// 產生檔案、存檔並建立匯出記錄
+    async fn render_and_store(
+        pool: &PgPool,
+        target: ExportTarget<'_>,
+        data: &[PigMedicalData],
+        export_type: ExportType,
+        format: ExportFormat,
+        created_by: Uuid,
+    ) -> Result<ExportFile> {
+        let (label, subtitle) = match target {
+            ExportTarget::Pig(_) => {
+                let ear_tag = data.first().map(|d| d.pig.ear_tag.clone()).unwrap_or_default();
+                (ear_tag.clone(), format!("耳號：{}", ear_tag))
+            }
+            ExportTarget::Project(iacuc_no) => (
+                iacuc_no.to_string(),
+                format!("IACUC No.：{}（{} 隻）", iacuc_no, data.len()),
+            ),
+        };
+
+        let tables = Self::build_tables(export_type, data);
+        let title = format!("豬隻醫療資料匯出 - {}", export_type.display_name());
+        let bytes = match format {
+            ExportFormat::Pdf => PdfService::generate_table_pdf(&title, &subtitle, &tables)?,
+            ExportFormat::Excel => Self::generate_excel(&tables)?,
+        };
+
+        let file_name = Self::file_name(export_type, format, &label, Utc::now());
+        let mut upload = FileService::upload(
+            pool,
+            FileCategory::MedicalExport,
+            &file_name,
+            format.mime_type(),
+            &bytes,
+        ).await?;
+
+        let (pig_id, iacuc_no) = match target {
+            ExportTarget::Pig(pig_id) => (Some(pig_id), None),
+            ExportTarget::Project(iacuc_no) => (None, Some(iacuc_no)),
+        };
+        let record = AnimalService::create_export_record(
+            &mut upload.tx,
+            pig_id,
+            iacuc_no,
+            export_type,
+            format,
+            Some(&upload.result.file_path),
+            created_by,
+        ).await?;
+        upload.tx.commit().await?;
+
+        Ok(ExportFile {
+            record,
+            file_name,
+            mime_type: format.mime_type().to_string(),
+            data: bytes,
+        })
+    }
+
+    fn file_name(
+        export_type: ExportType,
+        format: ExportFormat,
+        label: &str,
+        at: chrono::DateTime<Utc>,
+    ) -> String {
+        format!(
+            "醫療資料_{}_{}_{}.{}",
+            label,
+            export_type.display_name(),
+            at.format("%Y%m%d%H%M%S"),
+            format.extension()
+        )
+    }
+
+    /// 依匯出類型整理表格
+    fn build_tables(export_type: ExportType, data: &[PigMedicalData]) -> Vec<ExportTable> {
+        match export_type {
+            ExportType::MedicalSummary => vec![
+                Self::basic_table(data),
+                Self::weight_table(data),
+                Self::vaccination_table(data),
+                Self::sacrifice_table(data),
+            ],
+            ExportType::ObservationRecords => vec![Self::observation_table(
+                "觀察 / 異常紀錄",
+                data,
+                |o| o.record_type != RecordType::Experiment,
+            )],
+            ExportType::ExperimentRecords => vec![Self::observation_table(
+                "試驗紀錄",
+                data,
+                |o| o.record_type == RecordType::Experiment,
+            )],
+            ExportType::SurgeryRecords => vec![Self::surgery_table(data)],
+        }
+    }
+
+    fn basic_table(data: &[PigMedicalData]) -> ExportTable {
+        ExportTable {
+            title: "豬隻基本資料".to_string(),
+            headers: vec![
+                "耳號", "狀態", "品種", "性別", "出生日期", "進場日期", "進場體重(kg)",
+                "欄位", "IACUC No.", "試驗日期", "觀察紀錄數", "手術紀錄數",
+            ],
+            rows: data.iter().map(|d| {
+                let pig = &d.pig;
+                vec![
+                    pig.ear_tag.clone(),
+                    pig.status.display_name().to_string(),
+                    pig.breed_other.clone()
+                        .filter(|_| pig.breed == PigBreed::Other)
+                        .unwrap_or_else(|| pig.breed.display_name().to_string()),
+                    pig.gender.display_name().to_string(),
+                    opt(pig.birth_date),
+                    pig.entry_date.to_string(),
+                    opt(pig.entry_weight),
+                    pig.pen_location.clone().unwrap_or_default(),
+                    pig.iacuc_no.clone().unwrap_or_default(),
+                    opt(pig.experiment_date),
+                    d.observations.len().to_string(),
+                    d.surgeries.len().to_string(),
+                ]
+            }).collect(),
+        }
+    }
+
+    fn weight_table(data: &[PigMedicalData]) -> ExportTable {
+        ExportTable {
+            title: "體重紀錄".to_string(),
+            headers: vec!["耳號", "測量日期", "體重(kg)"],
+            rows: data.iter().flat_map(|d| {
+                d.weights.iter().map(|w| vec![
+                    d.pig.ear_tag.clone(),
+                    w.measure_date.to_string(),
+                    w.weight.to_string(),
+                ])
+            }).collect(),
+        }
+    }
+
+    fn vaccination_table(data: &[PigMedicalData]) -> ExportTable {
+        ExportTable {
+            title: "疫苗 / 驅蟲紀錄".to_string(),
+            headers: vec!["耳號", "施打日期", "疫苗", "驅蟲劑量"],
+            rows: data.iter().flat_map(|d| {
+                d.vaccinations.iter().map(|v| vec![
+                    d.pig.ear_tag.clone(),
+                    v.administered_date.to_string(),
+                    v.vaccine.clone().unwrap_or_default(),
+                    v.deworming_dose.clone().unwrap_or_default(),
+                ])
+            }).collect(),
+        }
+    }
+
+    fn sacrifice_table(data: &[PigMedicalData]) -> ExportTable {
+        ExportTable {
+            title: "犧牲 / 採樣紀錄".to_string(),
+            headers: vec!["耳號", "犧牲日期", "Zoletil 劑量", "方式", "採樣", "採血量(ml)", "已確認犧牲"],
+            rows: data.iter().filter_map(|d| {
+                d.sacrifice.as_ref().map(|s| {
+                    let mut methods = Vec::new();
+                    if s.method_electrocution {
+                        methods.push("電擊".to_string());
+                    }
+                    if s.method_bloodletting {
+                        methods.push("放血".to_string());
+                    }
+                    if let Some(other) = s.method_other.as_ref().filter(|o| !o.is_empty()) {
+                        methods.push(other.clone());
+                    }
+                    let sampling = [s.sampling.as_deref(), s.sampling_other.as_deref()]
+                        .into_iter()
+                        .flatten()
+                        .filter(|v| !v.is_empty())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    vec![
+                        d.pig.ear_tag.clone(),
+                        opt(s.sacrifice_date),
+                        s.zoletil_dose.clone().unwrap_or_default(),
+                        methods.join(", "),
+                        sampling,
+                        opt(s.blood_volume_ml),
+                        yes_no(s.confirmed_sacrifice),
+                    ]
+                })
+            }).collect(),
+        }
+    }
+
+    fn observation_table(
+        title: &str,
+        data: &[PigMedicalData],
+        filter: impl Fn(&PigObservation) -> bool,
+    ) -> ExportTable {
+        ExportTable {
+            title: title.to_string(),
+            headers: vec![
+                "耳號", "事件日期", "紀錄類型", "內容", "使用器材", "治療方式",
+                "無需用藥", "停止用藥", "備註", "獸醫已讀",
+            ],
+            rows: data.iter().flat_map(|d| {
+                d.observations.iter().filter(|o| filter(o)).map(|o| vec![
+                    d.pig.ear_tag.clone(),
+                    o.event_date.to_string(),
+                    o.record_type.display_name().to_string(),
+                    o.content.clone(),
+                    json_text(&o.equipment_used),
+                    json_text(&o.treatments),
+                    yes_no(o.no_medication_needed),
+                    yes_no(o.stop_medication),
+                    o.remark.clone().unwrap_or_default(),
+                    yes_no(o.vet_read),
+                ]).collect::<Vec<_>>()
+            }).collect(),
+        }
+    }
+
+    fn surgery_table(data: &[PigMedicalData]) -> ExportTable {
+        ExportTable {
+            title: "手術紀錄".to_string(),
+            headers: vec![
+                "耳號", "手術日期", "首次試驗", "手術部位", "誘導麻醉", "術前用藥", "擺位",
+                "麻醉維持", "麻醉觀察", "生命徵象", "反射恢復", "呼吸速率", "術後用藥",
+                "無需用藥", "備註", "獸醫已讀",
+            ],
+            rows: data.iter().flat_map(|d| {
+                d.surgeries.iter().map(|s| vec![
+                    d.pig.ear_tag.clone(),
+                    s.surgery_date.to_string(),
+                    yes_no(s.is_first_experiment),
+                    s.surgery_site.clone(),
+                    json_text(&s.induction_anesthesia),
+                    json_text(&s.pre_surgery_medication),
+                    s.positioning.clone().unwrap_or_default(),
+                    json_text(&s.anesthesia_maintenance),
+                    s.anesthesia_observation.clone().unwrap_or_default(),
+                    json_text(&s.vital_signs),
+                    s.reflex_recovery.clone().unwrap_or_default(),
+                    opt(s.respiration_rate),
+                    json_text(&s.post_surgery_medication),
+                    yes_no(s.no_medication_needed),
+                    s.remark.clone().unwrap_or_default(),
+                    yes_no(s.vet_read),
+                ])
+            }).collect(),
+        }
+    }
+
+    /// 生成 Excel（每個表格一個工作表）
+    pub(crate) fn generate_excel(tables: &[ExportTable]) -> Result<Vec<u8>> {
+        use rust_xlsxwriter::{Format, FormatAlign, Workbook};
+
+        let mut workbook = Workbook::new();
+
+        let header_format = Format::new()
+            .set_bold()
+            .set_background_color("#4472C4")
+            .set_font_color("#FFFFFF")
+            .set_align(FormatAlign::Center);
+
+        for table in tables {
+            let worksheet = workbook.add_worksheet();
+            // 工作表名稱不可含 / 且最長 31 字元
+            let sheet_name: String = table.title.replace('/', "-").chars().take(31).collect();
+            worksheet.set_name(sheet_name)?;
+
+            for (col, header) in table.headers.iter().enumerate() {
+                worksheet.set_column_width(col as u16, 16.0)?;
+                worksheet.write_string_with_format(0, col as u16, *header, &header_format)?;
+            }
+            for (row, values) in table.rows.iter().enumerate() {
+                for (col, value) in values.iter().enumerate() {
+                    worksheet.write_string(row as u32 + 1, col as u16, value)?;
+                }
+            }
+
+            worksheet.set_freeze_panes(1, 0)?;
+        }
+
+        let buffer = workbook.save_to_buffer()?;
+
+        Ok(buffer)
+    }
+}
+
+/// 匯出對象
+#[derive(Clone, Copy)]
+enum ExportTarget<'a> {
+    Pig(i32),
+    Project(&'a str),
+}
+
+fn opt<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn yes_no(value: bool) -> String {
+    if value { "是" } else { "否" }.to_string()
+}
+
+/// JSON 欄位轉為文字（字串陣列以逗號串接，其餘以 JSON 呈現）
+fn json_text(value: &Option<serde_json::Value>) -> String {
+    match value {
+        None | Some(serde_json::Value::Null) => String::new(),
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(serde_json::Value::Array(items)) if items.iter().all(|v| v.is_string()) => items
+            .iter()
+            .filter_map(|v| v.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        Some(other) => other.to_string(),
+    }
+}
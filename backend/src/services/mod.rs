@@ -4,20 +4,30 @@ mod auth;
 mod user;
 mod role;
 mod warehouse;
+mod storage_location;
 mod product;
 mod partner;
 mod document;
 mod stock;
+mod costing;
 mod audit;
 mod sku;
 mod protocol;
 mod animal;
 mod notification;
 mod file;
+mod attachment;
+mod storage;
 mod hr;
 mod facility;
 mod calendar;
 mod pdf;
+mod medical_export;
+mod welfare;
+mod weight_analytics;
+mod report_runner;
+mod stocktake;
+mod activity_log;
 pub mod google_calendar;
 mod login_tracker;
 mod session_manager;
@@ -25,27 +35,60 @@ pub mod scheduler;
 pub mod report;
 pub mod email;
 mod signature;
+mod access;
+mod integrity;
 
 pub use auth::AuthService;
 pub use user::UserService;
 pub use role::RoleService;
 pub use warehouse::WarehouseService;
+pub use storage_location::StorageLocationService;
 pub use product::ProductService;
 pub use partner::PartnerService;
 pub use document::DocumentService;
 pub use stock::StockService;
+pub use costing::CostingService;
 pub use audit::AuditService;
 pub use sku::SkuService;
 pub use protocol::ProtocolService;
 pub use animal::AnimalService;
-pub use email::EmailService;
+pub use email::{EmailService, EmailAttachment};
 pub use notification::NotificationService;
 pub use file::{FileService, FileCategory, UploadResult};
+pub use attachment::AttachmentService;
+pub use storage::{build_storage, init_storage, migrate_storage, S3Settings, StorageKind};
 pub use hr::HrService;
 pub use facility::FacilityService;
 pub use calendar::CalendarService;
 pub use pdf::PdfService;
-pub use signature::{SignatureService, AnnotationService, SignatureType, AnnotationType};
+pub use medical_export::{MedicalExportService, ExportTable, ExportFile};
+pub use welfare::WelfareService;
+pub use weight_analytics::WeightAnalyticsService;
+pub use report_runner::ScheduledReportRunner;
+pub use session_manager::SessionManager;
+pub use activity_log::{ActivityLogService, ActivityLogWriter, ActivityLogEntry};
+pub use stocktake::StocktakeService;
+pub use signature::{
+    AnnotationService, AnnotationType, EntitySignatureVerification, SignatureMeaning, SignatureService,
+    SignatureType, UnlockSigner,
+};
+pub use access::{AccessService, DataScope, PigRecord};
+pub use integrity::{IntegrityProblem, IntegrityScanSummary, IntegrityService};
 
 mod balance_expiration;
 pub use balance_expiration::BalanceExpirationJob;
+
+mod leave_approval;
+pub use leave_approval::LeaveApprovalService;
+
+mod reviewer_conflict;
+pub use reviewer_conflict::ReviewerConflictService;
+
+mod review_vote;
+pub use review_vote::ReviewVoteService;
+
+mod review_deadline;
+pub use review_deadline::ReviewDeadlineService;
+
+mod protocol_amendment;
+pub use protocol_amendment::ProtocolAmendmentService;
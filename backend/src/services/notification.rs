@@ -1,3 +1,4 @@
+use chrono::Utc;
 use sqlx::PgPool;
 use uuid::Uuid;
 
@@ -6,9 +7,10 @@ use crate::{
     models::{
         CreateNotificationRequest, CreateScheduledReportRequest, ExpiryAlert, LowStockAlert, Notification, NotificationItem, NotificationQuery,
         NotificationSettings, NotificationType, PaginatedResponse, ReportHistory,
-        ScheduledReport, UpdateNotificationSettingsRequest,
+        ReportType, ScheduledReport, UpdateNotificationSettingsRequest,
         UpdateScheduledReportRequest,
     },
+    services::report_runner::{ScheduledReportRunner, REPORT_HISTORY_COLUMNS, SCHEDULED_REPORT_COLUMNS},
 };
 
 pub struct NotificationService {
@@ -287,6 +289,79 @@ impl NotificationService {
         Ok(())
     }
 
+    /// 通知審查期限將至（給審查委員）
+    pub async fn notify_review_deadline(
+        &self,
+        protocol_id: Uuid,
+        protocol_no: &str,
+        title: &str,
+        reviewer_id: Uuid,
+        due_date: &str,
+    ) -> Result<(), AppError> {
+        let notification_title = format!("[iPig] 審查期限提醒 - {}", protocol_no);
+        let content = format!(
+            "您負責審查的計畫即將到期，請於期限內完成審查並提交表決。\n\n計畫編號：{}\n計畫名稱：{}\n審查期限：{}",
+            protocol_no, title, due_date
+        );
+
+        self.create_notification(CreateNotificationRequest {
+            user_id: reviewer_id,
+            notification_type: NotificationType::ReviewDeadline,
+            title: notification_title,
+            content: Some(content),
+            related_entity_type: Some("protocol".to_string()),
+            related_entity_id: Some(protocol_id),
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// 通知審查逾期（給 IACUC 主委）
+    pub async fn notify_review_overdue(
+        &self,
+        protocol_id: Uuid,
+        protocol_no: &str,
+        title: &str,
+        overdue_detail: &str,
+        due_date: &str,
+    ) -> Result<i32, AppError> {
+        let chairs: Vec<(Uuid,)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT u.id
+            FROM users u
+            JOIN user_roles ur ON u.id = ur.user_id
+            JOIN roles r ON ur.role_id = r.id
+            WHERE u.is_active = true AND r.code = 'CHAIR'
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let notification_title = format!("[iPig] 審查逾期 - {}", protocol_no);
+        let content = format!(
+            "以下計畫的審查已超過期限，請協助追蹤或重新指派。\n\n計畫編號：{}\n計畫名稱：{}\n逾期項目：{}\n原定期限：{}",
+            protocol_no, title, overdue_detail, due_date
+        );
+
+        let mut count = 0;
+        for (user_id,) in chairs {
+            let _ = self
+                .create_notification(CreateNotificationRequest {
+                    user_id,
+                    notification_type: NotificationType::ReviewDeadline,
+                    title: notification_title.clone(),
+                    content: Some(content.clone()),
+                    related_entity_type: Some("protocol".to_string()),
+                    related_entity_id: Some(protocol_id),
+                })
+                .await;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
     /// 通知獸醫師建議
     pub async fn notify_vet_recommendation(
         &self,
@@ -340,6 +415,109 @@ impl NotificationService {
         Ok(count)
     }
 
+    /// 通知福祉評分達人道終點（給獸醫師與計畫主持人）
+    pub async fn notify_humane_endpoint(
+        &self,
+        ear_tag: &str,
+        iacuc_no: Option<&str>,
+        total_score: &str,
+        reason: &str,
+    ) -> Result<i32, AppError> {
+        // 所有獸醫師，加上豬隻所屬計畫的 PI
+        let recipients: Vec<(Uuid,)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT u.id
+            FROM users u
+            JOIN user_roles ur ON u.id = ur.user_id
+            JOIN roles r ON ur.role_id = r.id
+            WHERE u.is_active = true AND r.code = 'VET'
+            UNION
+            SELECT p.pi_user_id
+            FROM protocols p
+            JOIN users u ON p.pi_user_id = u.id
+            WHERE $1::varchar IS NOT NULL AND p.iacuc_no = $1 AND u.is_active = true
+            "#,
+        )
+        .bind(iacuc_no)
+        .fetch_all(&self.db)
+        .await?;
+
+        let notification_title = format!("[iPig] 人道終點警示 - 耳號 {}", ear_tag);
+        let content = format!(
+            "豬隻福祉評分已達計畫設定的人道終點，請立即評估處置。\n\n耳號：{}\nIACUC NO.：{}\n評分總分：{}\n觸發原因：{}",
+            ear_tag,
+            iacuc_no.unwrap_or("-"),
+            total_score,
+            reason
+        );
+
+        let mut count = 0;
+        for (user_id,) in recipients {
+            let _ = self
+                .create_notification(CreateNotificationRequest {
+                    user_id,
+                    notification_type: NotificationType::HumaneEndpoint,
+                    title: notification_title.clone(),
+                    content: Some(content.clone()),
+                    related_entity_type: Some("pig".to_string()),
+                    related_entity_id: None, // pig uses i32, not UUID
+                })
+                .await;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// 通知豬隻體重異常下降（給獸醫師）
+    pub async fn notify_weight_loss(
+        &self,
+        ear_tag: &str,
+        iacuc_no: Option<&str>,
+        loss_percent: &str,
+        window_days: i64,
+        latest_weight: &str,
+    ) -> Result<i32, AppError> {
+        let vets: Vec<(Uuid,)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT u.id
+            FROM users u
+            JOIN user_roles ur ON u.id = ur.user_id
+            JOIN roles r ON ur.role_id = r.id
+            WHERE u.is_active = true AND r.code = 'VET'
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let notification_title = format!("[iPig] 體重下降警示 - 耳號 {}", ear_tag);
+        let content = format!(
+            "豬隻體重於 {} 天內下降 {}%，請評估健康狀況。\n\n耳號：{}\nIACUC NO.：{}\n最新體重：{} kg",
+            window_days,
+            loss_percent,
+            ear_tag,
+            iacuc_no.unwrap_or("-"),
+            latest_weight
+        );
+
+        let mut count = 0;
+        for (user_id,) in vets {
+            let _ = self
+                .create_notification(CreateNotificationRequest {
+                    user_id,
+                    notification_type: NotificationType::WeightLossAlert,
+                    title: notification_title.clone(),
+                    content: Some(content.clone()),
+                    related_entity_type: Some("pig".to_string()),
+                    related_entity_id: None, // pig uses i32, not UUID
+                })
+                .await;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
     /// 清理過期通知（90 天前的已讀通知）
     pub async fn cleanup_old_notifications(&self) -> Result<i64, AppError> {
         let result = sqlx::query(
@@ -473,12 +651,12 @@ impl NotificationService {
 
     /// 取得定期報表列表
     pub async fn list_scheduled_reports(&self) -> Result<Vec<ScheduledReport>, AppError> {
-        let reports: Vec<ScheduledReport> = sqlx::query_as(
+        let reports: Vec<ScheduledReport> = sqlx::query_as(&format!(
             r#"
-            SELECT * FROM scheduled_reports
+            SELECT {SCHEDULED_REPORT_COLUMNS} FROM scheduled_reports
             ORDER BY created_at DESC
-            "#,
-        )
+            "#
+        ))
         .fetch_all(&self.db)
         .await?;
 
@@ -487,9 +665,9 @@ impl NotificationService {
 
     /// 取得單一定期報表
     pub async fn get_scheduled_report(&self, id: Uuid) -> Result<ScheduledReport, AppError> {
-        let report: ScheduledReport = sqlx::query_as(
-            r#"SELECT * FROM scheduled_reports WHERE id = $1"#,
-        )
+        let report: ScheduledReport = sqlx::query_as(&format!(
+            r#"SELECT {SCHEDULED_REPORT_COLUMNS} FROM scheduled_reports WHERE id = $1"#
+        ))
         .bind(id)
         .fetch_optional(&self.db)
         .await?
@@ -504,17 +682,29 @@ impl NotificationService {
         request: CreateScheduledReportRequest,
         created_by: Uuid,
     ) -> Result<ScheduledReport, AppError> {
-        let report: ScheduledReport = sqlx::query_as(
+        if ReportType::parse(&request.report_type).is_none() {
+            return Err(AppError::Validation(format!("Invalid report type: {}", request.report_type)));
+        }
+        ScheduledReportRunner::parse_parameters(&request.parameters)?;
+        let next_run_at = ScheduledReportRunner::compute_next_run(
+            &request.schedule_type,
+            request.day_of_week,
+            request.day_of_month,
+            request.hour_of_day,
+            Utc::now(),
+        )?;
+
+        let report: ScheduledReport = sqlx::query_as(&format!(
             r#"
             INSERT INTO scheduled_reports 
                 (id, report_type, schedule_type, day_of_week, day_of_month, 
-                 hour_of_day, parameters, recipients, created_by)
+                 hour_of_day, parameters, recipients, created_by, next_run_at)
             VALUES 
                 (gen_random_uuid(), $1::report_type, $2::schedule_type, $3, $4, 
-                 $5, $6, $7, $8)
-            RETURNING *
-            "#,
-        )
+                 $5, $6, $7, $8, $9)
+            RETURNING {SCHEDULED_REPORT_COLUMNS}
+            "#
+        ))
         .bind(&request.report_type)
         .bind(&request.schedule_type)
         .bind(request.day_of_week)
@@ -523,6 +713,7 @@ impl NotificationService {
         .bind(&request.parameters)
         .bind(&request.recipients)
         .bind(created_by)
+        .bind(next_run_at)
         .fetch_one(&self.db)
         .await?;
 
@@ -535,7 +726,30 @@ impl NotificationService {
         id: Uuid,
         request: UpdateScheduledReportRequest,
     ) -> Result<ScheduledReport, AppError> {
-        let report: ScheduledReport = sqlx::query_as(
+        let existing = self.get_scheduled_report(id).await?;
+        if request.parameters.is_some() {
+            ScheduledReportRunner::parse_parameters(&request.parameters)?;
+        }
+        // 僅在排程欄位變更時重新計算下次執行時間，避免覆蓋尚待補跑的排程
+        let day_of_week = request.day_of_week.or(existing.day_of_week);
+        let day_of_month = request.day_of_month.or(existing.day_of_month);
+        let hour_of_day = request.hour_of_day.unwrap_or(existing.hour_of_day);
+        let schedule_changed = day_of_week != existing.day_of_week
+            || day_of_month != existing.day_of_month
+            || hour_of_day != existing.hour_of_day;
+        let next_run_at = if schedule_changed || existing.next_run_at.is_none() {
+            Some(ScheduledReportRunner::compute_next_run(
+                &existing.schedule_type,
+                day_of_week,
+                day_of_month,
+                hour_of_day,
+                Utc::now(),
+            )?)
+        } else {
+            None
+        };
+
+        let report: ScheduledReport = sqlx::query_as(&format!(
             r#"
             UPDATE scheduled_reports
             SET 
@@ -545,11 +759,12 @@ impl NotificationService {
                 parameters = COALESCE($5, parameters),
                 recipients = COALESCE($6, recipients),
                 is_active = COALESCE($7, is_active),
+                next_run_at = COALESCE($8, next_run_at),
                 updated_at = NOW()
             WHERE id = $1
-            RETURNING *
-            "#,
-        )
+            RETURNING {SCHEDULED_REPORT_COLUMNS}
+            "#
+        ))
         .bind(id)
         .bind(request.day_of_week)
         .bind(request.day_of_month)
@@ -557,6 +772,7 @@ impl NotificationService {
         .bind(&request.parameters)
         .bind(&request.recipients)
         .bind(request.is_active)
+        .bind(next_run_at)
         .fetch_one(&self.db)
         .await?;
 
@@ -587,13 +803,13 @@ impl NotificationService {
     ) -> Result<PaginatedResponse<ReportHistory>, AppError> {
         let offset = (page - 1) * per_page;
 
-        let reports: Vec<ReportHistory> = sqlx::query_as(
+        let reports: Vec<ReportHistory> = sqlx::query_as(&format!(
             r#"
-            SELECT * FROM report_history
+            SELECT {REPORT_HISTORY_COLUMNS} FROM report_history
             ORDER BY generated_at DESC
             LIMIT $1 OFFSET $2
-            "#,
-        )
+            "#
+        ))
         .bind(per_page)
         .bind(offset)
         .fetch_all(&self.db)
@@ -610,9 +826,9 @@ impl NotificationService {
 
     /// 取得單一報表歷史
     pub async fn get_report_history(&self, id: Uuid) -> Result<ReportHistory, AppError> {
-        let report: ReportHistory = sqlx::query_as(
-            r#"SELECT * FROM report_history WHERE id = $1"#,
-        )
+        let report: ReportHistory = sqlx::query_as(&format!(
+            r#"SELECT {REPORT_HISTORY_COLUMNS} FROM report_history WHERE id = $1"#
+        ))
         .bind(id)
         .fetch_optional(&self.db)
         .await?
@@ -747,3 +963,59 @@ impl NotificationService {
         Ok(count)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::insert_user;
+    use chrono::Duration;
+
+    async fn insert_overdue_report(db: &PgPool) -> (Uuid, chrono::DateTime<Utc>) {
+        let owner = insert_user(db, "report-owner@example.com", true).await;
+        let overdue = Utc::now() - Duration::hours(3);
+        let id = sqlx::query_scalar(
+            r#"
+            INSERT INTO scheduled_reports
+                (id, report_type, schedule_type, hour_of_day, recipients, created_by, next_run_at)
+            VALUES (gen_random_uuid(), 'stock_on_hand', 'daily', 6, $1, $2, $3)
+            RETURNING id
+            "#,
+        )
+        .bind(vec![owner])
+        .bind(owner)
+        .bind(overdue)
+        .fetch_one(db)
+        .await
+        .unwrap();
+        (id, overdue)
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_update_without_schedule_change_keeps_pending_run(db: PgPool) {
+        let (id, overdue) = insert_overdue_report(&db).await;
+        let service = NotificationService::new(db);
+
+        let request = UpdateScheduledReportRequest {
+            day_of_week: None,
+            day_of_month: None,
+            hour_of_day: Some(6),
+            parameters: None,
+            recipients: Some(vec![]),
+            is_active: Some(true),
+        };
+        let report = service.update_scheduled_report(id, request).await.unwrap();
+        assert_eq!(report.next_run_at.map(|t| t.timestamp_micros()), Some(overdue.timestamp_micros()));
+
+        let request = UpdateScheduledReportRequest {
+            day_of_week: None,
+            day_of_month: None,
+            hour_of_day: Some(8),
+            parameters: None,
+            recipients: None,
+            is_active: None,
+        };
+        let report = service.update_scheduled_report(id, request).await.unwrap();
+        assert!(report.next_run_at.unwrap() > Utc::now());
+    }
+}
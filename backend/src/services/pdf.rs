@@ -1,8 +1,9 @@
 // PDF 生成服務
-// 使用 printpdf 函式庫生成 AUP 計畫書 PDF
+// 使用 printpdf 函式庫生成 AUP 計畫書與醫療資料匯出 PDF
 
 use printpdf::*;
 use crate::models::ProtocolResponse;
+use crate::services::ExportTable;
 use crate::{AppError, Result};
 
 /// PDF 頁面配置常數
@@ -27,18 +28,7 @@ impl PdfService {
         );
 
         // 載入中文字型
-        let font_path = std::path::Path::new("resources/fonts/NotoSansSC-Regular.ttf");
-        if !font_path.exists() {
-            return Err(AppError::Internal(
-                "Font file not found: resources/fonts/NotoSansSC-Regular.ttf".to_string()
-            ));
-        }
-        
-        let font_bytes = std::fs::read(font_path)
-            .map_err(|e| AppError::Internal(format!("Failed to read font file: {}", e)))?;
-        
-        let font = doc.add_external_font(&*font_bytes)
-            .map_err(|e| AppError::Internal(format!("Failed to load font: {}", e)))?;
+        let font = Self::load_font(&doc)?;
 
         // 開始渲染 PDF
         let current_layer = doc.get_page(page1).get_layer(layer1);
@@ -444,6 +434,80 @@ impl PdfService {
         Ok(pdf_bytes)
     }
 
+    /// 載入中文字型
+    fn load_font(doc: &PdfDocumentReference) -> Result<IndirectFontRef> {
+        let font_path = std::path::Path::new("resources/fonts/NotoSansSC-Regular.ttf");
+        if !font_path.exists() {
+            return Err(AppError::Internal(
+                "Font file not found: resources/fonts/NotoSansSC-Regular.ttf".to_string()
+            ));
+        }
+        
+        let font_bytes = std::fs::read(font_path)
+            .map_err(|e| AppError::Internal(format!("Failed to read font file: {}", e)))?;
+        
+        doc.add_external_font(&*font_bytes)
+            .map_err(|e| AppError::Internal(format!("Failed to load font: {}", e)))
+    }
+
+    /// 生成表格資料 PDF（每筆資料以「欄位：值」逐行列出，超出頁面自動換頁）
+    pub fn generate_table_pdf(title: &str, subtitle: &str, tables: &[ExportTable]) -> Result<Vec<u8>> {
+        let (doc, page1, layer1) = PdfDocument::new(
+            title,
+            Mm(PAGE_WIDTH_MM),
+            Mm(PAGE_HEIGHT_MM),
+            "第1頁"
+        );
+        let font = Self::load_font(&doc)?;
+        let generated_at = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
+
+        let mut page_no = 1;
+        let mut layer = doc.get_page(page1).get_layer(layer1);
+        let mut y = PAGE_HEIGHT_MM - MARGIN_MM;
+
+        layer.use_text(title, 18.0, Mm(MARGIN_MM), Mm(y), &font);
+        y -= 9.0;
+        layer.use_text(subtitle, 11.0, Mm(MARGIN_MM), Mm(y), &font);
+        y -= SECTION_SPACING_MM;
+
+        for table in tables {
+            let mut lines: Vec<(f32, String)> = vec![(14.0, format!("{}（{} 筆）", table.title, table.rows.len()))];
+            if table.rows.is_empty() {
+                lines.push((10.0, "無資料".to_string()));
+            }
+            for (i, row) in table.rows.iter().enumerate() {
+                lines.push((11.0, format!("#{}", i + 1)));
+                for (header, value) in table.headers.iter().zip(row) {
+                    let text = format!("{}：{}", header, if value.is_empty() { "-" } else { value });
+                    let chars: Vec<char> = text.chars().collect();
+                    for chunk in chars.chunks(45) {
+                        lines.push((10.0, chunk.iter().collect()));
+                    }
+                }
+            }
+
+            for (size, text) in lines {
+                // 保留頁尾空間，不足時換頁
+                if y < MARGIN_MM + 15.0 {
+                    layer.use_text(format!("生成日期: {}　第 {} 頁", generated_at, page_no), 8.0, Mm(MARGIN_MM), Mm(MARGIN_MM), &font);
+                    page_no += 1;
+                    let (page, page_layer) = doc.add_page(Mm(PAGE_WIDTH_MM), Mm(PAGE_HEIGHT_MM), format!("第{}頁", page_no));
+                    layer = doc.get_page(page).get_layer(page_layer);
+                    y = PAGE_HEIGHT_MM - MARGIN_MM;
+                }
+                let indent = if size >= 14.0 { 0.0 } else { 5.0 };
+                layer.use_text(text, size, Mm(MARGIN_MM + indent), Mm(y), &font);
+                y -= if size >= 14.0 { LINE_HEIGHT_MM * 1.5 } else { LINE_HEIGHT_MM };
+            }
+            y -= SECTION_SPACING_MM;
+        }
+
+        layer.use_text(format!("生成日期: {}　第 {} 頁", generated_at, page_no), 8.0, Mm(MARGIN_MM), Mm(MARGIN_MM), &font);
+
+        doc.save_to_bytes()
+            .map_err(|e| AppError::Internal(format!("Failed to generate PDF: {}", e)))
+    }
+
     /// 渲染 section 標題
     fn render_section_header(layer: &PdfLayerReference, font: &IndirectFontRef, text: &str, y: f32) -> f32 {
         layer.use_text(text, 14.0, Mm(MARGIN_MM), Mm(y), font);
@@ -1,18 +1,21 @@
 use chrono::Utc;
-use sqlx::PgPool;
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
 use uuid::Uuid;
 use serde_json::Value;
 use validator::Validate;
 
 use crate::{
+    config::Config,
     models::{
         AssignReviewerRequest, AssignCoEditorRequest, ChangeStatusRequest, CreateCommentRequest, CreateProtocolRequest,
         Protocol, ProtocolListItem, ProtocolQuery, ProtocolResponse, ProtocolStatus,
         ProtocolStatusHistory, ProtocolVersion, ReplyCommentRequest, ReviewAssignment, ReviewComment,
         ReviewCommentResponse, UpdateProtocolRequest, ProtocolRole, UserProtocol, CreatePartnerRequest, PartnerType,
-        CoEditorAssignmentResponse,
+        CoEditorAssignmentResponse, ProtocolTransition, ProtocolTransitionOption, TransitionPrecondition,
+        FieldChangeType, ProtocolFieldChange, ProtocolSectionDiff, ProtocolVersionDiff,
     },
-    services::PartnerService,
+    middleware::CurrentUser,
+    services::{AuthService, NotificationService, PartnerService, ReviewDeadlineService, ReviewVoteService, ReviewerConflictService, SignatureMeaning, SignatureService, SignatureType},
     AppError, Result,
 };
 
@@ -275,7 +278,7 @@ impl ProtocolService {
     }
 
     /// 驗證計畫內容
-    fn validate_protocol_content(content: &Option<Value>) -> Result<()> {
+    pub(crate) fn validate_protocol_content(content: &Option<Value>) -> Result<()> {
         let content = content.as_ref().ok_or_else(|| AppError::Validation("Protocol content is empty".to_string()))?;
         
         // 驗證基本資料
@@ -387,8 +390,10 @@ impl ProtocolService {
         pool: &PgPool,
         id: Uuid,
         req: &ChangeStatusRequest,
-        changed_by: Uuid,
+        current_user: &CurrentUser,
+        config: &Config,
     ) -> Result<Protocol> {
+        let changed_by = current_user.id;
         let protocol = sqlx::query_as::<_, Protocol>(
             "SELECT * FROM protocols WHERE id = $1"
         )
@@ -397,17 +402,31 @@ impl ProtocolService {
         .await?
         .ok_or_else(|| AppError::NotFound("Protocol not found".to_string()))?;
 
-        // 驗證 UNDER_REVIEW 狀態必須提供 2-3 位審查委員
-        if req.to_status == ProtocolStatus::UnderReview {
-            let reviewer_ids = req.reviewer_ids.as_ref()
-                .ok_or_else(|| AppError::Validation("必須選擇審查委員".to_string()))?;
-            
-            if reviewer_ids.len() < 2 || reviewer_ids.len() > 3 {
-                return Err(AppError::Validation("必須選擇 2-3 位審查委員".to_string()));
-            }
+        // 依狀態轉移表驗證轉移是否合法
+        let transition = ProtocolTransition::find(protocol.status, req.to_status)
+            .ok_or_else(|| {
+                let allowed: Vec<&str> = ProtocolTransition::from_status(protocol.status)
+                    .map(|t| t.to.as_str())
+                    .collect();
+                AppError::Conflict(format!(
+                    "Cannot change protocol status from {} to {}. Allowed next statuses: {}",
+                    protocol.status.as_str(),
+                    req.to_status.as_str(),
+                    if allowed.is_empty() { "none".to_string() } else { allowed.join(", ") }
+                ))
+            })?;
+
+        if !transition.allows_roles(&current_user.roles) {
+            return Err(AppError::Forbidden(format!(
+                "Changing protocol status from {} to {} requires role: {}",
+                protocol.status.as_str(),
+                req.to_status.as_str(),
+                transition.roles.join(" / ")
+            )));
         }
 
-        // TODO: 驗證狀態轉移是否合法（根據角色和當前狀態）
+        let vote_override = ReviewVoteService::check_decision(pool, &protocol, req, current_user, config.review_vote_rule).await?;
+        Self::check_transition_preconditions(pool, &protocol, transition, req, current_user).await?;
 
         // IACUC 編號生成規則：
         // 1. 在計劃被提交審查與核准前（Submitted 狀態），生成 APIG-{ROC}{03}
@@ -435,46 +454,72 @@ impl ProtocolService {
                 protocol.iacuc_no.clone()
             }
         } else if req.to_status == ProtocolStatus::Approved || req.to_status == ProtocolStatus::ApprovedWithConditions {
-            // 核准時生成 IACUC 編號（PIG-{ROC}{03}）
-            Some(Self::generate_iacuc_no(pool).await?)
+            // 核准時生成 IACUC 編號（PIG-{ROC}{03}），附條件核准後轉核准或暫停後恢復則沿用
+            let needs_pig = protocol.iacuc_no.as_ref()
+                .map(|no| !no.starts_with("PIG-"))
+                .unwrap_or(true);
+
+            if needs_pig {
+                Some(Self::generate_iacuc_no(pool).await?)
+            } else {
+                protocol.iacuc_no.clone()
+            }
         } else {
             protocol.iacuc_no.clone()
         };
 
+        // 進入行政預審時重新起算預審期限
+        let pre_review_due_date = (req.to_status == ProtocolStatus::PreReview)
+            .then(|| ReviewDeadlineService::due_date_from_today(config.pre_review_due_working_days));
+
+        // 核准簽章與狀態更新於同一交易，狀態更新失敗時不留下簽章
+        let mut tx = pool.begin().await?;
+        if transition.preconditions.contains(&TransitionPrecondition::ElectronicSignature) {
+            Self::ensure_approval_signature(pool, &mut tx, &protocol, req, current_user).await?;
+        }
+
         let updated = sqlx::query_as::<_, Protocol>(
             r#"
             UPDATE protocols SET 
                 status = $2, 
                 iacuc_no = $3,
+                pre_review_due_date = COALESCE($4, pre_review_due_date),
+                pre_review_escalated_at = CASE WHEN $4::date IS NULL THEN pre_review_escalated_at END,
                 updated_at = NOW() 
-            WHERE id = $1 
+            WHERE id = $1 AND status = $5
             RETURNING *
             "#
         )
         .bind(id)
         .bind(req.to_status)
         .bind(&new_iacuc_no)
-        .fetch_one(pool)
-        .await?;
+        .bind(pre_review_due_date)
+        .bind(protocol.status)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::Conflict("Protocol status has changed, please refresh and try again".to_string()))?;
 
         // 記錄狀態變更
-        Self::record_status_change(pool, id, Some(protocol.status), req.to_status, changed_by, req.remark.clone()).await?;
+        Self::record_status_change(&mut *tx, id, Some(protocol.status), req.to_status, changed_by, req.remark.clone()).await?;
 
-        // 當狀態變為 UNDER_REVIEW 時，自動指派選定的審查委員
+        // 當狀態變為 UNDER_REVIEW 時，於同一交易指派選定的審查委員（資格已於前置條件檢查）
+        let mut assignments = Vec::new();
         if req.to_status == ProtocolStatus::UnderReview {
-            if let Some(reviewer_ids) = &req.reviewer_ids {
-                for reviewer_id in reviewer_ids {
-                    Self::assign_reviewer(
-                        pool,
-                        &AssignReviewerRequest {
-                            protocol_id: id,
-                            reviewer_id: *reviewer_id,
-                        },
-                        changed_by,
-                    ).await?;
-                }
+            for reviewer_id in req.reviewer_ids.iter().flatten() {
+                assignments.push(
+                    Self::insert_review_assignment(&mut *tx, id, *reviewer_id, changed_by, config.review_due_working_days)
+                        .await?,
+                );
             }
         }
+        tx.commit().await?;
+
+        if let Some(summary) = &vote_override {
+            ReviewVoteService::log_override(pool, summary, req.to_status, req.remark.as_deref(), changed_by).await?;
+        }
+        for assignment in &assignments {
+            Self::notify_review_assignment(pool, assignment).await?;
+        }
 
         // 當計劃通過時，自動依照 IACUC No. 自動填入客戶
         if (req.to_status == ProtocolStatus::Approved || req.to_status == ProtocolStatus::ApprovedWithConditions) 
@@ -552,6 +597,209 @@ impl ProtocolService {
         Ok(updated)
     }
 
+    /// 檢查狀態轉移前置條件（電子簽章於狀態更新交易內處理）
+    async fn check_transition_preconditions(
+        pool: &PgPool,
+        protocol: &Protocol,
+        transition: &ProtocolTransition,
+        req: &ChangeStatusRequest,
+        current_user: &CurrentUser,
+    ) -> Result<()> {
+        for precondition in transition.preconditions {
+            match precondition {
+                TransitionPrecondition::ReviewersSelected => {
+                    let reviewer_ids = req.reviewer_ids.as_deref().unwrap_or_default();
+                    let distinct: std::collections::HashSet<&Uuid> = reviewer_ids.iter().collect();
+                    if !(2..=3).contains(&reviewer_ids.len()) || distinct.len() != reviewer_ids.len() {
+                        return Err(AppError::Validation(precondition.description().to_string()));
+                    }
+                    for reviewer_id in req.reviewer_ids.iter().flatten() {
+                        ReviewerConflictService::ensure_eligible(pool, protocol.id, *reviewer_id, current_user.id).await?;
+                    }
+                }
+                TransitionPrecondition::RemarkRequired => {
+                    let has_remark = req.remark.as_ref().is_some_and(|r| !r.trim().is_empty());
+                    if !has_remark {
+                        return Err(AppError::Validation(precondition.description().to_string()));
+                    }
+                }
+                TransitionPrecondition::AllReviewsCompleted
+                | TransitionPrecondition::NoUnresolvedComments => {
+                    if let Some(message) = Self::unmet_precondition(pool, protocol.id, *precondition).await? {
+                        return Err(AppError::Conflict(message));
+                    }
+                }
+                TransitionPrecondition::ElectronicSignature => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 檢查計畫資料狀態類前置條件，未滿足時回傳說明
+    async fn unmet_precondition(
+        pool: &PgPool,
+        protocol_id: Uuid,
+        precondition: TransitionPrecondition,
+    ) -> Result<Option<String>> {
+        match precondition {
+            TransitionPrecondition::AllReviewsCompleted => {
+                let (total, pending): (i64, i64) = sqlx::query_as(
+                    r#"
+                    SELECT COUNT(*), COUNT(*) FILTER (WHERE completed_at IS NULL)
+                    FROM review_assignments
+                    WHERE protocol_id = $1 AND amendment_id IS NULL
+                    "#
+                )
+                .bind(protocol_id)
+                .fetch_one(pool)
+                .await?;
+
+                if total == 0 {
+                    Ok(Some(format!("{}（尚未指派審查委員）", precondition.description())))
+                } else if pending > 0 {
+                    Ok(Some(format!("{}（尚有 {} 位審查委員未完成）", precondition.description(), pending)))
+                } else {
+                    Ok(None)
+                }
+            }
+            TransitionPrecondition::NoUnresolvedComments => {
+                let unresolved: i64 = sqlx::query_scalar(
+                    r#"
+                    SELECT COUNT(*)
+                    FROM review_comments rc
+                    INNER JOIN protocol_versions pv ON rc.protocol_version_id = pv.id
+                    WHERE pv.protocol_id = $1
+                      AND rc.amendment_id IS NULL
+                      AND rc.is_resolved = false
+                    "#
+                )
+                .bind(protocol_id)
+                .fetch_one(pool)
+                .await?;
+
+                if unresolved > 0 {
+                    Ok(Some(format!("{}（尚有 {} 則未解決）", precondition.description(), unresolved)))
+                } else {
+                    Ok(None)
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// 核准電子簽章：每次核准轉移皆須以請求密碼重新驗證並於同一交易內簽章
+    async fn ensure_approval_signature(
+        pool: &PgPool,
+        tx: &mut Transaction<'_, Postgres>,
+        protocol: &Protocol,
+        req: &ChangeStatusRequest,
+        current_user: &CurrentUser,
+    ) -> Result<()> {
+        let entity_id = protocol.id.to_string();
+        let password = req.signature_password.as_deref()
+            .filter(|p| !p.is_empty())
+            .ok_or_else(|| AppError::Validation(
+                TransitionPrecondition::ElectronicSignature.description().to_string()
+            ))?;
+
+        let user = AuthService::verify_password_by_id(pool, current_user.id, password)
+            .await
+            .map_err(|_| AppError::Unauthorized)?;
+
+        SignatureService::sign_entity(
+            tx,
+            "protocol",
+            &entity_id,
+            current_user.id,
+            &user.password_hash,
+            SignatureType::Approve,
+            SignatureMeaning::Approval,
+        ).await?;
+
+        Ok(())
+    }
+
+    /// 取得目前使用者可執行的狀態轉移
+    pub async fn get_available_transitions(
+        pool: &PgPool,
+        id: Uuid,
+        current_user: &CurrentUser,
+    ) -> Result<Vec<ProtocolTransitionOption>> {
+        let status: ProtocolStatus = sqlx::query_scalar(
+            "SELECT status FROM protocols WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Protocol not found".to_string()))?;
+
+        let mut options = Vec::new();
+        for transition in ProtocolTransition::from_status(status) {
+            if !transition.allows_roles(&current_user.roles) {
+                continue;
+            }
+
+            let mut unmet_preconditions = Vec::new();
+            for precondition in transition.preconditions {
+                if let Some(message) = Self::unmet_precondition(pool, id, *precondition).await? {
+                    unmet_preconditions.push(message);
+                }
+            }
+
+            options.push(ProtocolTransitionOption {
+                to_status: transition.to,
+                to_status_display: transition.to.display_name().to_string(),
+                required_inputs: transition.preconditions
+                    .iter()
+                    .copied()
+                    .filter(|p| p.is_request_input())
+                    .collect(),
+                can_transition: unmet_preconditions.is_empty(),
+                unmet_preconditions,
+            });
+        }
+
+        Ok(options)
+    }
+
+    /// 審查委員完成審查
+    pub async fn complete_review(
+        pool: &PgPool,
+        protocol_id: Uuid,
+        reviewer_id: Uuid,
+    ) -> Result<ReviewAssignment> {
+        let status: ProtocolStatus = sqlx::query_scalar(
+            "SELECT status FROM protocols WHERE id = $1"
+        )
+        .bind(protocol_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Protocol not found".to_string()))?;
+
+        if status != ProtocolStatus::UnderReview {
+            return Err(AppError::Conflict(format!(
+                "Reviews can only be completed while the protocol is UNDER_REVIEW (current: {})",
+                status.as_str()
+            )));
+        }
+
+        let assignment = sqlx::query_as::<_, ReviewAssignment>(
+            r#"
+            UPDATE review_assignments SET completed_at = NOW()
+            WHERE protocol_id = $1 AND reviewer_id = $2 AND amendment_id IS NULL
+            RETURNING *
+            "#
+        )
+        .bind(protocol_id)
+        .bind(reviewer_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Review assignment not found".to_string()))?;
+
+        Ok(assignment)
+    }
+
     /// 生成 APIG 編號
     /// 格式：APIG-{ROC}{03}
     /// {ROC} 為民國年（西元年 - 1911）
@@ -684,7 +932,7 @@ impl ProtocolService {
     }
 
     /// 取得下一個版本號
-    async fn get_next_version_no(pool: &PgPool, protocol_id: Uuid) -> Result<i32> {
+    pub(crate) async fn get_next_version_no(pool: &PgPool, protocol_id: Uuid) -> Result<i32> {
         let max_version: Option<i32> = sqlx::query_scalar(
             "SELECT MAX(version_no) FROM protocol_versions WHERE protocol_id = $1"
         )
@@ -697,7 +945,7 @@ impl ProtocolService {
 
     /// 記錄狀態變更
     async fn record_status_change(
-        pool: &PgPool,
+        executor: impl PgExecutor<'_>,
         protocol_id: Uuid,
         from_status: Option<ProtocolStatus>,
         to_status: ProtocolStatus,
@@ -716,7 +964,7 @@ impl ProtocolService {
         .bind(to_status)
         .bind(changed_by)
         .bind(remark)
-        .execute(pool)
+        .execute(executor)
         .await?;
 
         Ok(())
@@ -734,6 +982,64 @@ impl ProtocolService {
         Ok(versions)
     }
 
+    /// 比較兩個版本的 working_content，依章節列出新增、移除與變更欄位
+    pub async fn diff_versions(
+        pool: &PgPool,
+        protocol_id: Uuid,
+        from_version: i32,
+        to_version: i32,
+    ) -> Result<ProtocolVersionDiff> {
+        if from_version == to_version {
+            return Err(AppError::Validation("Cannot compare a version with itself".to_string()));
+        }
+
+        let from = Self::get_version_by_no(pool, protocol_id, from_version).await?;
+        let to = Self::get_version_by_no(pool, protocol_id, to_version).await?;
+
+        // 各章節未解決的審查意見數（跨所有版本）
+        let open_comments: Vec<(String, i64)> = sqlx::query_as(
+            r#"
+            SELECT rc.section, COUNT(*)
+            FROM review_comments rc
+            INNER JOIN protocol_versions pv ON rc.protocol_version_id = pv.id
+            WHERE pv.protocol_id = $1
+              AND rc.amendment_id IS NULL
+              AND rc.section IS NOT NULL
+              AND rc.is_resolved = false
+            GROUP BY rc.section
+            "#
+        )
+        .bind(protocol_id)
+        .fetch_all(pool)
+        .await?;
+
+        let (total_changes, sections) = diff_sections(&from.content_snapshot, &to.content_snapshot, &open_comments);
+
+        Ok(ProtocolVersionDiff {
+            protocol_id,
+            from_version,
+            to_version,
+            from_submitted_at: from.submitted_at,
+            to_submitted_at: to.submitted_at,
+            total_changes,
+            sections,
+        })
+    }
+
+    /// 依版本號取得版本
+    pub(crate) async fn get_version_by_no(pool: &PgPool, protocol_id: Uuid, version_no: i32) -> Result<ProtocolVersion> {
+        let version = sqlx::query_as::<_, ProtocolVersion>(
+            "SELECT * FROM protocol_versions WHERE protocol_id = $1 AND version_no = $2"
+        )
+        .bind(protocol_id)
+        .bind(version_no)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Protocol version {} not found", version_no)))?;
+
+        Ok(version)
+    }
+
     /// 取得狀態歷程
     pub async fn get_status_history(pool: &PgPool, protocol_id: Uuid) -> Result<Vec<ProtocolStatusHistory>> {
         let history = sqlx::query_as::<_, ProtocolStatusHistory>(
@@ -747,29 +1053,85 @@ impl ProtocolService {
     }
 
     /// 指派審查人員
+    ///
+    /// 審查期限為指派日起算 due_working_days 個工作日；重新指派時重新起算。
     pub async fn assign_reviewer(
         pool: &PgPool,
         req: &AssignReviewerRequest,
         assigned_by: Uuid,
+        due_working_days: i64,
     ) -> Result<ReviewAssignment> {
+        ReviewerConflictService::ensure_eligible(pool, req.protocol_id, req.reviewer_id, assigned_by).await?;
+
+        let assignment =
+            Self::insert_review_assignment(pool, req.protocol_id, req.reviewer_id, assigned_by, due_working_days).await?;
+        Self::notify_review_assignment(pool, &assignment).await?;
+
+        Ok(assignment)
+    }
+
+    /// 新增或重新指派審查委員（重新指派時重設完成狀態與期限）
+    async fn insert_review_assignment(
+        executor: impl PgExecutor<'_>,
+        protocol_id: Uuid,
+        reviewer_id: Uuid,
+        assigned_by: Uuid,
+        due_working_days: i64,
+    ) -> Result<ReviewAssignment> {
+        let due_date = ReviewDeadlineService::due_date_from_today(due_working_days);
         let assignment = sqlx::query_as::<_, ReviewAssignment>(
             r#"
-            INSERT INTO review_assignments (id, protocol_id, reviewer_id, assigned_by, assigned_at)
-            VALUES ($1, $2, $3, $4, NOW())
-            ON CONFLICT (protocol_id, reviewer_id) DO UPDATE SET assigned_at = NOW()
+            INSERT INTO review_assignments (id, protocol_id, reviewer_id, assigned_by, assigned_at, due_date)
+            VALUES ($1, $2, $3, $4, NOW(), $5)
+            ON CONFLICT (protocol_id, reviewer_id) WHERE amendment_id IS NULL DO UPDATE SET
+                assigned_at = NOW(), completed_at = NULL,
+                due_date = EXCLUDED.due_date, last_reminded_on = NULL, escalated_at = NULL
             RETURNING *
             "#
         )
         .bind(Uuid::new_v4())
-        .bind(req.protocol_id)
-        .bind(req.reviewer_id)
+        .bind(protocol_id)
+        .bind(reviewer_id)
         .bind(assigned_by)
-        .fetch_one(pool)
+        .bind(due_date)
+        .fetch_one(executor)
         .await?;
 
         Ok(assignment)
     }
 
+    /// 站內通知審查委員（含審查期限），失敗不影響指派
+    async fn notify_review_assignment(pool: &PgPool, assignment: &ReviewAssignment) -> Result<()> {
+        let protocol: Option<(String, String, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT p.protocol_no, p.title, u.display_name
+            FROM protocols p
+            LEFT JOIN users u ON u.id = p.pi_user_id
+            WHERE p.id = $1
+            "#
+        )
+        .bind(assignment.protocol_id)
+        .fetch_optional(pool)
+        .await?;
+        if let Some((protocol_no, title, pi_name)) = protocol {
+            if let Err(e) = NotificationService::new(pool.clone())
+                .notify_review_assignment(
+                    assignment.protocol_id,
+                    &protocol_no,
+                    &title,
+                    pi_name.as_deref().unwrap_or("-"),
+                    assignment.reviewer_id,
+                    assignment.due_date.map(|d| d.to_string()).as_deref(),
+                )
+                .await
+            {
+                tracing::warn!("Failed to notify reviewer {} of assignment: {}", assignment.reviewer_id, e);
+            }
+        }
+
+        Ok(())
+    }
+
     /// 指派 co-editor（試驗工作人員）
     pub async fn assign_co_editor(
         pool: &PgPool,
@@ -907,8 +1269,8 @@ impl ProtocolService {
     ) -> Result<ReviewComment> {
         let comment = sqlx::query_as::<_, ReviewComment>(
             r#"
-            INSERT INTO review_comments (id, protocol_version_id, reviewer_id, content, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, NOW(), NOW())
+            INSERT INTO review_comments (id, protocol_version_id, reviewer_id, content, section, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, NOW(), NOW())
             RETURNING *
             "#
         )
@@ -916,6 +1278,7 @@ impl ProtocolService {
         .bind(req.protocol_version_id)
         .bind(reviewer_id)
         .bind(&req.content)
+        .bind(&req.section)
         .fetch_one(pool)
         .await?;
 
@@ -932,11 +1295,11 @@ impl ProtocolService {
                 c.content, c.is_resolved, c.resolved_by, c.resolved_at, 
                 c.parent_comment_id, c.replied_by,
                 ru.display_name as replied_by_name, ru.email as replied_by_email,
-                c.created_at
+                c.section, c.created_at
             FROM review_comments c
             LEFT JOIN users u ON c.reviewer_id = u.id
             LEFT JOIN users ru ON c.replied_by = ru.id
-            WHERE c.protocol_version_id = $1
+            WHERE c.protocol_version_id = $1 AND c.amendment_id IS NULL
             ORDER BY 
                 COALESCE(c.parent_comment_id, c.id) ASC,
                 c.created_at ASC
@@ -955,10 +1318,10 @@ impl ProtocolService {
         req: &ReplyCommentRequest,
         replied_by: Uuid,
     ) -> Result<ReviewComment> {
-        // 驗證父評論存在並獲取 protocol_version_id
-        let parent_comment: Option<(Uuid,)> = sqlx::query_as(
+        // 驗證父評論存在並獲取 protocol_version_id（修正案意見一併沿用 amendment_id）
+        let parent_comment: Option<(Uuid, Option<Uuid>)> = sqlx::query_as(
             r#"
-            SELECT protocol_version_id 
+            SELECT protocol_version_id, amendment_id
             FROM review_comments 
             WHERE id = $1 AND parent_comment_id IS NULL
             "#
@@ -967,7 +1330,7 @@ impl ProtocolService {
         .fetch_optional(pool)
         .await?;
 
-        let (protocol_version_id,) = parent_comment
+        let (protocol_version_id, amendment_id) = parent_comment
             .ok_or_else(|| AppError::NotFound("Parent comment not found".to_string()))?;
 
         // 插入回覆
@@ -975,9 +1338,9 @@ impl ProtocolService {
             r#"
             INSERT INTO review_comments (
                 id, protocol_version_id, reviewer_id, content, 
-                parent_comment_id, replied_by, created_at, updated_at
+                parent_comment_id, replied_by, amendment_id, created_at, updated_at
             )
-            VALUES ($1, $2, $3, $4, $5, $6, NOW(), NOW())
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW(), NOW())
             RETURNING *
             "#
         )
@@ -987,6 +1350,7 @@ impl ProtocolService {
         .bind(&req.content)
         .bind(req.parent_comment_id)
         .bind(replied_by) // replied_by 欄位
+        .bind(amendment_id)
         .fetch_one(pool)
         .await?;
 
@@ -1106,3 +1470,258 @@ impl ProtocolService {
         Ok(protocols)
     }
 }
+
+/// 比較兩份計畫內容並依章節分組，附上各章節未解決的審查意見數
+pub(crate) fn diff_sections(
+    old: &Value,
+    new: &Value,
+    open_comments: &[(String, i64)],
+) -> (usize, Vec<ProtocolSectionDiff>) {
+    let mut changes = Vec::new();
+    diff_json_values("", Some(old), Some(new), &mut changes);
+
+    let total_changes = changes.len();
+    let mut sections: Vec<ProtocolSectionDiff> = Vec::new();
+    for change in changes {
+        let section = section_of_path(&change.path).to_string();
+        match sections.iter_mut().find(|s| s.section == section) {
+            Some(diff) => diff.changes.push(change),
+            None => {
+                let open_comment_count = open_comments.iter()
+                    .find(|(name, _)| *name == section)
+                    .map(|(_, count)| *count)
+                    .unwrap_or(0);
+                sections.push(ProtocolSectionDiff {
+                    section,
+                    changes: vec![change],
+                    open_comment_count,
+                    has_open_comments: open_comment_count > 0,
+                });
+            }
+        }
+    }
+
+    (total_changes, sections)
+}
+
+/// 取得 JSON 路徑所屬章節（第一層欄位）
+fn section_of_path(path: &str) -> &str {
+    path.split(['.', '[']).next().unwrap_or_default()
+}
+
+/// 遞迴比較 JSON 值，物件依欄位、陣列依索引比較
+fn diff_json_values(
+    path: &str,
+    old: Option<&Value>,
+    new: Option<&Value>,
+    changes: &mut Vec<ProtocolFieldChange>,
+) {
+    match (old, new) {
+        (None, None) => {}
+        (Some(old), Some(new)) if old == new => {}
+        (None, Some(new)) => changes.push(ProtocolFieldChange {
+            path: path.to_string(),
+            change_type: FieldChangeType::Added,
+            old_value: None,
+            new_value: Some(new.clone()),
+        }),
+        (Some(old), None) => changes.push(ProtocolFieldChange {
+            path: path.to_string(),
+            change_type: FieldChangeType::Removed,
+            old_value: Some(old.clone()),
+            new_value: None,
+        }),
+        (Some(Value::Object(old_map)), Some(Value::Object(new_map))) => {
+            let mut keys: Vec<&String> = old_map.keys().chain(new_map.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                diff_json_values(&child, old_map.get(key), new_map.get(key), changes);
+            }
+        }
+        (Some(Value::Array(old_items)), Some(Value::Array(new_items))) => {
+            for i in 0..old_items.len().max(new_items.len()) {
+                let child = format!("{}[{}]", path, i);
+                diff_json_values(&child, old_items.get(i), new_items.get(i), changes);
+            }
+        }
+        // 以 null 表示的欄位視為新增 / 移除
+        (Some(Value::Null), Some(new)) => diff_json_values(path, None, Some(new), changes),
+        (Some(old), Some(Value::Null)) => diff_json_values(path, Some(old), None, changes),
+        (Some(old), Some(new)) => changes.push(ProtocolFieldChange {
+            path: path.to_string(),
+            change_type: FieldChangeType::Changed,
+            old_value: Some(old.clone()),
+            new_value: Some(new.clone()),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{app_state, current_user, insert_protocol, insert_user, set_password};
+    use serde_json::json;
+
+    fn diff(old: Value, new: Value) -> Vec<ProtocolFieldChange> {
+        let mut changes = Vec::new();
+        diff_json_values("", Some(&old), Some(&new), &mut changes);
+        changes
+    }
+
+    #[test]
+    fn test_diff_reports_paths_per_change_type() {
+        let changes = diff(
+            json!({"basic": {"title": "A", "pi": "x"}, "design": {"items": [1, 2]}}),
+            json!({"basic": {"title": "B", "budget": 10}, "design": {"items": [1]}}),
+        );
+
+        let summary: Vec<(&str, FieldChangeType)> = changes.iter()
+            .map(|c| (c.path.as_str(), c.change_type))
+            .collect();
+        assert_eq!(summary, vec![
+            ("basic.budget", FieldChangeType::Added),
+            ("basic.pi", FieldChangeType::Removed),
+            ("basic.title", FieldChangeType::Changed),
+            ("design.items[1]", FieldChangeType::Removed),
+        ]);
+        assert_eq!(section_of_path("design.items[1]"), "design");
+    }
+
+    #[test]
+    fn test_diff_identical_content_is_empty() {
+        let content = json!({"purpose": {"aim": "x", "list": [{"a": null}]}});
+        assert!(diff(content.clone(), content).is_empty());
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_change_status_conflicts_when_status_changed_concurrently(db: PgPool) {
+        let pi = insert_user(&db, "pi@example.com", false).await;
+        let staff_id = insert_user(&db, "iacuc-staff@example.com", true).await;
+        let staff = current_user(staff_id, "iacuc-staff@example.com", &["IACUC_STAFF"], &[]);
+        let id = insert_protocol(&db, pi, "PIG-STATUS-1").await;
+        sqlx::query("UPDATE protocols SET status = 'SUBMITTED' WHERE id = $1")
+            .bind(id)
+            .execute(&db)
+            .await
+            .unwrap();
+        let config = app_state(db.clone()).config;
+
+        // 另一筆交易已將計畫送入預審但尚未提交；其提交後，以舊狀態判斷的轉移不可再套用
+        let mut other = db.begin().await.unwrap();
+        sqlx::query("UPDATE protocols SET status = 'PRE_REVIEW' WHERE id = $1")
+            .bind(id)
+            .execute(&mut *other)
+            .await
+            .unwrap();
+        let pending = tokio::spawn({
+            let db = db.clone();
+            async move {
+                let req = ChangeStatusRequest {
+                    to_status: ProtocolStatus::PreReview,
+                    remark: None,
+                    reviewer_ids: None,
+                    signature_password: None,
+                };
+                ProtocolService::change_status(&db, id, &req, &staff, &config).await
+            }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        other.commit().await.unwrap();
+
+        assert!(matches!(pending.await.unwrap(), Err(AppError::Conflict(_))));
+        let history: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM protocol_status_history WHERE protocol_id = $1")
+            .bind(id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(history, 0);
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_failed_reviewer_assignment_rolls_back_status_change(db: PgPool) {
+        let pi = insert_user(&db, "pi@example.com", false).await;
+        let reviewer = insert_user(&db, "reviewer@example.com", true).await;
+        let staff_id = insert_user(&db, "iacuc-staff@example.com", true).await;
+        let staff = current_user(staff_id, "iacuc-staff@example.com", &["IACUC_STAFF"], &[]);
+        let id = insert_protocol(&db, pi, "PIG-STATUS-3").await;
+        sqlx::query("UPDATE protocols SET status = 'PRE_REVIEW' WHERE id = $1")
+            .bind(id)
+            .execute(&db)
+            .await
+            .unwrap();
+        let config = app_state(db.clone()).config;
+
+        // 第二位委員不存在，指派失敗時狀態與已指派的委員皆不保留
+        let req = ChangeStatusRequest {
+            to_status: ProtocolStatus::UnderReview,
+            remark: None,
+            reviewer_ids: Some(vec![reviewer, Uuid::new_v4()]),
+            signature_password: None,
+        };
+        assert!(ProtocolService::change_status(&db, id, &req, &staff, &config).await.is_err());
+
+        let status: ProtocolStatus = sqlx::query_scalar("SELECT status FROM protocols WHERE id = $1")
+            .bind(id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(status, ProtocolStatus::PreReview);
+        let assigned: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM review_assignments WHERE protocol_id = $1")
+            .bind(id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(assigned, 0);
+
+        // 重複的委員不算兩位
+        let req = ChangeStatusRequest { reviewer_ids: Some(vec![reviewer, reviewer]), ..req };
+        let result = ProtocolService::change_status(&db, id, &req, &staff, &config).await;
+        assert!(matches!(result, Err(AppError::Validation(_))));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_every_approval_requires_a_fresh_signature(db: PgPool) {
+        let pi = insert_user(&db, "pi@example.com", false).await;
+        let chair_id = insert_user(&db, "chair@example.com", true).await;
+        set_password(&db, chair_id, "chair-password").await;
+        let chair = current_user(chair_id, "chair@example.com", &["CHAIR"], &[]);
+        let id = insert_protocol(&db, pi, "PIG-STATUS-2").await;
+        sqlx::query("UPDATE protocols SET status = 'SUSPENDED' WHERE id = $1")
+            .bind(id)
+            .execute(&db)
+            .await
+            .unwrap();
+        // 原核准時的簽章不可沿用於暫停後恢復核准
+        SignatureService::sign(&db, "protocol", &id.to_string(), chair_id, "x", SignatureType::Approve, "protocol", None, None)
+            .await
+            .unwrap();
+        let config = app_state(db.clone()).config;
+
+        let mut req = ChangeStatusRequest {
+            to_status: ProtocolStatus::Approved,
+            remark: Some("暫停原因已排除".to_string()),
+            reviewer_ids: None,
+            signature_password: None,
+        };
+        let result = ProtocolService::change_status(&db, id, &req, &chair, &config).await;
+        assert!(matches!(result, Err(AppError::Validation(_))));
+
+        req.signature_password = Some("chair-password".to_string());
+        let approved = ProtocolService::change_status(&db, id, &req, &chair, &config).await.unwrap();
+        assert_eq!(approved.status, ProtocolStatus::Approved);
+
+        let signatures: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM electronic_signatures WHERE entity_type = 'protocol' AND entity_id = $1",
+        )
+        .bind(id.to_string())
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert_eq!(signatures, 2);
+    }
+}
@@ -0,0 +1,1034 @@
+// 計畫修正案
+// 核准後計畫的變更申請：編號、審查（沿用審查指派與審查意見）、與核准版本比較，核准後產生新的生效版本
+
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    middleware::CurrentUser,
+    models::{
+        AuditAction, ChangeStatusRequest, CreateAmendmentCommentRequest, CreateAmendmentRequest,
+        ProtocolAmendment, ProtocolAmendmentDiff, ProtocolStatus, ProtocolTransition, ProtocolTransitionOption,
+        ProtocolVersion, ReviewAssignment, ReviewComment, ReviewCommentResponse, TransitionPrecondition,
+        UpdateAmendmentRequest, AMENDMENT_TRANSITIONS,
+    },
+    services::{
+        protocol::diff_sections, AuditService, AuthService, NotificationService, ProtocolService,
+        ReviewDeadlineService, ReviewerConflictService, SignatureMeaning, SignatureService, SignatureType,
+    },
+    AppError, Result,
+};
+
+pub struct ProtocolAmendmentService;
+
+impl ProtocolAmendmentService {
+    /// 建立修正案（僅限已核准的計畫，同一計畫同時僅能有一件進行中的修正案）
+    ///
+    /// 以最新版本為比較基準；未提供修正內容時以該版本內容為起點。
+    pub async fn create(
+        pool: &PgPool,
+        protocol_id: Uuid,
+        req: &CreateAmendmentRequest,
+        created_by: Uuid,
+    ) -> Result<ProtocolAmendment> {
+        let (status, iacuc_no, protocol_no): (ProtocolStatus, Option<String>, String) = sqlx::query_as(
+            "SELECT status, iacuc_no, protocol_no FROM protocols WHERE id = $1",
+        )
+        .bind(protocol_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Protocol not found".to_string()))?;
+
+        if !matches!(status, ProtocolStatus::Approved | ProtocolStatus::ApprovedWithConditions) {
+            return Err(AppError::BusinessRule(format!(
+                "Amendments can only be filed for approved protocols (current: {})",
+                status.as_str()
+            )));
+        }
+
+        let open: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT amendment_no FROM protocol_amendments
+            WHERE protocol_id = $1 AND status NOT IN ('APPROVED', 'REJECTED', 'DELETED')
+            "#,
+        )
+        .bind(protocol_id)
+        .fetch_optional(pool)
+        .await?;
+        if let Some(amendment_no) = open {
+            return Err(AppError::Conflict(format!(
+                "計畫已有進行中的修正案 {}，須待其結案後才能提出新的修正案",
+                amendment_no
+            )));
+        }
+
+        let base = sqlx::query_as::<_, ProtocolVersion>(
+            "SELECT * FROM protocol_versions WHERE protocol_id = $1 ORDER BY version_no DESC LIMIT 1",
+        )
+        .bind(protocol_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::BusinessRule("計畫尚無核准版本，無法提出修正案".to_string()))?;
+
+        let amendment_no = Self::generate_amendment_no(pool, protocol_id, iacuc_no.as_deref().unwrap_or(&protocol_no)).await?;
+        let change_types: Vec<&str> = req.change_types.iter().map(|t| t.as_str()).collect();
+        let proposed_content = req.proposed_content.as_ref().unwrap_or(&base.content_snapshot);
+
+        let amendment = sqlx::query_as::<_, ProtocolAmendment>(
+            r#"
+            INSERT INTO protocol_amendments (
+                id, protocol_id, amendment_no, title, reason, change_types, status,
+                base_version_id, proposed_content, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(protocol_id)
+        .bind(&amendment_no)
+        .bind(req.title.trim())
+        .bind(req.reason.trim())
+        .bind(&change_types)
+        .bind(ProtocolStatus::Draft)
+        .bind(base.id)
+        .bind(proposed_content)
+        .bind(created_by)
+        .fetch_one(pool)
+        .await?;
+
+        AuditService::log(
+            pool,
+            created_by,
+            AuditAction::Create,
+            "protocol_amendment",
+            amendment.id,
+            None,
+            Some(serde_json::to_value(&amendment).unwrap_or_default()),
+        )
+        .await?;
+
+        Ok(amendment)
+    }
+
+    /// 列出計畫的修正案歷程（新到舊）
+    pub async fn list_by_protocol(pool: &PgPool, protocol_id: Uuid) -> Result<Vec<ProtocolAmendment>> {
+        let amendments = sqlx::query_as::<_, ProtocolAmendment>(
+            r#"
+            SELECT * FROM protocol_amendments
+            WHERE protocol_id = $1 AND status <> 'DELETED'
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(protocol_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(amendments)
+    }
+
+    /// 取得修正案
+    pub async fn get(pool: &PgPool, id: Uuid) -> Result<ProtocolAmendment> {
+        sqlx::query_as::<_, ProtocolAmendment>("SELECT * FROM protocol_amendments WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Amendment not found".to_string()))
+    }
+
+    /// 更新修正案（僅限草稿或需修訂）
+    pub async fn update(
+        pool: &PgPool,
+        id: Uuid,
+        req: &UpdateAmendmentRequest,
+        updated_by: Uuid,
+    ) -> Result<ProtocolAmendment> {
+        let before = Self::get(pool, id).await?;
+        Self::ensure_editable(&before)?;
+
+        let change_types: Option<Vec<&str>> = req.change_types
+            .as_ref()
+            .map(|types| types.iter().map(|t| t.as_str()).collect());
+
+        let amendment = sqlx::query_as::<_, ProtocolAmendment>(
+            r#"
+            UPDATE protocol_amendments SET
+                title = COALESCE($2, title),
+                reason = COALESCE($3, reason),
+                change_types = COALESCE($4, change_types),
+                proposed_content = COALESCE($5, proposed_content),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(req.title.as_deref().map(str::trim))
+        .bind(req.reason.as_deref().map(str::trim))
+        .bind(change_types)
+        .bind(&req.proposed_content)
+        .fetch_one(pool)
+        .await?;
+
+        AuditService::log(
+            pool,
+            updated_by,
+            AuditAction::Update,
+            "protocol_amendment",
+            id,
+            Some(serde_json::to_value(&before).unwrap_or_default()),
+            Some(serde_json::to_value(&amendment).unwrap_or_default()),
+        )
+        .await?;
+
+        Ok(amendment)
+    }
+
+    /// 提交修正案：草稿提交為 SUBMITTED，需修訂者重送為 RESUBMITTED
+    ///
+    /// 修正內容須通過計畫內容驗證，且須與核准版本有差異。
+    pub async fn submit(pool: &PgPool, id: Uuid, submitted_by: Uuid) -> Result<ProtocolAmendment> {
+        let before = Self::get(pool, id).await?;
+        Self::ensure_editable(&before)?;
+
+        ProtocolService::validate_protocol_content(&Some(before.proposed_content.clone()))?;
+        let base = Self::get_base_version(pool, &before).await?;
+        let (total_changes, _) = diff_sections(&base.content_snapshot, &before.proposed_content, &[]);
+        if total_changes == 0 {
+            return Err(AppError::Validation("修正案內容與核准版本相同，沒有可審查的變更".to_string()));
+        }
+
+        let new_status = if before.status == ProtocolStatus::RevisionRequired {
+            ProtocolStatus::Resubmitted
+        } else {
+            ProtocolStatus::Submitted
+        };
+
+        let amendment = sqlx::query_as::<_, ProtocolAmendment>(
+            r#"
+            UPDATE protocol_amendments SET
+                status = $2,
+                revision_no = revision_no + 1,
+                submitted_at = NOW(),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(new_status)
+        .fetch_one(pool)
+        .await?;
+
+        Self::log_status_change(pool, submitted_by, &before, &amendment, None).await?;
+
+        Ok(amendment)
+    }
+
+    /// 撤回尚未送審的修正案（草稿或需修訂）
+    pub async fn withdraw(pool: &PgPool, id: Uuid, withdrawn_by: Uuid) -> Result<ProtocolAmendment> {
+        let before = Self::get(pool, id).await?;
+        Self::ensure_editable(&before)?;
+
+        let amendment = sqlx::query_as::<_, ProtocolAmendment>(
+            "UPDATE protocol_amendments SET status = $2, updated_at = NOW() WHERE id = $1 RETURNING *",
+        )
+        .bind(id)
+        .bind(ProtocolStatus::Deleted)
+        .fetch_one(pool)
+        .await?;
+
+        Self::log_status_change(pool, withdrawn_by, &before, &amendment, None).await?;
+
+        Ok(amendment)
+    }
+
+    /// 變更修正案狀態（依修正案狀態轉移表）
+    ///
+    /// 送委員審查時指派選定的審查委員；核准時產生新的計畫版本並更新計畫內容。
+    pub async fn change_status(
+        pool: &PgPool,
+        id: Uuid,
+        req: &ChangeStatusRequest,
+        current_user: &CurrentUser,
+        config: &Config,
+    ) -> Result<ProtocolAmendment> {
+        let before = Self::get(pool, id).await?;
+
+        let transition = ProtocolTransition::find_in(AMENDMENT_TRANSITIONS, before.status, req.to_status)
+            .ok_or_else(|| {
+                let allowed: Vec<&str> = ProtocolTransition::from_status_in(AMENDMENT_TRANSITIONS, before.status)
+                    .map(|t| t.to.as_str())
+                    .collect();
+                AppError::Conflict(format!(
+                    "Cannot change amendment status from {} to {}. Allowed next statuses: {}",
+                    before.status.as_str(),
+                    req.to_status.as_str(),
+                    if allowed.is_empty() { "none".to_string() } else { allowed.join(", ") }
+                ))
+            })?;
+
+        if !transition.allows_roles(&current_user.roles) {
+            return Err(AppError::Forbidden(format!(
+                "Changing amendment status from {} to {} requires role: {}",
+                before.status.as_str(),
+                req.to_status.as_str(),
+                transition.roles.join(" / ")
+            )));
+        }
+
+        Self::check_transition_preconditions(pool, &before, transition, req, current_user).await?;
+
+        // 核准簽章與狀態更新於同一交易，狀態更新失敗時不留下簽章
+        let mut tx = pool.begin().await?;
+
+        // 鎖定修正案，避免同時核准產生兩個生效版本
+        let locked_status: ProtocolStatus =
+            sqlx::query_scalar("SELECT status FROM protocol_amendments WHERE id = $1 FOR UPDATE")
+                .bind(id)
+                .fetch_one(&mut *tx)
+                .await?;
+        if locked_status != before.status {
+            return Err(AppError::Conflict(
+                "Amendment status has changed, please refresh and try again".to_string(),
+            ));
+        }
+
+        if transition.preconditions.contains(&TransitionPrecondition::ElectronicSignature) {
+            Self::ensure_approval_signature(pool, &mut tx, &before, req, current_user).await?;
+        }
+
+        let remark = req.remark.as_deref().map(str::trim).filter(|r| !r.is_empty());
+        let amendment = if req.to_status == ProtocolStatus::Approved {
+            Self::apply(&mut tx, &before, remark, current_user.id).await?
+        } else {
+            let is_decision = req.to_status == ProtocolStatus::Rejected;
+            sqlx::query_as::<_, ProtocolAmendment>(
+                r#"
+                UPDATE protocol_amendments SET
+                    status = $2,
+                    decided_by = CASE WHEN $3 THEN $4 ELSE decided_by END,
+                    decided_at = CASE WHEN $3 THEN NOW() ELSE decided_at END,
+                    decision_remark = CASE WHEN $3 THEN $5 ELSE decision_remark END,
+                    updated_at = NOW()
+                WHERE id = $1
+                RETURNING *
+                "#,
+            )
+            .bind(id)
+            .bind(req.to_status)
+            .bind(is_decision)
+            .bind(current_user.id)
+            .bind(remark)
+            .fetch_one(&mut *tx)
+            .await?
+        };
+        tx.commit().await?;
+
+        Self::log_status_change(pool, current_user.id, &before, &amendment, remark).await?;
+
+        if req.to_status == ProtocolStatus::UnderReview {
+            for reviewer_id in req.reviewer_ids.iter().flatten() {
+                Self::assign_reviewer(pool, &amendment, *reviewer_id, current_user.id, config.review_due_working_days).await?;
+            }
+        }
+
+        Ok(amendment)
+    }
+
+    /// 取得目前使用者可執行的修正案狀態轉移
+    pub async fn get_available_transitions(
+        pool: &PgPool,
+        id: Uuid,
+        current_user: &CurrentUser,
+    ) -> Result<Vec<ProtocolTransitionOption>> {
+        let amendment = Self::get(pool, id).await?;
+
+        let mut options = Vec::new();
+        for transition in ProtocolTransition::from_status_in(AMENDMENT_TRANSITIONS, amendment.status) {
+            if !transition.allows_roles(&current_user.roles) {
+                continue;
+            }
+
+            let mut unmet_preconditions = Vec::new();
+            for precondition in transition.preconditions {
+                if let Some(message) = Self::unmet_precondition(pool, id, *precondition).await? {
+                    unmet_preconditions.push(message);
+                }
+            }
+
+            options.push(ProtocolTransitionOption {
+                to_status: transition.to,
+                to_status_display: transition.to.display_name().to_string(),
+                required_inputs: transition.preconditions
+                    .iter()
+                    .copied()
+                    .filter(|p| p.is_request_input())
+                    .collect(),
+                can_transition: unmet_preconditions.is_empty(),
+                unmet_preconditions,
+            });
+        }
+
+        Ok(options)
+    }
+
+    /// 比較修正內容與核准版本（比較基準），依章節列出變更
+    pub async fn diff(pool: &PgPool, id: Uuid) -> Result<ProtocolAmendmentDiff> {
+        let amendment = Self::get(pool, id).await?;
+        let base = Self::get_base_version(pool, &amendment).await?;
+
+        let open_comments: Vec<(String, i64)> = sqlx::query_as(
+            r#"
+            SELECT section, COUNT(*)
+            FROM review_comments
+            WHERE amendment_id = $1
+              AND section IS NOT NULL
+              AND is_resolved = false
+            GROUP BY section
+            "#,
+        )
+        .bind(id)
+        .fetch_all(pool)
+        .await?;
+
+        let (total_changes, sections) = diff_sections(&base.content_snapshot, &amendment.proposed_content, &open_comments);
+
+        Ok(ProtocolAmendmentDiff {
+            amendment_id: amendment.id,
+            protocol_id: amendment.protocol_id,
+            base_version: base.version_no,
+            base_submitted_at: base.submitted_at,
+            total_changes,
+            sections,
+        })
+    }
+
+    /// 列出修正案的審查指派
+    pub async fn list_assignments(pool: &PgPool, id: Uuid) -> Result<Vec<ReviewAssignment>> {
+        let assignments = sqlx::query_as::<_, ReviewAssignment>(
+            "SELECT * FROM review_assignments WHERE amendment_id = $1 ORDER BY assigned_at",
+        )
+        .bind(id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(assignments)
+    }
+
+    /// 審查委員完成修正案審查
+    pub async fn complete_review(pool: &PgPool, id: Uuid, reviewer_id: Uuid) -> Result<ReviewAssignment> {
+        let amendment = Self::get(pool, id).await?;
+        if amendment.status != ProtocolStatus::UnderReview {
+            return Err(AppError::Conflict(format!(
+                "Reviews can only be completed while the amendment is UNDER_REVIEW (current: {})",
+                amendment.status.as_str()
+            )));
+        }
+
+        let assignment = sqlx::query_as::<_, ReviewAssignment>(
+            r#"
+            UPDATE review_assignments SET completed_at = NOW()
+            WHERE amendment_id = $1 AND reviewer_id = $2
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(reviewer_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Review assignment not found".to_string()))?;
+
+        Ok(assignment)
+    }
+
+    /// 新增修正案審查意見（回覆與解決沿用審查意見端點）
+    pub async fn add_comment(
+        pool: &PgPool,
+        id: Uuid,
+        req: &CreateAmendmentCommentRequest,
+        reviewer_id: Uuid,
+    ) -> Result<ReviewComment> {
+        let amendment = Self::get(pool, id).await?;
+        if matches!(
+            amendment.status,
+            ProtocolStatus::Draft | ProtocolStatus::Approved | ProtocolStatus::Rejected | ProtocolStatus::Deleted
+        ) {
+            return Err(AppError::Conflict(format!(
+                "Comments cannot be added to an amendment in {} status",
+                amendment.status.as_str()
+            )));
+        }
+
+        let comment = sqlx::query_as::<_, ReviewComment>(
+            r#"
+            INSERT INTO review_comments (id, protocol_version_id, amendment_id, reviewer_id, content, section, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, NOW(), NOW())
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(amendment.base_version_id)
+        .bind(id)
+        .bind(reviewer_id)
+        .bind(&req.content)
+        .bind(&req.section)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(comment)
+    }
+
+    /// 取得修正案審查意見（含回覆）
+    pub async fn get_comments(pool: &PgPool, id: Uuid) -> Result<Vec<ReviewCommentResponse>> {
+        let comments = sqlx::query_as::<_, ReviewCommentResponse>(
+            r#"
+            SELECT
+                c.id, c.protocol_version_id, c.reviewer_id,
+                u.display_name as reviewer_name, u.email as reviewer_email,
+                c.content, c.is_resolved, c.resolved_by, c.resolved_at,
+                c.parent_comment_id, c.replied_by,
+                ru.display_name as replied_by_name, ru.email as replied_by_email,
+                c.section, c.created_at
+            FROM review_comments c
+            LEFT JOIN users u ON c.reviewer_id = u.id
+            LEFT JOIN users ru ON c.replied_by = ru.id
+            WHERE c.amendment_id = $1
+            ORDER BY
+                COALESCE(c.parent_comment_id, c.id) ASC,
+                c.created_at ASC
+            "#,
+        )
+        .bind(id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(comments)
+    }
+
+    /// 生成修正案編號
+    /// 格式：{IACUC No.}-A{序號:02}，例如：PIG-115001-A01
+    async fn generate_amendment_no(pool: &PgPool, protocol_id: Uuid, iacuc_no: &str) -> Result<String> {
+        let amendment_nos: Vec<String> = sqlx::query_scalar(
+            "SELECT amendment_no FROM protocol_amendments WHERE protocol_id = $1",
+        )
+        .bind(protocol_id)
+        .fetch_all(pool)
+        .await?;
+
+        let seq = next_amendment_seq(&amendment_nos);
+        Ok(format!("{}-A{:02}", iacuc_no, seq))
+    }
+
+    /// 核准修正案：以修正內容建立新的計畫版本，並更新計畫內容
+    async fn apply(
+        tx: &mut Transaction<'_, Postgres>,
+        amendment: &ProtocolAmendment,
+        remark: Option<&str>,
+        approved_by: Uuid,
+    ) -> Result<ProtocolAmendment> {
+        // 鎖定計畫，避免版本號重複
+        let protocol_status: ProtocolStatus =
+            sqlx::query_scalar("SELECT status FROM protocols WHERE id = $1 FOR UPDATE")
+                .bind(amendment.protocol_id)
+                .fetch_one(&mut **tx)
+                .await?;
+        if !matches!(protocol_status, ProtocolStatus::Approved | ProtocolStatus::ApprovedWithConditions) {
+            return Err(AppError::BusinessRule(format!(
+                "Amendments can only take effect on approved protocols (current: {})",
+                protocol_status.as_str()
+            )));
+        }
+
+        let version_no: i32 = sqlx::query_scalar(
+            "SELECT COALESCE(MAX(version_no), 0) + 1 FROM protocol_versions WHERE protocol_id = $1",
+        )
+        .bind(amendment.protocol_id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        let version_id = Uuid::new_v4();
+        sqlx::query(
+            r#"
+            INSERT INTO protocol_versions (id, protocol_id, version_no, content_snapshot, submitted_at, submitted_by, amendment_id)
+            VALUES ($1, $2, $3, $4, NOW(), $5, $6)
+            "#,
+        )
+        .bind(version_id)
+        .bind(amendment.protocol_id)
+        .bind(version_no)
+        .bind(&amendment.proposed_content)
+        .bind(amendment.created_by)
+        .bind(amendment.id)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query("UPDATE protocols SET working_content = $2, updated_at = NOW() WHERE id = $1")
+            .bind(amendment.protocol_id)
+            .bind(&amendment.proposed_content)
+            .execute(&mut **tx)
+            .await?;
+
+        let approved = sqlx::query_as::<_, ProtocolAmendment>(
+            r#"
+            UPDATE protocol_amendments SET
+                status = $2,
+                decided_by = $3,
+                decided_at = NOW(),
+                decision_remark = $4,
+                effective_version_id = $5,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(amendment.id)
+        .bind(ProtocolStatus::Approved)
+        .bind(approved_by)
+        .bind(remark)
+        .bind(version_id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        // 計畫狀態不變，於狀態歷程記錄修正案生效
+        sqlx::query(
+            r#"
+            INSERT INTO protocol_status_history (id, protocol_id, from_status, to_status, changed_by, remark, created_at)
+            VALUES ($1, $2, $3, $3, $4, $5, NOW())
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(amendment.protocol_id)
+        .bind(protocol_status)
+        .bind(approved_by)
+        .bind(format!("修正案 {} 核准生效（版本 {}）", amendment.amendment_no, version_no))
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(approved)
+    }
+
+    /// 檢查修正案狀態轉移前置條件（電子簽章於狀態更新交易內處理）
+    async fn check_transition_preconditions(
+        pool: &PgPool,
+        amendment: &ProtocolAmendment,
+        transition: &ProtocolTransition,
+        req: &ChangeStatusRequest,
+        current_user: &CurrentUser,
+    ) -> Result<()> {
+        for precondition in transition.preconditions {
+            match precondition {
+                TransitionPrecondition::ReviewersSelected => {
+                    let count = req.reviewer_ids.as_ref().map(|ids| ids.len()).unwrap_or(0);
+                    if !(2..=3).contains(&count) {
+                        return Err(AppError::Validation(precondition.description().to_string()));
+                    }
+                    for reviewer_id in req.reviewer_ids.iter().flatten() {
+                        ReviewerConflictService::ensure_eligible(pool, amendment.protocol_id, *reviewer_id, current_user.id).await?;
+                    }
+                }
+                TransitionPrecondition::RemarkRequired => {
+                    let has_remark = req.remark.as_ref().is_some_and(|r| !r.trim().is_empty());
+                    if !has_remark {
+                        return Err(AppError::Validation(precondition.description().to_string()));
+                    }
+                }
+                TransitionPrecondition::AllReviewsCompleted
+                | TransitionPrecondition::NoUnresolvedComments => {
+                    if let Some(message) = Self::unmet_precondition(pool, amendment.id, *precondition).await? {
+                        return Err(AppError::Conflict(message));
+                    }
+                }
+                TransitionPrecondition::ElectronicSignature => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 檢查修正案資料狀態類前置條件，未滿足時回傳說明
+    async fn unmet_precondition(
+        pool: &PgPool,
+        amendment_id: Uuid,
+        precondition: TransitionPrecondition,
+    ) -> Result<Option<String>> {
+        match precondition {
+            TransitionPrecondition::AllReviewsCompleted => {
+                let (total, pending): (i64, i64) = sqlx::query_as(
+                    r#"
+                    SELECT COUNT(*), COUNT(*) FILTER (WHERE completed_at IS NULL)
+                    FROM review_assignments
+                    WHERE amendment_id = $1
+                    "#,
+                )
+                .bind(amendment_id)
+                .fetch_one(pool)
+                .await?;
+
+                if total == 0 {
+                    Ok(Some(format!("{}（尚未指派審查委員）", precondition.description())))
+                } else if pending > 0 {
+                    Ok(Some(format!("{}（尚有 {} 位審查委員未完成）", precondition.description(), pending)))
+                } else {
+                    Ok(None)
+                }
+            }
+            TransitionPrecondition::NoUnresolvedComments => {
+                let unresolved: i64 = sqlx::query_scalar(
+                    "SELECT COUNT(*) FROM review_comments WHERE amendment_id = $1 AND is_resolved = false",
+                )
+                .bind(amendment_id)
+                .fetch_one(pool)
+                .await?;
+
+                if unresolved > 0 {
+                    Ok(Some(format!("{}（尚有 {} 則未解決）", precondition.description(), unresolved)))
+                } else {
+                    Ok(None)
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// 核准電子簽章：每次核准皆須以請求密碼重新驗證並於同一交易內簽章
+    async fn ensure_approval_signature(
+        pool: &PgPool,
+        tx: &mut Transaction<'_, Postgres>,
+        amendment: &ProtocolAmendment,
+        req: &ChangeStatusRequest,
+        current_user: &CurrentUser,
+    ) -> Result<()> {
+        let entity_id = amendment.id.to_string();
+        let password = req.signature_password.as_deref()
+            .filter(|p| !p.is_empty())
+            .ok_or_else(|| AppError::Validation(
+                TransitionPrecondition::ElectronicSignature.description().to_string()
+            ))?;
+
+        let user = AuthService::verify_password_by_id(pool, current_user.id, password)
+            .await
+            .map_err(|_| AppError::Unauthorized)?;
+
+        SignatureService::sign_entity(
+            tx,
+            "protocol_amendment",
+            &entity_id,
+            current_user.id,
+            &user.password_hash,
+            SignatureType::Approve,
+            SignatureMeaning::Approval,
+        ).await?;
+
+        Ok(())
+    }
+
+    /// 指派修正案審查委員（審查期限同計畫審查），並以站內通知告知
+    async fn assign_reviewer(
+        pool: &PgPool,
+        amendment: &ProtocolAmendment,
+        reviewer_id: Uuid,
+        assigned_by: Uuid,
+        due_working_days: i64,
+    ) -> Result<ReviewAssignment> {
+        let due_date = ReviewDeadlineService::due_date_from_today(due_working_days);
+        let assignment = sqlx::query_as::<_, ReviewAssignment>(
+            r#"
+            INSERT INTO review_assignments (id, protocol_id, amendment_id, reviewer_id, assigned_by, assigned_at, due_date)
+            VALUES ($1, $2, $3, $4, $5, NOW(), $6)
+            ON CONFLICT (amendment_id, reviewer_id) WHERE amendment_id IS NOT NULL DO UPDATE SET
+                assigned_at = NOW(), completed_at = NULL,
+                due_date = EXCLUDED.due_date, last_reminded_on = NULL, escalated_at = NULL
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(amendment.protocol_id)
+        .bind(amendment.id)
+        .bind(reviewer_id)
+        .bind(assigned_by)
+        .bind(due_date)
+        .fetch_one(pool)
+        .await?;
+
+        let pi_name: Option<String> = sqlx::query_scalar(
+            "SELECT u.display_name FROM protocols p JOIN users u ON u.id = p.pi_user_id WHERE p.id = $1",
+        )
+        .bind(amendment.protocol_id)
+        .fetch_optional(pool)
+        .await?;
+        if let Err(e) = NotificationService::new(pool.clone())
+            .notify_review_assignment(
+                amendment.protocol_id,
+                &amendment.amendment_no,
+                &amendment.title,
+                pi_name.as_deref().unwrap_or("-"),
+                reviewer_id,
+                Some(&due_date.to_string()),
+            )
+            .await
+        {
+            tracing::warn!("Failed to notify reviewer {} of amendment assignment: {}", reviewer_id, e);
+        }
+
+        Ok(assignment)
+    }
+
+    async fn get_base_version(pool: &PgPool, amendment: &ProtocolAmendment) -> Result<ProtocolVersion> {
+        sqlx::query_as::<_, ProtocolVersion>("SELECT * FROM protocol_versions WHERE id = $1")
+            .bind(amendment.base_version_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Base protocol version not found".to_string()))
+    }
+
+    fn ensure_editable(amendment: &ProtocolAmendment) -> Result<()> {
+        if !matches!(amendment.status, ProtocolStatus::Draft | ProtocolStatus::RevisionRequired) {
+            return Err(AppError::BusinessRule(format!(
+                "Only draft or revision-required amendments can be changed (current: {})",
+                amendment.status.as_str()
+            )));
+        }
+        Ok(())
+    }
+
+    async fn log_status_change(
+        pool: &PgPool,
+        changed_by: Uuid,
+        before: &ProtocolAmendment,
+        after: &ProtocolAmendment,
+        remark: Option<&str>,
+    ) -> Result<()> {
+        AuditService::log(
+            pool,
+            changed_by,
+            AuditAction::StatusChange,
+            "protocol_amendment",
+            after.id,
+            Some(serde_json::json!({ "status": before.status })),
+            Some(serde_json::json!({
+                "status": after.status,
+                "remark": remark,
+                "effective_version_id": after.effective_version_id,
+            })),
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// 下一個修正案序號（取編號中 -A 之後的流水號最大值加一）
+fn next_amendment_seq(amendment_nos: &[String]) -> i32 {
+    amendment_nos
+        .iter()
+        .filter_map(|no| no.rsplit_once("-A").and_then(|(_, seq)| seq.parse::<i32>().ok()))
+        .max()
+        .map(|s| s + 1)
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_amendment_seq() {
+        assert_eq!(next_amendment_seq(&[]), 1);
+        let nos = vec![
+            "PIG-115001-A01".to_string(),
+            "PIG-115001-A03".to_string(),
+            "PIG-115001-A02".to_string(),
+        ];
+        assert_eq!(next_amendment_seq(&nos), 4);
+    }
+
+    #[test]
+    fn test_amendment_transitions_require_review_before_approval() {
+        assert!(ProtocolTransition::find_in(AMENDMENT_TRANSITIONS, ProtocolStatus::Submitted, ProtocolStatus::Approved).is_none());
+
+        let approve = ProtocolTransition::find_in(AMENDMENT_TRANSITIONS, ProtocolStatus::UnderReview, ProtocolStatus::Approved)
+            .expect("amendments can be approved after review");
+        assert!(approve.preconditions.contains(&TransitionPrecondition::AllReviewsCompleted));
+        assert!(approve.preconditions.contains(&TransitionPrecondition::ElectronicSignature));
+        assert!(!approve.allows_roles(&["IACUC_STAFF".to_string()]));
+        assert!(approve.allows_roles(&["CHAIR".to_string()]));
+    }
+
+    use crate::test_support::{app_state, current_user, insert_protocol, insert_user, set_password};
+
+    /// 已完成審查、待主委核准的修正案；回傳 (修正案 ID, 主委)
+    async fn amendment_under_review(db: &PgPool) -> (Uuid, CurrentUser) {
+        let pi = insert_user(db, "pi@example.com", false).await;
+        let chair_id = insert_user(db, "chair@example.com", true).await;
+        let reviewer = insert_user(db, "reviewer@example.com", true).await;
+        let protocol_id = insert_protocol(db, pi, "PIG-AMEND-1").await;
+
+        let base_version: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO protocol_versions (id, protocol_id, version_no, content_snapshot, submitted_by)
+            VALUES ($1, $2, 1, '{"basic": {"title": "原計畫"}}', $3)
+            RETURNING id
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(protocol_id)
+        .bind(pi)
+        .fetch_one(db)
+        .await
+        .unwrap();
+
+        let amendment_id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO protocol_amendments (
+                id, protocol_id, amendment_no, title, reason, change_types, status,
+                base_version_id, proposed_content, created_by
+            )
+            VALUES ($1, $2, 'PIG-AMEND-1-A01', '增加動物數量', '樣本數不足', '{}', 'UNDER_REVIEW', $3,
+                    '{"basic": {"title": "修正後計畫"}}', $4)
+            RETURNING id
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(protocol_id)
+        .bind(base_version)
+        .bind(pi)
+        .fetch_one(db)
+        .await
+        .unwrap();
+
+        sqlx::query(
+            r#"
+            INSERT INTO review_assignments (id, protocol_id, reviewer_id, assigned_by, completed_at, amendment_id)
+            VALUES ($1, $2, $3, $4, NOW(), $5)
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(protocol_id)
+        .bind(reviewer)
+        .bind(chair_id)
+        .bind(amendment_id)
+        .execute(db)
+        .await
+        .unwrap();
+
+        set_password(db, chair_id, "chair-password").await;
+
+        (amendment_id, current_user(chair_id, "chair@example.com", &["CHAIR"], &[]))
+    }
+
+    fn approve_request() -> ChangeStatusRequest {
+        ChangeStatusRequest {
+            to_status: ProtocolStatus::Approved,
+            remark: None,
+            reviewer_ids: None,
+            signature_password: Some("chair-password".to_string()),
+        }
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_approval_creates_effective_version(db: PgPool) {
+        let (id, chair) = amendment_under_review(&db).await;
+        let config = app_state(db.clone()).config;
+
+        let approved = ProtocolAmendmentService::change_status(&db, id, &approve_request(), &chair, &config)
+            .await
+            .unwrap();
+        assert_eq!(approved.status, ProtocolStatus::Approved);
+
+        let version_id = approved.effective_version_id.expect("approval sets the effective version");
+        let (version_no, amendment_id): (i32, Option<Uuid>) =
+            sqlx::query_as("SELECT version_no, amendment_id FROM protocol_versions WHERE id = $1")
+                .bind(version_id)
+                .fetch_one(&db)
+                .await
+                .unwrap();
+        assert_eq!((version_no, amendment_id), (2, Some(id)));
+
+        let working_title: Option<String> = sqlx::query_scalar(
+            "SELECT working_content->'basic'->>'title' FROM protocols WHERE id = $1",
+        )
+        .bind(approved.protocol_id)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert_eq!(working_title.as_deref(), Some("修正後計畫"));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_approval_rejected_once_protocol_is_no_longer_approved(db: PgPool) {
+        let (id, chair) = amendment_under_review(&db).await;
+        let config = app_state(db.clone()).config;
+        sqlx::query("UPDATE protocols SET status = 'SUSPENDED' WHERE protocol_no = 'PIG-AMEND-1'")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let result = ProtocolAmendmentService::change_status(&db, id, &approve_request(), &chair, &config).await;
+        assert!(matches!(result, Err(AppError::BusinessRule(_))));
+        let amendment = ProtocolAmendmentService::get(&db, id).await.unwrap();
+        assert_eq!((amendment.status, amendment.effective_version_id), (ProtocolStatus::UnderReview, None));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_concurrent_approvals_create_one_version(db: PgPool) {
+        let (id, chair) = amendment_under_review(&db).await;
+        let config = app_state(db.clone()).config;
+        let req = approve_request();
+
+        let (first, second) = tokio::join!(
+            ProtocolAmendmentService::change_status(&db, id, &req, &chair, &config),
+            ProtocolAmendmentService::change_status(&db, id, &req, &chair, &config),
+        );
+        assert_eq!([&first, &second].iter().filter(|r| r.is_ok()).count(), 1);
+        assert!([first, second].into_iter().any(|r| matches!(r, Err(AppError::Conflict(_)))));
+
+        let versions: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM protocol_versions WHERE amendment_id = $1")
+            .bind(id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(versions, 1);
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_protocol_signature_survives_amendment(db: PgPool) {
+        let (id, chair) = amendment_under_review(&db).await;
+        let config = app_state(db.clone()).config;
+        let protocol_id: Uuid = sqlx::query_scalar("SELECT protocol_id FROM protocol_amendments WHERE id = $1")
+            .bind(id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+
+        // 原計畫核准簽章
+        let mut conn = db.acquire().await.unwrap();
+        SignatureService::sign_entity(
+            &mut conn,
+            "protocol",
+            &protocol_id.to_string(),
+            chair.id,
+            "x",
+            SignatureType::Approve,
+            SignatureMeaning::Approval,
+        )
+        .await
+        .unwrap();
+        drop(conn);
+
+        ProtocolAmendmentService::change_status(&db, id, &approve_request(), &chair, &config)
+            .await
+            .unwrap();
+
+        let signatures = SignatureService::verify_entity(&db, "protocol", &protocol_id.to_string(), chair.id)
+            .await
+            .unwrap();
+        assert_eq!(signatures.len(), 1);
+        assert!(signatures[0].content_matches);
+    }
+}
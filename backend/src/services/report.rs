@@ -118,10 +118,19 @@ impl ReportService {
                     SUM(CASE 
                         WHEN direction IN ('in', 'transfer_in', 'adjust_in') THEN qty_base 
                         ELSE -qty_base 
-                    END) as qty_on_hand,
-                    AVG(unit_cost) FILTER (WHERE unit_cost IS NOT NULL) as avg_cost
+                    END) as qty_on_hand
                 FROM stock_ledger
                 GROUP BY warehouse_id, product_id
+            ),
+            valuation AS (
+                SELECT
+                    warehouse_id,
+                    product_id,
+                    SUM(remaining_qty * unit_cost) as total_value,
+                    ROUND(SUM(remaining_qty * unit_cost) / NULLIF(SUM(remaining_qty), 0), 4) as avg_cost
+                FROM stock_cost_layers
+                WHERE remaining_qty > 0
+                GROUP BY warehouse_id, product_id
             )
             SELECT 
                 w.id as warehouse_id,
@@ -133,13 +142,14 @@ impl ReportService {
                 pc.name as category_name,
                 p.base_uom,
                 COALESCE(i.qty_on_hand, 0) as qty_on_hand,
-                i.avg_cost,
-                COALESCE(i.qty_on_hand, 0) * COALESCE(i.avg_cost, 0) as total_value,
+                v.avg_cost,
+                COALESCE(v.total_value, 0) as total_value,
                 p.safety_stock,
                 p.reorder_point
             FROM warehouses w
             CROSS JOIN products p
             LEFT JOIN inventory i ON w.id = i.warehouse_id AND p.id = i.product_id
+            LEFT JOIN valuation v ON w.id = v.warehouse_id AND p.id = v.product_id
             LEFT JOIN product_categories pc ON p.category_id = pc.id
             WHERE w.is_active = true AND p.is_active = true
             "#
@@ -293,14 +303,23 @@ impl ReportService {
                     SUM(CASE 
                         WHEN direction IN ('in', 'transfer_in', 'adjust_in') THEN qty_base 
                         ELSE -qty_base 
-                    END) as qty_on_hand,
-                    AVG(unit_cost) FILTER (WHERE unit_cost IS NOT NULL) as avg_cost
+                    END) as qty_on_hand
                 FROM stock_ledger
                 GROUP BY warehouse_id, product_id
                 HAVING SUM(CASE 
                     WHEN direction IN ('in', 'transfer_in', 'adjust_in') THEN qty_base 
                     ELSE -qty_base 
                 END) > 0
+            ),
+            valuation AS (
+                SELECT
+                    warehouse_id,
+                    product_id,
+                    SUM(remaining_qty * unit_cost) as total_value,
+                    ROUND(SUM(remaining_qty * unit_cost) / NULLIF(SUM(remaining_qty), 0), 4) as avg_cost
+                FROM stock_cost_layers
+                WHERE remaining_qty > 0
+                GROUP BY warehouse_id, product_id
             )
             SELECT 
                 w.id as warehouse_id,
@@ -311,9 +330,10 @@ impl ReportService {
                 p.name as product_name,
                 pc.name as category_name,
                 i.qty_on_hand,
-                i.avg_cost,
-                i.qty_on_hand * COALESCE(i.avg_cost, 0) as total_value
+                v.avg_cost,
+                COALESCE(v.total_value, 0) as total_value
             FROM inventory i
+            LEFT JOIN valuation v ON i.warehouse_id = v.warehouse_id AND i.product_id = v.product_id
             INNER JOIN warehouses w ON i.warehouse_id = w.id
             INNER JOIN products p ON i.product_id = p.id
             LEFT JOIN product_categories pc ON p.category_id = pc.id
@@ -0,0 +1,577 @@
+// 定期報表執行服務
+// 依 scheduled_reports 的排程產生報表檔、寫入 report_history，並通知與寄送給收件人
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Months, NaiveDate, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    models::{
+        CreateNotificationRequest, ExpiryAlert, LowStockAlert, NotificationType, ReportFileFormat,
+        ReportHistory, ReportType, ScheduleType, ScheduledReport,
+    },
+    services::{
+        report::{ReportQuery, ReportService},
+        EmailAttachment, EmailService, ExportTable, FileCategory, FileService, MedicalExportService,
+        NotificationService,
+    },
+    AppError, Result,
+};
+
+/// scheduled_reports 查詢欄位（enum 欄位轉為文字）
+pub(crate) const SCHEDULED_REPORT_COLUMNS: &str = r#"
+    id, report_type::text AS report_type, schedule_type::text AS schedule_type,
+    day_of_week, day_of_month, hour_of_day, parameters, recipients, is_active,
+    last_run_at, next_run_at, created_by, created_at, updated_at
+"#;
+
+/// report_history 查詢欄位（enum 欄位轉為文字）
+pub(crate) const REPORT_HISTORY_COLUMNS: &str = r#"
+    id, scheduled_report_id, report_type::text AS report_type, file_name, file_path,
+    file_size, parameters, generated_at, generated_by
+"#;
+
+/// 排程時間以台灣時間（UTC+8）解讀
+fn local_offset() -> FixedOffset {
+    FixedOffset::east_opt(8 * 3600).unwrap()
+}
+
+pub struct ScheduledReportRunner;
+
+impl ScheduledReportRunner {
+    /// 執行所有到期的定期報表；停機期間錯過的排程會合併為一次補跑
+    pub async fn run_due(pool: &PgPool, config: &Config) -> Result<usize> {
+        Self::initialize_next_runs(pool).await?;
+
+        let due: Vec<ScheduledReport> = sqlx::query_as(&format!(
+            r#"
+            SELECT {SCHEDULED_REPORT_COLUMNS} FROM scheduled_reports
+            WHERE is_active = true AND next_run_at <= NOW()
+            ORDER BY next_run_at
+            "#
+        ))
+        .fetch_all(pool)
+        .await?;
+
+        let mut count = 0;
+        for report in due {
+            match Self::run_scheduled(pool, config, &report).await {
+                Ok(true) => count += 1,
+                Ok(false) => {}
+                Err(e) => tracing::error!("Scheduled report {} failed: {}", report.id, e),
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// 立即執行一次（手動觸發，不影響排程）
+    pub async fn run_now(
+        pool: &PgPool,
+        config: &Config,
+        report: &ScheduledReport,
+        generated_by: Uuid,
+    ) -> Result<ReportHistory> {
+        let schedule = parse_schedule(&report.schedule_type)?;
+        let now = Utc::now();
+        let period_start = report
+            .last_run_at
+            .unwrap_or_else(|| previous_period_start(schedule, now));
+        Self::generate_and_deliver(pool, config, report, period_start, now, Some(generated_by), false).await
+    }
+
+    /// 依排程設定計算下次執行時間
+    pub fn compute_next_run(
+        schedule_type: &str,
+        day_of_week: Option<i32>,
+        day_of_month: Option<i32>,
+        hour_of_day: i32,
+        after: DateTime<Utc>,
+    ) -> Result<DateTime<Utc>> {
+        let schedule = parse_schedule(schedule_type)?;
+        Ok(next_run_after(schedule, day_of_week, day_of_month, hour_of_day, after))
+    }
+
+    /// 解析報表參數（篩選條件與輸出格式）
+    pub fn parse_parameters(
+        parameters: &Option<serde_json::Value>,
+    ) -> Result<(ReportQuery, ReportFileFormat)> {
+        let value = parameters.clone().unwrap_or_else(|| serde_json::json!({}));
+        let query: ReportQuery = serde_json::from_value(value.clone())
+            .map_err(|e| AppError::Validation(format!("Invalid report parameters: {}", e)))?;
+        let format = match value.get("format") {
+            None | Some(serde_json::Value::Null) => ReportFileFormat::default(),
+            Some(format) => serde_json::from_value(format.clone()).map_err(|_| {
+                AppError::Validation("Report format must be 'xlsx' or 'csv'".to_string())
+            })?,
+        };
+        Ok((query, format))
+    }
+
+    /// 為尚未排定的報表補上 next_run_at
+    async fn initialize_next_runs(pool: &PgPool) -> Result<()> {
+        let pending: Vec<ScheduledReport> = sqlx::query_as(&format!(
+            "SELECT {SCHEDULED_REPORT_COLUMNS} FROM scheduled_reports WHERE is_active = true AND next_run_at IS NULL"
+        ))
+        .fetch_all(pool)
+        .await?;
+
+        let now = Utc::now();
+        for report in pending {
+            let next_run = match Self::compute_next_run(
+                &report.schedule_type,
+                report.day_of_week,
+                report.day_of_month,
+                report.hour_of_day,
+                now,
+            ) {
+                Ok(next_run) => next_run,
+                Err(e) => {
+                    tracing::warn!("Scheduled report {} has invalid schedule: {}", report.id, e);
+                    continue;
+                }
+            };
+
+            sqlx::query("UPDATE scheduled_reports SET next_run_at = $2 WHERE id = $1 AND next_run_at IS NULL")
+                .bind(report.id)
+                .bind(next_run)
+                .execute(pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// 執行一筆到期報表；回傳 false 表示已由其他執行個體處理
+    async fn run_scheduled(pool: &PgPool, config: &Config, report: &ScheduledReport) -> Result<bool> {
+        let Some(due_at) = report.next_run_at else {
+            return Ok(false);
+        };
+        let schedule = parse_schedule(&report.schedule_type)?;
+        let now = Utc::now();
+        // 從現在起算下次執行時間，錯過的多個週期只補跑一次（涵蓋整段期間）
+        let next_run = next_run_after(schedule, report.day_of_week, report.day_of_month, report.hour_of_day, now);
+
+        // 以 next_run_at 作為樂觀鎖，避免多個執行個體重複產生
+        let claimed = sqlx::query(
+            "UPDATE scheduled_reports SET next_run_at = $2 WHERE id = $1 AND next_run_at = $3",
+        )
+        .bind(report.id)
+        .bind(next_run)
+        .bind(due_at)
+        .execute(pool)
+        .await?
+        .rows_affected()
+            > 0;
+        if !claimed {
+            return Ok(false);
+        }
+
+        if due_at < now - Duration::hours(1) {
+            tracing::info!("Catching up missed scheduled report {} (due {})", report.id, due_at);
+        }
+
+        let period_start = report
+            .last_run_at
+            .unwrap_or_else(|| previous_period_start(schedule, due_at));
+
+        if let Err(e) = Self::generate_and_deliver(pool, config, report, period_start, now, None, true).await {
+            // 產生失敗時還原排程，下次檢查時重試
+            sqlx::query("UPDATE scheduled_reports SET next_run_at = $2 WHERE id = $1 AND next_run_at = $3")
+                .bind(report.id)
+                .bind(due_at)
+                .bind(next_run)
+                .execute(pool)
+                .await?;
+            return Err(e);
+        }
+
+        Ok(true)
+    }
+
+    /// 產生報表檔、建立歷史記錄並通知收件人
+    ///
+    /// `advance_schedule` 為 true 時（排程執行）更新 last_run_at，作為下次報表期間的起點；
+    /// 手動執行不更新，避免縮短下一份排程報表涵蓋的期間。
+    async fn generate_and_deliver(
+        pool: &PgPool,
+        config: &Config,
+        report: &ScheduledReport,
+        period_start: DateTime<Utc>,
+        period_end: DateTime<Utc>,
+        generated_by: Option<Uuid>,
+        advance_schedule: bool,
+    ) -> Result<ReportHistory> {
+        let report_type = parse_report_type(&report.report_type)?;
+        let (mut query, format) = Self::parse_parameters(&report.parameters)?;
+        let tz = local_offset();
+        query.date_from = query.date_from.or(Some(period_start.with_timezone(&tz).date_naive()));
+        query.date_to = query.date_to.or(Some(period_end.with_timezone(&tz).date_naive()));
+
+        let table = Self::build_table(pool, report_type, &query).await?;
+        let data = match format {
+            ReportFileFormat::Xlsx => MedicalExportService::generate_excel(std::slice::from_ref(&table))?,
+            ReportFileFormat::Csv => generate_csv(&table)?,
+        };
+
+        let file_name = format!(
+            "{}_{}.{}",
+            report_type.display_name(),
+            period_end.with_timezone(&tz).format("%Y%m%d%H%M"),
+            format.extension()
+        );
+        let upload = FileService::upload(
+            pool,
+            FileCategory::ScheduledReport,
+            &file_name,
+            format.mime_type(),
+            &data,
+        )
+        .await?;
+
+        let mut parameters = report.parameters.clone().unwrap_or_else(|| serde_json::json!({}));
+        if let Some(object) = parameters.as_object_mut() {
+            object.insert("date_from".to_string(), serde_json::json!(query.date_from));
+            object.insert("date_to".to_string(), serde_json::json!(query.date_to));
+            object.insert("format".to_string(), serde_json::json!(format));
+        }
+
+        // 報表紀錄寫入上傳回傳的交易，與檔案清除互斥
+        let mut tx = upload.tx;
+        let history: ReportHistory = sqlx::query_as(&format!(
+            r#"
+            INSERT INTO report_history
+                (scheduled_report_id, report_type, file_name, file_path, file_size, parameters, generated_by)
+            VALUES ($1, $2::report_type, $3, $4, $5, $6, $7)
+            RETURNING {REPORT_HISTORY_COLUMNS}
+            "#
+        ))
+        .bind(report.id)
+        .bind(&report.report_type)
+        .bind(&file_name)
+        .bind(&upload.result.file_path)
+        .bind(upload.result.file_size as i32)
+        .bind(&parameters)
+        .bind(generated_by)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if advance_schedule {
+            sqlx::query("UPDATE scheduled_reports SET last_run_at = $2, updated_at = NOW() WHERE id = $1")
+                .bind(report.id)
+                .bind(period_end)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        let attachment = EmailAttachment {
+            file_name,
+            mime_type: format.mime_type().to_string(),
+            data,
+        };
+        Self::deliver(pool, config, report, report_type, &history, &attachment).await?;
+
+        Ok(history)
+    }
+
+    /// 站內通知並寄送 Email 給收件人
+    async fn deliver(
+        pool: &PgPool,
+        config: &Config,
+        report: &ScheduledReport,
+        report_type: ReportType,
+        history: &ReportHistory,
+        attachment: &EmailAttachment,
+    ) -> Result<()> {
+        let recipients: Vec<(Uuid, String, String, bool)> = sqlx::query_as(
+            r#"
+            SELECT u.id, u.email, u.display_name, COALESCE(ns.email_monthly_report, true)
+            FROM users u
+            LEFT JOIN notification_settings ns ON ns.user_id = u.id
+            WHERE u.id = ANY($1) AND u.is_active = true
+            "#,
+        )
+        .bind(&report.recipients)
+        .fetch_all(pool)
+        .await?;
+
+        let service = NotificationService::new(pool.clone());
+        for (user_id, email, display_name, email_enabled) in recipients {
+            if let Err(e) = service
+                .create_notification(CreateNotificationRequest {
+                    user_id,
+                    notification_type: NotificationType::MonthlyReport,
+                    title: format!("[定期報表] {}", report_type.display_name()),
+                    content: Some(format!("報表 {} 已產生，可至報表歷史下載。", history.file_name)),
+                    related_entity_type: Some("report_history".to_string()),
+                    related_entity_id: Some(history.id),
+                })
+                .await
+            {
+                tracing::error!("Failed to notify {} of report {}: {}", user_id, history.id, e);
+            }
+
+            if !email_enabled {
+                continue;
+            }
+            if let Err(e) = EmailService::send_scheduled_report_email(
+                config,
+                &email,
+                &display_name,
+                report_type.display_name(),
+                attachment,
+            )
+            .await
+            {
+                tracing::error!("Failed to send scheduled report email to {}: {}", email, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 取得報表資料並轉為表格
+    async fn build_table(pool: &PgPool, report_type: ReportType, query: &ReportQuery) -> Result<ExportTable> {
+        let title = report_type.display_name().to_string();
+        let table = match report_type {
+            ReportType::StockOnHand => {
+                let rows = ReportService::stock_on_hand(pool, query).await?;
+                ExportTable {
+                    title,
+                    headers: vec!["倉庫代碼", "倉庫", "SKU", "品名", "分類", "單位", "庫存量", "平均成本", "庫存金額", "安全庫存", "再訂購點"],
+                    rows: rows.into_iter().map(|r| vec![
+                        r.warehouse_code,
+                        r.warehouse_name,
+                        r.product_sku,
+                        r.product_name,
+                        r.category_name.unwrap_or_default(),
+                        r.base_uom,
+                        r.qty_on_hand.to_string(),
+                        opt(r.avg_cost),
+                        opt(r.total_value),
+                        opt(r.safety_stock),
+                        opt(r.reorder_point),
+                    ]).collect(),
+                }
+            }
+            ReportType::StockLedger => {
+                let rows = ReportService::stock_ledger(pool, query).await?;
+                ExportTable {
+                    title,
+                    headers: vec!["交易時間", "倉庫代碼", "倉庫", "SKU", "品名", "單據類型", "單號", "方向", "數量", "單位成本", "批號", "效期"],
+                    rows: rows.into_iter().map(|r| vec![
+                        r.trx_date.with_timezone(&local_offset()).format("%Y-%m-%d %H:%M").to_string(),
+                        r.warehouse_code,
+                        r.warehouse_name,
+                        r.product_sku,
+                        r.product_name,
+                        r.doc_type,
+                        r.doc_no,
+                        r.direction,
+                        r.qty_base.to_string(),
+                        opt(r.unit_cost),
+                        r.batch_no.unwrap_or_default(),
+                        opt(r.expiry_date),
+                    ]).collect(),
+                }
+            }
+            ReportType::PurchaseSummary => {
+                let rows = ReportService::purchase_lines(pool, query).await?;
+                ExportTable {
+                    title,
+                    headers: vec!["單據日期", "單號", "狀態", "供應商代碼", "供應商", "倉庫", "SKU", "品名", "數量", "單位", "單價", "小計", "建立者", "核准者"],
+                    rows: rows.into_iter().map(|r| vec![
+                        r.doc_date.to_string(),
+                        r.doc_no,
+                        r.status,
+                        r.partner_code.unwrap_or_default(),
+                        r.partner_name.unwrap_or_default(),
+                        r.warehouse_name.unwrap_or_default(),
+                        r.product_sku,
+                        r.product_name,
+                        r.qty.to_string(),
+                        r.uom,
+                        opt(r.unit_price),
+                        opt(r.line_total),
+                        r.created_by_name,
+                        r.approved_by_name.unwrap_or_default(),
+                    ]).collect(),
+                }
+            }
+            ReportType::CostSummary => {
+                let rows = ReportService::cost_summary(pool, query).await?;
+                ExportTable {
+                    title,
+                    headers: vec!["倉庫代碼", "倉庫", "SKU", "品名", "分類", "庫存量", "平均成本", "庫存金額"],
+                    rows: rows.into_iter().map(|r| vec![
+                        r.warehouse_code,
+                        r.warehouse_name,
+                        r.product_sku,
+                        r.product_name,
+                        r.category_name.unwrap_or_default(),
+                        r.qty_on_hand.to_string(),
+                        opt(r.avg_cost),
+                        opt(r.total_value),
+                    ]).collect(),
+                }
+            }
+            ReportType::ExpiryReport => {
+                let rows: Vec<ExpiryAlert> = sqlx::query_as(
+                    "SELECT * FROM v_expiry_alerts ORDER BY days_until_expiry, product_name",
+                )
+                .fetch_all(pool)
+                .await?;
+                ExportTable {
+                    title,
+                    headers: vec!["SKU", "品名", "規格", "倉庫", "批號", "效期", "庫存量", "單位", "剩餘天數", "狀態"],
+                    rows: rows.into_iter().map(|r| vec![
+                        r.sku,
+                        r.product_name,
+                        r.spec.unwrap_or_default(),
+                        r.warehouse_name,
+                        r.batch_no.unwrap_or_default(),
+                        r.expiry_date.to_string(),
+                        r.on_hand_qty.to_string(),
+                        r.base_uom,
+                        r.days_until_expiry.to_string(),
+                        r.expiry_status,
+                    ]).collect(),
+                }
+            }
+            ReportType::LowStockReport => {
+                let rows: Vec<LowStockAlert> = sqlx::query_as(
+                    "SELECT * FROM v_low_stock_alerts ORDER BY stock_status, product_name",
+                )
+                .fetch_all(pool)
+                .await?;
+                ExportTable {
+                    title,
+                    headers: vec!["倉庫", "SKU", "品名", "單位", "庫存量", "安全庫存", "再訂購點", "狀態"],
+                    rows: rows.into_iter().map(|r| vec![
+                        r.warehouse_name,
+                        r.product_sku,
+                        r.product_name,
+                        r.base_uom,
+                        r.qty_on_hand.to_string(),
+                        opt(r.safety_stock),
+                        opt(r.reorder_point),
+                        r.stock_status,
+                    ]).collect(),
+                }
+            }
+        };
+
+        Ok(table)
+    }
+}
+
+fn parse_schedule(value: &str) -> Result<ScheduleType> {
+    ScheduleType::parse(value)
+        .ok_or_else(|| AppError::Validation(format!("Invalid schedule type: {}", value)))
+}
+
+fn parse_report_type(value: &str) -> Result<ReportType> {
+    ReportType::parse(value)
+        .ok_or_else(|| AppError::Validation(format!("Invalid report type: {}", value)))
+}
+
+/// 下一個符合排程且晚於 after 的時間點（台灣時間整點）
+fn next_run_after(
+    schedule: ScheduleType,
+    day_of_week: Option<i32>,
+    day_of_month: Option<i32>,
+    hour_of_day: i32,
+    after: DateTime<Utc>,
+) -> DateTime<Utc> {
+    let tz = local_offset();
+    let hour = hour_of_day.clamp(0, 23) as u32;
+    let start = after.with_timezone(&tz).date_naive();
+
+    // 月排程最多需往後找兩個月
+    for offset in 0..=62 {
+        let date = start + Duration::days(offset);
+        let matches = match schedule {
+            ScheduleType::Daily => true,
+            ScheduleType::Weekly => {
+                date.weekday().num_days_from_sunday() as i32 == day_of_week.unwrap_or(1).clamp(0, 6)
+            }
+            // 指定日大於當月天數時於月底執行
+            ScheduleType::Monthly => {
+                date.day() as i32 == day_of_month.unwrap_or(1).clamp(1, days_in_month(date))
+            }
+        };
+        if !matches {
+            continue;
+        }
+        let candidate = date
+            .and_hms_opt(hour, 0, 0)
+            .and_then(|local| local.and_local_timezone(tz).single())
+            .map(|local| local.with_timezone(&Utc));
+        if let Some(candidate) = candidate.filter(|c| *c > after) {
+            return candidate;
+        }
+    }
+
+    after + Duration::days(1)
+}
+
+/// 沒有上次執行記錄時，報表涵蓋的期間起點
+fn previous_period_start(schedule: ScheduleType, end: DateTime<Utc>) -> DateTime<Utc> {
+    match schedule {
+        ScheduleType::Daily => end - Duration::days(1),
+        ScheduleType::Weekly => end - Duration::days(7),
+        ScheduleType::Monthly => end.checked_sub_months(Months::new(1)).unwrap_or(end - Duration::days(30)),
+    }
+}
+
+fn days_in_month(date: NaiveDate) -> i32 {
+    let first = date.with_day(1).unwrap_or(date);
+    first
+        .checked_add_months(Months::new(1))
+        .map(|next| (next - first).num_days() as i32)
+        .unwrap_or(31)
+}
+
+fn generate_csv(table: &ExportTable) -> Result<Vec<u8>> {
+    // 加上 UTF-8 BOM，讓 Excel 正確辨識中文
+    let mut wtr = csv::Writer::from_writer(b"\xEF\xBB\xBF".to_vec());
+    wtr.write_record(&table.headers)
+        .map_err(|e| AppError::Internal(format!("CSV 寫入失敗: {}", e)))?;
+    for row in &table.rows {
+        wtr.write_record(row)
+            .map_err(|e| AppError::Internal(format!("CSV 寫入失敗: {}", e)))?;
+    }
+    wtr.into_inner()
+        .map_err(|e| AppError::Internal(format!("CSV 生成失敗: {}", e)))
+}
+
+fn opt<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn utc(y: i32, m: u32, d: u32, h: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_next_run_after() {
+        // 06:00 台灣時間 = 前一日 22:00 UTC
+        let after = utc(2026, 3, 10, 23);
+        assert_eq!(next_run_after(ScheduleType::Daily, None, None, 6, after), utc(2026, 3, 11, 22));
+        // 2026-03-10 為週二；週一排程應排到 3/16
+        assert_eq!(next_run_after(ScheduleType::Weekly, Some(1), None, 6, after), utc(2026, 3, 15, 22));
+        // 31 日於二月時落在月底
+        let feb = utc(2026, 2, 1, 0);
+        assert_eq!(next_run_after(ScheduleType::Monthly, None, Some(31), 8, feb), utc(2026, 2, 28, 0));
+        // 剛好等於執行時間時排到下一期
+        let exact = utc(2026, 2, 28, 0);
+        assert_eq!(next_run_after(ScheduleType::Monthly, None, Some(31), 8, exact), utc(2026, 3, 31, 0));
+    }
+}
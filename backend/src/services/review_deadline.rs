@@ -0,0 +1,317 @@
+// 審查期限
+// 依工作日計算審查期限，提醒審查委員並將逾期審查上呈 IACUC 主委
+
+use chrono::{Datelike, Duration, NaiveDate, Utc, Weekday};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    models::OverdueReview,
+    services::{EmailService, NotificationService},
+    Result,
+};
+
+/// 尚未完成的委員審查
+#[derive(Debug, FromRow)]
+struct OpenAssignment {
+    id: Uuid,
+    protocol_id: Uuid,
+    protocol_no: String,
+    title: String,
+    reviewer_id: Uuid,
+    reviewer_email: String,
+    reviewer_name: String,
+    due_date: NaiveDate,
+}
+
+/// 逾期的行政預審
+#[derive(Debug, FromRow)]
+struct OverduePreReview {
+    protocol_id: Uuid,
+    protocol_no: String,
+    title: String,
+    due_date: NaiveDate,
+}
+
+/// 上呈通知內容
+struct Escalation<'a> {
+    protocol_id: Uuid,
+    protocol_no: &'a str,
+    title: &'a str,
+    detail: &'a str,
+    due_date: NaiveDate,
+}
+
+pub struct ReviewDeadlineService;
+
+impl ReviewDeadlineService {
+    /// 自 from 起算第 working_days 個工作日（僅計週一至週五，未排除國定假日）
+    pub fn due_date(from: NaiveDate, working_days: i64) -> NaiveDate {
+        let mut date = from;
+        let mut remaining = working_days;
+        while remaining > 0 {
+            date += Duration::days(1);
+            if !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+                remaining -= 1;
+            }
+        }
+        date
+    }
+
+    /// 今日起算的審查期限
+    pub fn due_date_from_today(working_days: i64) -> NaiveDate {
+        Self::due_date(Utc::now().date_naive(), working_days)
+    }
+
+    /// 提醒期限將至的審查委員（含修正案審查；站內通知與 Email，每位委員每日最多一次）
+    pub async fn send_reminders(pool: &PgPool, config: &Config) -> Result<i64> {
+        let today = Utc::now().date_naive();
+        let window_end = Self::due_date(today, config.review_reminder_working_days);
+
+        let assignments = sqlx::query_as::<_, OpenAssignment>(
+            r#"
+            SELECT ra.id, ra.protocol_id, COALESCE(am.amendment_no, p.protocol_no) AS protocol_no, p.title, ra.reviewer_id,
+                   u.email AS reviewer_email, u.display_name AS reviewer_name, ra.due_date
+            FROM review_assignments ra
+            INNER JOIN protocols p ON p.id = ra.protocol_id
+            LEFT JOIN protocol_amendments am ON am.id = ra.amendment_id
+            INNER JOIN users u ON u.id = ra.reviewer_id
+            WHERE COALESCE(am.status, p.status) = 'UNDER_REVIEW'
+              AND ra.completed_at IS NULL
+              AND ra.due_date BETWEEN $1 AND $2
+              AND (ra.last_reminded_on IS NULL OR ra.last_reminded_on < $1)
+              AND u.is_active = true
+            ORDER BY ra.due_date
+            "#,
+        )
+        .bind(today)
+        .bind(window_end)
+        .fetch_all(pool)
+        .await?;
+
+        let notifications = NotificationService::new(pool.clone());
+        let mut reminded = 0;
+        for assignment in assignments {
+            let due_date = assignment.due_date.to_string();
+            if let Err(e) = notifications
+                .notify_review_deadline(
+                    assignment.protocol_id,
+                    &assignment.protocol_no,
+                    &assignment.title,
+                    assignment.reviewer_id,
+                    &due_date,
+                )
+                .await
+            {
+                tracing::error!("Failed to create review deadline notification for {}: {}", assignment.reviewer_id, e);
+            }
+            if let Err(e) = EmailService::send_review_deadline_reminder_email(
+                config,
+                &assignment.reviewer_email,
+                &assignment.reviewer_name,
+                &assignment.protocol_no,
+                &assignment.title,
+                &due_date,
+            )
+            .await
+            {
+                tracing::error!("Failed to send review deadline email to {}: {}", assignment.reviewer_email, e);
+            }
+
+            sqlx::query("UPDATE review_assignments SET last_reminded_on = $2 WHERE id = $1")
+                .bind(assignment.id)
+                .bind(today)
+                .execute(pool)
+                .await?;
+            reminded += 1;
+        }
+
+        Ok(reminded)
+    }
+
+    /// 逾期未完成的委員審查與行政預審上呈主委（每筆僅上呈一次）
+    pub async fn escalate_overdue(pool: &PgPool, config: &Config) -> Result<i64> {
+        let today = Utc::now().date_naive();
+
+        let chairs: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT u.email, u.display_name
+            FROM users u
+            JOIN user_roles ur ON u.id = ur.user_id
+            JOIN roles r ON ur.role_id = r.id
+            WHERE u.is_active = true AND r.code = 'CHAIR'
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+        if chairs.is_empty() {
+            // 未標記為已上呈，待設定主委後下次排程再上呈
+            tracing::warn!("Overdue reviews cannot be escalated: no active IACUC chair");
+            return Ok(0);
+        }
+
+        let assignments = sqlx::query_as::<_, OpenAssignment>(
+            r#"
+            SELECT ra.id, ra.protocol_id, COALESCE(am.amendment_no, p.protocol_no) AS protocol_no, p.title, ra.reviewer_id,
+                   u.email AS reviewer_email, u.display_name AS reviewer_name, ra.due_date
+            FROM review_assignments ra
+            INNER JOIN protocols p ON p.id = ra.protocol_id
+            LEFT JOIN protocol_amendments am ON am.id = ra.amendment_id
+            INNER JOIN users u ON u.id = ra.reviewer_id
+            WHERE COALESCE(am.status, p.status) = 'UNDER_REVIEW'
+              AND ra.completed_at IS NULL
+              AND ra.due_date < $1
+              AND ra.escalated_at IS NULL
+            ORDER BY ra.due_date
+            "#,
+        )
+        .bind(today)
+        .fetch_all(pool)
+        .await?;
+
+        let pre_reviews = sqlx::query_as::<_, OverduePreReview>(
+            r#"
+            SELECT id AS protocol_id, protocol_no, title, pre_review_due_date AS due_date
+            FROM protocols
+            WHERE status = 'PRE_REVIEW'
+              AND pre_review_due_date < $1
+              AND pre_review_escalated_at IS NULL
+            ORDER BY pre_review_due_date
+            "#,
+        )
+        .bind(today)
+        .fetch_all(pool)
+        .await?;
+
+        let mut escalated = 0;
+        for assignment in assignments {
+            let detail = format!("審查委員 {} 未於期限內完成審查", assignment.reviewer_name);
+            Self::notify_chairs(pool, config, &chairs, Escalation {
+                protocol_id: assignment.protocol_id,
+                protocol_no: &assignment.protocol_no,
+                title: &assignment.title,
+                detail: &detail,
+                due_date: assignment.due_date,
+            })
+            .await;
+
+            sqlx::query("UPDATE review_assignments SET escalated_at = NOW() WHERE id = $1")
+                .bind(assignment.id)
+                .execute(pool)
+                .await?;
+            escalated += 1;
+        }
+
+        for pre_review in pre_reviews {
+            Self::notify_chairs(pool, config, &chairs, Escalation {
+                protocol_id: pre_review.protocol_id,
+                protocol_no: &pre_review.protocol_no,
+                title: &pre_review.title,
+                detail: "行政預審未於期限內完成",
+                due_date: pre_review.due_date,
+            })
+            .await;
+
+            sqlx::query("UPDATE protocols SET pre_review_escalated_at = NOW() WHERE id = $1")
+                .bind(pre_review.protocol_id)
+                .execute(pool)
+                .await?;
+            escalated += 1;
+        }
+
+        Ok(escalated)
+    }
+
+    /// 逾期審查看板：逾期未完成的行政預審與委員審查（含修正案審查，逾期最久者在前）
+    pub async fn list_overdue(pool: &PgPool) -> Result<Vec<OverdueReview>> {
+        let overdue = sqlx::query_as::<_, OverdueReview>(
+            r#"
+            SELECT 'review'::varchar AS stage, p.id AS protocol_id, p.protocol_no, p.title, pi.display_name AS pi_name,
+                   am.id AS amendment_id, am.amendment_no,
+                   ra.reviewer_id, u.display_name AS reviewer_name, ra.due_date,
+                   ($1::date - ra.due_date) AS days_overdue, ra.escalated_at
+            FROM review_assignments ra
+            INNER JOIN protocols p ON p.id = ra.protocol_id
+            LEFT JOIN protocol_amendments am ON am.id = ra.amendment_id
+            INNER JOIN users u ON u.id = ra.reviewer_id
+            LEFT JOIN users pi ON pi.id = p.pi_user_id
+            WHERE COALESCE(am.status, p.status) = 'UNDER_REVIEW'
+              AND ra.completed_at IS NULL
+              AND ra.due_date < $1
+            UNION ALL
+            SELECT 'pre_review'::varchar AS stage, p.id AS protocol_id, p.protocol_no, p.title, pi.display_name AS pi_name,
+                   NULL::uuid AS amendment_id, NULL::varchar AS amendment_no,
+                   NULL::uuid AS reviewer_id, NULL::varchar AS reviewer_name, p.pre_review_due_date AS due_date,
+                   ($1::date - p.pre_review_due_date) AS days_overdue, p.pre_review_escalated_at AS escalated_at
+            FROM protocols p
+            LEFT JOIN users pi ON pi.id = p.pi_user_id
+            WHERE p.status = 'PRE_REVIEW'
+              AND p.pre_review_due_date < $1
+            ORDER BY due_date, protocol_no
+            "#,
+        )
+        .bind(Utc::now().date_naive())
+        .fetch_all(pool)
+        .await?;
+
+        Ok(overdue)
+    }
+
+    /// 以站內通知與 Email 通知所有主委（通知失敗僅記錄，不中斷上呈）
+    async fn notify_chairs(pool: &PgPool, config: &Config, chairs: &[(String, String)], escalation: Escalation<'_>) {
+        let due_date = escalation.due_date.to_string();
+
+        if let Err(e) = NotificationService::new(pool.clone())
+            .notify_review_overdue(
+                escalation.protocol_id,
+                escalation.protocol_no,
+                escalation.title,
+                escalation.detail,
+                &due_date,
+            )
+            .await
+        {
+            tracing::error!("Failed to create review overdue notification for {}: {}", escalation.protocol_no, e);
+        }
+
+        for (email, name) in chairs {
+            if let Err(e) = EmailService::send_review_overdue_escalation_email(
+                config,
+                email,
+                name,
+                escalation.protocol_no,
+                escalation.title,
+                escalation.detail,
+                &due_date,
+            )
+            .await
+            {
+                tracing::error!("Failed to send review overdue email to {}: {}", email, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_due_date_skips_weekends() {
+        // 2026-10-16 為週五
+        assert_eq!(ReviewDeadlineService::due_date(date(2026, 10, 16), 1), date(2026, 10, 19));
+        assert_eq!(ReviewDeadlineService::due_date(date(2026, 10, 12), 10), date(2026, 10, 26));
+        // 週六指派，下一個工作日為週一
+        assert_eq!(ReviewDeadlineService::due_date(date(2026, 10, 17), 1), date(2026, 10, 19));
+    }
+
+    #[test]
+    fn test_zero_working_days_is_same_day() {
+        assert_eq!(ReviewDeadlineService::due_date(date(2026, 10, 17), 0), date(2026, 10, 17));
+    }
+}
@@ -0,0 +1,376 @@
+// 審查委員表決
+// 表決提交，並依設定規則（一致決、多數決）彙整為建議狀態
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    middleware::CurrentUser,
+    models::{
+        AuditAction, ChangeStatusRequest, Protocol, ProtocolStatus, ReviewDecision, ReviewVote,
+        ReviewVoteRule, ReviewVoteSummary, ReviewVoteTally, SubmitReviewVoteRequest,
+    },
+    services::AuditService,
+    AppError, Result,
+};
+
+pub struct ReviewVoteService;
+
+impl ReviewVoteService {
+    /// 提交或修改表決
+    ///
+    /// 僅限審查中計畫的已指派委員，針對最新提交版本；提交表決即視為完成審查。
+    pub async fn submit(
+        pool: &PgPool,
+        protocol_id: Uuid,
+        reviewer_id: Uuid,
+        req: &SubmitReviewVoteRequest,
+    ) -> Result<ReviewVote> {
+        let conditions = req.conditions.as_deref().map(str::trim).filter(|c| !c.is_empty());
+        if req.decision == ReviewDecision::ApproveWithConditions && conditions.is_none() {
+            return Err(AppError::Validation("附條件核准須填寫核准條件".to_string()));
+        }
+        let comment = req.comment.as_deref().map(str::trim).filter(|c| !c.is_empty());
+
+        let status: ProtocolStatus = sqlx::query_scalar("SELECT status FROM protocols WHERE id = $1")
+            .bind(protocol_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Protocol not found".to_string()))?;
+        if status != ProtocolStatus::UnderReview {
+            return Err(AppError::Conflict(format!(
+                "Votes can only be submitted while the protocol is UNDER_REVIEW (current: {})",
+                status.as_str()
+            )));
+        }
+
+        let assigned: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM review_assignments WHERE protocol_id = $1 AND reviewer_id = $2 AND amendment_id IS NULL)",
+        )
+        .bind(protocol_id)
+        .bind(reviewer_id)
+        .fetch_one(pool)
+        .await?;
+        if !assigned {
+            return Err(AppError::Forbidden("Only assigned reviewers can vote on this protocol".to_string()));
+        }
+
+        let version_id = Self::latest_version_id(pool, protocol_id)
+            .await?
+            .ok_or_else(|| AppError::BusinessRule("計畫尚無提交版本，無法表決".to_string()))?;
+
+        let before = Self::find(pool, version_id, reviewer_id).await?;
+
+        let mut tx = pool.begin().await?;
+
+        let vote = sqlx::query_as::<_, ReviewVote>(
+            r#"
+            INSERT INTO review_votes (id, protocol_id, protocol_version_id, reviewer_id, decision, conditions, comment)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (protocol_version_id, reviewer_id) DO UPDATE SET
+                decision = EXCLUDED.decision,
+                conditions = EXCLUDED.conditions,
+                comment = EXCLUDED.comment,
+                updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(protocol_id)
+        .bind(version_id)
+        .bind(reviewer_id)
+        .bind(req.decision)
+        .bind(conditions)
+        .bind(comment)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE review_assignments SET completed_at = COALESCE(completed_at, NOW())
+            WHERE protocol_id = $1 AND reviewer_id = $2 AND amendment_id IS NULL
+            "#,
+        )
+        .bind(protocol_id)
+        .bind(reviewer_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        AuditService::log(
+            pool,
+            reviewer_id,
+            if before.is_some() { AuditAction::Update } else { AuditAction::Create },
+            "review_vote",
+            vote.id,
+            before.map(|v| serde_json::to_value(v).unwrap_or_default()),
+            Some(serde_json::to_value(&vote).unwrap_or_default()),
+        )
+        .await?;
+
+        Ok(vote)
+    }
+
+    /// 彙整最新版本的表決
+    ///
+    /// 僅計入目前仍指派中的委員（已迴避者的表決不計）；尚有委員未表決時不提供建議狀態。
+    pub async fn summary(pool: &PgPool, protocol_id: Uuid, rule: ReviewVoteRule) -> Result<ReviewVoteSummary> {
+        let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM protocols WHERE id = $1)")
+            .bind(protocol_id)
+            .fetch_one(pool)
+            .await?;
+        if !exists {
+            return Err(AppError::NotFound("Protocol not found".to_string()));
+        }
+
+        let version_id = Self::latest_version_id(pool, protocol_id).await?;
+
+        let assigned_reviewers: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM review_assignments WHERE protocol_id = $1 AND amendment_id IS NULL")
+                .bind(protocol_id)
+                .fetch_one(pool)
+                .await?;
+
+        let votes = sqlx::query_as::<_, ReviewVote>(
+            r#"
+            SELECT v.*, u.display_name AS reviewer_name
+            FROM review_votes v
+            INNER JOIN review_assignments ra
+                ON ra.protocol_id = v.protocol_id AND ra.reviewer_id = v.reviewer_id AND ra.amendment_id IS NULL
+            INNER JOIN users u ON u.id = v.reviewer_id
+            WHERE v.protocol_version_id = $1
+            ORDER BY v.voted_at
+            "#,
+        )
+        .bind(version_id)
+        .fetch_all(pool)
+        .await?;
+
+        let decisions: Vec<ReviewDecision> = votes.iter().map(|v| v.decision).collect();
+        let tally = ReviewDecision::ALL
+            .iter()
+            .map(|decision| ReviewVoteTally {
+                decision: *decision,
+                decision_display: decision.display_name().to_string(),
+                count: decisions.iter().filter(|d| *d == decision).count() as i64,
+            })
+            .collect();
+        let pending_reviewers = (assigned_reviewers - votes.len() as i64).max(0);
+        let suggested_status = if assigned_reviewers > 0 && pending_reviewers == 0 {
+            aggregate(rule, &decisions)
+        } else {
+            None
+        };
+
+        Ok(ReviewVoteSummary {
+            protocol_id,
+            protocol_version_id: version_id,
+            rule,
+            votes,
+            tally,
+            assigned_reviewers,
+            pending_reviewers,
+            suggested_status,
+            suggested_status_display: suggested_status.map(|s| s.display_name().to_string()),
+        })
+    }
+
+    /// 審查決定與表決建議不同、或尚有委員未表決時，僅主委可做出決定且須填寫備註
+    ///
+    /// 回傳被推翻的表決彙整，供狀態變更後記錄稽核日誌。
+    pub async fn check_decision(
+        pool: &PgPool,
+        protocol: &Protocol,
+        req: &ChangeStatusRequest,
+        current_user: &CurrentUser,
+        rule: ReviewVoteRule,
+    ) -> Result<Option<ReviewVoteSummary>> {
+        if protocol.status != ProtocolStatus::UnderReview {
+            return Ok(None);
+        }
+
+        let summary = Self::summary(pool, protocol.id, rule).await?;
+        let reason = match summary.suggested_status {
+            Some(suggested) if suggested == req.to_status => return Ok(None),
+            Some(suggested) => format!("審查表決建議為「{}」", suggested.display_name()),
+            None => format!("尚有 {} 位審查委員未表決", summary.pending_reviewers),
+        };
+
+        let is_chair = current_user
+            .roles
+            .iter()
+            .any(|r| r == "CHAIR" || r == "SYSTEM_ADMIN" || r.eq_ignore_ascii_case("admin"));
+        if !is_chair {
+            return Err(AppError::Forbidden(format!("{}，僅主委可做出不同決定", reason)));
+        }
+        if req.remark.as_ref().is_none_or(|r| r.trim().is_empty()) {
+            return Err(AppError::Validation(format!("{}，推翻建議須填寫備註說明", reason)));
+        }
+
+        Ok(Some(summary))
+    }
+
+    /// 記錄主委推翻表決建議（含委員未全數表決即先行決定）
+    pub async fn log_override(
+        pool: &PgPool,
+        summary: &ReviewVoteSummary,
+        decided: ProtocolStatus,
+        remark: Option<&str>,
+        decided_by: Uuid,
+    ) -> Result<()> {
+        AuditService::log(
+            pool,
+            decided_by,
+            AuditAction::Override,
+            "protocol",
+            summary.protocol_id,
+            Some(serde_json::json!({
+                "rule": summary.rule,
+                "protocol_version_id": summary.protocol_version_id,
+                "suggested_status": summary.suggested_status,
+                "pending_reviewers": summary.pending_reviewers,
+                "tally": summary.tally,
+            })),
+            Some(serde_json::json!({
+                "status": decided,
+                "remark": remark,
+            })),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn latest_version_id(pool: &PgPool, protocol_id: Uuid) -> Result<Option<Uuid>> {
+        let version_id = sqlx::query_scalar(
+            "SELECT id FROM protocol_versions WHERE protocol_id = $1 ORDER BY version_no DESC LIMIT 1",
+        )
+        .bind(protocol_id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(version_id)
+    }
+
+    async fn find(pool: &PgPool, protocol_version_id: Uuid, reviewer_id: Uuid) -> Result<Option<ReviewVote>> {
+        let vote = sqlx::query_as::<_, ReviewVote>(
+            "SELECT * FROM review_votes WHERE protocol_version_id = $1 AND reviewer_id = $2",
+        )
+        .bind(protocol_version_id)
+        .bind(reviewer_id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(vote)
+    }
+}
+
+/// 依規則將表決彙整為建議狀態；意見分歧（或未過半）時建議暫緩，提會討論
+fn aggregate(rule: ReviewVoteRule, decisions: &[ReviewDecision]) -> Option<ProtocolStatus> {
+    let first = decisions.first()?;
+    let status = match rule {
+        ReviewVoteRule::Unanimous => decisions
+            .iter()
+            .all(|d| d == first)
+            .then(|| first.status()),
+        ReviewVoteRule::Majority => ReviewDecision::ALL
+            .iter()
+            .find(|decision| decisions.iter().filter(|d| d == decision).count() * 2 > decisions.len())
+            .map(|decision| decision.status()),
+    };
+
+    Some(status.unwrap_or(ProtocolStatus::Deferred))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ReviewDecision::*;
+
+    #[test]
+    fn test_unanimous_requires_identical_votes() {
+        assert_eq!(aggregate(ReviewVoteRule::Unanimous, &[Approve, Approve]), Some(ProtocolStatus::Approved));
+        assert_eq!(
+            aggregate(ReviewVoteRule::Unanimous, &[Approve, ApproveWithConditions]),
+            Some(ProtocolStatus::Deferred)
+        );
+    }
+
+    #[test]
+    fn test_majority_needs_more_than_half() {
+        assert_eq!(
+            aggregate(ReviewVoteRule::Majority, &[Revise, Revise, Approve]),
+            Some(ProtocolStatus::RevisionRequired)
+        );
+        assert_eq!(aggregate(ReviewVoteRule::Majority, &[Approve, Reject]), Some(ProtocolStatus::Deferred));
+        assert_eq!(
+            aggregate(ReviewVoteRule::Majority, &[Approve, Reject, Revise]),
+            Some(ProtocolStatus::Deferred)
+        );
+    }
+
+    #[test]
+    fn test_no_votes_has_no_suggestion() {
+        assert_eq!(aggregate(ReviewVoteRule::Majority, &[]), None);
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_decision_with_pending_votes_requires_chair_override(db: PgPool) {
+        use crate::services::ProtocolService;
+        use crate::test_support::{app_state, current_user, insert_protocol, insert_user};
+
+        let pi = insert_user(&db, "pi@example.com", false).await;
+        let reviewer = insert_user(&db, "reviewer@example.com", true).await;
+        let staff_id = insert_user(&db, "iacuc-staff@example.com", true).await;
+        let chair_id = insert_user(&db, "chair@example.com", true).await;
+        let staff = current_user(staff_id, "iacuc-staff@example.com", &["IACUC_STAFF"], &[]);
+        let chair = current_user(chair_id, "chair@example.com", &["CHAIR"], &[]);
+        let id = insert_protocol(&db, pi, "PIG-VOTE-1").await;
+        sqlx::query("UPDATE protocols SET status = 'UNDER_REVIEW' WHERE id = $1")
+            .bind(id)
+            .execute(&db)
+            .await
+            .unwrap();
+        // 委員已標記完成審查但未表決
+        sqlx::query(
+            "INSERT INTO review_assignments (id, protocol_id, reviewer_id, assigned_by, completed_at) \
+             VALUES (gen_random_uuid(), $1, $2, $3, NOW())",
+        )
+        .bind(id)
+        .bind(reviewer)
+        .bind(staff_id)
+        .execute(&db)
+        .await
+        .unwrap();
+        let config = app_state(db.clone()).config;
+
+        let mut req = ChangeStatusRequest {
+            to_status: ProtocolStatus::RevisionRequired,
+            remark: Some("請補充實驗設計".to_string()),
+            reviewer_ids: None,
+            signature_password: None,
+        };
+        let result = ProtocolService::change_status(&db, id, &req, &staff, &config).await;
+        assert!(matches!(result, Err(AppError::Forbidden(_))));
+
+        req.remark = None;
+        let result = ProtocolService::change_status(&db, id, &req, &chair, &config).await;
+        assert!(matches!(result, Err(AppError::Validation(_))));
+
+        req.remark = Some("委員逾期未表決，依審查意見先行退回修訂".to_string());
+        let changed = ProtocolService::change_status(&db, id, &req, &chair, &config).await.unwrap();
+        assert_eq!(changed.status, ProtocolStatus::RevisionRequired);
+
+        let overrides: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM audit_logs WHERE entity_id = $1 AND action = 'OVERRIDE' AND actor_user_id = $2",
+        )
+        .bind(id)
+        .bind(chair_id)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert_eq!(overrides, 1);
+    }
+}
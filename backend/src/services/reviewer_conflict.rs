@@ -0,0 +1,457 @@
+// 審查委員利益衝突
+// 利益衝突聲明、指派前資格檢查（同部門、同機構、共同編輯者）與盲審
+
+use std::collections::{HashMap, HashSet};
+
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    middleware::CurrentUser,
+    models::{
+        AuditAction, DeclareCoiRequest, Protocol, ReviewAssignment, ReviewCommentResponse,
+        ReviewerCoiDeclaration, ReviewerConflict,
+    },
+    services::{AccessService, AuditService},
+    AppError, Result,
+};
+
+/// 資格檢查所需的使用者資料
+#[derive(Debug, Clone, FromRow)]
+struct PartyProfile {
+    id: Uuid,
+    department_id: Option<Uuid>,
+    organization: Option<String>,
+}
+
+pub struct ReviewerConflictService;
+
+impl ReviewerConflictService {
+    /// 聲明利益衝突（僅新增，以最新一筆為準）
+    ///
+    /// 聲明有衝突時，尚未完成審查的指派（含修正案審查）自動撤銷（迴避）。
+    pub async fn declare(
+        pool: &PgPool,
+        protocol_id: Uuid,
+        reviewer_id: Uuid,
+        req: &DeclareCoiRequest,
+    ) -> Result<ReviewerCoiDeclaration> {
+        let description = req.description.as_deref().map(str::trim).filter(|d| !d.is_empty());
+        if req.has_conflict && description.is_none() {
+            return Err(AppError::Validation("聲明利益衝突時須說明衝突內容".to_string()));
+        }
+        Self::get_protocol(pool, protocol_id).await?;
+
+        let mut tx = pool.begin().await?;
+
+        let declaration = sqlx::query_as::<_, ReviewerCoiDeclaration>(
+            r#"
+            INSERT INTO reviewer_coi_declarations (id, protocol_id, reviewer_id, has_conflict, description, declared_at)
+            VALUES ($1, $2, $3, $4, $5, NOW())
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(protocol_id)
+        .bind(reviewer_id)
+        .bind(req.has_conflict)
+        .bind(description)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let withdrawn: Vec<ReviewAssignment> = if req.has_conflict {
+            sqlx::query_as(
+                r#"
+                DELETE FROM review_assignments
+                WHERE protocol_id = $1 AND reviewer_id = $2 AND completed_at IS NULL
+                RETURNING *
+                "#,
+            )
+            .bind(protocol_id)
+            .bind(reviewer_id)
+            .fetch_all(&mut *tx)
+            .await?
+        } else {
+            Vec::new()
+        };
+
+        tx.commit().await?;
+
+        AuditService::log(
+            pool,
+            reviewer_id,
+            AuditAction::Create,
+            "reviewer_coi_declaration",
+            declaration.id,
+            None,
+            Some(serde_json::to_value(&declaration).unwrap_or_default()),
+        )
+        .await?;
+        for assignment in withdrawn {
+            AuditService::log(
+                pool,
+                reviewer_id,
+                AuditAction::Unassign,
+                "review_assignment",
+                assignment.id,
+                Some(serde_json::to_value(&assignment).unwrap_or_default()),
+                Some(serde_json::json!({ "reason": "declared_conflict", "declaration_id": declaration.id })),
+            )
+            .await?;
+        }
+
+        Ok(declaration)
+    }
+
+    /// 列出計畫的所有利益衝突聲明（含歷程，新到舊）
+    pub async fn list(pool: &PgPool, protocol_id: Uuid) -> Result<Vec<ReviewerCoiDeclaration>> {
+        let declarations = sqlx::query_as::<_, ReviewerCoiDeclaration>(
+            r#"
+            SELECT d.*, COALESCE(u.display_name, u.email) AS reviewer_name
+            FROM reviewer_coi_declarations d
+            INNER JOIN users u ON u.id = d.reviewer_id
+            WHERE d.protocol_id = $1
+            ORDER BY d.declared_at DESC
+            "#,
+        )
+        .bind(protocol_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(declarations)
+    }
+
+    /// 檢查審查委員與計畫的利益衝突
+    pub async fn conflicts(pool: &PgPool, protocol_id: Uuid, reviewer_id: Uuid) -> Result<Vec<ReviewerConflict>> {
+        let protocol = Self::get_protocol(pool, protocol_id).await?;
+
+        let profiles: Vec<PartyProfile> =
+            sqlx::query_as("SELECT id, department_id, organization FROM users WHERE id = ANY($1)")
+                .bind([protocol.pi_user_id, reviewer_id])
+                .fetch_all(pool)
+                .await?;
+        let reviewer = profiles
+            .iter()
+            .find(|p| p.id == reviewer_id)
+            .ok_or_else(|| AppError::NotFound("Reviewer not found".to_string()))?;
+        let pi = profiles
+            .iter()
+            .find(|p| p.id == protocol.pi_user_id)
+            .ok_or_else(|| AppError::NotFound("Principal investigator not found".to_string()))?;
+
+        let is_co_editor: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM user_protocols
+                WHERE protocol_id = $1 AND user_id = $2 AND role_in_protocol IN ('PI', 'CO_EDITOR')
+            )
+            "#,
+        )
+        .bind(protocol_id)
+        .bind(reviewer_id)
+        .fetch_one(pool)
+        .await?;
+
+        let declared_conflict: bool = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE((
+                SELECT has_conflict FROM reviewer_coi_declarations
+                WHERE protocol_id = $1 AND reviewer_id = $2
+                ORDER BY declared_at DESC
+                LIMIT 1
+            ), false)
+            "#,
+        )
+        .bind(protocol_id)
+        .bind(reviewer_id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(conflict_reasons(pi, reviewer, is_co_editor, declared_conflict))
+    }
+
+    /// 確認審查委員可指派；有利益衝突時記錄稽核日誌並拒絕
+    pub async fn ensure_eligible(pool: &PgPool, protocol_id: Uuid, reviewer_id: Uuid, assigned_by: Uuid) -> Result<()> {
+        let conflicts = Self::conflicts(pool, protocol_id, reviewer_id).await?;
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+
+        AuditService::log(
+            pool,
+            assigned_by,
+            AuditAction::AssignRejected,
+            "protocol",
+            protocol_id,
+            None,
+            Some(serde_json::json!({
+                "reviewer_id": reviewer_id,
+                "conflicts": conflicts,
+            })),
+        )
+        .await?;
+
+        let reasons: Vec<&str> = conflicts.iter().map(|c| c.description()).collect();
+        Err(AppError::BusinessRule(format!("無法指派此審查委員：{}", reasons.join("、"))))
+    }
+
+    /// 設定盲審
+    pub async fn set_blinded_review(
+        pool: &PgPool,
+        protocol_id: Uuid,
+        blinded_review: bool,
+        changed_by: Uuid,
+    ) -> Result<Protocol> {
+        let before = Self::get_protocol(pool, protocol_id).await?;
+
+        let protocol = sqlx::query_as::<_, Protocol>(
+            "UPDATE protocols SET blinded_review = $2, updated_at = NOW() WHERE id = $1 RETURNING *",
+        )
+        .bind(protocol_id)
+        .bind(blinded_review)
+        .fetch_one(pool)
+        .await?;
+
+        AuditService::log(
+            pool,
+            changed_by,
+            AuditAction::Update,
+            "protocol",
+            protocol_id,
+            Some(serde_json::json!({ "blinded_review": before.blinded_review })),
+            Some(serde_json::json!({ "blinded_review": protocol.blinded_review })),
+        )
+        .await?;
+
+        Ok(protocol)
+    }
+
+    /// 盲審計畫中，PI、共同編輯者等申請方不得得知審查委員身分
+    ///
+    /// IACUC 人員與該計畫的審查委員不受限。
+    pub async fn hides_reviewers(pool: &PgPool, viewer: &CurrentUser, protocol: &Protocol) -> Result<bool> {
+        if !protocol.blinded_review || AccessService::can_view_all_protocols(viewer) {
+            return Ok(false);
+        }
+
+        let is_reviewer: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM review_assignments WHERE protocol_id = $1 AND reviewer_id = $2)",
+        )
+        .bind(protocol.id)
+        .bind(viewer.id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(!is_reviewer)
+    }
+
+    /// 依檢視者隱藏審查意見中的審查委員身分（以「審查委員 N」代稱）
+    pub async fn blind_comments(
+        pool: &PgPool,
+        viewer: &CurrentUser,
+        protocol_version_id: Uuid,
+        comments: &mut [ReviewCommentResponse],
+    ) -> Result<()> {
+        let protocol = sqlx::query_as::<_, Protocol>(
+            r#"
+            SELECT p.* FROM protocols p
+            INNER JOIN protocol_versions v ON v.protocol_id = p.id
+            WHERE v.id = $1
+            "#,
+        )
+        .bind(protocol_version_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Protocol version not found".to_string()))?;
+
+        if !Self::hides_reviewers(pool, viewer, &protocol).await? {
+            return Ok(());
+        }
+
+        let mut applicants: HashSet<Uuid> = sqlx::query_scalar(
+            "SELECT user_id FROM user_protocols WHERE protocol_id = $1",
+        )
+        .bind(protocol.id)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+        applicants.insert(protocol.pi_user_id);
+
+        mask_reviewers(comments, &applicants);
+        Ok(())
+    }
+
+    /// 依檢視者隱藏審查指派中的審查委員身分
+    pub async fn blind_assignments(
+        pool: &PgPool,
+        viewer: &CurrentUser,
+        protocol_id: Uuid,
+        assignments: &mut [ReviewAssignment],
+    ) -> Result<()> {
+        let protocol = Self::get_protocol(pool, protocol_id).await?;
+        if Self::hides_reviewers(pool, viewer, &protocol).await? {
+            for assignment in assignments.iter_mut() {
+                assignment.reviewer_id = Uuid::nil();
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_protocol(pool: &PgPool, protocol_id: Uuid) -> Result<Protocol> {
+        sqlx::query_as::<_, Protocol>("SELECT * FROM protocols WHERE id = $1")
+            .bind(protocol_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Protocol not found".to_string()))
+    }
+}
+
+/// 審查委員與 PI 之間的利益衝突
+fn conflict_reasons(
+    pi: &PartyProfile,
+    reviewer: &PartyProfile,
+    is_co_editor: bool,
+    declared_conflict: bool,
+) -> Vec<ReviewerConflict> {
+    let mut conflicts = Vec::new();
+
+    if pi.id == reviewer.id {
+        conflicts.push(ReviewerConflict::IsPrincipalInvestigator);
+    }
+    if pi.department_id.is_some() && pi.department_id == reviewer.department_id {
+        conflicts.push(ReviewerConflict::SameDepartment);
+    }
+    let normalize = |org: &Option<String>| {
+        org.as_deref()
+            .map(|o| o.trim().to_lowercase())
+            .filter(|o| !o.is_empty())
+    };
+    if let Some(organization) = normalize(&pi.organization) {
+        if normalize(&reviewer.organization).as_ref() == Some(&organization) {
+            conflicts.push(ReviewerConflict::SameOrganization);
+        }
+    }
+    if is_co_editor {
+        conflicts.push(ReviewerConflict::CoEditor);
+    }
+    if declared_conflict {
+        conflicts.push(ReviewerConflict::DeclaredConflict);
+    }
+
+    conflicts
+}
+
+/// 將非申請方撰寫的意見改以代稱顯示，同一審查委員使用相同代稱
+fn mask_reviewers(comments: &mut [ReviewCommentResponse], applicants: &HashSet<Uuid>) {
+    let mut aliases: HashMap<Uuid, String> = HashMap::new();
+    let mut alias_of = |id: Uuid| {
+        let next = aliases.len() + 1;
+        aliases.entry(id).or_insert_with(|| format!("審查委員 {}", next)).clone()
+    };
+
+    for comment in comments.iter_mut() {
+        if !applicants.contains(&comment.reviewer_id) {
+            comment.reviewer_name = alias_of(comment.reviewer_id);
+            comment.reviewer_email = String::new();
+            comment.reviewer_id = Uuid::nil();
+        }
+        if let Some(replied_by) = comment.replied_by.filter(|id| !applicants.contains(id)) {
+            comment.replied_by_name = Some(alias_of(replied_by));
+            comment.replied_by_email = None;
+            comment.replied_by = Some(Uuid::nil());
+        }
+        if comment.resolved_by.is_some_and(|id| !applicants.contains(&id)) {
+            comment.resolved_by = Some(Uuid::nil());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn profile(department_id: Option<Uuid>, organization: Option<&str>) -> PartyProfile {
+        PartyProfile {
+            id: Uuid::new_v4(),
+            department_id,
+            organization: organization.map(String::from),
+        }
+    }
+
+    fn comment(reviewer_id: Uuid) -> ReviewCommentResponse {
+        ReviewCommentResponse {
+            id: Uuid::new_v4(),
+            protocol_version_id: Uuid::new_v4(),
+            reviewer_id,
+            reviewer_name: "Reviewer".to_string(),
+            reviewer_email: "reviewer@example.com".to_string(),
+            content: "comment".to_string(),
+            is_resolved: false,
+            resolved_by: None,
+            resolved_at: None,
+            parent_comment_id: None,
+            replied_by: None,
+            replied_by_name: None,
+            replied_by_email: None,
+            section: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_conflict_reasons() {
+        let department = Some(Uuid::new_v4());
+        let pi = profile(department, Some("Pig Lab "));
+
+        let unrelated = profile(Some(Uuid::new_v4()), Some("Other Institute"));
+        assert!(conflict_reasons(&pi, &unrelated, false, false).is_empty());
+
+        let colleague = profile(department, Some("pig lab"));
+        assert_eq!(
+            conflict_reasons(&pi, &colleague, true, true),
+            vec![
+                ReviewerConflict::SameDepartment,
+                ReviewerConflict::SameOrganization,
+                ReviewerConflict::CoEditor,
+                ReviewerConflict::DeclaredConflict,
+            ]
+        );
+
+        assert_eq!(
+            conflict_reasons(&pi, &pi, false, false),
+            vec![
+                ReviewerConflict::IsPrincipalInvestigator,
+                ReviewerConflict::SameDepartment,
+                ReviewerConflict::SameOrganization,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_department_or_organization_is_not_a_conflict() {
+        let pi = profile(None, Some("  "));
+        let reviewer = profile(None, Some("  "));
+        assert!(conflict_reasons(&pi, &reviewer, false, false).is_empty());
+    }
+
+    #[test]
+    fn test_mask_reviewers_keeps_applicants_and_stable_aliases() {
+        let pi = Uuid::new_v4();
+        let reviewer_a = Uuid::new_v4();
+        let reviewer_b = Uuid::new_v4();
+        let mut comments = vec![comment(reviewer_a), comment(reviewer_b), comment(pi), comment(reviewer_a)];
+        comments[2].replied_by = Some(pi);
+        comments[2].replied_by_name = Some("PI".to_string());
+
+        mask_reviewers(&mut comments, &HashSet::from([pi]));
+
+        assert_eq!(comments[0].reviewer_name, "審查委員 1");
+        assert_eq!(comments[1].reviewer_name, "審查委員 2");
+        assert_eq!(comments[3].reviewer_name, "審查委員 1");
+        assert!(comments[0].reviewer_id.is_nil() && comments[0].reviewer_email.is_empty());
+        assert_eq!(comments[2].reviewer_id, pi);
+        assert_eq!(comments[2].replied_by_name.as_deref(), Some("PI"));
+    }
+}
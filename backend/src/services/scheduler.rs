@@ -5,7 +5,7 @@ use tracing::{info, error};
 
 use crate::{
     config::Config,
-    services::{EmailService, NotificationService, BalanceExpirationJob, CalendarService},
+    services::{EmailService, NotificationService, BalanceExpirationJob, CalendarService, LeaveApprovalService, ScheduledReportRunner, SessionManager, ActivityLogService, IntegrityService, AttachmentService, ReviewDeadlineService},
 };
 
 pub struct SchedulerService;
@@ -73,6 +73,140 @@ impl SchedulerService {
             })
         })?).await?;
 
+        // 每小時執行請假審核逾時上呈
+        let db_clone = db.clone();
+        let config_clone = config.clone();
+        sched.add(Job::new_async("0 0 * * * *", move |_uuid, _l| {
+            let db = db_clone.clone();
+            let config = config_clone.clone();
+            Box::pin(async move {
+                match LeaveApprovalService::escalate_overdue(&db, config.leave_escalation_hours).await {
+                    Ok(count) if count > 0 => {
+                        info!("Escalated {} overdue leave approvals", count);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        error!("Leave approval escalation failed: {}", e);
+                    }
+                }
+            })
+        })?).await?;
+
+        // 每日 08:30 提醒審查期限將至的審查委員
+        let db_clone = db.clone();
+        let config_clone = config.clone();
+        sched.add(Job::new_async("0 30 8 * * *", move |_uuid, _l| {
+            let db = db_clone.clone();
+            let config = config_clone.clone();
+            Box::pin(async move {
+                match ReviewDeadlineService::send_reminders(&db, &config).await {
+                    Ok(count) => info!("Review deadline reminders sent: {}", count),
+                    Err(e) => error!("Review deadline reminders failed: {}", e),
+                }
+            })
+        })?).await?;
+
+        // 每日 09:00 將逾期審查上呈 IACUC 主委
+        let db_clone = db.clone();
+        let config_clone = config.clone();
+        sched.add(Job::new_async("0 0 9 * * *", move |_uuid, _l| {
+            let db = db_clone.clone();
+            let config = config_clone.clone();
+            Box::pin(async move {
+                match ReviewDeadlineService::escalate_overdue(&db, &config).await {
+                    Ok(count) if count > 0 => info!("Escalated {} overdue reviews to the IACUC chair", count),
+                    Ok(_) => {}
+                    Err(e) => error!("Review overdue escalation failed: {}", e),
+                }
+            })
+        })?).await?;
+
+        // 每小時第 5 分執行到期的定期報表
+        let db_clone = db.clone();
+        let config_clone = config.clone();
+        sched.add(Job::new_async("0 5 * * * *", move |_uuid, _l| {
+            let db = db_clone.clone();
+            let config = config_clone.clone();
+            Box::pin(async move {
+                Self::run_scheduled_reports(&db, &config).await;
+            })
+        })?).await?;
+
+        // 啟動時補跑停機期間錯過的定期報表
+        let db_clone = db.clone();
+        let config_clone = config.clone();
+        tokio::spawn(async move {
+            Self::run_scheduled_reports(&db_clone, &config_clone).await;
+        });
+
+        // 每 15 分鐘結束閒置逾時的 Sessions
+        let db_clone = db.clone();
+        let config_clone = config.clone();
+        sched.add(Job::new_async("0 */15 * * * *", move |_uuid, _l| {
+            let db = db_clone.clone();
+            let config = config_clone.clone();
+            Box::pin(async move {
+                if config.session_idle_timeout_minutes <= 0 {
+                    return;
+                }
+                match SessionManager::cleanup_expired(&db, config.session_idle_timeout_minutes).await {
+                    Ok(count) if count > 0 => info!("Ended {} idle sessions", count),
+                    Ok(_) => {}
+                    Err(e) => error!("Session cleanup failed: {}", e),
+                }
+            })
+        })?).await?;
+
+        // 每月 1 日 02:00 建立未來季度的活動日誌分區（啟動時亦執行一次）
+        let db_clone = db.clone();
+        sched.add(Job::new_async("0 0 2 1 * *", move |_uuid, _l| {
+            let db = db_clone.clone();
+            Box::pin(async move {
+                Self::maintain_activity_log_partitions(&db).await;
+            })
+        })?).await?;
+
+        let db_clone = db.clone();
+        tokio::spawn(async move {
+            Self::maintain_activity_log_partitions(&db_clone).await;
+        });
+
+        // 每日 03:30 重新計算附件雜湊，檢查檔案遺失或遭竄改
+        let db_clone = db.clone();
+        sched.add(Job::new_async("0 30 3 * * *", move |_uuid, _l| {
+            let db = db_clone.clone();
+            Box::pin(async move {
+                info!("Running attachment integrity scan...");
+                match IntegrityService::scan(&db).await {
+                    Ok(summary) => {
+                        info!("Attachment integrity scan completed: {} checked, {} backfilled, {} missing, {} tampered",
+                              summary.checked, summary.backfilled, summary.missing, summary.tampered);
+                    }
+                    Err(e) => {
+                        error!("Attachment integrity scan failed: {}", e);
+                    }
+                }
+            })
+        })?).await?;
+
+        // 每日 04:00 清除保存期限屆滿的已刪除、已取代附件版本檔案
+        let db_clone = db.clone();
+        let retention_days = config.attachment_retention_days;
+        sched.add(Job::new_async("0 0 4 * * *", move |_uuid, _l| {
+            let db = db_clone.clone();
+            Box::pin(async move {
+                info!("Running attachment retention purge...");
+                match AttachmentService::purge_expired(&db, retention_days).await {
+                    Ok(count) => {
+                        info!("Attachment retention purge completed: {} versions purged", count);
+                    }
+                    Err(e) => {
+                        error!("Attachment retention purge failed: {}", e);
+                    }
+                }
+            })
+        })?).await?;
+
         // 每日 08:00 和 18:00 執行 Google Calendar 同步
         let db_clone = db.clone();
         sched.add(Job::new_async("0 0 8,18 * * *", move |_uuid, _l| {
@@ -97,6 +231,28 @@ impl SchedulerService {
         Ok(sched)
     }
 
+    /// 維護活動日誌分區（預先建立未來四季）
+    async fn maintain_activity_log_partitions(db: &PgPool) {
+        match ActivityLogService::ensure_partitions(db, chrono::Utc::now().date_naive(), 4).await {
+            Ok(count) if count > 0 => info!("Created {} activity log partitions", count),
+            Ok(_) => {}
+            Err(e) => error!("Activity log partition maintenance failed: {}", e),
+        }
+    }
+
+    /// 執行到期的定期報表
+    async fn run_scheduled_reports(db: &PgPool, config: &Config) {
+        match ScheduledReportRunner::run_due(db, config).await {
+            Ok(count) if count > 0 => {
+                info!("Generated {} scheduled reports", count);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                error!("Scheduled report run failed: {}", e);
+            }
+        }
+    }
+
     /// 檢查低庫存並發送通知
     async fn check_low_stock(db: &PgPool, config: &Config) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let service = NotificationService::new(db.clone());
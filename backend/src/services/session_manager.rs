@@ -1,6 +1,12 @@
 // Session Manager Service
 // 管理使用者 Sessions
 
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 
@@ -8,6 +14,18 @@ use crate::Result;
 
 pub struct SessionManager;
 
+/// Session 驗證結果快取（降低每個請求查詢資料庫的負擔）
+struct CachedSession {
+    user_id: Uuid,
+    valid: bool,
+    checked_at: Instant,
+}
+
+fn session_cache() -> &'static Mutex<HashMap<Uuid, CachedSession>> {
+    static CACHE: OnceLock<Mutex<HashMap<Uuid, CachedSession>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
 impl SessionManager {
     /// 建立新 Session
     pub async fn create_session(
@@ -23,12 +41,12 @@ impl SessionManager {
             INSERT INTO user_sessions (
                 id, user_id, started_at, last_activity_at,
                 ip_address, user_agent, is_active
-            ) VALUES ($1, $2, NOW(), NOW(), $3, $4, true)
+            ) VALUES ($1, $2, NOW(), NOW(), $3::inet, $4, true)
             "#,
         )
         .bind(session_id)
         .bind(user_id)
-        .bind(ip)
+        .bind(ip.filter(|v| v.parse::<IpAddr>().is_ok()))
         .bind(user_agent)
         .execute(pool)
         .await?;
@@ -85,6 +103,7 @@ impl SessionManager {
         .execute(pool)
         .await?;
         
+        Self::invalidate(session_id);
         Ok(())
     }
     
@@ -104,6 +123,7 @@ impl SessionManager {
         .execute(pool)
         .await?;
         
+        Self::invalidate_user(user_id);
         Ok(result.rows_affected() as i64)
     }
     
@@ -143,11 +163,12 @@ impl SessionManager {
         .execute(pool)
         .await?;
         
+        Self::invalidate(session_id);
         Ok(())
     }
     
     /// 清理過期 Sessions
-    pub async fn cleanup_expired(pool: &PgPool, inactive_minutes: i32) -> Result<i64> {
+    pub async fn cleanup_expired(pool: &PgPool, inactive_minutes: i64) -> Result<i64> {
         let result = sqlx::query(
             r#"
             UPDATE user_sessions
@@ -180,7 +201,7 @@ impl SessionManager {
     /// 檢查 Session 是否有效
     pub async fn is_session_valid(pool: &PgPool, session_id: Uuid) -> Result<bool> {
         let (is_active,): (bool,) = sqlx::query_as(
-            "SELECT is_active FROM user_sessions WHERE id = $1",
+            "SELECT COALESCE(is_active, false) FROM user_sessions WHERE id = $1",
         )
         .bind(session_id)
         .fetch_optional(pool)
@@ -189,4 +210,210 @@ impl SessionManager {
         
         Ok(is_active)
     }
+    
+    /// 驗證請求所帶的 Session（含閒置逾時），並更新活動時間
+    ///
+    /// 結果快取 `cache_seconds` 秒；快取失效時才查詢資料庫，
+    /// 因此活動時間最多每個快取週期寫入一次。
+    pub async fn validate(
+        pool: &PgPool,
+        session_id: Uuid,
+        user_id: Uuid,
+        idle_timeout_minutes: i64,
+        cache_seconds: u64,
+    ) -> Result<bool> {
+        let ttl = Duration::from_secs(cache_seconds);
+        if let Some(valid) = Self::cached(session_id, user_id, ttl) {
+            return Ok(valid);
+        }
+
+        let row: Option<(Uuid, bool, DateTime<Utc>)> = sqlx::query_as(
+            r#"
+            SELECT user_id, COALESCE(is_active, false), last_activity_at
+            FROM user_sessions WHERE id = $1
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(pool)
+        .await?;
+
+        let valid = match row {
+            Some((owner, true, last_activity_at)) if owner == user_id => {
+                if is_idle(last_activity_at, Utc::now(), idle_timeout_minutes) {
+                    sqlx::query(
+                        r#"
+                        UPDATE user_sessions
+                        SET is_active = false,
+                            ended_at = NOW(),
+                            ended_reason = 'timeout'
+                        WHERE id = $1 AND is_active = true
+                        "#,
+                    )
+                    .bind(session_id)
+                    .execute(pool)
+                    .await?;
+                    false
+                } else {
+                    Self::update_activity(pool, session_id).await?;
+                    true
+                }
+            }
+            _ => false,
+        };
+
+        if ttl > Duration::ZERO {
+            let mut cache = session_cache().lock().unwrap_or_else(|e| e.into_inner());
+            cache.retain(|_, entry| entry.checked_at.elapsed() < ttl);
+            cache.insert(session_id, CachedSession { user_id, valid, checked_at: Instant::now() });
+        }
+
+        Ok(valid)
+    }
+
+    /// 依角色上限結束最舊的 Sessions，為新登入保留一個名額
+    ///
+    /// 回傳被結束的 Session ID；`max_sessions` 為 0 表示不限制。
+    pub async fn enforce_session_limit(
+        pool: &PgPool,
+        user_id: Uuid,
+        max_sessions: i64,
+    ) -> Result<Vec<Uuid>> {
+        if max_sessions <= 0 {
+            return Ok(Vec::new());
+        }
+
+        let ended: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            UPDATE user_sessions
+            SET is_active = false,
+                ended_at = NOW(),
+                ended_reason = 'session_limit'
+            WHERE id IN (
+                SELECT id FROM user_sessions
+                WHERE user_id = $1 AND is_active = true
+                ORDER BY last_activity_at DESC
+                OFFSET $2
+            )
+            RETURNING id
+            "#,
+        )
+        .bind(user_id)
+        .bind(max_sessions - 1)
+        .fetch_all(pool)
+        .await?;
+
+        for session_id in &ended {
+            Self::invalidate(*session_id);
+        }
+        Ok(ended)
+    }
+
+    /// 記錄 Session 目前使用的 refresh token
+    pub async fn link_refresh_token(
+        pool: &PgPool,
+        session_id: Uuid,
+        refresh_token_id: Uuid,
+    ) -> Result<()> {
+        sqlx::query("UPDATE user_sessions SET refresh_token_id = $2 WHERE id = $1")
+            .bind(session_id)
+            .bind(refresh_token_id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 依 refresh token 找出所屬 Session（回傳 ID 與是否仍有效）
+    pub async fn find_by_refresh_token(
+        pool: &PgPool,
+        refresh_token_id: Uuid,
+        idle_timeout_minutes: i64,
+    ) -> Result<Option<(Uuid, bool)>> {
+        let row: Option<(Uuid, bool, DateTime<Utc>)> = sqlx::query_as(
+            r#"
+            SELECT id, COALESCE(is_active, false), last_activity_at
+            FROM user_sessions WHERE refresh_token_id = $1
+            "#,
+        )
+        .bind(refresh_token_id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row.map(|(id, is_active, last_activity_at)| {
+            (id, is_active && !is_idle(last_activity_at, Utc::now(), idle_timeout_minutes))
+        }))
+    }
+
+    /// 清除單一 Session 的驗證快取
+    pub fn invalidate(session_id: Uuid) {
+        let mut cache = session_cache().lock().unwrap_or_else(|e| e.into_inner());
+        cache.remove(&session_id);
+    }
+
+    /// 清除使用者所有 Sessions 的驗證快取
+    pub fn invalidate_user(user_id: Uuid) {
+        let mut cache = session_cache().lock().unwrap_or_else(|e| e.into_inner());
+        cache.retain(|_, entry| entry.user_id != user_id);
+    }
+
+    fn cached(session_id: Uuid, user_id: Uuid, ttl: Duration) -> Option<bool> {
+        let cache = session_cache().lock().unwrap_or_else(|e| e.into_inner());
+        cache
+            .get(&session_id)
+            .filter(|entry| entry.user_id == user_id && entry.checked_at.elapsed() < ttl)
+            .map(|entry| entry.valid)
+    }
+}
+
+/// 最後活動時間是否已超過閒置逾時（0 表示不限制）
+fn is_idle(last_activity_at: DateTime<Utc>, now: DateTime<Utc>, idle_timeout_minutes: i64) -> bool {
+    idle_timeout_minutes > 0 && now - last_activity_at > chrono::Duration::minutes(idle_timeout_minutes)
+}
+
+/// 依使用者角色決定可同時登入的 Session 數（取角色中最寬鬆者，0 表示不限制）
+pub fn max_sessions_for_roles(
+    per_role: &HashMap<String, i64>,
+    default_limit: i64,
+    roles: &[String],
+) -> i64 {
+    let limits: Vec<i64> = roles.iter().filter_map(|r| per_role.get(r).copied()).collect();
+    if limits.is_empty() {
+        return default_limit;
+    }
+    if limits.contains(&0) {
+        return 0;
+    }
+    limits.into_iter().max().unwrap_or(default_limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_sessions_uses_most_permissive_role() {
+        let per_role: HashMap<String, i64> =
+            [("SYSTEM_ADMIN".to_string(), 0), ("EXPERIMENT_STAFF".to_string(), 2), ("VET".to_string(), 3)]
+                .into_iter()
+                .collect();
+
+        assert_eq!(max_sessions_for_roles(&per_role, 5, &[]), 5);
+        assert_eq!(max_sessions_for_roles(&per_role, 5, &["UNKNOWN".to_string()]), 5);
+        assert_eq!(
+            max_sessions_for_roles(&per_role, 5, &["EXPERIMENT_STAFF".to_string(), "VET".to_string()]),
+            3
+        );
+        assert_eq!(
+            max_sessions_for_roles(&per_role, 5, &["VET".to_string(), "SYSTEM_ADMIN".to_string()]),
+            0
+        );
+    }
+
+    #[test]
+    fn test_idle_timeout_zero_disables_expiry() {
+        let now = Utc::now();
+        let last = now - chrono::Duration::minutes(90);
+        assert!(is_idle(last, now, 60));
+        assert!(!is_idle(last, now, 120));
+        assert!(!is_idle(last, now, 0));
+    }
 }
@@ -5,7 +5,7 @@ use crate::{AppError, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
-use sqlx::{FromRow, PgPool};
+use sqlx::{FromRow, PgConnection, PgPool};
 use uuid::Uuid;
 
 /// 簽章類型
@@ -25,6 +25,34 @@ impl SignatureType {
             SignatureType::Witness => "WITNESS",
         }
     }
+
+    /// 未指定簽章意義時的預設值
+    pub fn default_meaning(&self) -> SignatureMeaning {
+        match self {
+            SignatureType::Approve => SignatureMeaning::Approval,
+            SignatureType::Confirm => SignatureMeaning::Authorship,
+            SignatureType::Witness => SignatureMeaning::Review,
+        }
+    }
+}
+
+/// 簽章意義（撰寫、審查、核准）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SignatureMeaning {
+    Authorship, // 撰寫者確認
+    Review,     // 審查
+    Approval,   // 核准
+}
+
+impl SignatureMeaning {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignatureMeaning::Authorship => "AUTHORSHIP",
+            SignatureMeaning::Review => "REVIEW",
+            SignatureMeaning::Approval => "APPROVAL",
+        }
+    }
 }
 
 /// 電子簽章記錄
@@ -35,6 +63,7 @@ pub struct ElectronicSignature {
     pub entity_id: String,
     pub signer_id: Uuid,
     pub signature_type: String,
+    pub signature_meaning: Option<String>,
     pub content_hash: String,
     pub signature_data: String,
     pub ip_address: Option<String>,
@@ -70,6 +99,47 @@ pub struct VerifyResult {
     pub failure_reason: Option<String>,
 }
 
+/// 實體簽章與目前內容比對結果
+#[derive(Debug, Serialize)]
+pub struct EntitySignatureVerification {
+    pub id: Uuid,
+    pub signer_id: Uuid,
+    pub signer_name: Option<String>,
+    pub signature_type: String,
+    pub signature_meaning: Option<String>,
+    pub signed_at: DateTime<Utc>,
+    pub is_valid: bool,
+    pub invalidated_reason: Option<String>,
+    /// 簽章時內容雜湊與目前記錄內容是否一致
+    pub content_matches: bool,
+}
+
+/// 待寫入的簽章
+struct NewSignature<'a> {
+    entity_type: &'a str,
+    entity_id: &'a str,
+    signer_id: Uuid,
+    password_hash: &'a str,
+    signature_type: SignatureType,
+    meaning: SignatureMeaning,
+    content: &'a str,
+    ip_address: Option<&'a str>,
+    user_agent: Option<&'a str>,
+}
+
+/// 解鎖時的簽署者（密碼已驗證）
+pub struct UnlockSigner<'a> {
+    pub user_id: Uuid,
+    pub password_hash: &'a str,
+}
+
+/// 解鎖結果：解鎖者簽章與共同簽署者簽章
+#[derive(Debug)]
+pub struct RecordUnlock {
+    pub signature: ElectronicSignature,
+    pub co_signature: ElectronicSignature,
+}
+
 pub struct SignatureService;
 
 impl SignatureService {
@@ -80,6 +150,13 @@ impl SignatureService {
         format!("{:x}", hasher.finalize())
     }
 
+    /// 建立簽章資料（簽章 = 使用者ID + 內容雜湊 + 時間戳記 的雜湊）
+    fn signature_data(signer_id: Uuid, content_hash: &str, password_hash: &str) -> String {
+        let timestamp = Utc::now();
+        let signature_input = format!("{}:{}:{}:{}", signer_id, content_hash, timestamp.timestamp(), password_hash);
+        Self::compute_hash(&signature_input)
+    }
+
     /// 建立電子簽章
     pub async fn sign(
         pool: &PgPool,
@@ -92,39 +169,175 @@ impl SignatureService {
         ip_address: Option<&str>,
         user_agent: Option<&str>,
     ) -> Result<ElectronicSignature> {
+        let mut conn = pool.acquire().await?;
+        Self::insert(&mut conn, NewSignature {
+            entity_type,
+            entity_id,
+            signer_id,
+            password_hash,
+            signature_type,
+            meaning: signature_type.default_meaning(),
+            content,
+            ip_address,
+            user_agent,
+        }).await
+    }
+
+    /// 依目前記錄內容建立電子簽章（內容由 `signable_content` 產生，之後可比對驗證）
+    pub async fn sign_entity(
+        conn: &mut PgConnection,
+        entity_type: &str,
+        entity_id: &str,
+        signer_id: Uuid,
+        password_hash: &str,
+        signature_type: SignatureType,
+        meaning: SignatureMeaning,
+    ) -> Result<ElectronicSignature> {
+        let content = Self::signable_content(&mut *conn, entity_type, entity_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("找不到簽章對象".to_string()))?;
+
+        Self::insert(conn, NewSignature {
+            entity_type,
+            entity_id,
+            signer_id,
+            password_hash,
+            signature_type,
+            meaning,
+            content: &content,
+            ip_address: None,
+            user_agent: None,
+        }).await
+    }
+
+    /// 寫入簽章記錄
+    async fn insert(conn: &mut PgConnection, new: NewSignature<'_>) -> Result<ElectronicSignature> {
         // 計算內容雜湊
-        let content_hash = Self::compute_hash(content);
-        
-        // 建立簽章資料（簽章 = 使用者ID + 內容雜湊 + 時間戳記 的雜湊）
-        let timestamp = Utc::now();
-        let signature_input = format!("{}:{}:{}:{}", signer_id, content_hash, timestamp.timestamp(), password_hash);
-        let signature_data = Self::compute_hash(&signature_input);
-        
-        // 儲存到資料庫
+        let content_hash = Self::compute_hash(new.content);
+        let signature_data = Self::signature_data(new.signer_id, &content_hash, new.password_hash);
+
         let signature = sqlx::query_as::<_, ElectronicSignature>(
             r#"
             INSERT INTO electronic_signatures (
-                entity_type, entity_id, signer_id, signature_type,
+                entity_type, entity_id, signer_id, signature_type, signature_meaning,
                 content_hash, signature_data, ip_address, user_agent
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             RETURNING *
             "#
         )
-        .bind(entity_type)
-        .bind(entity_id)
-        .bind(signer_id)
-        .bind(signature_type.as_str())
+        .bind(new.entity_type)
+        .bind(new.entity_id)
+        .bind(new.signer_id)
+        .bind(new.signature_type.as_str())
+        .bind(new.meaning.as_str())
         .bind(&content_hash)
         .bind(&signature_data)
-        .bind(ip_address)
-        .bind(user_agent)
-        .fetch_one(pool)
+        .bind(new.ip_address)
+        .bind(new.user_agent)
+        .fetch_one(conn)
         .await?;
 
         Ok(signature)
     }
 
+    /// 取得可簽章實體目前的標準化內容（不含狀態、鎖定等簽章後會變動的欄位）
+    pub async fn signable_content(
+        conn: &mut PgConnection,
+        entity_type: &str,
+        entity_id: &str,
+    ) -> Result<Option<String>> {
+        let (sql, key_type) = signable_source(entity_type)?;
+        let valid_key = match key_type {
+            "uuid" => entity_id.parse::<Uuid>().is_ok(),
+            _ => entity_id.parse::<i32>().is_ok(),
+        };
+        if !valid_key {
+            return Err(AppError::Validation(format!("無效的記錄 ID: {}", entity_id)));
+        }
+
+        let content: Option<String> = sqlx::query_scalar(sql)
+            .bind(entity_id)
+            .fetch_optional(conn)
+            .await?;
+
+        Ok(content)
+    }
+
+    /// 列出實體所有簽章，並以目前記錄內容驗證雜湊（結果寫入驗證日誌）
+    pub async fn verify_entity(
+        pool: &PgPool,
+        entity_type: &str,
+        entity_id: &str,
+        verified_by: Uuid,
+    ) -> Result<Vec<EntitySignatureVerification>> {
+        let mut conn = pool.acquire().await?;
+        let current_hash = Self::signable_content(&mut conn, entity_type, entity_id)
+            .await?
+            .map(|content| Self::compute_hash(&content))
+            .ok_or_else(|| AppError::NotFound("找不到簽章對象".to_string()))?;
+
+        let signatures = sqlx::query_as::<_, ElectronicSignature>(
+            r#"
+            SELECT * FROM electronic_signatures
+            WHERE entity_type = $1 AND entity_id = $2
+            ORDER BY signed_at
+            "#
+        )
+        .bind(entity_type)
+        .bind(entity_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut results = Vec::with_capacity(signatures.len());
+        for sig in signatures {
+            let signer_name: Option<String> = sqlx::query_scalar(
+                "SELECT display_name FROM users WHERE id = $1"
+            )
+            .bind(sig.signer_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+            let content_matches = sig.content_hash == current_hash;
+            let failure_reason = if !sig.is_valid {
+                Some(sig.invalidated_reason.clone().unwrap_or("簽章已失效".to_string()))
+            } else if !content_matches {
+                Some("內容已被修改，簽章失效".to_string())
+            } else {
+                None
+            };
+
+            sqlx::query(
+                r#"
+                INSERT INTO signature_verification_logs (
+                    signature_id, verified_by, verification_result, verification_method, failure_reason
+                )
+                VALUES ($1, $2, $3, 'CONTENT_HASH', $4)
+                "#
+            )
+            .bind(sig.id)
+            .bind(verified_by)
+            .bind(failure_reason.is_none())
+            .bind(&failure_reason)
+            .execute(&mut *conn)
+            .await?;
+
+            results.push(EntitySignatureVerification {
+                id: sig.id,
+                signer_id: sig.signer_id,
+                signer_name,
+                signature_type: sig.signature_type,
+                signature_meaning: sig.signature_meaning,
+                signed_at: sig.signed_at,
+                is_valid: sig.is_valid,
+                invalidated_reason: sig.invalidated_reason,
+                content_matches,
+            });
+        }
+
+        Ok(results)
+    }
+
     /// 取得實體的所有有效簽章
     pub async fn get_signatures(
         pool: &PgPool,
@@ -251,19 +464,32 @@ impl SignatureService {
         Ok(count > 0)
     }
 
-    /// 鎖定記錄（簽章後自動鎖定）
+    /// 以 FOR UPDATE 鎖住待簽章記錄，確保簽章與鎖定之間記錄不被修改
+    pub async fn lock_for_signing(
+        conn: &mut PgConnection,
+        record_type: &str,
+        record_id: i32,
+    ) -> Result<()> {
+        let table_name = lockable_table(record_type)?;
+
+        let query = format!("SELECT id FROM {} WHERE id = $1 FOR UPDATE", table_name);
+        sqlx::query(&query)
+            .bind(record_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or_else(|| AppError::NotFound("找不到記錄".to_string()))?;
+
+        Ok(())
+    }
+
+    /// 鎖定記錄（簽章後自動鎖定，與簽章於同一交易）
     pub async fn lock_record(
-        pool: &PgPool,
-        record_type: &str,  // "observation", "surgery", "sacrifice"
+        conn: &mut PgConnection,
+        record_type: &str,  // "observation", "surgery", "weight", "vaccination", "sacrifice", "pathology"
         record_id: i32,
         locked_by: Uuid,
     ) -> Result<()> {
-        let table_name = match record_type {
-            "observation" => "pig_observations",
-            "surgery" => "pig_surgeries",
-            "sacrifice" => "pig_sacrifices",
-            _ => return Err(AppError::Validation(format!("不支援的記錄類型: {}", record_type))),
-        };
+        let table_name = lockable_table(record_type)?;
 
         let query = format!(
             "UPDATE {} SET is_locked = true, locked_at = NOW(), locked_by = $2 WHERE id = $1",
@@ -273,7 +499,7 @@ impl SignatureService {
         sqlx::query(&query)
             .bind(record_id)
             .bind(locked_by)
-            .execute(pool)
+            .execute(&mut *conn)
             .await?;
 
         Ok(())
@@ -285,12 +511,7 @@ impl SignatureService {
         record_type: &str,
         record_id: i32,
     ) -> Result<bool> {
-        let table_name = match record_type {
-            "observation" => "pig_observations",
-            "surgery" => "pig_surgeries",
-            "sacrifice" => "pig_sacrifices",
-            _ => return Err(AppError::Validation(format!("不支援的記錄類型: {}", record_type))),
-        };
+        let table_name = lockable_table(record_type)?;
 
         let query = format!(
             "SELECT COALESCE(is_locked, false) FROM {} WHERE id = $1",
@@ -305,6 +526,280 @@ impl SignatureService {
 
         Ok(is_locked)
     }
+
+    /// 解除記錄鎖定（需原因、解鎖者與共同簽署者簽章）
+    ///
+    /// 共同簽署者須為解鎖者以外、且未簽署原記錄的使用者。
+    /// 原有簽章全數失效，兩份解鎖簽章與原因記錄於 change_reasons。
+    pub async fn unlock_record(
+        pool: &PgPool,
+        record_type: &str,
+        record_id: i32,
+        unlocker: UnlockSigner<'_>,
+        co_signer: UnlockSigner<'_>,
+        reason: &str,
+    ) -> Result<RecordUnlock> {
+        if co_signer.user_id == unlocker.user_id {
+            return Err(AppError::BusinessRule("共同簽署者不可為解鎖者本人".to_string()));
+        }
+
+        let table_name = lockable_table(record_type)?;
+        let entity_id = record_id.to_string();
+        let mut tx = pool.begin().await?;
+
+        let lock: Option<(bool, Option<DateTime<Utc>>, Option<Uuid>)> = sqlx::query_as(&format!(
+            "SELECT COALESCE(is_locked, false), locked_at, locked_by FROM {} WHERE id = $1 FOR UPDATE",
+            table_name
+        ))
+        .bind(record_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let (locked_at, locked_by) = match lock {
+            None => return Err(AppError::NotFound("找不到記錄".to_string())),
+            Some((false, ..)) => return Err(AppError::BusinessRule("記錄未鎖定，無需解鎖".to_string())),
+            Some((true, locked_at, locked_by)) => (locked_at, locked_by),
+        };
+
+        let original_signers: Vec<Uuid> = sqlx::query_scalar(
+            "SELECT signer_id FROM electronic_signatures WHERE entity_type = $1 AND entity_id = $2 AND is_valid = true",
+        )
+        .bind(record_type)
+        .bind(&entity_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        if locked_by == Some(co_signer.user_id) || original_signers.contains(&co_signer.user_id) {
+            return Err(AppError::BusinessRule("共同簽署者不可為原記錄簽署者".to_string()));
+        }
+
+        sqlx::query(&format!(
+            "UPDATE {} SET is_locked = false, locked_at = NULL, locked_by = NULL WHERE id = $1",
+            table_name
+        ))
+        .bind(record_id)
+        .execute(&mut *tx)
+        .await?;
+
+        // 原有簽章失效
+        let invalidated: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            UPDATE electronic_signatures SET
+                is_valid = false,
+                invalidated_reason = $3,
+                invalidated_at = NOW(),
+                invalidated_by = $4
+            WHERE entity_type = $1 AND entity_id = $2 AND is_valid = true
+            RETURNING id
+            "#
+        )
+        .bind(record_type)
+        .bind(&entity_id)
+        .bind(format!("解除鎖定：{}", reason))
+        .bind(unlocker.user_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        // 解鎖簽章與共同簽署簽章
+        let unlock_entity_type = format!("{}_unlock", record_type);
+        let content = format!("unlock:{}:{}:{}", record_type, record_id, reason);
+        let signature = Self::insert(&mut tx, NewSignature {
+            entity_type: &unlock_entity_type,
+            entity_id: &entity_id,
+            signer_id: unlocker.user_id,
+            password_hash: unlocker.password_hash,
+            signature_type: SignatureType::Approve,
+            meaning: SignatureMeaning::Approval,
+            content: &content,
+            ip_address: None,
+            user_agent: None,
+        }).await?;
+        let co_signature = Self::insert(&mut tx, NewSignature {
+            entity_type: &unlock_entity_type,
+            entity_id: &entity_id,
+            signer_id: co_signer.user_id,
+            password_hash: co_signer.password_hash,
+            signature_type: SignatureType::Witness,
+            meaning: SignatureMeaning::Review,
+            content: &content,
+            ip_address: None,
+            user_agent: None,
+        }).await?;
+
+        // 稽核記錄
+        sqlx::query(
+            r#"
+            INSERT INTO change_reasons (entity_type, entity_id, change_type, reason, old_values, new_values, changed_by)
+            VALUES ($1, $2, 'UNLOCK', $3, $4, $5, $6)
+            "#
+        )
+        .bind(record_type)
+        .bind(&entity_id)
+        .bind(reason)
+        .bind(serde_json::json!({
+            "is_locked": true,
+            "locked_at": locked_at,
+            "locked_by": locked_by,
+            "signature_ids": invalidated,
+        }))
+        .bind(serde_json::json!({
+            "is_locked": false,
+            "unlock_signature_id": signature.id,
+            "co_signature_id": co_signature.id,
+            "co_signed_by": co_signer.user_id,
+        }))
+        .bind(unlocker.user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(RecordUnlock { signature, co_signature })
+    }
+}
+
+/// 可鎖定的記錄類型對應資料表
+fn lockable_table(record_type: &str) -> Result<&'static str> {
+    match record_type {
+        "observation" => Ok("pig_observations"),
+        "surgery" => Ok("pig_surgeries"),
+        "weight" => Ok("pig_weights"),
+        "vaccination" => Ok("pig_vaccinations"),
+        "sacrifice" => Ok("pig_sacrifices"),
+        "pathology" => Ok("pig_pathology_reports"),
+        _ => Err(AppError::Validation(format!("不支援的記錄類型: {}", record_type))),
+    }
+}
+
+/// 可簽章實體的標準化內容查詢與鍵值型別
+///
+/// 犧牲、觀察紀錄沿用既有簽章格式，確保舊簽章仍可驗證。
+fn signable_source(entity_type: &str) -> Result<(&'static str, &'static str)> {
+    let source = match entity_type {
+        "sacrifice" => (
+            r#"
+            SELECT CONCAT(
+                'sacrifice_id:', id::text,
+                ',pig_id:', pig_id::text,
+                ',date:', COALESCE(sacrifice_date::text, ''),
+                ',confirmed:', confirmed_sacrifice::text
+            ) FROM pig_sacrifices WHERE id = $1::integer
+            "#,
+            "integer",
+        ),
+        "observation" => (
+            r#"
+            SELECT CONCAT(
+                'observation_id:', id::text,
+                ',pig_id:', pig_id::text,
+                ',date:', event_date::text,
+                ',content:', content
+            ) FROM pig_observations WHERE id = $1::integer
+            "#,
+            "integer",
+        ),
+        "surgery" => (
+            r#"
+            SELECT (to_jsonb(t) - ARRAY['is_locked', 'locked_at', 'locked_by', 'vet_read', 'vet_read_at', 'updated_at'])::text
+            FROM pig_surgeries t WHERE t.id = $1::integer
+            "#,
+            "integer",
+        ),
+        "pathology" => (
+            r#"
+            SELECT CONCAT(
+                'pathology_id:', r.id::text,
+                ',pig_id:', r.pig_id::text,
+                ',attachments:', COALESCE(
+                    string_agg(
+                        a.id::text || '/' || a.file_name || '/' || a.file_size::text || '/' || COALESCE(a.content_hash, ''),
+                        ';' ORDER BY a.created_at, a.id
+                    ),
+                    ''
+                )
+            )
+            FROM pig_pathology_reports r
+            LEFT JOIN attachments a ON a.entity_type = 'pathology' AND a.entity_id = r.pig_id::text
+                AND a.superseded_at IS NULL AND a.deleted_at IS NULL
+            WHERE r.id = $1::integer
+            GROUP BY r.id
+            "#,
+            "integer",
+        ),
+        // 計畫簽章綁定最新送審版本的快照，修正案套用後變更的工作內容不影響原核准簽章
+        "protocol" => (
+            r#"
+            SELECT CONCAT(
+                'protocol_id:', p.id::text,
+                ',protocol_no:', p.protocol_no,
+                ',version_id:', COALESCE(pv.id::text, ''),
+                ',content:', COALESCE(pv.content_snapshot::text, '')
+            )
+            FROM protocols p
+            LEFT JOIN LATERAL (
+                SELECT id, content_snapshot FROM protocol_versions
+                WHERE protocol_id = p.id AND amendment_id IS NULL
+                ORDER BY version_no DESC
+                LIMIT 1
+            ) pv ON true
+            WHERE p.id = $1::uuid
+            "#,
+            "uuid",
+        ),
+        "protocol_amendment" => (
+            r#"
+            SELECT CONCAT(
+                'amendment_id:', id::text,
+                ',amendment_no:', amendment_no,
+                ',protocol_id:', protocol_id::text,
+                ',content:', proposed_content::text
+            ) FROM protocol_amendments WHERE id = $1::uuid
+            "#,
+            "uuid",
+        ),
+        "document" => (
+            r#"
+            SELECT jsonb_build_object(
+                'id', d.id,
+                'doc_type', d.doc_type,
+                'doc_no', d.doc_no,
+                'doc_date', d.doc_date,
+                'warehouse_id', d.warehouse_id,
+                'warehouse_from_id', d.warehouse_from_id,
+                'warehouse_to_id', d.warehouse_to_id,
+                'partner_id', d.partner_id,
+                'source_doc_id', d.source_doc_id,
+                'remark', d.remark,
+                'lines', COALESCE(
+                    (SELECT jsonb_agg(to_jsonb(l) ORDER BY l.line_no) FROM document_lines l WHERE l.document_id = d.id),
+                    '[]'::jsonb
+                )
+            )::text
+            FROM documents d WHERE d.id = $1::uuid
+            "#,
+            "uuid",
+        ),
+        "leave" => (
+            r#"
+            SELECT jsonb_build_object(
+                'id', id,
+                'user_id', user_id,
+                'proxy_user_id', proxy_user_id,
+                'leave_type', leave_type,
+                'start_date', start_date,
+                'end_date', end_date,
+                'start_time', start_time,
+                'end_time', end_time,
+                'total_days', total_days,
+                'total_hours', total_hours,
+                'reason', reason
+            )::text
+            FROM leave_requests WHERE id = $1::uuid
+            "#,
+            "uuid",
+        ),
+        _ => return Err(AppError::Validation(format!("不支援的簽章實體類型: {}", entity_type))),
+    };
+
+    Ok(source)
 }
 
 // ============================================
@@ -410,3 +905,187 @@ impl AnnotationService {
         Ok(annotations)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_pig, insert_user};
+
+    #[test]
+    fn test_signature_type_maps_to_default_meaning() {
+        assert_eq!(SignatureType::Approve.default_meaning(), SignatureMeaning::Approval);
+        assert_eq!(SignatureType::Confirm.default_meaning(), SignatureMeaning::Authorship);
+        assert_eq!(SignatureType::Witness.default_meaning(), SignatureMeaning::Review);
+    }
+
+    #[test]
+    fn test_signable_source_covers_signed_entities_only() {
+        for entity_type in ["sacrifice", "observation", "surgery", "pathology", "protocol", "document", "leave"] {
+            assert!(signable_source(entity_type).is_ok(), "{entity_type}");
+        }
+        assert!(matches!(signable_source("sacrifice_unlock"), Err(AppError::Validation(_))));
+        assert_eq!(signable_source("leave").unwrap().1, "uuid");
+        assert_eq!(signable_source("surgery").unwrap().1, "integer");
+    }
+
+    /// 建立一筆由 `signer` 簽章鎖定的體重紀錄，回傳 (豬隻 ID, 紀錄 ID)
+    async fn locked_weight(db: &PgPool, signer: Uuid) -> (i32, i32) {
+        let pig_id = insert_pig(db, "L001", None).await;
+        let weight_id: i32 = sqlx::query_scalar(
+            "INSERT INTO pig_weights (pig_id, measure_date, weight, created_by) VALUES ($1, CURRENT_DATE, 20.5, $2) RETURNING id",
+        )
+        .bind(pig_id)
+        .bind(signer)
+        .fetch_one(db)
+        .await
+        .unwrap();
+
+        let entity_id = weight_id.to_string();
+        SignatureService::sign(db, "weight", &entity_id, signer, "x", SignatureType::Confirm, "weight", None, None)
+            .await
+            .unwrap();
+        SignatureService::lock_record(&mut db.acquire().await.unwrap(), "weight", weight_id, signer)
+            .await
+            .unwrap();
+        (pig_id, weight_id)
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_locked_records_cannot_be_edited(db: PgPool) {
+        let signer = insert_user(&db, "signer@example.com", true).await;
+        let (pig_id, weight_id) = locked_weight(&db, signer).await;
+
+        let update = crate::models::UpdateWeightRequest { measure_date: None, weight: None };
+        let result = crate::services::AnimalService::update_weight(&db, weight_id, &update).await;
+        assert!(matches!(result, Err(AppError::BusinessRule(_))));
+
+        sqlx::query("INSERT INTO pig_sacrifices (pig_id, is_locked) VALUES ($1, true)")
+            .bind(pig_id)
+            .execute(&db)
+            .await
+            .unwrap();
+        let sacrifice = crate::models::CreateSacrificeRequest {
+            sacrifice_date: None,
+            zoletil_dose: None,
+            method_electrocution: false,
+            method_bloodletting: false,
+            method_other: None,
+            sampling: None,
+            sampling_other: None,
+            blood_volume_ml: None,
+            confirmed_sacrifice: true,
+        };
+        let result = crate::services::AnimalService::upsert_sacrifice(&db, pig_id, &sacrifice, signer).await;
+        assert!(matches!(result, Err(AppError::BusinessRule(_))));
+
+        let confirmed: Option<bool> = sqlx::query_scalar("SELECT confirmed_sacrifice FROM pig_sacrifices WHERE pig_id = $1")
+            .bind(pig_id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_ne!(confirmed, Some(true));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_unlock_rejects_self_and_original_signer_co_signatures(db: PgPool) {
+        let signer = insert_user(&db, "signer@example.com", true).await;
+        let unlocker = insert_user(&db, "unlocker@example.com", true).await;
+        let (_, weight_id) = locked_weight(&db, signer).await;
+
+        let as_signer = |user_id| UnlockSigner { user_id, password_hash: "x" };
+        let self_signed =
+            SignatureService::unlock_record(&db, "weight", weight_id, as_signer(unlocker), as_signer(unlocker), "更正").await;
+        assert!(matches!(self_signed, Err(AppError::BusinessRule(_))));
+
+        let original =
+            SignatureService::unlock_record(&db, "weight", weight_id, as_signer(unlocker), as_signer(signer), "更正").await;
+        assert!(matches!(original, Err(AppError::BusinessRule(_))));
+
+        assert!(SignatureService::is_locked(&db, "weight", weight_id).await.unwrap());
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_unlock_with_co_signer_records_both_signatures(db: PgPool) {
+        let signer = insert_user(&db, "signer@example.com", true).await;
+        let unlocker = insert_user(&db, "unlocker@example.com", true).await;
+        let co_signer = insert_user(&db, "co-signer@example.com", true).await;
+        let (_, weight_id) = locked_weight(&db, signer).await;
+
+        let unlock = SignatureService::unlock_record(
+            &db,
+            "weight",
+            weight_id,
+            UnlockSigner { user_id: unlocker, password_hash: "x" },
+            UnlockSigner { user_id: co_signer, password_hash: "x" },
+            "更正體重",
+        )
+        .await
+        .unwrap();
+        assert_eq!(unlock.signature.signer_id, unlocker);
+        assert_eq!(unlock.co_signature.signer_id, co_signer);
+        assert_eq!(unlock.co_signature.entity_type, "weight_unlock");
+
+        assert!(!SignatureService::is_locked(&db, "weight", weight_id).await.unwrap());
+        assert!(!SignatureService::is_signed(&db, "weight", &weight_id.to_string()).await.unwrap());
+
+        let update = crate::models::UpdateWeightRequest { measure_date: None, weight: None };
+        crate::services::AnimalService::update_weight(&db, weight_id, &update).await.unwrap();
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_pathology_signature_covers_live_attachments_and_lock(db: PgPool) {
+        use crate::{models::NewAttachment, services::AttachmentService};
+
+        let signer = insert_user(&db, "pathologist@example.com", true).await;
+        let pig_id = insert_pig(&db, "P001", None).await;
+        let report_id: i32 = sqlx::query_scalar("INSERT INTO pig_pathology_reports (pig_id) VALUES ($1) RETURNING id")
+            .bind(pig_id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        let entity_id = pig_id.to_string();
+        let new = |file_name| NewAttachment {
+            category: "pathology",
+            entity_type: "pathology",
+            entity_id: &entity_id,
+            file_name,
+            file_path: "pathology/ab/ab.pdf",
+            file_size: 3,
+            mime_type: "application/pdf",
+            content_hash: "ab",
+            uploaded_by: signer,
+        };
+        let mut conn = db.acquire().await.unwrap();
+        let first = AttachmentService::create(&mut conn, &new("a.pdf")).await.unwrap();
+        AttachmentService::create(&mut conn, &new("b.pdf")).await.unwrap();
+
+        let report = report_id.to_string();
+        SignatureService::sign_entity(&mut conn, "pathology", &report, signer, "x", SignatureType::Confirm, SignatureMeaning::Authorship)
+            .await
+            .unwrap();
+        let verify = |db: PgPool| {
+            let report = report.clone();
+            async move { SignatureService::verify_entity(&db, "pathology", &report, signer).await.unwrap()[0].content_matches }
+        };
+        assert!(verify(db.clone()).await);
+
+        // 軟刪除已簽核報告的附件後簽章內容不再相符
+        AttachmentService::delete_with_reason(&db, first.id, "誤傳", signer).await.unwrap();
+        assert!(!verify(db.clone()).await);
+
+        // 鎖定後附件不可新增、取代或刪除
+        SignatureService::lock_record(&mut conn, "pathology", report_id, signer).await.unwrap();
+        let created = AttachmentService::create(&mut conn, &new("c.pdf")).await;
+        assert!(matches!(created, Err(AppError::BusinessRule(_))));
+        let current = AttachmentService::list_current(&db, "pathology", &entity_id).await.unwrap();
+        assert_eq!(current.len(), 1);
+        let replaced = AttachmentService::replace(&mut conn, current[0].id, &new("b2.pdf"), "更新").await;
+        assert!(matches!(replaced, Err(AppError::BusinessRule(_))));
+        let deleted = AttachmentService::delete_with_reason(&db, current[0].id, "誤傳", signer).await;
+        assert!(matches!(deleted, Err(AppError::BusinessRule(_))));
+    }
+}
@@ -1,25 +1,45 @@
 use chrono::Utc;
 use rust_decimal::Decimal;
-use sqlx::{PgPool, Postgres, Transaction};
+use sqlx::{FromRow, PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
 use crate::{
     models::{
-        DocType, Document, DocumentLine, InventoryOnHand, InventoryQuery, LowStockAlert,
-        StockDirection, StockLedgerDetail, StockLedgerQuery,
+        CostingMethod, DocType, Document, DocumentLine, InventoryByLocation, InventoryOnHand,
+        InventoryQuery, LowStockAlert, StockDirection, StockLedgerDetail, StockLedgerQuery,
+        StorageLocation,
     },
+    services::costing::{ConsumedCost, CostingService, NewCostLayer},
     AppError, Result,
 };
 
 pub struct StockService;
 
+/// 可沖銷的原單據庫存流水
+#[derive(Debug, FromRow)]
+struct ReversibleEntry {
+    id: Uuid,
+    warehouse_id: Uuid,
+    direction: StockDirection,
+    qty_base: Decimal,
+    total_cost: Option<Decimal>,
+    line_no: i32,
+}
+
 impl StockService {
     /// 處理單據核准後的庫存變動
+    ///
+    /// 入庫建立成本層，出庫依計價方式耗用成本層，並將耗用成本寫回庫存流水。
     pub async fn process_document(
         tx: &mut Transaction<'_, Postgres>,
         document: &Document,
         lines: &[DocumentLine],
+        costing_method: CostingMethod,
     ) -> Result<()> {
+        if document.is_reversal {
+            return Self::process_reversal(tx, document, lines, costing_method).await;
+        }
+
         for line in lines {
             match document.doc_type {
                 DocType::GRN => {
@@ -27,15 +47,14 @@ impl StockService {
                     let warehouse_id = document.warehouse_id
                         .ok_or_else(|| AppError::BusinessRule("Warehouse is required for GRN".to_string()))?;
                     
-                    Self::create_ledger_entry(
+                    Self::receive_stock(
                         tx,
+                        costing_method,
                         warehouse_id,
-                        line.product_id,
                         document,
                         line,
                         StockDirection::In,
                         line.qty,
-                        line.unit_price,
                     ).await?;
                 }
                 DocType::PR => {
@@ -46,16 +65,7 @@ impl StockService {
                     // 檢查庫存
                     Self::check_stock_available(tx, warehouse_id, line.product_id, line.qty).await?;
                     
-                    Self::create_ledger_entry(
-                        tx,
-                        warehouse_id,
-                        line.product_id,
-                        document,
-                        line,
-                        StockDirection::Out,
-                        line.qty,
-                        line.unit_price,
-                    ).await?;
+                    Self::issue_stock(tx, warehouse_id, document, line, StockDirection::Out, line.qty).await?;
                 }
                 DocType::DO => {
                     // 銷售出庫：減少庫存
@@ -65,16 +75,7 @@ impl StockService {
                     // 檢查庫存
                     Self::check_stock_available(tx, warehouse_id, line.product_id, line.qty).await?;
                     
-                    Self::create_ledger_entry(
-                        tx,
-                        warehouse_id,
-                        line.product_id,
-                        document,
-                        line,
-                        StockDirection::Out,
-                        line.qty,
-                        line.unit_price,
-                    ).await?;
+                    Self::issue_stock(tx, warehouse_id, document, line, StockDirection::Out, line.qty).await?;
                 }
                 DocType::TR => {
                     // 調撥：從來源倉減少，目標倉增加
@@ -87,28 +88,41 @@ impl StockService {
                     Self::check_stock_available(tx, from_warehouse, line.product_id, line.qty).await?;
                     
                     // 從來源倉扣減
-                    Self::create_ledger_entry(
+                    let consumed = Self::issue_stock(
                         tx,
                         from_warehouse,
-                        line.product_id,
                         document,
                         line,
                         StockDirection::TransferOut,
                         line.qty,
-                        None,
                     ).await?;
                     
-                    // 增加到目標倉
-                    Self::create_ledger_entry(
+                    // 增加到目標倉（沿用來源成本層的批號與成本）
+                    let ledger_id = Self::create_ledger_entry(
                         tx,
                         to_warehouse,
-                        line.product_id,
                         document,
                         line,
                         StockDirection::TransferIn,
                         line.qty,
-                        None,
+                        Some(consumed.total_cost),
                     ).await?;
+
+                    for slice in &consumed.slices {
+                        CostingService::receive(
+                            tx,
+                            costing_method,
+                            NewCostLayer {
+                                warehouse_id: to_warehouse,
+                                product_id: line.product_id,
+                                batch_no: slice.batch_no.as_deref(),
+                                expiry_date: slice.expiry_date,
+                                source_ledger_id: ledger_id,
+                                qty: slice.qty,
+                                unit_cost: slice.unit_cost,
+                            },
+                        ).await?;
+                    }
                 }
                 DocType::ADJ => {
                     // 調整：正數增加，負數減少
@@ -116,32 +130,69 @@ impl StockService {
                         .ok_or_else(|| AppError::BusinessRule("Warehouse is required for adjustment".to_string()))?;
                     
                     if line.qty > Decimal::ZERO {
-                        Self::create_ledger_entry(
+                        Self::receive_stock(
                             tx,
+                            costing_method,
                             warehouse_id,
-                            line.product_id,
                             document,
                             line,
                             StockDirection::AdjustIn,
                             line.qty,
-                            line.unit_price,
                         ).await?;
                     } else {
                         // 檢查庫存
                         Self::check_stock_available(tx, warehouse_id, line.product_id, -line.qty).await?;
                         
-                        Self::create_ledger_entry(
+                        Self::issue_stock(
                             tx,
                             warehouse_id,
-                            line.product_id,
                             document,
                             line,
                             StockDirection::AdjustOut,
                             -line.qty,
-                            line.unit_price,
                         ).await?;
                     }
                 }
+                DocType::MV => {
+                    // 庫內上架 / 移動：同倉庫內由來源儲位移至目的儲位，倉庫庫存數量與成本層不變
+                    let warehouse_id = document.warehouse_id
+                        .ok_or_else(|| AppError::BusinessRule("Warehouse is required for move".to_string()))?;
+
+                    if line.to_location_id.is_none() {
+                        return Err(AppError::BusinessRule(format!(
+                            "Line {}: target location is required for move",
+                            line.line_no
+                        )));
+                    }
+                    if line.from_location_id == line.to_location_id {
+                        return Err(AppError::BusinessRule(format!(
+                            "Line {}: source and target locations must differ",
+                            line.line_no
+                        )));
+                    }
+
+                    let unit_cost = CostingService::fallback_unit_cost(tx, warehouse_id, line.product_id).await?;
+                    let total_cost = Some(line.qty * unit_cost);
+
+                    Self::create_ledger_entry(
+                        tx,
+                        warehouse_id,
+                        document,
+                        line,
+                        StockDirection::TransferOut,
+                        line.qty,
+                        total_cost,
+                    ).await?;
+                    Self::create_ledger_entry(
+                        tx,
+                        warehouse_id,
+                        document,
+                        line,
+                        StockDirection::TransferIn,
+                        line.qty,
+                        total_cost,
+                    ).await?;
+                }
                 _ => {
                     // PO, SO, STK 等不直接影響庫存的單據
                 }
@@ -151,42 +202,364 @@ impl StockService {
         Ok(())
     }
 
+    /// 處理沖銷單：依原單據的庫存流水寫入反向流水
+    ///
+    /// 原單據入庫的成本層必須尚未被耗用；原單據出庫耗用的成本層則歸還原數量。
+    async fn process_reversal(
+        tx: &mut Transaction<'_, Postgres>,
+        document: &Document,
+        lines: &[DocumentLine],
+        costing_method: CostingMethod,
+    ) -> Result<()> {
+        let original_id = document.source_doc_id
+            .ok_or_else(|| AppError::BusinessRule("Reversal document must reference the original document".to_string()))?;
+
+        // 先處理入庫流水，確保原入庫數量未被耗用
+        let entries = sqlx::query_as::<_, ReversibleEntry>(
+            r#"
+            SELECT sl.id, sl.warehouse_id, sl.direction, sl.qty_base, sl.total_cost, dl.line_no
+            FROM stock_ledger sl
+            INNER JOIN document_lines dl ON sl.line_id = dl.id
+            WHERE sl.doc_id = $1
+            ORDER BY sl.direction IN ('in', 'transfer_in', 'adjust_in') DESC, sl.created_at
+            "#
+        )
+        .bind(original_id)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        for entry in &entries {
+            let line = lines
+                .iter()
+                .find(|l| l.line_no == entry.line_no)
+                .ok_or_else(|| AppError::Internal(format!("Reversal line {} not found", entry.line_no)))?;
+            let direction = entry.direction.reversed();
+
+            if document.doc_type == DocType::MV {
+                // 庫內移動不影響成本層，僅寫回反向儲位流水
+                Self::create_ledger_entry(
+                    tx,
+                    entry.warehouse_id,
+                    document,
+                    line,
+                    direction,
+                    entry.qty_base,
+                    entry.total_cost,
+                ).await?;
+            } else if entry.direction.is_inbound() {
+                let released = CostingService::release_ledger_layers(tx, entry.id).await?;
+                let ledger_id = Self::create_ledger_entry(
+                    tx,
+                    entry.warehouse_id,
+                    document,
+                    line,
+                    direction,
+                    entry.qty_base,
+                    Some(released.total_cost),
+                ).await?;
+                CostingService::record_consumptions(tx, ledger_id, &released).await?;
+            } else {
+                let restored = CostingService::restore_consumptions(tx, costing_method, entry.id).await?;
+                let total_cost = restored.or(entry.total_cost);
+                let ledger_id = Self::create_ledger_entry(
+                    tx,
+                    entry.warehouse_id,
+                    document,
+                    line,
+                    direction,
+                    entry.qty_base,
+                    total_cost,
+                ).await?;
+
+                // 成本層建立前的舊出庫流水沒有耗用紀錄，改以新成本層歸還
+                if restored.is_none() {
+                    let unit_cost = total_cost
+                        .filter(|_| !entry.qty_base.is_zero())
+                        .map(|total| (total / entry.qty_base).round_dp(4))
+                        .unwrap_or(Decimal::ZERO);
+                    CostingService::receive(
+                        tx,
+                        costing_method,
+                        NewCostLayer {
+                            warehouse_id: entry.warehouse_id,
+                            product_id: line.product_id,
+                            batch_no: line.batch_no.as_deref(),
+                            expiry_date: line.expiry_date,
+                            source_ledger_id: ledger_id,
+                            qty: entry.qty_base,
+                            unit_cost,
+                        },
+                    ).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 入庫：寫入流水並建立成本層（未指定單價時沿用現有成本）
+    async fn receive_stock(
+        tx: &mut Transaction<'_, Postgres>,
+        costing_method: CostingMethod,
+        warehouse_id: Uuid,
+        document: &Document,
+        line: &DocumentLine,
+        direction: StockDirection,
+        qty: Decimal,
+    ) -> Result<Uuid> {
+        let unit_cost = match line.unit_price {
+            Some(price) => price,
+            None => CostingService::fallback_unit_cost(tx, warehouse_id, line.product_id).await?,
+        };
+
+        let ledger_id = Self::create_ledger_entry(
+            tx,
+            warehouse_id,
+            document,
+            line,
+            direction,
+            qty,
+            Some(qty * unit_cost),
+        ).await?;
+
+        CostingService::receive(
+            tx,
+            costing_method,
+            NewCostLayer {
+                warehouse_id,
+                product_id: line.product_id,
+                batch_no: line.batch_no.as_deref(),
+                expiry_date: line.expiry_date,
+                source_ledger_id: ledger_id,
+                qty,
+                unit_cost,
+            },
+        ).await?;
+
+        Ok(ledger_id)
+    }
+
+    /// 出庫：耗用成本層並寫入含耗用成本的流水
+    async fn issue_stock(
+        tx: &mut Transaction<'_, Postgres>,
+        warehouse_id: Uuid,
+        document: &Document,
+        line: &DocumentLine,
+        direction: StockDirection,
+        qty: Decimal,
+    ) -> Result<ConsumedCost> {
+        let consumed = CostingService::consume(
+            tx,
+            warehouse_id,
+            line.product_id,
+            line.batch_no.as_deref(),
+            qty,
+        ).await?;
+
+        let ledger_id = Self::create_ledger_entry(
+            tx,
+            warehouse_id,
+            document,
+            line,
+            direction,
+            qty,
+            Some(consumed.total_cost),
+        ).await?;
+
+        CostingService::record_consumptions(tx, ledger_id, &consumed).await?;
+
+        Ok(consumed)
+    }
+
     /// 建立庫存流水記錄
+    ///
+    /// 入庫方向記入明細的目的儲位，出庫方向記入來源儲位，並檢查儲位是否可用。
     async fn create_ledger_entry(
         tx: &mut Transaction<'_, Postgres>,
         warehouse_id: Uuid,
-        product_id: Uuid,
         document: &Document,
         line: &DocumentLine,
         direction: StockDirection,
         qty: Decimal,
-        unit_price: Option<Decimal>,
-    ) -> Result<()> {
+        total_cost: Option<Decimal>,
+    ) -> Result<Uuid> {
+        let location_id = if direction.is_inbound() {
+            line.to_location_id
+        } else {
+            line.from_location_id
+        };
+
+        if let Some(location_id) = location_id {
+            Self::check_location(tx, warehouse_id, location_id, line.product_id, direction, qty).await?;
+        }
+
+        // 指定來源儲位（或庫內移動自未指定儲位上架）時，檢查該儲位庫存
+        if !direction.is_inbound() && (location_id.is_some() || document.doc_type == DocType::MV) {
+            Self::check_location_stock_available(tx, warehouse_id, location_id, line.product_id, qty).await?;
+        }
+
+        let ledger_id = Uuid::new_v4();
+        let unit_cost = total_cost
+            .filter(|_| !qty.is_zero())
+            .map(|total| (total / qty).round_dp(4));
+
         sqlx::query(
             r#"
             INSERT INTO stock_ledger (
                 id, warehouse_id, product_id, trx_date, doc_type, doc_id, doc_no,
-                line_id, direction, qty_base, unit_cost, batch_no, expiry_date, created_at
+                line_id, direction, qty_base, unit_cost, total_cost, batch_no, expiry_date,
+                location_id, created_at
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, NOW())
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, NOW())
             "#
         )
-        .bind(Uuid::new_v4())
+        .bind(ledger_id)
         .bind(warehouse_id)
-        .bind(product_id)
+        .bind(line.product_id)
         .bind(Utc::now())
-        .bind(&document.doc_type)
+        .bind(document.doc_type)
         .bind(document.id)
         .bind(&document.doc_no)
         .bind(line.id)
         .bind(direction)
         .bind(qty)
-        .bind(unit_price)
+        .bind(unit_cost)
+        .bind(total_cost)
         .bind(&line.batch_no)
         .bind(line.expiry_date)
+        .bind(location_id)
         .execute(&mut **tx)
         .await?;
 
+        Ok(ledger_id)
+    }
+
+    /// 檢查儲位是否可用：須屬於該倉庫且啟用；入庫時另檢查保存條件與容量
+    async fn check_location(
+        tx: &mut Transaction<'_, Postgres>,
+        warehouse_id: Uuid,
+        location_id: Uuid,
+        product_id: Uuid,
+        direction: StockDirection,
+        qty: Decimal,
+    ) -> Result<()> {
+        let location = sqlx::query_as::<_, StorageLocation>(
+            "SELECT * FROM storage_locations WHERE id = $1"
+        )
+        .bind(location_id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Storage location not found".to_string()))?;
+
+        if location.warehouse_id != warehouse_id {
+            return Err(AppError::BusinessRule(format!(
+                "Storage location '{}' does not belong to the warehouse",
+                location.code
+            )));
+        }
+
+        if !direction.is_inbound() {
+            return Ok(());
+        }
+
+        if !location.is_active {
+            return Err(AppError::BusinessRule(format!(
+                "Storage location '{}' is inactive",
+                location.code
+            )));
+        }
+
+        // 保存條件須與產品一致
+        if let Some(ref condition) = location.storage_condition {
+            let product_condition: Option<String> = sqlx::query_scalar(
+                "SELECT storage_condition FROM products WHERE id = $1"
+            )
+            .bind(product_id)
+            .fetch_one(&mut **tx)
+            .await?;
+
+            if let Some(product_condition) = product_condition.filter(|c| !c.is_empty()) {
+                if &product_condition != condition {
+                    return Err(AppError::BusinessRule(format!(
+                        "Storage location '{}' ({}) does not match product storage condition ({})",
+                        location.code, condition, product_condition
+                    )));
+                }
+            }
+        }
+
+        // 容量以儲位內所有產品的基本單位數量合計
+        if let Some(capacity) = location.capacity {
+            let occupied: Decimal = sqlx::query_scalar(
+                r#"
+                SELECT COALESCE(SUM(
+                    CASE 
+                        WHEN direction IN ('in', 'transfer_in', 'adjust_in') THEN qty_base
+                        WHEN direction IN ('out', 'transfer_out', 'adjust_out') THEN -qty_base
+                    END
+                ), 0) as qty
+                FROM stock_ledger
+                WHERE location_id = $1
+                "#
+            )
+            .bind(location_id)
+            .fetch_one(&mut **tx)
+            .await?;
+
+            if occupied + qty > capacity {
+                return Err(AppError::BusinessRule(format!(
+                    "Storage location '{}' capacity exceeded. Capacity: {}, Occupied: {}, Incoming: {}",
+                    location.code, capacity, occupied, qty
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 檢查儲位庫存是否足夠（location_id 為 None 時檢查未指定儲位的庫存）
+    async fn check_location_stock_available(
+        tx: &mut Transaction<'_, Postgres>,
+        warehouse_id: Uuid,
+        location_id: Option<Uuid>,
+        product_id: Uuid,
+        required_qty: Decimal,
+    ) -> Result<()> {
+        let on_hand: Decimal = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(
+                CASE 
+                    WHEN direction IN ('in', 'transfer_in', 'adjust_in') THEN qty_base
+                    WHEN direction IN ('out', 'transfer_out', 'adjust_out') THEN -qty_base
+                END
+            ), 0) as qty
+            FROM stock_ledger
+            WHERE warehouse_id = $1 AND location_id IS NOT DISTINCT FROM $2 AND product_id = $3
+            "#
+        )
+        .bind(warehouse_id)
+        .bind(location_id)
+        .bind(product_id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        if on_hand < required_qty {
+            let location_code: Option<String> = match location_id {
+                Some(id) => sqlx::query_scalar("SELECT code FROM storage_locations WHERE id = $1")
+                    .bind(id)
+                    .fetch_optional(&mut **tx)
+                    .await?,
+                None => None,
+            };
+
+            return Err(AppError::BusinessRule(format!(
+                "Insufficient stock at location '{}'. Available: {}, Required: {}",
+                location_code.as_deref().unwrap_or("未指定儲位"),
+                on_hand,
+                required_qty
+            )));
+        }
+
         Ok(())
     }
 
@@ -250,7 +623,11 @@ impl StockService {
                         ELSE 0
                     END
                 ), 0) as qty_on_hand,
-                AVG(sl.unit_cost) as avg_cost,
+                (
+                    SELECT ROUND(SUM(cl.remaining_qty * cl.unit_cost) / NULLIF(SUM(cl.remaining_qty), 0), 4)
+                    FROM stock_cost_layers cl
+                    WHERE cl.warehouse_id = w.id AND cl.product_id = p.id AND cl.remaining_qty > 0
+                ) as avg_cost,
                 p.safety_stock,
                 p.reorder_point
             FROM warehouses w
@@ -300,7 +677,11 @@ impl StockService {
                             ELSE 0
                         END
                     ), 0) as qty_on_hand,
-                    AVG(sl.unit_cost) as avg_cost,
+                    (
+                        SELECT ROUND(SUM(cl.remaining_qty * cl.unit_cost) / NULLIF(SUM(cl.remaining_qty), 0), 4)
+                        FROM stock_cost_layers cl
+                        WHERE cl.warehouse_id = w.id AND cl.product_id = p.id AND cl.remaining_qty > 0
+                    ) as avg_cost,
                     p.safety_stock,
                     p.reorder_point
                 FROM warehouses w
@@ -325,6 +706,89 @@ impl StockService {
         Ok(inventory)
     }
 
+    /// 查詢庫存現況（依儲位）
+    pub async fn get_on_hand_by_location(
+        pool: &PgPool,
+        query: &InventoryQuery,
+    ) -> Result<Vec<InventoryByLocation>> {
+        let mut sql = String::from(
+            r#"
+            SELECT 
+                w.id as warehouse_id,
+                w.code as warehouse_code,
+                w.name as warehouse_name,
+                sl.location_id,
+                loc.code as location_code,
+                loc.name as location_name,
+                p.id as product_id,
+                p.sku as product_sku,
+                p.name as product_name,
+                p.base_uom,
+                SUM(
+                    CASE 
+                        WHEN sl.direction IN ('in', 'transfer_in', 'adjust_in') THEN sl.qty_base
+                        WHEN sl.direction IN ('out', 'transfer_out', 'adjust_out') THEN -sl.qty_base
+                        ELSE 0
+                    END
+                ) as qty_on_hand
+            FROM stock_ledger sl
+            INNER JOIN warehouses w ON sl.warehouse_id = w.id
+            INNER JOIN products p ON sl.product_id = p.id
+            LEFT JOIN storage_locations loc ON sl.location_id = loc.id
+            WHERE w.is_active = true AND p.is_active = true
+            "#
+        );
+
+        let mut param_count = 0;
+
+        if query.warehouse_id.is_some() {
+            param_count += 1;
+            sql.push_str(&format!(" AND sl.warehouse_id = ${}", param_count));
+        }
+
+        if query.product_id.is_some() {
+            param_count += 1;
+            sql.push_str(&format!(" AND sl.product_id = ${}", param_count));
+        }
+
+        if query.location_id.is_some() {
+            param_count += 1;
+            sql.push_str(&format!(" AND sl.location_id = ${}", param_count));
+        }
+
+        sql.push_str(
+            r#"
+            GROUP BY w.id, w.code, w.name, sl.location_id, loc.code, loc.name, p.id, p.sku, p.name, p.base_uom
+            HAVING SUM(
+                CASE 
+                    WHEN sl.direction IN ('in', 'transfer_in', 'adjust_in') THEN sl.qty_base
+                    WHEN sl.direction IN ('out', 'transfer_out', 'adjust_out') THEN -sl.qty_base
+                    ELSE 0
+                END
+            ) != 0
+            ORDER BY w.code, loc.code NULLS FIRST, p.sku
+            "#
+        );
+
+        let mut query_builder = sqlx::query_as::<_, InventoryByLocation>(&sql);
+
+        if let Some(warehouse_id) = query.warehouse_id {
+            query_builder = query_builder.bind(warehouse_id);
+        }
+
+        if let Some(product_id) = query.product_id {
+            query_builder = query_builder.bind(product_id);
+        }
+
+        if let Some(location_id) = query.location_id {
+            query_builder = query_builder.bind(location_id);
+        }
+
+        let inventory = query_builder.fetch_all(pool).await?;
+
+        Ok(inventory)
+    }
+
     /// 查詢庫存流水
     pub async fn get_ledger(pool: &PgPool, query: &StockLedgerQuery) -> Result<Vec<StockLedgerDetail>> {
         let mut sql = String::from(
@@ -343,12 +807,16 @@ impl StockService {
                 sl.direction,
                 sl.qty_base,
                 sl.unit_cost,
+                sl.total_cost,
                 sl.batch_no,
                 sl.expiry_date,
+                sl.location_id,
+                loc.code as location_code,
                 NULL::numeric as running_balance
             FROM stock_ledger sl
             INNER JOIN warehouses w ON sl.warehouse_id = w.id
             INNER JOIN products p ON sl.product_id = p.id
+            LEFT JOIN storage_locations loc ON sl.location_id = loc.id
             WHERE 1=1
             "#
         );
@@ -385,6 +853,11 @@ impl StockService {
             sql.push_str(&format!(" AND sl.doc_type = ${}", param_count));
         }
 
+        if query.location_id.is_some() {
+            param_count += 1;
+            sql.push_str(&format!(" AND sl.location_id = ${}", param_count));
+        }
+
         sql.push_str(" ORDER BY sl.trx_date DESC, sl.created_at DESC LIMIT 1000");
 
         // Build query with bindings in correct order
@@ -414,6 +887,10 @@ impl StockService {
             query_builder = query_builder.bind(doc_type);
         }
 
+        if let Some(location_id) = query.location_id {
+            query_builder = query_builder.bind(location_id);
+        }
+
         let ledger = query_builder.fetch_all(pool).await?;
 
         Ok(ledger)
@@ -438,7 +915,11 @@ impl StockService {
                         ELSE 0
                     END
                 ), 0) as qty_on_hand,
-                AVG(sl.unit_cost) as avg_cost,
+                (
+                    SELECT ROUND(SUM(cl.remaining_qty * cl.unit_cost) / NULLIF(SUM(cl.remaining_qty), 0), 4)
+                    FROM stock_cost_layers cl
+                    WHERE cl.warehouse_id = w.id AND cl.product_id = p.id AND cl.remaining_qty > 0
+                ) as avg_cost,
                 p.safety_stock,
                 p.reorder_point
             FROM warehouses w
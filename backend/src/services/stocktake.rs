@@ -0,0 +1,736 @@
+// 盤點作業服務
+// 開始盤點時依批號凍結帳面數量，登錄實盤數量後計算差異，核准時產生調整單（ADJ）過帳
+
+use std::io::Cursor;
+
+use calamine::{open_workbook_from_rs, Data, Reader, Xls, Xlsx};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        CostingMethod, DocStatus, DocType, Document, DocumentLine, DocumentLineInput, DocumentWithLines,
+        StocktakeDifferenceItem, StocktakeImportError, StocktakeImportResult, StocktakeItem,
+        StocktakeResultInput, StocktakeSheet, StocktakeVarianceReport,
+    },
+    services::{CostingService, DocumentService, StockService},
+    AppError, Result,
+};
+
+const STOCKTAKE_ITEM_SELECT: &str = r#"
+    SELECT
+        si.id, si.document_id, si.product_id,
+        p.sku AS product_sku, p.name AS product_name, p.base_uom AS uom,
+        si.batch_no, si.expiry_date, si.book_qty, si.unit_cost,
+        si.counted_qty, si.counted_by, u.display_name AS counted_by_name, si.counted_at
+    FROM stocktake_items si
+    INNER JOIN products p ON si.product_id = p.id
+    LEFT JOIN users u ON si.counted_by = u.id
+    WHERE si.document_id = $1
+    ORDER BY p.sku, si.batch_no NULLS FIRST
+"#;
+
+/// 匯入檔案的一列（SKU / 批號 / 效期 / 實盤數量）
+struct CountRow {
+    row: i32,
+    sku: String,
+    batch_no: String,
+    expiry_date: String,
+    actual_qty: String,
+}
+
+pub struct StocktakeService;
+
+impl StocktakeService {
+    /// 開始盤點：依批號凍結帳面數量與單位成本
+    pub async fn start(pool: &PgPool, id: Uuid, blind_count: bool) -> Result<Document> {
+        let mut tx = pool.begin().await?;
+
+        let document = sqlx::query_as::<_, Document>(
+            r#"
+            UPDATE documents SET blind_count = $2, count_started_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND doc_type = 'STK' AND status = 'draft' AND count_started_at IS NULL
+            RETURNING *
+            "#
+        )
+        .bind(id)
+        .bind(blind_count)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BusinessRule(
+            "Stocktake not found, not in draft status, or counting already started".to_string()
+        ))?;
+
+        let warehouse_id = document.warehouse_id
+            .ok_or_else(|| AppError::BusinessRule("Warehouse is required for stocktake".to_string()))?;
+
+        // 盤點範圍為盤點單明細上的品項
+        sqlx::query(
+            r#"
+            INSERT INTO stocktake_items (id, document_id, product_id, batch_no, expiry_date, book_qty, unit_cost)
+            SELECT
+                gen_random_uuid(), $1, l.product_id, l.batch_no, MIN(l.expiry_date),
+                SUM(l.remaining_qty),
+                COALESCE(ROUND(SUM(l.remaining_qty * l.unit_cost) / NULLIF(SUM(l.remaining_qty), 0), 4), 0)
+            FROM stock_cost_layers l
+            WHERE l.warehouse_id = $2
+              AND l.remaining_qty > 0
+              AND l.product_id IN (SELECT product_id FROM document_lines WHERE document_id = $1)
+            GROUP BY l.product_id, l.batch_no
+            "#
+        )
+        .bind(id)
+        .bind(warehouse_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(document)
+    }
+
+    /// 取得盤點表；盲盤時僅審核者（can_review）可看到帳面數量與成本
+    pub async fn get_sheet(pool: &PgPool, id: Uuid, can_review: bool) -> Result<StocktakeSheet> {
+        let document = Self::get_stocktake(pool, id).await?;
+        let mut items = sqlx::query_as::<_, StocktakeItem>(STOCKTAKE_ITEM_SELECT)
+            .bind(id)
+            .fetch_all(pool)
+            .await?;
+
+        if document.blind_count && !can_review {
+            for item in &mut items {
+                item.book_qty = None;
+                item.unit_cost = None;
+            }
+        }
+
+        Ok(StocktakeSheet {
+            document_id: document.id,
+            doc_no: document.doc_no,
+            status: document.status,
+            warehouse_id: document.warehouse_id,
+            blind_count: document.blind_count,
+            count_started_at: document.count_started_at,
+            items,
+        })
+    }
+
+    /// 登錄實盤數量（同品項同批號重複登錄時以最後一次為準）
+    pub async fn record_counts(
+        pool: &PgPool,
+        id: Uuid,
+        counts: &[StocktakeResultInput],
+        counted_by: Uuid,
+    ) -> Result<usize> {
+        if counts.is_empty() {
+            return Err(AppError::Validation("At least one count is required".to_string()));
+        }
+
+        let document = Self::get_stocktake(pool, id).await?;
+        let warehouse_id = Self::ensure_counting(&document)?;
+
+        let mut tx = pool.begin().await?;
+        for count in counts {
+            Self::upsert_count(&mut tx, id, warehouse_id, count, counted_by).await?;
+        }
+        tx.commit().await?;
+
+        Ok(counts.len())
+    }
+
+    /// 匯入實盤數量（Excel / CSV：SKU、批號、效期、實盤數量）
+    pub async fn import_counts(
+        pool: &PgPool,
+        id: Uuid,
+        file_data: &[u8],
+        file_name: &str,
+        counted_by: Uuid,
+    ) -> Result<StocktakeImportResult> {
+        let document = Self::get_stocktake(pool, id).await?;
+        let warehouse_id = Self::ensure_counting(&document)?;
+
+        let lower_name = file_name.to_lowercase();
+        let rows = if lower_name.ends_with(".xlsx") || lower_name.ends_with(".xls") {
+            parse_excel_rows(file_data)?
+        } else if lower_name.ends_with(".csv") {
+            parse_csv_rows(file_data)?
+        } else {
+            return Err(AppError::Validation(
+                "不支援的檔案格式，請使用 Excel (.xlsx, .xls) 或 CSV 格式".to_string(),
+            ));
+        };
+
+        if rows.is_empty() {
+            return Err(AppError::Validation("檔案中沒有資料".to_string()));
+        }
+
+        let mut errors = Vec::new();
+        let mut success_count = 0;
+        let mut tx = pool.begin().await?;
+
+        for row in &rows {
+            let input = match Self::resolve_row(&mut tx, id, row).await? {
+                Ok(input) => input,
+                Err(error) => {
+                    errors.push(StocktakeImportError {
+                        row: row.row,
+                        sku: Some(row.sku.clone()).filter(|s| !s.is_empty()),
+                        error,
+                    });
+                    continue;
+                }
+            };
+            Self::upsert_count(&mut tx, id, warehouse_id, &input, counted_by).await?;
+            success_count += 1;
+        }
+
+        tx.commit().await?;
+
+        Ok(StocktakeImportResult {
+            total_rows: rows.len() as i32,
+            success_count,
+            error_count: errors.len() as i32,
+            errors,
+        })
+    }
+
+    /// 盤點差異（依批號，含差異金額）
+    pub async fn variance(pool: &PgPool, id: Uuid) -> Result<StocktakeVarianceReport> {
+        let document = Self::get_stocktake(pool, id).await?;
+        let items = sqlx::query_as::<_, StocktakeItem>(STOCKTAKE_ITEM_SELECT)
+            .bind(id)
+            .fetch_all(pool)
+            .await?;
+
+        let uncounted_count = items.iter().filter(|i| i.counted_qty.is_none()).count() as i64;
+        let differences: Vec<StocktakeDifferenceItem> = items
+            .into_iter()
+            .filter_map(|item| {
+                let actual_qty = item.counted_qty?;
+                let system_qty = item.book_qty.unwrap_or_default();
+                let unit_cost = item.unit_cost.unwrap_or_default();
+                let difference = actual_qty - system_qty;
+                Some(StocktakeDifferenceItem {
+                    product_id: item.product_id,
+                    product_sku: item.product_sku,
+                    product_name: item.product_name,
+                    batch_no: item.batch_no,
+                    expiry_date: item.expiry_date,
+                    system_qty,
+                    actual_qty,
+                    difference,
+                    uom: item.uom,
+                    unit_cost,
+                    value_impact: (difference * unit_cost).round_dp(2),
+                })
+            })
+            .collect();
+
+        Ok(StocktakeVarianceReport {
+            document_id: document.id,
+            doc_no: document.doc_no,
+            count_started_at: document.count_started_at,
+            total_value_impact: differences.iter().map(|d| d.value_impact).sum(),
+            items: differences,
+            uncounted_count,
+        })
+    }
+
+    /// 送審前檢查：須已開始盤點且所有項目皆已盤點
+    pub async fn ensure_complete(pool: &PgPool, document: &Document) -> Result<()> {
+        if document.count_started_at.is_none() {
+            return Err(AppError::BusinessRule("Stocktake counting has not started".to_string()));
+        }
+
+        let uncounted: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM stocktake_items WHERE document_id = $1 AND counted_qty IS NULL"
+        )
+        .bind(document.id)
+        .fetch_one(pool)
+        .await?;
+
+        if uncounted > 0 {
+            return Err(AppError::BusinessRule(format!(
+                "{} stocktake items have not been counted",
+                uncounted
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// 核准盤點單：依差異產生已核准的調整單並寫入庫存流水
+    ///
+    /// 調整單以 source_doc_id 指向盤點單；無差異時不產生調整單。
+    pub async fn post_adjustment(
+        tx: &mut Transaction<'_, Postgres>,
+        document: &Document,
+        approved_by: Uuid,
+        costing_method: CostingMethod,
+    ) -> Result<Option<Uuid>> {
+        if document.count_started_at.is_none() {
+            return Err(AppError::BusinessRule("Stocktake counting has not started".to_string()));
+        }
+
+        let items = sqlx::query_as::<_, StocktakeItem>(STOCKTAKE_ITEM_SELECT)
+            .bind(document.id)
+            .fetch_all(&mut **tx)
+            .await?;
+
+        let uncounted = items.iter().filter(|i| i.counted_qty.is_none()).count();
+        if uncounted > 0 {
+            return Err(AppError::BusinessRule(format!(
+                "{} stocktake items have not been counted",
+                uncounted
+            )));
+        }
+
+        let lines = adjustment_lines(&items);
+        if lines.is_empty() {
+            return Ok(None);
+        }
+
+        let doc_no = DocumentService::generate_doc_no(tx, DocType::ADJ).await?;
+        let adjustment = sqlx::query_as::<_, Document>(
+            r#"
+            INSERT INTO documents (
+                id, doc_type, doc_no, status, warehouse_id, doc_date, source_doc_id, remark,
+                created_by, approved_by, created_at, updated_at, approved_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $9, NOW(), NOW(), NOW())
+            RETURNING *
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(DocType::ADJ)
+        .bind(&doc_no)
+        .bind(DocStatus::Approved)
+        .bind(document.warehouse_id)
+        .bind(chrono::Utc::now().date_naive())
+        .bind(document.id)
+        .bind(format!("盤點調整：{}", document.doc_no))
+        .bind(approved_by)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        let mut adjustment_lines = Vec::with_capacity(lines.len());
+        for (idx, line) in lines.iter().enumerate() {
+            let adjustment_line = sqlx::query_as::<_, DocumentLine>(
+                r#"
+                INSERT INTO document_lines (
+                    id, document_id, line_no, product_id, qty, uom, unit_price,
+                    batch_no, expiry_date, remark
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                RETURNING *
+                "#
+            )
+            .bind(Uuid::new_v4())
+            .bind(adjustment.id)
+            .bind((idx + 1) as i32)
+            .bind(line.product_id)
+            .bind(line.qty)
+            .bind(&line.uom)
+            .bind(line.unit_price)
+            .bind(&line.batch_no)
+            .bind(line.expiry_date)
+            .bind(&line.remark)
+            .fetch_one(&mut **tx)
+            .await?;
+            adjustment_lines.push(adjustment_line);
+        }
+
+        StockService::process_document(tx, &adjustment, &adjustment_lines, costing_method).await?;
+
+        Ok(Some(adjustment.id))
+    }
+
+    /// 盲盤時隱藏盤點單明細上的系統庫存數量
+    pub fn mask_book_quantities(document: &mut DocumentWithLines) {
+        if document.document.doc_type == DocType::STK && document.document.blind_count {
+            for line in &mut document.lines {
+                line.qty = Decimal::ZERO;
+            }
+        }
+    }
+
+    async fn get_stocktake(pool: &PgPool, id: Uuid) -> Result<Document> {
+        sqlx::query_as::<_, Document>(
+            "SELECT * FROM documents WHERE id = $1 AND doc_type = 'STK'"
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Stocktake not found".to_string()))
+    }
+
+    /// 盤點中（草稿且已開始）才可登錄數量
+    fn ensure_counting(document: &Document) -> Result<Uuid> {
+        if document.status != DocStatus::Draft || document.count_started_at.is_none() {
+            return Err(AppError::BusinessRule(
+                "Counts can only be recorded for a started stocktake in draft status".to_string(),
+            ));
+        }
+        document.warehouse_id
+            .ok_or_else(|| AppError::BusinessRule("Warehouse is required for stocktake".to_string()))
+    }
+
+    /// 寫入實盤數量；品項須在盤點範圍內。開始盤點時未凍結的批號於首次登錄時
+    /// 依成本層凍結帳面數量與單位成本（帳面沒有的批號視為盤盈，帳面數量為 0）
+    async fn upsert_count(
+        tx: &mut Transaction<'_, Postgres>,
+        document_id: Uuid,
+        warehouse_id: Uuid,
+        count: &StocktakeResultInput,
+        counted_by: Uuid,
+    ) -> Result<()> {
+        if count.actual_qty < Decimal::ZERO {
+            return Err(AppError::Validation("Counted quantity cannot be negative".to_string()));
+        }
+        if !Self::in_scope(tx, document_id, count.product_id).await? {
+            return Err(AppError::BusinessRule(
+                "Product is not in the stocktake scope".to_string(),
+            ));
+        }
+
+        let batch_no = count.batch_no.as_deref().map(str::trim).filter(|b| !b.is_empty());
+        let fallback_cost = CostingService::fallback_unit_cost(tx, warehouse_id, count.product_id).await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO stocktake_items (
+                id, document_id, product_id, batch_no, expiry_date, book_qty, unit_cost,
+                counted_qty, counted_by, counted_at
+            )
+            SELECT
+                $1, $2, $3, $4, COALESCE($5, MIN(l.expiry_date)),
+                COALESCE(SUM(l.remaining_qty), 0),
+                COALESCE(ROUND(SUM(l.remaining_qty * l.unit_cost) / NULLIF(SUM(l.remaining_qty), 0), 4), $6),
+                $7, $8, NOW()
+            FROM stock_cost_layers l
+            WHERE l.warehouse_id = $9
+              AND l.product_id = $3
+              AND l.batch_no IS NOT DISTINCT FROM $4
+              AND l.remaining_qty > 0
+            ON CONFLICT (document_id, product_id, batch_no) DO UPDATE SET
+                expiry_date = COALESCE(stocktake_items.expiry_date, EXCLUDED.expiry_date),
+                counted_qty = EXCLUDED.counted_qty,
+                counted_by = EXCLUDED.counted_by,
+                counted_at = NOW()
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(document_id)
+        .bind(count.product_id)
+        .bind(batch_no)
+        .bind(count.expiry_date)
+        .bind(fallback_cost)
+        .bind(count.actual_qty)
+        .bind(counted_by)
+        .bind(warehouse_id)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 品項是否在盤點範圍（盤點單明細）內
+    async fn in_scope(
+        tx: &mut Transaction<'_, Postgres>,
+        document_id: Uuid,
+        product_id: Uuid,
+    ) -> Result<bool> {
+        let in_scope: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM document_lines WHERE document_id = $1 AND product_id = $2)"
+        )
+        .bind(document_id)
+        .bind(product_id)
+        .fetch_one(&mut **tx)
+        .await?;
+        Ok(in_scope)
+    }
+
+    /// 將匯入列轉為盤點輸入；資料錯誤回傳 Ok(Err(訊息))
+    async fn resolve_row(
+        tx: &mut Transaction<'_, Postgres>,
+        document_id: Uuid,
+        row: &CountRow,
+    ) -> Result<std::result::Result<StocktakeResultInput, String>> {
+        if row.sku.is_empty() {
+            return Ok(Err("SKU 為必填".to_string()));
+        }
+        let Ok(actual_qty) = row.actual_qty.parse::<Decimal>() else {
+            return Ok(Err(format!("實盤數量格式錯誤: {}", row.actual_qty)));
+        };
+        if actual_qty < Decimal::ZERO {
+            return Ok(Err("實盤數量不可為負數".to_string()));
+        }
+        let expiry_date = if row.expiry_date.is_empty() {
+            None
+        } else {
+            match NaiveDate::parse_from_str(&row.expiry_date, "%Y-%m-%d") {
+                Ok(date) => Some(date),
+                Err(_) => return Ok(Err(format!("效期格式錯誤（應為 YYYY-MM-DD）: {}", row.expiry_date))),
+            }
+        };
+
+        let product_id: Option<Uuid> = sqlx::query_scalar("SELECT id FROM products WHERE sku = $1")
+            .bind(&row.sku)
+            .fetch_optional(&mut **tx)
+            .await?;
+        let Some(product_id) = product_id else {
+            return Ok(Err(format!("找不到 SKU: {}", row.sku)));
+        };
+        if !Self::in_scope(tx, document_id, product_id).await? {
+            return Ok(Err(format!("SKU 不在盤點範圍內: {}", row.sku)));
+        }
+
+        Ok(Ok(StocktakeResultInput {
+            product_id,
+            batch_no: Some(row.batch_no.clone()).filter(|b| !b.is_empty()),
+            expiry_date,
+            actual_qty,
+        }))
+    }
+}
+
+/// 依盤點差異產生調整單明細（差異為 0 或未盤點的項目略過）
+fn adjustment_lines(items: &[StocktakeItem]) -> Vec<DocumentLineInput> {
+    items
+        .iter()
+        .filter_map(|item| {
+            let difference = item.counted_qty? - item.book_qty.unwrap_or_default();
+            if difference.is_zero() {
+                return None;
+            }
+            Some(DocumentLineInput {
+                product_id: item.product_id,
+                qty: difference,
+                uom: item.uom.clone(),
+                // 盤盈以凍結時的單位成本入帳
+                unit_price: item.unit_cost,
+                batch_no: item.batch_no.clone(),
+                expiry_date: item.expiry_date,
+                remark: Some(if difference > Decimal::ZERO { "盤盈" } else { "盤虧" }.to_string()),
+                from_location_id: None,
+                to_location_id: None,
+            })
+        })
+        .collect()
+}
+
+fn parse_excel_rows(file_data: &[u8]) -> Result<Vec<CountRow>> {
+    let range = if let Ok(mut wb) = open_workbook_from_rs::<Xlsx<_>, _>(Cursor::new(file_data)) {
+        let sheet_name = wb.sheet_names().first().cloned()
+            .ok_or_else(|| AppError::Validation("Excel 檔案中沒有工作表".to_string()))?;
+        wb.worksheet_range(&sheet_name)
+            .map_err(|e| AppError::Validation(format!("無法讀取工作表: {}", e)))?
+    } else {
+        let mut wb = open_workbook_from_rs::<Xls<_>, _>(Cursor::new(file_data))
+            .map_err(|_| AppError::Validation("無法讀取 Excel 檔案，請確認檔案格式為 .xlsx 或 .xls".to_string()))?;
+        let sheet_name = wb.sheet_names().first().cloned()
+            .ok_or_else(|| AppError::Validation("Excel 檔案中沒有工作表".to_string()))?;
+        wb.worksheet_range(&sheet_name)
+            .map_err(|e| AppError::Validation(format!("無法讀取工作表: {}", e)))?
+    };
+
+    // 第一列為標題
+    Ok(range
+        .rows()
+        .enumerate()
+        .skip(1)
+        .map(|(idx, row)| CountRow {
+            row: idx as i32 + 1,
+            sku: cell_text(row.first()),
+            batch_no: cell_text(row.get(1)),
+            expiry_date: cell_text(row.get(2)),
+            actual_qty: cell_text(row.get(3)),
+        })
+        .filter(|r| !(r.sku.is_empty() && r.actual_qty.is_empty()))
+        .collect())
+}
+
+fn parse_csv_rows(file_data: &[u8]) -> Result<Vec<CountRow>> {
+    let content = String::from_utf8_lossy(file_data);
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(content.trim_start_matches('\u{feff}').as_bytes());
+
+    let mut rows = Vec::new();
+    for (idx, record) in reader.records().enumerate() {
+        let record = record
+            .map_err(|e| AppError::Validation(format!("盤點 CSV 第 {} 行解析錯誤: {}", idx + 2, e)))?;
+        let field = |i: usize| record.get(i).unwrap_or_default().to_string();
+        let row = CountRow {
+            row: idx as i32 + 2,
+            sku: field(0),
+            batch_no: field(1),
+            expiry_date: field(2),
+            actual_qty: field(3),
+        };
+        if !(row.sku.is_empty() && row.actual_qty.is_empty()) {
+            rows.push(row);
+        }
+    }
+
+    Ok(rows)
+}
+
+fn cell_text(cell: Option<&Data>) -> String {
+    match cell {
+        // Excel 日期序號以 1899-12-30 為基準
+        Some(Data::DateTime(dt)) => NaiveDate::from_ymd_opt(1899, 12, 30)
+            .and_then(|base| base.checked_add_signed(chrono::Duration::days(dt.as_f64() as i64)))
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .unwrap_or_default(),
+        Some(Data::Empty) | None => String::new(),
+        Some(other) => other.to_string().trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(v: i64) -> Decimal {
+        Decimal::from(v)
+    }
+
+    fn item(book_qty: Decimal, counted_qty: Option<Decimal>) -> StocktakeItem {
+        StocktakeItem {
+            id: Uuid::new_v4(),
+            document_id: Uuid::new_v4(),
+            product_id: Uuid::new_v4(),
+            product_sku: "SKU".to_string(),
+            product_name: "品項".to_string(),
+            uom: "EA".to_string(),
+            batch_no: Some("B1".to_string()),
+            expiry_date: None,
+            book_qty: Some(book_qty),
+            unit_cost: Some(Decimal::new(125, 1)),
+            counted_qty,
+            counted_by: None,
+            counted_by_name: None,
+            counted_at: None,
+        }
+    }
+
+    #[test]
+    fn test_adjustment_lines() {
+        let items = vec![
+            item(dec(10), Some(dec(8))),
+            item(dec(5), Some(dec(5))),
+            item(dec(0), Some(dec(3))),
+            item(dec(4), None),
+        ];
+        let lines = adjustment_lines(&items);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].qty, dec(-2));
+        assert_eq!(lines[0].remark.as_deref(), Some("盤虧"));
+        assert_eq!(lines[1].qty, dec(3));
+        assert_eq!(lines[1].unit_price, Some(Decimal::new(125, 1)));
+    }
+
+    use crate::test_support::insert_user;
+
+    async fn insert_product(db: &PgPool, sku: &str) -> Uuid {
+        sqlx::query_scalar("INSERT INTO products (id, sku, name) VALUES ($1, $2, $2) RETURNING id")
+            .bind(Uuid::new_v4())
+            .bind(sku)
+            .fetch_one(db)
+            .await
+            .unwrap()
+    }
+
+    async fn insert_layer(db: &PgPool, warehouse_id: Uuid, product_id: Uuid, batch_no: &str, qty: i64) {
+        sqlx::query(
+            r#"
+            INSERT INTO stock_cost_layers (id, warehouse_id, product_id, batch_no, layer_date, original_qty, remaining_qty, unit_cost)
+            VALUES ($1, $2, $3, $4, NOW(), $5, $5, 12.5)
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(warehouse_id)
+        .bind(product_id)
+        .bind(batch_no)
+        .bind(dec(qty))
+        .execute(db)
+        .await
+        .unwrap();
+    }
+
+    fn count(product_id: Uuid, batch_no: &str, actual_qty: i64) -> StocktakeResultInput {
+        StocktakeResultInput {
+            product_id,
+            batch_no: Some(batch_no.to_string()),
+            expiry_date: None,
+            actual_qty: dec(actual_qty),
+        }
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_counts_outside_frozen_scope(db: PgPool) {
+        let counter = insert_user(&db, "counter@example.com", true).await;
+        let warehouse_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO warehouses (id, code, name) VALUES ($1, 'WH-STK', 'WH-STK') RETURNING id",
+        )
+        .bind(Uuid::new_v4())
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        let counted = insert_product(&db, "STK-IN").await;
+        let other = insert_product(&db, "STK-OUT").await;
+        insert_layer(&db, warehouse_id, counted, "B1", 10).await;
+        insert_layer(&db, warehouse_id, other, "B1", 20).await;
+
+        let stocktake_id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO documents (id, doc_type, doc_no, warehouse_id, doc_date, created_by)
+            VALUES ($1, 'STK', 'STK-TEST-001', $2, CURRENT_DATE, $3)
+            RETURNING id
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(warehouse_id)
+        .bind(counter)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO document_lines (id, document_id, line_no, product_id, qty, uom) VALUES ($1, $2, 1, $3, 10, 'pcs')",
+        )
+        .bind(Uuid::new_v4())
+        .bind(stocktake_id)
+        .bind(counted)
+        .execute(&db)
+        .await
+        .unwrap();
+
+        StocktakeService::start(&db, stocktake_id, false).await.unwrap();
+
+        // 範圍外但有庫存的品項不可登錄，否則核准時整批數量會被當成盤盈
+        let result = StocktakeService::record_counts(&db, stocktake_id, &[count(other, "B1", 20)], counter).await;
+        assert!(matches!(result, Err(AppError::BusinessRule(_))));
+
+        // 開始盤點後才入庫的批號於首次登錄時凍結帳面數量與成本
+        insert_layer(&db, warehouse_id, counted, "B2", 5).await;
+        StocktakeService::record_counts(
+            &db,
+            stocktake_id,
+            &[count(counted, "B1", 10), count(counted, "B2", 5)],
+            counter,
+        )
+        .await
+        .unwrap();
+
+        let report = StocktakeService::variance(&db, stocktake_id).await.unwrap();
+        assert_eq!(report.uncounted_count, 0);
+        assert_eq!(report.items.len(), 2);
+        assert!(report.items.iter().all(|item| item.product_id == counted && item.difference.is_zero()));
+        assert_eq!(report.items[1].unit_cost, Decimal::new(125, 1));
+    }
+}
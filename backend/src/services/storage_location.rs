@@ -0,0 +1,221 @@
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        CreateStorageLocationRequest, StorageLocation, StorageLocationQuery,
+        UpdateStorageLocationRequest,
+    },
+    AppError, Result,
+};
+
+pub struct StorageLocationService;
+
+impl StorageLocationService {
+    /// 建立儲位（區 → 架 → 格）
+    pub async fn create(
+        pool: &PgPool,
+        warehouse_id: Uuid,
+        req: &CreateStorageLocationRequest,
+    ) -> Result<StorageLocation> {
+        let warehouse_active: bool = sqlx::query_scalar(
+            "SELECT is_active FROM warehouses WHERE id = $1"
+        )
+        .bind(warehouse_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Warehouse not found".to_string()))?;
+
+        if !warehouse_active {
+            return Err(AppError::BusinessRule("Warehouse is inactive".to_string()));
+        }
+
+        if req.capacity.is_some_and(|c| c < Decimal::ZERO) {
+            return Err(AppError::Validation("Capacity must not be negative".to_string()));
+        }
+
+        // 檢查上層儲位層級：區不可有上層，架須在區下，格須在架下
+        match (req.location_type.parent_type(), req.parent_id) {
+            (None, Some(_)) => {
+                return Err(AppError::Validation("Zone cannot have a parent location".to_string()));
+            }
+            (Some(_), None) => {
+                return Err(AppError::Validation("Parent location is required".to_string()));
+            }
+            (Some(expected), Some(parent_id)) => {
+                let parent = Self::get_by_id(pool, parent_id).await?;
+                if parent.warehouse_id != warehouse_id {
+                    return Err(AppError::Validation("Parent location belongs to another warehouse".to_string()));
+                }
+                if parent.location_type != expected {
+                    return Err(AppError::Validation(format!(
+                        "Parent location must be a {}",
+                        expected.as_str()
+                    )));
+                }
+            }
+            (None, None) => {}
+        }
+
+        let code = req.code.trim().to_string();
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM storage_locations WHERE warehouse_id = $1 AND code = $2)"
+        )
+        .bind(warehouse_id)
+        .bind(&code)
+        .fetch_one(pool)
+        .await?;
+
+        if exists {
+            return Err(AppError::Conflict("Storage location code already exists".to_string()));
+        }
+
+        let location = sqlx::query_as::<_, StorageLocation>(
+            r#"
+            INSERT INTO storage_locations (
+                id, warehouse_id, parent_id, code, name, location_type, capacity,
+                storage_condition, is_active, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, true, NOW(), NOW())
+            RETURNING *
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(warehouse_id)
+        .bind(req.parent_id)
+        .bind(&code)
+        .bind(&req.name)
+        .bind(req.location_type)
+        .bind(req.capacity)
+        .bind(req.storage_condition.as_ref().map(|c| c.code()))
+        .fetch_one(pool)
+        .await?;
+
+        Ok(location)
+    }
+
+    /// 取得倉庫儲位列表
+    pub async fn list(
+        pool: &PgPool,
+        warehouse_id: Uuid,
+        query: &StorageLocationQuery,
+    ) -> Result<Vec<StorageLocation>> {
+        let locations = sqlx::query_as::<_, StorageLocation>(
+            r#"
+            SELECT * FROM storage_locations
+            WHERE warehouse_id = $1
+              AND ($2::varchar IS NULL OR location_type = $2)
+              AND ($3::uuid IS NULL OR parent_id = $3)
+              AND ($4::boolean IS NULL OR is_active = $4)
+            ORDER BY code
+            "#
+        )
+        .bind(warehouse_id)
+        .bind(query.location_type)
+        .bind(query.parent_id)
+        .bind(query.is_active)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(locations)
+    }
+
+    /// 取得單一儲位
+    pub async fn get_by_id(pool: &PgPool, id: Uuid) -> Result<StorageLocation> {
+        let location = sqlx::query_as::<_, StorageLocation>(
+            "SELECT * FROM storage_locations WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Storage location not found".to_string()))?;
+
+        Ok(location)
+    }
+
+    /// 更新儲位
+    pub async fn update(
+        pool: &PgPool,
+        id: Uuid,
+        req: &UpdateStorageLocationRequest,
+    ) -> Result<StorageLocation> {
+        if req.capacity.is_some_and(|c| c < Decimal::ZERO) {
+            return Err(AppError::Validation("Capacity must not be negative".to_string()));
+        }
+
+        let location = sqlx::query_as::<_, StorageLocation>(
+            r#"
+            UPDATE storage_locations SET
+                name = COALESCE($1, name),
+                capacity = COALESCE($2, capacity),
+                storage_condition = COALESCE($3, storage_condition),
+                is_active = COALESCE($4, is_active),
+                updated_at = NOW()
+            WHERE id = $5
+            RETURNING *
+            "#
+        )
+        .bind(&req.name)
+        .bind(req.capacity)
+        .bind(req.storage_condition.as_ref().map(|c| c.code()))
+        .bind(req.is_active)
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Storage location not found".to_string()))?;
+
+        Ok(location)
+    }
+
+    /// 刪除儲位（軟刪除，須無啟用中的下層儲位且無庫存）
+    pub async fn delete(pool: &PgPool, id: Uuid) -> Result<()> {
+        let location = Self::get_by_id(pool, id).await?;
+
+        let has_children: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM storage_locations WHERE parent_id = $1 AND is_active = true)"
+        )
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+
+        if has_children {
+            return Err(AppError::BusinessRule(format!(
+                "Storage location '{}' still has active child locations",
+                location.code
+            )));
+        }
+
+        let on_hand: Decimal = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(
+                CASE
+                    WHEN direction IN ('in', 'transfer_in', 'adjust_in') THEN qty_base
+                    WHEN direction IN ('out', 'transfer_out', 'adjust_out') THEN -qty_base
+                END
+            ), 0) as qty
+            FROM stock_ledger
+            WHERE location_id = $1
+            "#
+        )
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+
+        if !on_hand.is_zero() {
+            return Err(AppError::BusinessRule(format!(
+                "Storage location '{}' still holds stock ({})",
+                location.code, on_hand
+            )));
+        }
+
+        sqlx::query(
+            "UPDATE storage_locations SET is_active = false, updated_at = NOW() WHERE id = $1"
+        )
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
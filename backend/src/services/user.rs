@@ -3,7 +3,7 @@ use uuid::Uuid;
 
 use crate::{
     models::{CreateUserRequest, UpdateUserRequest, User, UserResponse},
-    services::AuthService,
+    services::{AuthService, SessionManager},
     AppError, Result,
 };
 
@@ -179,6 +179,11 @@ impl UserService {
         .fetch_one(pool)
         .await?;
 
+        // 停用帳號時立即結束所有 Sessions
+        if req.is_active == Some(false) {
+            SessionManager::end_all_sessions(pool, id, "account_disabled").await?;
+        }
+
         // 如果要更新角色
         if let Some(ref role_ids) = req.role_ids {
             // 刪除現有角色
@@ -240,6 +245,7 @@ impl UserService {
             return Err(AppError::NotFound("User not found".to_string()));
         }
 
+        SessionManager::invalidate_user(id);
         Ok(())
     }
 }
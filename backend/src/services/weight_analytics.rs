@@ -0,0 +1,460 @@
+use chrono::NaiveDate;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+
+use crate::{
+    models::{
+        Pig, PigGrowthAnalytics, PigWeight, PigWeightAlert, ProtocolGrowthStats,
+        WeightLossFinding, WeightLossRule,
+    },
+    services::{AnimalService, NotificationService},
+    AppError, Result,
+};
+
+/// 群組比對的日齡容許範圍（±天）
+const COHORT_AGE_TOLERANCE_DAYS: i64 = 7;
+/// 群組至少需要的豬隻數，不足時不計算偏差
+const COHORT_MIN_PIGS: i64 = 3;
+
+pub struct WeightAnalyticsService;
+
+impl WeightAnalyticsService {
+    /// 單隻豬的生長分析
+    pub async fn pig_growth(
+        pool: &PgPool,
+        pig_id: i32,
+        rule: WeightLossRule,
+    ) -> Result<PigGrowthAnalytics> {
+        validate_rule(rule)?;
+        let pig = AnimalService::get_by_id(pool, pig_id).await?;
+        Self::analyze(pool, &pig, rule).await
+    }
+
+    /// 計畫（IACUC No.）生長統計
+    pub async fn protocol_growth(
+        pool: &PgPool,
+        iacuc_no: &str,
+        rule: WeightLossRule,
+    ) -> Result<ProtocolGrowthStats> {
+        validate_rule(rule)?;
+        let pigs = sqlx::query_as::<_, Pig>(
+            "SELECT * FROM pigs WHERE iacuc_no = $1 AND deleted_at IS NULL ORDER BY ear_tag"
+        )
+        .bind(iacuc_no)
+        .fetch_all(pool)
+        .await?;
+
+        let mut analytics = Vec::with_capacity(pigs.len());
+        for mut pig in pigs {
+            pig.ear_tag = AnimalService::format_ear_tag(&pig.ear_tag);
+            analytics.push(Self::analyze(pool, &pig, rule).await?);
+        }
+
+        let latest: Vec<Decimal> = analytics.iter().filter_map(|a| a.latest_weight).collect();
+        let adg: Vec<Decimal> = analytics.iter().filter_map(|a| a.average_daily_gain).collect();
+        let change: Vec<Decimal> = analytics
+            .iter()
+            .filter_map(|a| a.percent_change_since_entry)
+            .collect();
+
+        Ok(ProtocolGrowthStats {
+            iacuc_no: iacuc_no.to_string(),
+            pig_count: analytics.len(),
+            measured_pig_count: latest.len(),
+            latest_weight_mean: mean(&latest),
+            latest_weight_min: latest.iter().min().copied(),
+            latest_weight_max: latest.iter().max().copied(),
+            latest_weight_stddev: stddev(&latest),
+            average_daily_gain_mean: mean(&adg).map(|v| v.round_dp(3)),
+            percent_change_mean: mean(&change),
+            weight_loss_count: analytics.iter().filter(|a| a.weight_loss.is_some()).count(),
+            pigs: analytics,
+        })
+    }
+
+    /// 檢查體重下降並建立警示；同一筆體重只警示與通知一次
+    pub async fn check_weight_loss(
+        pool: &PgPool,
+        pig_ids: &[i32],
+        rule: WeightLossRule,
+    ) -> Result<Vec<PigWeightAlert>> {
+        validate_rule(rule)?;
+        let notifier = NotificationService::new(pool.clone());
+        let mut alerts = Vec::new();
+
+        for &pig_id in pig_ids {
+            let weights = AnimalService::list_weights(pool, pig_id).await?;
+            let Some(finding) = detect_weight_loss(&weights, rule) else {
+                continue;
+            };
+
+            let alert = sqlx::query_as::<_, PigWeightAlert>(
+                r#"
+                INSERT INTO pig_weight_alerts (
+                    pig_id, weight_id, baseline_weight_id, loss_percent,
+                    threshold_percent, window_days, created_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, NOW())
+                ON CONFLICT (pig_id, weight_id) DO NOTHING
+                RETURNING *
+                "#
+            )
+            .bind(pig_id)
+            .bind(finding.weight_id)
+            .bind(finding.baseline_weight_id)
+            .bind(finding.loss_percent)
+            .bind(rule.threshold_percent)
+            .bind(rule.window_days as i32)
+            .fetch_optional(pool)
+            .await?;
+
+            // 已警示過的體重不再重複通知
+            let Some(alert) = alert else {
+                continue;
+            };
+
+            let pig = AnimalService::get_by_id(pool, pig_id).await?;
+            if let Err(e) = notifier
+                .notify_weight_loss(
+                    &pig.ear_tag,
+                    pig.iacuc_no.as_deref(),
+                    &finding.loss_percent.to_string(),
+                    rule.window_days,
+                    &finding.weight.to_string(),
+                )
+                .await
+            {
+                tracing::warn!("Failed to send weight loss notification: {}", e);
+            }
+
+            alerts.push(alert);
+        }
+
+        Ok(alerts)
+    }
+
+    /// 體重修正後重新檢查：以該筆體重觸發或作為基準、但修正後不再成立的警示予以移除，
+    /// 仍成立者更新下降幅度，新成立者建立警示並通知
+    pub async fn recheck_weight(
+        pool: &PgPool,
+        pig_id: i32,
+        weight_id: i32,
+        rule: WeightLossRule,
+    ) -> Result<Vec<PigWeightAlert>> {
+        validate_rule(rule)?;
+        let weights = AnimalService::list_weights(pool, pig_id).await?;
+        let finding = detect_weight_loss(&weights, rule);
+
+        sqlx::query(
+            r#"
+            DELETE FROM pig_weight_alerts
+            WHERE pig_id = $1
+              AND (weight_id = $2 OR baseline_weight_id = $2)
+              AND NOT (weight_id IS NOT DISTINCT FROM $3 AND baseline_weight_id IS NOT DISTINCT FROM $4)
+            "#
+        )
+        .bind(pig_id)
+        .bind(weight_id)
+        .bind(finding.as_ref().map(|f| f.weight_id))
+        .bind(finding.as_ref().map(|f| f.baseline_weight_id))
+        .execute(pool)
+        .await?;
+
+        if let Some(finding) = &finding {
+            sqlx::query(
+                r#"
+                UPDATE pig_weight_alerts SET loss_percent = $4
+                WHERE pig_id = $1 AND weight_id = $2 AND baseline_weight_id = $3
+                "#
+            )
+            .bind(pig_id)
+            .bind(finding.weight_id)
+            .bind(finding.baseline_weight_id)
+            .bind(finding.loss_percent)
+            .execute(pool)
+            .await?;
+        }
+
+        Self::check_weight_loss(pool, &[pig_id], rule).await
+    }
+
+    /// 檢查計畫內（或全部）未刪除豬隻的體重下降
+    pub async fn check_all(
+        pool: &PgPool,
+        iacuc_no: Option<&str>,
+        rule: WeightLossRule,
+    ) -> Result<Vec<PigWeightAlert>> {
+        let pig_ids: Vec<i32> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM pigs
+            WHERE deleted_at IS NULL
+              AND ($1::varchar IS NULL OR iacuc_no = $1)
+            ORDER BY id
+            "#
+        )
+        .bind(iacuc_no)
+        .fetch_all(pool)
+        .await?;
+
+        Self::check_weight_loss(pool, &pig_ids, rule).await
+    }
+
+    /// 列出豬隻的體重下降警示
+    pub async fn list_alerts(pool: &PgPool, pig_id: i32) -> Result<Vec<PigWeightAlert>> {
+        let alerts = sqlx::query_as::<_, PigWeightAlert>(
+            "SELECT * FROM pig_weight_alerts WHERE pig_id = $1 ORDER BY created_at DESC"
+        )
+        .bind(pig_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(alerts)
+    }
+
+    async fn analyze(pool: &PgPool, pig: &Pig, rule: WeightLossRule) -> Result<PigGrowthAnalytics> {
+        let weights = AnimalService::list_weights(pool, pig.id).await?;
+        let latest = latest_weight(&weights);
+
+        let age_days = match (pig.birth_date, latest) {
+            (Some(birth), Some(w)) => Some((w.measure_date - birth).num_days()),
+            _ => None,
+        };
+
+        let (cohort_expected_weight, cohort_size) = match age_days {
+            Some(age) => Self::cohort_weight(pool, pig, age).await?,
+            None => (None, 0),
+        };
+
+        let latest_weight = latest.map(|w| w.weight);
+        Ok(PigGrowthAnalytics {
+            pig_id: pig.id,
+            ear_tag: pig.ear_tag.clone(),
+            breed: pig.breed,
+            iacuc_no: pig.iacuc_no.clone(),
+            entry_date: pig.entry_date,
+            entry_weight: pig.entry_weight,
+            measurement_count: weights.len(),
+            latest_weight,
+            latest_measure_date: latest.map(|w| w.measure_date),
+            age_days,
+            average_daily_gain: average_daily_gain(pig.entry_date, pig.entry_weight, &weights),
+            percent_change_since_entry: latest_weight
+                .and_then(|w| percent_change(pig.entry_weight?, w)),
+            cohort_expected_weight,
+            cohort_size,
+            cohort_deviation_percent: latest_weight
+                .and_then(|w| percent_change(cohort_expected_weight?, w)),
+            weight_loss: detect_weight_loss(&weights, rule),
+        })
+    }
+
+    /// 同品種、相近日齡（其他豬隻）的平均體重
+    async fn cohort_weight(pool: &PgPool, pig: &Pig, age_days: i64) -> Result<(Option<Decimal>, i64)> {
+        let (avg, pigs): (Option<Decimal>, i64) = sqlx::query_as(
+            r#"
+            SELECT AVG(w.weight), COUNT(DISTINCT w.pig_id)
+            FROM pig_weights w
+            JOIN pigs p ON w.pig_id = p.id
+            WHERE p.breed = $1 AND p.id <> $2
+              AND p.deleted_at IS NULL AND w.deleted_at IS NULL
+              AND p.birth_date IS NOT NULL
+              AND (w.measure_date - p.birth_date) BETWEEN $3 AND $4
+            "#
+        )
+        .bind(pig.breed)
+        .bind(pig.id)
+        .bind((age_days - COHORT_AGE_TOLERANCE_DAYS) as i32)
+        .bind((age_days + COHORT_AGE_TOLERANCE_DAYS) as i32)
+        .fetch_one(pool)
+        .await?;
+
+        if pigs < COHORT_MIN_PIGS {
+            return Ok((None, pigs));
+        }
+        Ok((avg.map(|v| v.round_dp(1)), pigs))
+    }
+}
+
+fn validate_rule(rule: WeightLossRule) -> Result<()> {
+    if rule.threshold_percent <= Decimal::ZERO || rule.threshold_percent >= Decimal::ONE_HUNDRED {
+        return Err(AppError::Validation("Weight loss threshold must be between 0 and 100 percent".to_string()));
+    }
+    if rule.window_days < 1 {
+        return Err(AppError::Validation("Weight loss window must be at least 1 day".to_string()));
+    }
+    Ok(())
+}
+
+fn latest_weight(weights: &[PigWeight]) -> Option<&PigWeight> {
+    weights.iter().max_by_key(|w| (w.measure_date, w.id))
+}
+
+/// 變化百分比（相對基準值）
+fn percent_change(base: Decimal, value: Decimal) -> Option<Decimal> {
+    if base <= Decimal::ZERO {
+        return None;
+    }
+    Some(((value - base) / base * Decimal::ONE_HUNDRED).round_dp(2))
+}
+
+/// 平均日增重：以進場體重為起點，無進場體重時以第一次測量為起點
+fn average_daily_gain(
+    entry_date: NaiveDate,
+    entry_weight: Option<Decimal>,
+    weights: &[PigWeight],
+) -> Option<Decimal> {
+    let latest = latest_weight(weights)?;
+    let (start_date, start_weight) = match entry_weight {
+        Some(w) => (entry_date, w),
+        None => {
+            let first = weights.iter().min_by_key(|w| (w.measure_date, w.id))?;
+            (first.measure_date, first.weight)
+        }
+    };
+
+    let days = (latest.measure_date - start_date).num_days();
+    if days <= 0 {
+        return None;
+    }
+    Some(((latest.weight - start_weight) / Decimal::from(days)).round_dp(3))
+}
+
+/// 最新體重相對觀察期間內（不含當日）最高體重的下降幅度
+fn detect_weight_loss(weights: &[PigWeight], rule: WeightLossRule) -> Option<WeightLossFinding> {
+    let latest = latest_weight(weights)?;
+    let window_start = latest.measure_date - chrono::Duration::days(rule.window_days);
+
+    let baseline = weights
+        .iter()
+        .filter(|w| w.measure_date >= window_start && w.measure_date < latest.measure_date)
+        .max_by_key(|w| (w.weight, w.measure_date))?;
+
+    if baseline.weight <= Decimal::ZERO {
+        return None;
+    }
+    let loss_percent =
+        ((baseline.weight - latest.weight) / baseline.weight * Decimal::ONE_HUNDRED).round_dp(2);
+    if loss_percent < rule.threshold_percent {
+        return None;
+    }
+
+    Some(WeightLossFinding {
+        baseline_weight_id: baseline.id,
+        baseline_date: baseline.measure_date,
+        baseline_weight: baseline.weight,
+        weight_id: latest.id,
+        measure_date: latest.measure_date,
+        weight: latest.weight,
+        loss_percent,
+    })
+}
+
+fn mean(values: &[Decimal]) -> Option<Decimal> {
+    if values.is_empty() {
+        return None;
+    }
+    let sum: Decimal = values.iter().sum();
+    Some((sum / Decimal::from(values.len())).round_dp(2))
+}
+
+/// 母體標準差
+fn stddev(values: &[Decimal]) -> Option<Decimal> {
+    let avg = mean(values)?.to_f64()?;
+    let variance = values
+        .iter()
+        .filter_map(|v| v.to_f64())
+        .map(|v| (v - avg).powi(2))
+        .sum::<f64>()
+        / values.len() as f64;
+    Decimal::from_f64_retain(variance.sqrt()).map(|v| v.round_dp(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CreateWeightRequest, UpdateWeightRequest};
+    use crate::test_support::*;
+    use chrono::Utc;
+
+    fn weight(id: i32, date: &str, kg: i64) -> PigWeight {
+        PigWeight {
+            id,
+            pig_id: 1,
+            measure_date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            weight: Decimal::from(kg),
+            created_by: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn rule(percent: i64, days: i64) -> WeightLossRule {
+        WeightLossRule {
+            threshold_percent: Decimal::from(percent),
+            window_days: days,
+        }
+    }
+
+    #[test]
+    fn test_detect_weight_loss_within_window() {
+        let weights = vec![
+            weight(1, "2026-01-01", 60),
+            weight(2, "2026-01-10", 50),
+            weight(3, "2026-01-14", 45),
+        ];
+        let finding = detect_weight_loss(&weights, rule(10, 7)).unwrap();
+        assert_eq!(finding.baseline_weight_id, 2);
+        assert_eq!(finding.weight_id, 3);
+        assert_eq!(finding.loss_percent, Decimal::from(10));
+
+        // 基準體重在觀察期間外時不警示
+        assert!(detect_weight_loss(&weights[..1], rule(10, 7)).is_none());
+        assert!(detect_weight_loss(&[weights[0].clone(), weights[2].clone()], rule(10, 7)).is_none());
+    }
+
+    #[test]
+    fn test_average_daily_gain() {
+        let entry = NaiveDate::parse_from_str("2026-01-01", "%Y-%m-%d").unwrap();
+        let weights = vec![weight(1, "2026-01-11", 30)];
+        assert_eq!(
+            average_daily_gain(entry, Some(Decimal::from(20)), &weights),
+            Some(Decimal::ONE)
+        );
+        assert_eq!(average_daily_gain(entry, None, &weights), None);
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_recheck_clears_alert_after_weight_correction(db: PgPool) {
+        let user_id = insert_user(&db, "weigher@example.com", true).await;
+        let pig_id = insert_pig(&db, "W-001", None).await;
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+
+        for (day, kg) in [("2026-01-10", 50), ("2026-01-14", 40)] {
+            let req = CreateWeightRequest { measure_date: date(day), weight: Decimal::from(kg) };
+            AnimalService::create_weight(&db, pig_id, &req, user_id).await.unwrap();
+        }
+        let alerts = WeightAnalyticsService::check_weight_loss(&db, &[pig_id], rule(10, 7)).await.unwrap();
+        assert_eq!(alerts.len(), 1);
+        let latest_id = alerts[0].weight_id;
+
+        // 誤植修正後下降幅度未達門檻，警示應移除
+        let req = UpdateWeightRequest { measure_date: None, weight: Some(Decimal::from(48)) };
+        AnimalService::update_weight(&db, latest_id, &req).await.unwrap();
+        let alerts = WeightAnalyticsService::recheck_weight(&db, pig_id, latest_id, rule(10, 7)).await.unwrap();
+        assert!(alerts.is_empty());
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM pig_weight_alerts WHERE pig_id = $1")
+            .bind(pig_id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(remaining, 0);
+
+        // 再修正為明顯下降時重新警示
+        let req = UpdateWeightRequest { measure_date: None, weight: Some(Decimal::from(30)) };
+        AnimalService::update_weight(&db, latest_id, &req).await.unwrap();
+        let alerts = WeightAnalyticsService::recheck_weight(&db, pig_id, latest_id, rule(10, 7)).await.unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].loss_percent, Decimal::from(40));
+    }
+}
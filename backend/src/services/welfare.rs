@@ -0,0 +1,611 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        CreateWelfareScoreRequest, CreateWelfareScoreSheetRequest, Pig, PigWelfareScore,
+        UpdateWelfareScoreRequest, UpdateWelfareScoreSheetRequest, WelfareCriterion,
+        WelfareScoreSheet, WelfareScoreSheetQuery,
+    },
+    services::{AnimalService, DataScope, NotificationService},
+    AppError, Result,
+};
+
+/// 未指定計畫評分表時使用的物種預設
+const DEFAULT_SPECIES: &str = "pig";
+
+pub struct WelfareService;
+
+impl WelfareService {
+    // ============================================
+    // 評分表
+    // ============================================
+
+    /// 建立評分表
+    pub async fn create_sheet(
+        pool: &PgPool,
+        req: &CreateWelfareScoreSheetRequest,
+        created_by: Uuid,
+    ) -> Result<WelfareScoreSheet> {
+        validate_criteria(&req.criteria)?;
+        validate_endpoints(req.endpoint_total, req.endpoint_trend_increase)?;
+
+        if req.protocol_id.is_none() && req.species.is_none() {
+            return Err(AppError::Validation(
+                "Score sheet must target a protocol or a species".to_string(),
+            ));
+        }
+
+        let criteria = serde_json::to_value(&req.criteria)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize criteria: {}", e)))?;
+
+        let sheet = sqlx::query_as::<_, WelfareScoreSheet>(
+            r#"
+            INSERT INTO welfare_score_sheets (
+                id, name, protocol_id, species, criteria, endpoint_total,
+                endpoint_trend_increase, is_active, created_by, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, true, $8, NOW(), NOW())
+            RETURNING *
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(&req.name)
+        .bind(req.protocol_id)
+        .bind(&req.species)
+        .bind(&criteria)
+        .bind(req.endpoint_total)
+        .bind(req.endpoint_trend_increase)
+        .bind(created_by)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(sheet)
+    }
+
+    /// 取得評分表列表
+    pub async fn list_sheets(
+        pool: &PgPool,
+        query: &WelfareScoreSheetQuery,
+        scope: &DataScope,
+    ) -> Result<Vec<WelfareScoreSheet>> {
+        // 共用評分表（未綁定計畫）不受資料範圍限制
+        let sheets = sqlx::query_as::<_, WelfareScoreSheet>(
+            r#"
+            SELECT * FROM welfare_score_sheets
+            WHERE ($1::uuid IS NULL OR protocol_id = $1)
+              AND ($2::varchar IS NULL OR species = $2)
+              AND ($3::boolean IS NULL OR is_active = $3)
+              AND (
+                  $4::text[] IS NULL
+                  OR protocol_id IS NULL
+                  OR protocol_id IN (SELECT id FROM protocols WHERE iacuc_no = ANY($4))
+              )
+            ORDER BY created_at DESC
+            "#
+        )
+        .bind(query.protocol_id)
+        .bind(&query.species)
+        .bind(query.is_active)
+        .bind(scope.iacuc_filter())
+        .fetch_all(pool)
+        .await?;
+
+        Ok(sheets)
+    }
+
+    /// 取得單一評分表
+    pub async fn get_sheet(pool: &PgPool, id: Uuid) -> Result<WelfareScoreSheet> {
+        let sheet = sqlx::query_as::<_, WelfareScoreSheet>(
+            "SELECT * FROM welfare_score_sheets WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Welfare score sheet not found".to_string()))?;
+
+        Ok(sheet)
+    }
+
+    /// 更新評分表（已評分的紀錄保留原計算結果）
+    pub async fn update_sheet(
+        pool: &PgPool,
+        id: Uuid,
+        req: &UpdateWelfareScoreSheetRequest,
+    ) -> Result<WelfareScoreSheet> {
+        if let Some(criteria) = &req.criteria {
+            validate_criteria(criteria)?;
+        }
+        validate_endpoints(req.endpoint_total, req.endpoint_trend_increase)?;
+
+        let criteria = req
+            .criteria
+            .as_ref()
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(|e| AppError::Internal(format!("Failed to serialize criteria: {}", e)))?;
+
+        let sheet = sqlx::query_as::<_, WelfareScoreSheet>(
+            r#"
+            UPDATE welfare_score_sheets SET
+                name = COALESCE($2, name),
+                criteria = COALESCE($3, criteria),
+                endpoint_total = COALESCE($4, endpoint_total),
+                endpoint_trend_increase = COALESCE($5, endpoint_trend_increase),
+                is_active = COALESCE($6, is_active),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#
+        )
+        .bind(id)
+        .bind(&req.name)
+        .bind(&criteria)
+        .bind(req.endpoint_total)
+        .bind(req.endpoint_trend_increase)
+        .bind(req.is_active)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Welfare score sheet not found".to_string()))?;
+
+        Ok(sheet)
+    }
+
+    /// 停用評分表
+    pub async fn deactivate_sheet(pool: &PgPool, id: Uuid) -> Result<()> {
+        let result = sqlx::query(
+            "UPDATE welfare_score_sheets SET is_active = false, updated_at = NOW() WHERE id = $1"
+        )
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Welfare score sheet not found".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// 選用評分表：指定 > 豬隻所屬計畫 > 物種預設
+    async fn resolve_sheet(
+        pool: &PgPool,
+        pig: &Pig,
+        sheet_id: Option<Uuid>,
+    ) -> Result<WelfareScoreSheet> {
+        if let Some(id) = sheet_id {
+            let sheet = Self::get_sheet(pool, id).await?;
+            if !sheet.is_active {
+                return Err(AppError::BusinessRule("Welfare score sheet is inactive".to_string()));
+            }
+            return Ok(sheet);
+        }
+
+        let sheet = sqlx::query_as::<_, WelfareScoreSheet>(
+            r#"
+            SELECT s.* FROM welfare_score_sheets s
+            LEFT JOIN protocols p ON s.protocol_id = p.id
+            WHERE s.is_active = true
+              AND (
+                  ($1::varchar IS NOT NULL AND p.iacuc_no = $1)
+                  OR (s.protocol_id IS NULL AND s.species = $2)
+              )
+            ORDER BY (s.protocol_id IS NOT NULL) DESC, s.created_at DESC
+            LIMIT 1
+            "#
+        )
+        .bind(&pig.iacuc_no)
+        .bind(DEFAULT_SPECIES)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| {
+            AppError::Validation("No active welfare score sheet for this pig".to_string())
+        })?;
+
+        Ok(sheet)
+    }
+
+    // ============================================
+    // 評分紀錄
+    // ============================================
+
+    /// 列出豬隻的福祉評分紀錄
+    pub async fn list_by_pig(pool: &PgPool, pig_id: i32) -> Result<Vec<PigWelfareScore>> {
+        let scores = sqlx::query_as::<_, PigWelfareScore>(
+            r#"
+            SELECT * FROM pig_welfare_scores
+            WHERE pig_id = $1 AND deleted_at IS NULL
+            ORDER BY assessed_at DESC, id DESC
+            "#
+        )
+        .bind(pig_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(scores)
+    }
+
+    /// 取得單一福祉評分紀錄
+    pub async fn get_by_id(pool: &PgPool, id: i32) -> Result<PigWelfareScore> {
+        let score = sqlx::query_as::<_, PigWelfareScore>(
+            "SELECT * FROM pig_welfare_scores WHERE id = $1 AND deleted_at IS NULL"
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Welfare score not found".to_string()))?;
+
+        Ok(score)
+    }
+
+    /// 建立福祉評分，達人道終點時通知獸醫師與 PI
+    pub async fn create_score(
+        pool: &PgPool,
+        pig_id: i32,
+        req: &CreateWelfareScoreRequest,
+        created_by: Uuid,
+    ) -> Result<PigWelfareScore> {
+        let pig = AnimalService::get_by_id(pool, pig_id).await?;
+        let sheet = Self::resolve_sheet(pool, &pig, req.score_sheet_id).await?;
+        let criteria = parse_criteria(&sheet)?;
+
+        let total = compute_total(&criteria, &req.scores)?;
+        let assessed_at = req.assessed_at.unwrap_or_else(Utc::now);
+        let previous = Self::previous_total(pool, pig_id, assessed_at, None).await?;
+        let reasons = evaluate_humane_endpoint(&sheet, total, previous);
+
+        let scores = serde_json::to_value(&req.scores)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize scores: {}", e)))?;
+
+        let score = sqlx::query_as::<_, PigWelfareScore>(
+            r#"
+            INSERT INTO pig_welfare_scores (
+                pig_id, score_sheet_id, assessed_at, scores, total_score,
+                endpoint_reached, endpoint_reason, remark, created_by, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW(), NOW())
+            RETURNING *
+            "#
+        )
+        .bind(pig_id)
+        .bind(sheet.id)
+        .bind(assessed_at)
+        .bind(&scores)
+        .bind(total)
+        .bind(!reasons.is_empty())
+        .bind(join_reasons(&reasons))
+        .bind(&req.remark)
+        .bind(created_by)
+        .fetch_one(pool)
+        .await?;
+
+        if score.endpoint_reached {
+            Self::notify_endpoint(pool, &pig, &score).await;
+        }
+
+        Ok(score)
+    }
+
+    /// 更新福祉評分（保存版本歷史與變更原因）
+    pub async fn update_score(
+        pool: &PgPool,
+        id: i32,
+        req: &UpdateWelfareScoreRequest,
+        change_reason: &str,
+        updated_by: Uuid,
+    ) -> Result<PigWelfareScore> {
+        let original = Self::get_by_id(pool, id).await?;
+        let sheet = Self::get_sheet(pool, original.score_sheet_id).await?;
+        let criteria = parse_criteria(&sheet)?;
+
+        let scores: HashMap<String, i32> = match &req.scores {
+            Some(scores) => scores.clone(),
+            None => serde_json::from_value(original.scores.clone())
+                .map_err(|e| AppError::Internal(format!("Invalid stored scores: {}", e)))?,
+        };
+        let total = compute_total(&criteria, &scores)?;
+        let assessed_at = req.assessed_at.unwrap_or(original.assessed_at);
+        let previous = Self::previous_total(pool, original.pig_id, assessed_at, Some(id)).await?;
+        let reasons = evaluate_humane_endpoint(&sheet, total, previous);
+
+        let mut tx = pool.begin().await?;
+        AnimalService::save_record_version(&mut tx, "welfare_score", id, &original, updated_by).await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO change_reasons (entity_type, entity_id, change_type, reason, changed_by)
+            VALUES ('welfare_score', $1::text, 'UPDATE', $2, $3)
+            "#
+        )
+        .bind(id)
+        .bind(change_reason)
+        .bind(updated_by)
+        .execute(&mut *tx)
+        .await?;
+
+        let scores = serde_json::to_value(&scores)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize scores: {}", e)))?;
+
+        let score = sqlx::query_as::<_, PigWelfareScore>(
+            r#"
+            UPDATE pig_welfare_scores SET
+                assessed_at = $2,
+                scores = $3,
+                total_score = $4,
+                endpoint_reached = $5,
+                endpoint_reason = $6,
+                remark = COALESCE($7, remark),
+                updated_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING *
+            "#
+        )
+        .bind(id)
+        .bind(assessed_at)
+        .bind(&scores)
+        .bind(total)
+        .bind(!reasons.is_empty())
+        .bind(join_reasons(&reasons))
+        .bind(&req.remark)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Welfare score not found".to_string()))?;
+        tx.commit().await?;
+
+        // 僅在本次修改才達到人道終點時通知，避免重複警示
+        if score.endpoint_reached && !original.endpoint_reached {
+            let pig = AnimalService::get_by_id(pool, score.pig_id).await?;
+            Self::notify_endpoint(pool, &pig, &score).await;
+        }
+
+        Ok(score)
+    }
+
+    /// 刪除福祉評分（軟刪除 + 刪除原因）- GLP 合規
+    pub async fn soft_delete_with_reason(pool: &PgPool, id: i32, reason: &str, deleted_by: Uuid) -> Result<()> {
+        let mut tx = pool.begin().await?;
+
+        let deleted = sqlx::query(
+            r#"
+            UPDATE pig_welfare_scores SET
+                deleted_at = NOW(),
+                deletion_reason = $2,
+                deleted_by = $3
+            WHERE id = $1 AND deleted_at IS NULL
+            "#
+        )
+        .bind(id)
+        .bind(reason)
+        .bind(deleted_by)
+        .execute(&mut *tx)
+        .await?;
+        if deleted.rows_affected() == 0 {
+            return Err(AppError::NotFound("Welfare score not found".to_string()));
+        }
+
+        // 記錄到 change_reasons 表
+        sqlx::query(
+            r#"
+            INSERT INTO change_reasons (entity_type, entity_id, change_type, reason, changed_by)
+            VALUES ('welfare_score', $1::text, 'DELETE', $2, $3)
+            "#
+        )
+        .bind(id)
+        .bind(reason)
+        .bind(deleted_by)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// 標記福祉評分為獸醫已讀
+    pub async fn mark_vet_read(pool: &PgPool, id: i32) -> Result<()> {
+        sqlx::query(
+            "UPDATE pig_welfare_scores SET vet_read = true, vet_read_at = NOW(), updated_at = NOW() WHERE id = $1"
+        )
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 取得評估時間前最近一次評分的總分（趨勢判斷用）
+    async fn previous_total(
+        pool: &PgPool,
+        pig_id: i32,
+        before: chrono::DateTime<Utc>,
+        exclude_id: Option<i32>,
+    ) -> Result<Option<Decimal>> {
+        let total: Option<Decimal> = sqlx::query_scalar(
+            r#"
+            SELECT total_score FROM pig_welfare_scores
+            WHERE pig_id = $1 AND deleted_at IS NULL
+              AND assessed_at < $2
+              AND ($3::int IS NULL OR id <> $3)
+            ORDER BY assessed_at DESC, id DESC
+            LIMIT 1
+            "#
+        )
+        .bind(pig_id)
+        .bind(before)
+        .bind(exclude_id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(total)
+    }
+
+    /// 發送人道終點通知（通知失敗不影響評分紀錄）
+    async fn notify_endpoint(pool: &PgPool, pig: &Pig, score: &PigWelfareScore) {
+        let service = NotificationService::new(pool.clone());
+        if let Err(e) = service
+            .notify_humane_endpoint(
+                &pig.ear_tag,
+                pig.iacuc_no.as_deref(),
+                &score.total_score.to_string(),
+                score.endpoint_reason.as_deref().unwrap_or("-"),
+            )
+            .await
+        {
+            tracing::warn!("Failed to send humane endpoint notification: {}", e);
+        }
+    }
+}
+
+/// 檢查評分項目設定
+fn validate_criteria(criteria: &[WelfareCriterion]) -> Result<()> {
+    let mut codes = HashSet::new();
+    for c in criteria {
+        let code = c.code.trim();
+        if code.is_empty() || c.name.trim().is_empty() {
+            return Err(AppError::Validation("Criterion code and name are required".to_string()));
+        }
+        if !codes.insert(code) {
+            return Err(AppError::Validation(format!("Duplicate criterion code '{}'", code)));
+        }
+        if c.weight < Decimal::ZERO {
+            return Err(AppError::Validation(format!("Criterion '{}' weight must not be negative", code)));
+        }
+        if !(1..=100).contains(&c.max_score) {
+            return Err(AppError::Validation(format!("Criterion '{}' max score must be 1-100", code)));
+        }
+    }
+    Ok(())
+}
+
+fn validate_endpoints(total: Option<Decimal>, trend: Option<Decimal>) -> Result<()> {
+    if total.is_some_and(|v| v <= Decimal::ZERO) || trend.is_some_and(|v| v <= Decimal::ZERO) {
+        return Err(AppError::Validation("Humane endpoint thresholds must be positive".to_string()));
+    }
+    Ok(())
+}
+
+fn parse_criteria(sheet: &WelfareScoreSheet) -> Result<Vec<WelfareCriterion>> {
+    serde_json::from_value(sheet.criteria.clone())
+        .map_err(|e| AppError::Internal(format!("Invalid score sheet criteria: {}", e)))
+}
+
+/// 計算加權總分：每個項目都須評分且在 0 ~ max_score 之間
+fn compute_total(criteria: &[WelfareCriterion], scores: &HashMap<String, i32>) -> Result<Decimal> {
+    if let Some(unknown) = scores.keys().find(|k| !criteria.iter().any(|c| &c.code == *k)) {
+        return Err(AppError::Validation(format!("Unknown criterion '{}'", unknown)));
+    }
+
+    let mut total = Decimal::ZERO;
+    for c in criteria {
+        let score = *scores
+            .get(&c.code)
+            .ok_or_else(|| AppError::Validation(format!("Criterion '{}' is not scored", c.code)))?;
+        if !(0..=c.max_score).contains(&score) {
+            return Err(AppError::Validation(format!(
+                "Score for '{}' must be between 0 and {}",
+                c.code, c.max_score
+            )));
+        }
+        total += c.weight * Decimal::from(score);
+    }
+    Ok(total)
+}
+
+/// 判斷是否達人道終點，回傳觸發原因
+fn evaluate_humane_endpoint(
+    sheet: &WelfareScoreSheet,
+    total: Decimal,
+    previous_total: Option<Decimal>,
+) -> Vec<String> {
+    let mut reasons = Vec::new();
+    if let Some(limit) = sheet.endpoint_total {
+        if total >= limit {
+            reasons.push(format!("總分 {} 已達終點門檻 {}", total, limit));
+        }
+    }
+    if let (Some(limit), Some(previous)) = (sheet.endpoint_trend_increase, previous_total) {
+        let increase = total - previous;
+        if increase >= limit {
+            reasons.push(format!("較前次評分上升 {}（門檻 {}）", increase, limit));
+        }
+    }
+    reasons
+}
+
+fn join_reasons(reasons: &[String]) -> Option<String> {
+    if reasons.is_empty() {
+        None
+    } else {
+        Some(reasons.join("；"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn criterion(code: &str, weight: i64, max_score: i32) -> WelfareCriterion {
+        WelfareCriterion {
+            code: code.to_string(),
+            name: code.to_string(),
+            weight: Decimal::from(weight),
+            max_score,
+            description: None,
+        }
+    }
+
+    fn sheet(endpoint_total: Option<i64>, trend: Option<i64>) -> WelfareScoreSheet {
+        WelfareScoreSheet {
+            id: Uuid::new_v4(),
+            name: "test".to_string(),
+            protocol_id: None,
+            species: Some(DEFAULT_SPECIES.to_string()),
+            criteria: serde_json::json!([]),
+            endpoint_total: endpoint_total.map(Decimal::from),
+            endpoint_trend_increase: trend.map(Decimal::from),
+            is_active: true,
+            created_by: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_compute_total_weighted() {
+        let criteria = vec![criterion("posture", 2, 3), criterion("appetite", 1, 3)];
+        let scores = HashMap::from([("posture".to_string(), 2), ("appetite".to_string(), 3)]);
+        assert_eq!(compute_total(&criteria, &scores).unwrap(), Decimal::from(7));
+
+        let missing = HashMap::from([("posture".to_string(), 2)]);
+        assert!(compute_total(&criteria, &missing).is_err());
+
+        let out_of_range = HashMap::from([("posture".to_string(), 4), ("appetite".to_string(), 0)]);
+        assert!(compute_total(&criteria, &out_of_range).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_humane_endpoint() {
+        let s = sheet(Some(10), Some(4));
+        assert!(evaluate_humane_endpoint(&s, Decimal::from(5), None).is_empty());
+        assert_eq!(evaluate_humane_endpoint(&s, Decimal::from(10), None).len(), 1);
+        assert_eq!(evaluate_humane_endpoint(&s, Decimal::from(8), Some(Decimal::from(4))).len(), 1);
+        assert_eq!(evaluate_humane_endpoint(&s, Decimal::from(12), Some(Decimal::from(2))).len(), 2);
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_soft_delete_missing_score_is_not_found(db: PgPool) {
+        let result = WelfareService::soft_delete_with_reason(&db, 999_999, "誤植", Uuid::new_v4()).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+
+        // 未刪除任何資料時不留下刪除原因
+        let reasons: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM change_reasons WHERE entity_type = 'welfare_score'")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(reasons, 0);
+    }
+}
@@ -0,0 +1,197 @@
+// 資料庫測試共用的建立資料輔助函式
+//
+// 需連線 PostgreSQL：DATABASE_URL=postgres://... cargo test -- --ignored
+
+use std::sync::{Arc, Once};
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    middleware::CurrentUser,
+    services::{build_storage, init_storage, ActivityLogWriter, AuthService, StorageKind},
+    AppState,
+};
+
+fn test_config() -> Config {
+    if std::env::var("JWT_SECRET").is_err() {
+        std::env::set_var("JWT_SECRET", "test-secret");
+    }
+    Config::from_env().expect("test config")
+}
+
+pub fn app_state(db: PgPool) -> AppState {
+    AppState {
+        activity_log: ActivityLogWriter::spawn(db.clone()),
+        db,
+        config: Arc::new(test_config()),
+    }
+}
+
+/// 全域儲存後端改用暫存目錄（整個測試行程共用，內容以雜湊定址不會互相衝突）
+pub fn init_test_storage() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        let dir = std::env::temp_dir().join(format!("erp-test-uploads-{}", Uuid::new_v4()));
+        let config = Config { upload_dir: dir.to_string_lossy().into_owned(), ..test_config() };
+        init_storage(build_storage(StorageKind::Local, &config).expect("local storage"));
+    });
+}
+
+pub async fn insert_user(db: &PgPool, email: &str, is_internal: bool) -> Uuid {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO users (id, email, password_hash, display_name, is_internal)
+        VALUES ($1, $2, 'x', $2, $3)
+        RETURNING id
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(email)
+    .bind(is_internal)
+    .fetch_one(db)
+    .await
+    .unwrap()
+}
+
+/// 設定可通過密碼驗證的登入密碼（電子簽章測試用）
+pub async fn set_password(db: &PgPool, user_id: Uuid, password: &str) {
+    sqlx::query("UPDATE users SET password_hash = $2 WHERE id = $1")
+        .bind(user_id)
+        .bind(AuthService::hash_password(password).unwrap())
+        .execute(db)
+        .await
+        .unwrap();
+}
+
+pub fn current_user(id: Uuid, email: &str, roles: &[&str], permissions: &[&str]) -> CurrentUser {
+    CurrentUser {
+        id,
+        email: email.to_string(),
+        roles: roles.iter().map(|r| r.to_string()).collect(),
+        permissions: permissions.iter().map(|p| p.to_string()).collect(),
+        session_id: Uuid::new_v4(),
+    }
+}
+
+/// 建立已核准的計畫，`iacuc_no` 兼作計畫編號與標題
+pub async fn insert_protocol(db: &PgPool, pi_user_id: Uuid, iacuc_no: &str) -> Uuid {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO protocols (id, protocol_no, title, status, pi_user_id, created_by, iacuc_no)
+        VALUES ($1, $2, $2, 'APPROVED', $3, $3, $2)
+        RETURNING id
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(iacuc_no)
+    .bind(pi_user_id)
+    .fetch_one(db)
+    .await
+    .unwrap()
+}
+
+pub async fn insert_pig(db: &PgPool, ear_tag: &str, iacuc_no: Option<&str>) -> i32 {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO pigs (ear_tag, status, breed, gender, entry_date, iacuc_no)
+        VALUES ($1, 'in_experiment', 'miniature', 'male', CURRENT_DATE, $2)
+        RETURNING id
+        "#,
+    )
+    .bind(ear_tag)
+    .bind(iacuc_no)
+    .fetch_one(db)
+    .await
+    .unwrap()
+}
+
+/// 外部使用者資料範圍測試：外部 PI 僅參與 PIG-SCOPE-A；PIG-SCOPE-B 屬於其他 PI
+pub struct ScopeFixture {
+    pub external_user: CurrentUser,
+    pub own_pig: i32,
+    pub other_pig: i32,
+    pub other_attachment: Uuid,
+    pub other_export: Uuid,
+    pub other_observation: i32,
+    pub other_surgery: i32,
+}
+
+pub async fn scope_fixture(db: &PgPool) -> ScopeFixture {
+    let external_id = insert_user(db, "external-pi@example.com", false).await;
+    let other_pi = insert_user(db, "other-pi@example.com", false).await;
+    insert_protocol(db, external_id, "PIG-SCOPE-A").await;
+    insert_protocol(db, other_pi, "PIG-SCOPE-B").await;
+
+    let own_pig = insert_pig(db, "A001", Some("PIG-SCOPE-A")).await;
+    let other_pig = insert_pig(db, "B001", Some("PIG-SCOPE-B")).await;
+
+    let other_attachment: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO attachments (id, root_id, entity_type, entity_id, category, file_name, file_path, file_size, mime_type, uploaded_by)
+        VALUES ($1, $1, 'pig', $2, 'pig_photo', 'b001.jpg', 'pig_photos/b001.jpg', 1, 'image/jpeg', $3)
+        RETURNING id
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(other_pig.to_string())
+    .bind(other_pi)
+    .fetch_one(db)
+    .await
+    .unwrap();
+
+    let other_export: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO pig_export_records (id, pig_id, iacuc_no, export_type, export_format, file_path, created_by)
+        VALUES ($1, NULL, 'PIG-SCOPE-B', 'medical_summary', 'pdf', 'exports/b.pdf', $2)
+        RETURNING id
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(other_pi)
+    .fetch_one(db)
+    .await
+    .unwrap();
+
+    let other_observation: i32 = sqlx::query_scalar(
+        r#"
+        INSERT INTO pig_observations (pig_id, event_date, record_type, content, created_by)
+        VALUES ($1, CURRENT_DATE, 'observation', 'B001 觀察', $2)
+        RETURNING id
+        "#,
+    )
+    .bind(other_pig)
+    .bind(other_pi)
+    .fetch_one(db)
+    .await
+    .unwrap();
+
+    let other_surgery: i32 = sqlx::query_scalar(
+        r#"
+        INSERT INTO pig_surgeries (pig_id, surgery_date, surgery_site, created_by)
+        VALUES ($1, CURRENT_DATE, '腹腔', $2)
+        RETURNING id
+        "#,
+    )
+    .bind(other_pig)
+    .bind(other_pi)
+    .fetch_one(db)
+    .await
+    .unwrap();
+
+    ScopeFixture {
+        external_user: current_user(
+            external_id,
+            "external-pi@example.com",
+            &["PI"],
+            &["animal.info.view_project", "animal.record.view", "animal.export.medical"],
+        ),
+        own_pig,
+        other_pig,
+        other_attachment,
+        other_export,
+        other_observation,
+        other_surgery,
+    }
+}
@@ -0,0 +1,107 @@
+import { useState } from 'react'
+import {
+    Dialog,
+    DialogContent,
+    DialogDescription,
+    DialogFooter,
+    DialogHeader,
+    DialogTitle,
+} from '@/components/ui/dialog'
+import { Button } from '@/components/ui/button'
+import { Input } from '@/components/ui/input'
+import { Label } from '@/components/ui/label'
+import { Loader2, ShieldCheck } from 'lucide-react'
+
+interface SignaturePasswordDialogProps {
+    open: boolean
+    onOpenChange: (open: boolean) => void
+    title?: string
+    description?: string
+    confirmLabel?: string
+    onConfirm: (password: string) => void
+    isPending?: boolean
+}
+
+/**
+ * 電子簽章對話框
+ * 核准操作須再次輸入登入密碼完成簽章
+ */
+export function SignaturePasswordDialog({
+    open,
+    onOpenChange,
+    title = '電子簽章',
+    description = '請輸入您的登入密碼以完成電子簽章。',
+    confirmLabel = '簽章並核准',
+    onConfirm,
+    isPending = false,
+}: SignaturePasswordDialogProps) {
+    const [password, setPassword] = useState('')
+    const [error, setError] = useState('')
+
+    const handleConfirm = () => {
+        if (!password) {
+            setError('請輸入密碼')
+            return
+        }
+        setError('')
+        onConfirm(password)
+    }
+
+    const handleClose = (newOpen: boolean) => {
+        if (!newOpen) {
+            setPassword('')
+            setError('')
+        }
+        onOpenChange(newOpen)
+    }
+
+    return (
+        <Dialog open={open} onOpenChange={handleClose}>
+            <DialogContent className="sm:max-w-md">
+                <DialogHeader>
+                    <DialogTitle className="flex items-center gap-2">
+                        <ShieldCheck className="h-5 w-5" />
+                        {title}
+                    </DialogTitle>
+                    <DialogDescription>{description}</DialogDescription>
+                </DialogHeader>
+
+                <form
+                    className="space-y-2 py-4"
+                    onSubmit={(e) => {
+                        e.preventDefault()
+                        handleConfirm()
+                    }}
+                >
+                    <Label htmlFor="signature-password" className="text-sm font-medium">
+                        密碼 <span className="text-red-500">*</span>
+                    </Label>
+                    <Input
+                        id="signature-password"
+                        type="password"
+                        autoComplete="current-password"
+                        value={password}
+                        onChange={(e) => {
+                            setPassword(e.target.value)
+                            if (error) setError('')
+                        }}
+                        className={error ? 'border-red-500' : ''}
+                        disabled={isPending}
+                        autoFocus
+                    />
+                    {error && <p className="text-sm text-red-500">{error}</p>}
+                </form>
+
+                <DialogFooter className="gap-2">
+                    <Button variant="outline" onClick={() => handleClose(false)} disabled={isPending}>
+                        取消
+                    </Button>
+                    <Button onClick={handleConfirm} disabled={isPending || !password}>
+                        {isPending && <Loader2 className="h-4 w-4 mr-2 animate-spin" />}
+                        {confirmLabel}
+                    </Button>
+                </DialogFooter>
+            </DialogContent>
+        </Dialog>
+    )
+}
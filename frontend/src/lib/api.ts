@@ -512,6 +512,9 @@ export interface Protocol {
   created_by: string
   created_at: string
   updated_at: string
+  /** 盲審：PI 端看到的審查委員 ID 為全 0，名稱為「審查委員 N」 */
+  blinded_review: boolean
+  pre_review_due_date?: string
 }
 
 export interface ProtocolListItem {
@@ -543,6 +546,8 @@ export interface ProtocolVersion {
   content_snapshot: Record<string, unknown>
   submitted_at: string
   submitted_by: string
+  /** 由修正案核准產生的版本 */
+  amendment_id?: string
 }
 
 export interface ProtocolStatusHistory {
@@ -562,6 +567,26 @@ export interface ReviewAssignment {
   assigned_by: string
   assigned_at: string
   completed_at?: string
+  /** 審查期限（工作日計算） */
+  due_date?: string
+  escalated_at?: string
+  /** 修正案審查時為修正案 ID */
+  amendment_id?: string
+}
+
+export interface OverdueReview {
+  stage: 'pre_review' | 'review'
+  protocol_id: string
+  protocol_no: string
+  title: string
+  pi_name?: string
+  amendment_id?: string
+  amendment_no?: string
+  reviewer_id?: string
+  reviewer_name?: string
+  due_date: string
+  days_overdue: number
+  escalated_at?: string
 }
 
 export interface ReviewComment {
@@ -572,6 +597,7 @@ export interface ReviewComment {
   is_resolved: boolean
   resolved_by?: string
   resolved_at?: string
+  amendment_id?: string
   created_at: string
   updated_at: string
 }
@@ -605,6 +631,8 @@ export interface ChangeStatusRequest {
   remark?: string
   /** 審查委員 ID 列表（當目標狀態為 UNDER_REVIEW 時必填 2-3 位） */
   reviewer_ids?: string[]
+  /** 電子簽章密碼（核准類狀態必填） */
+  signature_password?: string
 }
 
 export interface CreateCommentRequest {
@@ -638,6 +666,96 @@ export interface CoEditorAssignmentResponse {
   granted_by_name?: string
 }
 
+export interface ReviewerCoiDeclaration {
+  id: string
+  protocol_id: string
+  reviewer_id: string
+  has_conflict: boolean
+  description?: string
+  declared_at: string
+  reviewer_name?: string
+}
+
+export type AmendmentChangeType = 'PERSONNEL' | 'ANIMAL_NUMBER' | 'PROCEDURE' | 'OTHER'
+
+/** 計畫修正案：沿用計畫狀態（DRAFT → SUBMITTED → UNDER_REVIEW → APPROVED / REJECTED） */
+export interface ProtocolAmendment {
+  id: string
+  protocol_id: string
+  /** 例如 PIG-115001-A01 */
+  amendment_no: string
+  title: string
+  reason: string
+  change_types: AmendmentChangeType[]
+  status: ProtocolStatus
+  base_version_id: string
+  proposed_content: Record<string, unknown>
+  revision_no: number
+  submitted_at?: string
+  decided_by?: string
+  decided_at?: string
+  decision_remark?: string
+  /** 核准後產生的計畫版本 */
+  effective_version_id?: string
+  created_by: string
+  created_at: string
+  updated_at: string
+}
+
+export interface CreateAmendmentRequest {
+  title: string
+  reason: string
+  change_types: AmendmentChangeType[]
+  proposed_content?: Record<string, unknown>
+}
+
+export interface ProtocolAmendmentDiff {
+  amendment_id: string
+  protocol_id: string
+  base_version: number
+  base_submitted_at: string
+  total_changes: number
+  sections: {
+    section: string
+    changes: {
+      path: string
+      change_type: 'added' | 'removed' | 'changed'
+      old_value?: unknown
+      new_value?: unknown
+    }[]
+    open_comment_count: number
+    has_open_comments: boolean
+  }[]
+}
+
+export type ReviewDecision = 'APPROVE' | 'APPROVE_WITH_CONDITIONS' | 'REVISE' | 'REJECT'
+
+export interface ReviewVote {
+  id: string
+  protocol_id: string
+  protocol_version_id: string
+  reviewer_id: string
+  decision: ReviewDecision
+  conditions?: string
+  comment?: string
+  voted_at: string
+  updated_at: string
+  reviewer_name?: string
+}
+
+export interface ReviewVoteSummary {
+  protocol_id: string
+  protocol_version_id?: string
+  rule: 'unanimous' | 'majority'
+  votes: ReviewVote[]
+  tally: { decision: ReviewDecision; decision_display: string; count: number }[]
+  assigned_reviewers: number
+  pending_reviewers: number
+  /** 尚有委員未表決時為 null；主委可做出不同決定（須填備註） */
+  suggested_status?: ProtocolStatus
+  suggested_status_display?: string
+}
+
 export interface ReviewAssignmentResponse extends ReviewAssignment {
   reviewer_name: string
   reviewer_email: string
@@ -1,3 +1,4 @@
+import { useState } from 'react'
 import { useParams, useNavigate } from 'react-router-dom'
 import { useQuery, useMutation, useQueryClient } from '@tanstack/react-query'
 import api, { Document } from '@/lib/api'
@@ -13,6 +14,7 @@ import {
   TableRow,
 } from '@/components/ui/table'
 import { toast } from '@/components/ui/use-toast'
+import { SignaturePasswordDialog } from '@/components/ui/signature-password-dialog'
 import { ArrowLeft, Send, CheckCircle, XCircle, Loader2 } from 'lucide-react'
 import { formatDate, formatNumber, formatCurrency } from '@/lib/utils'
 
@@ -39,6 +41,7 @@ export function DocumentDetailPage() {
   const { id } = useParams<{ id: string }>()
   const navigate = useNavigate()
   const queryClient = useQueryClient()
+  const [showSignatureDialog, setShowSignatureDialog] = useState(false)
 
   const { data: document, isLoading } = useQuery({
     queryKey: ['document', id],
@@ -69,9 +72,11 @@ export function DocumentDetailPage() {
   })
 
   const approveMutation = useMutation({
-    mutationFn: () => api.post(`/documents/${id}/approve`),
+    mutationFn: (signaturePassword: string) =>
+      api.post(`/documents/${id}/approve`, { signature_password: signaturePassword }),
     onSuccess: () => {
       queryClient.invalidateQueries({ queryKey: ['document', id] })
+      setShowSignatureDialog(false)
       toast({ title: '成功', description: '單據已核准' })
     },
     onError: (error: any) => {
@@ -162,7 +167,10 @@ export function DocumentDetailPage() {
           )}
           {document.status === 'submitted' && (
             <>
-              <Button onClick={() => approveMutation.mutate()} disabled={approveMutation.isPending}>
+              <Button
+                onClick={() => setShowSignatureDialog(true)}
+                disabled={approveMutation.isPending}
+              >
                 {approveMutation.isPending ? (
                   <Loader2 className="mr-2 h-4 w-4 animate-spin" />
                 ) : (
@@ -309,6 +317,14 @@ export function DocumentDetailPage() {
           </Table>
         </CardContent>
       </Card>
+
+      <SignaturePasswordDialog
+        open={showSignatureDialog}
+        onOpenChange={setShowSignatureDialog}
+        title="核准單據"
+        onConfirm={(password) => approveMutation.mutate(password)}
+        isPending={approveMutation.isPending}
+      />
     </div>
   )
 }
@@ -46,6 +46,7 @@ import {
     SelectValue,
 } from '@/components/ui/select'
 import { toast } from '@/components/ui/use-toast'
+import { SignaturePasswordDialog } from '@/components/ui/signature-password-dialog'
 import {
     BalanceSummary,
     LeaveRequestWithUser,
@@ -85,6 +86,7 @@ const parseDecimal = (value: number | string | null | undefined): number => {
 export function HrLeavePage() {
     const [activeTab, setActiveTab] = useState('my-leaves')
     const [showCreateDialog, setShowCreateDialog] = useState(false)
+    const [approvingLeaveId, setApprovingLeaveId] = useState<string | null>(null)
     const queryClient = useQueryClient()
 
     // 新假單表單狀態
@@ -227,14 +229,22 @@ export function HrLeavePage() {
 
     // 核准請假
     const approveLeaveMutation = useMutation({
-        mutationFn: async (id: string) => {
-            return api.post(`/hr/leaves/${id}/approve`, {})
+        mutationFn: async ({ id, password }: { id: string; password: string }) => {
+            return api.post(`/hr/leaves/${id}/approve`, { signature_password: password })
         },
         onSuccess: () => {
             queryClient.invalidateQueries({ queryKey: ['hr-pending-leaves'] })
             queryClient.invalidateQueries({ queryKey: ['hr-my-leaves'] })
+            setApprovingLeaveId(null)
             toast({ title: '成功', description: '已核准' })
         },
+        onError: (error: any) => {
+            toast({
+                title: '錯誤',
+                description: error?.response?.data?.error?.message || '核准失敗',
+                variant: 'destructive',
+            })
+        },
     })
 
     // 駁回請假
@@ -700,7 +710,7 @@ export function HrLeavePage() {
                                                         <Button
                                                             variant="default"
                                                             size="sm"
-                                                            onClick={() => approveLeaveMutation.mutate(leave.id)}
+                                                            onClick={() => setApprovingLeaveId(leave.id)}
                                                             disabled={approveLeaveMutation.isPending}
                                                         >
                                                             <CheckCircle className="h-4 w-4 mr-1" />
@@ -728,6 +738,16 @@ export function HrLeavePage() {
                     </Card>
                 </TabsContent>
             </Tabs>
+
+            <SignaturePasswordDialog
+                open={approvingLeaveId !== null}
+                onOpenChange={(open) => !open && setApprovingLeaveId(null)}
+                title="核准請假"
+                onConfirm={(password) =>
+                    approvingLeaveId && approveLeaveMutation.mutate({ id: approvingLeaveId, password })
+                }
+                isPending={approveLeaveMutation.isPending}
+            />
         </div>
     )
 }
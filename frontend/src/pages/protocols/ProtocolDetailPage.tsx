@@ -45,7 +45,7 @@ import {
   SelectTrigger,
   SelectValue,
 } from '@/components/ui/select'
-import { Textarea } from '@/components/ui/input'
+import { Input, Textarea } from '@/components/ui/input'
 import { Label } from '@/components/ui/label'
 import { toast } from '@/components/ui/use-toast'
 import {
@@ -77,6 +77,7 @@ import {
 import { formatDate, formatDateTime, formatFileSize } from '@/lib/utils'
 import { useAuthStore } from '@/stores/auth'
 import { ProtocolContentView } from '@/components/protocol/ProtocolContentView'
+import { DeleteReasonDialog } from '@/components/ui/delete-reason-dialog'
 
 const statusColors: Record<ProtocolStatus, 'default' | 'secondary' | 'success' | 'warning' | 'destructive' | 'outline'> = {
   DRAFT: 'secondary',
@@ -111,6 +112,10 @@ const allowedTransitions: Record<ProtocolStatus, ProtocolStatus[]> = {
   DELETED: [],
 }
 
+// 須電子簽章的核准類狀態
+const requiresSignature = (status: ProtocolStatus | '') =>
+  status === 'APPROVED' || status === 'APPROVED_WITH_CONDITIONS'
+
 export function ProtocolDetailPage() {
   const { id } = useParams<{ id: string }>()
   const navigate = useNavigate()
@@ -129,11 +134,13 @@ export function ProtocolDetailPage() {
   const [selectedCommentForReply, setSelectedCommentForReply] = useState<ReviewCommentResponse | null>(null)
   const [newStatus, setNewStatus] = useState<ProtocolStatus | ''>('')
   const [statusRemark, setStatusRemark] = useState('')
+  const [signaturePassword, setSignaturePassword] = useState('')
   const [commentContent, setCommentContent] = useState('')
   const [replyContent, setReplyContent] = useState('')
   const [selectedReviewerId, setSelectedReviewerId] = useState('')
   const [selectedReviewerIds, setSelectedReviewerIds] = useState<string[]>([])
   const [selectedCoEditorId, setSelectedCoEditorId] = useState('')
+  const [deleteAttachmentTarget, setDeleteAttachmentTarget] = useState<string | null>(null)
 
   // 取得計畫詳情
   const { data: protocol, isLoading } = useQuery({
@@ -280,6 +287,7 @@ export function ProtocolDetailPage() {
       setShowStatusDialog(false)
       setNewStatus('')
       setStatusRemark('')
+      setSignaturePassword('')
       setSelectedReviewerIds([])
       setSelectedCoEditorId('')
     },
@@ -432,11 +440,12 @@ export function ProtocolDetailPage() {
 
   // 刪除附件
   const deleteAttachmentMutation = useMutation({
-    mutationFn: async (attachmentId: string) => {
-      return api.delete(`/attachments/${attachmentId}`)
+    mutationFn: async ({ id: attachmentId, reason }: { id: string; reason: string }) => {
+      return api.delete(`/attachments/${attachmentId}`, { data: { reason } })
     },
     onSuccess: () => {
       toast({ title: '成功', description: '附件已刪除' })
+      setDeleteAttachmentTarget(null)
       queryClient.invalidateQueries({ queryKey: ['protocol-attachments', id] })
     },
     onError: (error: any) => {
@@ -469,12 +478,23 @@ export function ProtocolDetailPage() {
       }
     }
 
+    // 核准類狀態須輸入密碼完成電子簽章
+    if (requiresSignature(newStatus) && !signaturePassword) {
+      toast({
+        title: '錯誤',
+        description: '請輸入密碼以完成電子簽章',
+        variant: 'destructive',
+      })
+      return
+    }
+
     // 先變更狀態
     try {
       await changeStatusMutation.mutateAsync({
         to_status: newStatus,
         remark: statusRemark || undefined,
         reviewer_ids: newStatus === 'UNDER_REVIEW' ? selectedReviewerIds : undefined,
+        signature_password: requiresSignature(newStatus) ? signaturePassword : undefined,
       })
 
       // 如果目標狀態是行政預審且選擇了 co-editor，則指派 co-editor
@@ -1166,11 +1186,7 @@ export function ProtocolDetailPage() {
                             <Button
                               variant="ghost"
                               size="sm"
-                              onClick={() => {
-                                if (confirm('確定要刪除此附件嗎？')) {
-                                  deleteAttachmentMutation.mutate(attachment.id)
-                                }
-                              }}
+                              onClick={() => setDeleteAttachmentTarget(attachment.id)}
                               disabled={deleteAttachmentMutation.isPending}
                             >
                               <Trash2 className="h-4 w-4 text-destructive" />
@@ -1216,7 +1232,13 @@ export function ProtocolDetailPage() {
       )}
 
       {/* 狀態變更對話框 */}
-      <Dialog open={showStatusDialog} onOpenChange={setShowStatusDialog}>
+      <Dialog
+        open={showStatusDialog}
+        onOpenChange={(open) => {
+          setShowStatusDialog(open)
+          if (!open) setSignaturePassword('')
+        }}
+      >
         <DialogContent>
           <DialogHeader>
             <DialogTitle>變更計畫狀態</DialogTitle>
@@ -1305,9 +1327,31 @@ export function ProtocolDetailPage() {
                 rows={3}
               />
             </div>
+            {requiresSignature(newStatus) && (
+              <div className="space-y-2">
+                <Label htmlFor="status-signature-password">電子簽章密碼 *</Label>
+                <Input
+                  id="status-signature-password"
+                  type="password"
+                  autoComplete="current-password"
+                  value={signaturePassword}
+                  onChange={(e) => setSignaturePassword(e.target.value)}
+                  placeholder="請輸入您的登入密碼"
+                />
+                <p className="text-xs text-muted-foreground">
+                  核准須以您的登入密碼完成電子簽章
+                </p>
+              </div>
+            )}
           </div>
           <DialogFooter>
-            <Button variant="outline" onClick={() => setShowStatusDialog(false)}>
+            <Button
+              variant="outline"
+              onClick={() => {
+                setShowStatusDialog(false)
+                setSignaturePassword('')
+              }}
+            >
               取消
             </Button>
             <Button
@@ -1316,7 +1360,8 @@ export function ProtocolDetailPage() {
                 !newStatus ||
                 changeStatusMutation.isPending ||
                 assignCoEditorMutation.isPending ||
-                (newStatus === 'UNDER_REVIEW' && (selectedReviewerIds.length < 2 || selectedReviewerIds.length > 3))
+                (newStatus === 'UNDER_REVIEW' && (selectedReviewerIds.length < 2 || selectedReviewerIds.length > 3)) ||
+                (requiresSignature(newStatus) && !signaturePassword)
               }
             >
               {(changeStatusMutation.isPending || assignCoEditorMutation.isPending) && (
@@ -1550,6 +1595,15 @@ export function ProtocolDetailPage() {
           </DialogFooter>
         </DialogContent>
       </Dialog>
+
+      <DeleteReasonDialog
+        open={deleteAttachmentTarget !== null}
+        onOpenChange={(open) => !open && setDeleteAttachmentTarget(null)}
+        title="刪除附件"
+        description="此操作將標記附件為已刪除，檔案與歷史版本將保留於系統中以符合 GLP 規範。"
+        onConfirm={(reason) => deleteAttachmentMutation.mutate({ id: deleteAttachmentTarget!, reason })}
+        isPending={deleteAttachmentMutation.isPending}
+      />
     </div>
   )
 }